@@ -13,8 +13,9 @@
 //! - RPC_URL: Blockchain RPC endpoint
 //! - API_URL: The protected API endpoint to access
 
-use base64::Engine;
 use x402_rs::client::{get, X402ClientConfig};
+use x402_rs::types::X_PAYMENT_RESPONSE_HEADER;
+use x402_rs::utils::decode_payment_response_header;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -52,14 +53,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("✅ Response status: {}", status);
 
             // Check for payment response header
-            if let Some(payment_response) = response.headers().get("X-PAYMENT-RESPONSE") {
+            if let Some(payment_response) = response.headers().get(X_PAYMENT_RESPONSE_HEADER) {
                 if let Ok(encoded) = payment_response.to_str() {
-                    if let Ok(decoded_bytes) = base64::engine::general_purpose::STANDARD
-                        .decode(encoded.as_bytes())
-                    {
-                        if let Ok(json_str) = String::from_utf8(decoded_bytes) {
-                            println!("💰 Payment settled: {}", json_str);
-                        }
+                    match decode_payment_response_header(encoded) {
+                        Ok(decoded) => println!("💰 Payment settled: {}", decoded.tx_hash),
+                        Err(e) => println!("⚠️  {}", e),
                     }
                 }
             }
@@ -12,32 +12,105 @@
 //! - FACILITATOR_KEY: Private key for paying gas fees
 //! - RPC_URL: Blockchain RPC endpoint
 //! - PORT: Server port (default: 3001)
+//! - FACILITATOR_API_KEY_ID / FACILITATOR_API_SECRET: API key servers must sign
+//!   `/verify` and `/settle` calls with (optional)
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    body::Body,
+    extract::{Path, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use serde_json::json;
 use std::sync::Arc;
 use x402_rs::facilitator::{
-    handle_settle, handle_supported, handle_verify, FacilitatorConfig,
+    handle_health, handle_payer_nonces, handle_settle, handle_settle_batch, handle_supported,
+    handle_verify, FacilitatorConfig, FacilitatorConfigBuilder, COMMON_EXACT_KINDS,
 };
-use x402_rs::types::{SettlementRequest, VerificationRequest};
+use x402_rs::types::{HealthStatus, SettlementRequest, VerificationRequest, X_REQUEST_ID_HEADER};
+use x402_rs::X402Error;
 
 #[derive(Clone)]
 struct AppState {
     config: FacilitatorConfig,
 }
 
+/// Extracts the caller's correlation id and User-Agent for logging.
+fn request_context(headers: &HeaderMap) -> (String, String) {
+    let get = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("-")
+            .to_string()
+    };
+    (get(X_REQUEST_ID_HEADER), get("User-Agent"))
+}
+
+/// Answers 429 with `Retry-After`, which `FacilitatorClient` honours.
+fn rate_limited_response(error: X402Error) -> Response {
+    let retry_after = match &error {
+        X402Error::RateLimited {
+            retry_after_seconds: Some(seconds),
+        } => seconds.to_string(),
+        _ => "1".to_string(),
+    };
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [("Retry-After", retry_after)],
+        Json(json!({ "error": error.to_string() })),
+    )
+        .into_response()
+}
+
+/// Largest request body accepted from servers.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Rejects calls not signed with a configured API key, when keys are configured.
+async fn authenticate(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(authenticator) = &state.config.authenticator else {
+        return next.run(request).await;
+    };
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, MAX_BODY_BYTES).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    if let Err(e) =
+        authenticator.verify(parts.method.as_str(), parts.uri.path(), &parts.headers, &body)
+    {
+        tracing::warn!(error = %e, "rejected unauthenticated call");
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": e.to_string() })),
+        )
+            .into_response();
+    }
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
 async fn verify_handler(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(request): Json<VerificationRequest>,
 ) -> impl IntoResponse {
-    match handle_verify(request, &state.config).await {
+    let (request_id, user_agent) = request_context(&headers);
+    tracing::info!(%request_id, %user_agent, "verify");
+
+    let verify = handle_verify(request, &state.config);
+    // Continue the server's trace, if it sent one
+    #[cfg(feature = "otel")]
+    let verify = x402_rs::telemetry::with_trace_context(&headers, verify);
+    match verify.await {
         Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(e @ X402Error::RateLimited { .. }) => rate_limited_response(e),
         Err(e) => (
             StatusCode::BAD_REQUEST,
             Json(json!({ "error": e.to_string() })),
@@ -48,16 +121,24 @@ async fn verify_handler(
 
 async fn settle_handler(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(request): Json<SettlementRequest>,
 ) -> impl IntoResponse {
-    match handle_settle(request, &state.config).await {
+    let (request_id, user_agent) = request_context(&headers);
+    tracing::info!(%request_id, %user_agent, "settle");
+
+    let settle = handle_settle(request, &state.config);
+    #[cfg(feature = "otel")]
+    let settle = x402_rs::telemetry::with_trace_context(&headers, settle);
+    match settle.await {
         Ok(response) => {
-            if response.error.is_some() {
+            if response.error.is_some() || response.invalid_reason.is_some() {
                 (StatusCode::BAD_REQUEST, Json(response)).into_response()
             } else {
                 (StatusCode::OK, Json(response)).into_response()
             }
         }
+        Err(e @ X402Error::RateLimited { .. }) => rate_limited_response(e),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(json!({ "error": e.to_string() })),
@@ -66,6 +147,17 @@ async fn settle_handler(
     }
 }
 
+async fn settle_batch_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(requests): Json<Vec<SettlementRequest>>,
+) -> impl IntoResponse {
+    let (request_id, user_agent) = request_context(&headers);
+    tracing::info!(%request_id, %user_agent, items = requests.len(), "settle batch");
+
+    Json(handle_settle_batch(requests, &state.config).await)
+}
+
 async fn supported_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     match handle_supported(&state.config).await {
         Ok(response) => (StatusCode::OK, Json(response)).into_response(),
@@ -77,12 +169,32 @@ async fn supported_handler(State(state): State<Arc<AppState>>) -> impl IntoRespo
     }
 }
 
-async fn health_handler() -> impl IntoResponse {
-    Json(json!({
-        "status": "healthy",
-        "service": "x402-facilitator",
-        "version": 1,
-    }))
+async fn nonces_handler(
+    State(state): State<Arc<AppState>>,
+    Path(payer): Path<String>,
+) -> impl IntoResponse {
+    match handle_payer_nonces(&payer, &state.config).await {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+async fn health_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match handle_health(&state.config).await {
+        Ok(response) if response.status == HealthStatus::Unhealthy => {
+            (StatusCode::SERVICE_UNAVAILABLE, Json(response)).into_response()
+        }
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
 }
 
 async fn root_handler() -> impl IntoResponse {
@@ -92,7 +204,9 @@ async fn root_handler() -> impl IntoResponse {
         "endpoints": {
             "/verify": "POST - Verify a payment payload",
             "/settle": "POST - Settle a payment on-chain",
+            "/settle/batch": "POST - Settle several payments, reporting each result",
             "/supported": "GET - List supported payment kinds",
+            "/nonces/{payer}": "GET - List nonces used by a payer",
             "/health": "GET - Health check"
         },
     }))
@@ -121,22 +235,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("   Port: {}", port);
 
     // Create facilitator configuration
-    let mut config = FacilitatorConfig::new(facilitator_key, rpc_url);
-    
-    // Add supported networks
-    config.add_supported("exact", "8453"); // Base mainnet (already added by default)
-    config.add_supported("exact", "84532"); // Base Sepolia
-    config.add_supported("exact", "1"); // Ethereum mainnet
-    config.add_supported("exact", "137"); // Polygon mainnet
+    let config = FacilitatorConfigBuilder::new()
+        .private_key(facilitator_key)
+        .rpc_url(rpc_url)
+        .support_all(COMMON_EXACT_KINDS) // Ethereum, Base and Polygon with their testnets
+        .payer_rate_limit(20, 1.0); // 20 requests per payer, then one per second
+    let config = match (
+        std::env::var("FACILITATOR_API_KEY_ID"),
+        std::env::var("FACILITATOR_API_SECRET"),
+    ) {
+        (Ok(key_id), Ok(secret)) => {
+            println!("   Auth: API key {}", key_id);
+            config.api_key(key_id, secret)
+        }
+        _ => config,
+    }
+    .build()?;
 
     let state = Arc::new(AppState { config });
 
     // Build router
-    let app = Router::new()
-        .route("/", get(root_handler))
+    let authenticated = Router::new()
         .route("/verify", post(verify_handler))
         .route("/settle", post(settle_handler))
+        .route("/settle/batch", post(settle_batch_handler))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate));
+    let app = Router::new()
+        .route("/", get(root_handler))
+        .merge(authenticated)
         .route("/supported", get(supported_handler))
+        .route("/nonces/{payer}", get(nonces_handler))
         .route("/health", get(health_handler))
         .with_state(state);
 
@@ -148,7 +276,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\nEndpoints:");
     println!("  POST   http://localhost:{}/verify", port);
     println!("  POST   http://localhost:{}/settle", port);
+    println!("  POST   http://localhost:{}/settle/batch", port);
     println!("  GET    http://localhost:{}/supported", port);
+    println!("  GET    http://localhost:{}/nonces/{{payer}}", port);
     println!("  GET    http://localhost:{}/health", port);
     println!();
 
@@ -20,14 +20,18 @@ use axum::{
     routing::get,
     Json, Router,
 };
-use base64::Engine;
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use x402_rs::server::{
-    create_payment_required_response, verify_and_settle_payment, PaymentConfig,
+    create_negotiated_payment_required_response, payment_header, verify_and_settle_payment,
+    PaymentConfig,
 };
-use x402_rs::types::PaymentResponse;
+use x402_rs::types::{
+    PayloadEncoding, PaymentResponse, ACCEPT_PAYMENT_HEADER, X_PAYMENT_ENCODING_HEADER,
+    X_PAYMENT_RESPONSE_HEADER,
+};
+use x402_rs::utils::{encode_payment_response_header, normalize_payment_header};
 
 #[derive(Clone)]
 struct AppState {
@@ -39,33 +43,47 @@ async fn weather_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    // Check for X-PAYMENT header
-    if let Some(payment_header) = headers.get("X-PAYMENT") {
-        let payment_str = payment_header
-            .to_str()
-            .map_err(|_| AppError::InvalidPayment("Invalid payment header encoding".into()))?;
-
-        // Verify and settle the payment
-        let tx_hash = verify_and_settle_payment(
-            payment_str,
+    // Check for a single X-PAYMENT header
+    let payment_header =
+        payment_header(&headers).map_err(|e| AppError::InvalidPayment(e.to_string()))?;
+    if let Some(payment_str) = payment_header {
+
+        // Re-encode non-JSON payloads as JSON for the facilitator
+        let encoding = PayloadEncoding::from_header(
+            headers
+                .get(X_PAYMENT_ENCODING_HEADER)
+                .and_then(|value| value.to_str().ok()),
+        )
+        .map_err(|e| AppError::InvalidPayment(e.to_string()))?;
+        let payment_str = normalize_payment_header(payment_str, encoding)
+            .map_err(|e| AppError::InvalidPayment(e.to_string()))?;
+
+        // Verify and settle the payment, offering the requirements again on failure
+        // so clients can pay anew (for example after an authorization expired)
+        let settlement = match verify_and_settle_payment(
+            &payment_str,
             &state.payment_config,
             "/weather",
         )
         .await
-        .map_err(|e| AppError::PaymentFailed(e.to_string()))?;
+        {
+            Ok(settlement) => settlement,
+            Err(e) => return payment_required(&state, &headers, Some(e.to_string())),
+        };
 
         // Create payment response
+        let tx_hash = settlement.tx_hash.clone();
         let payment_response = PaymentResponse {
             tx_hash: tx_hash.clone(),
             settled_at: Some(chrono::Utc::now().to_rfc3339()),
             metadata: None,
+            settlement: Some(settlement),
+            access_token: None,
         };
 
         // Encode payment response as Base64 JSON
-        let payment_response_json = serde_json::to_string(&payment_response)
+        let payment_response_encoded = encode_payment_response_header(&payment_response)
             .map_err(|e| AppError::ServerError(e.to_string()))?;
-        let payment_response_encoded = base64::engine::general_purpose::STANDARD
-            .encode(payment_response_json.as_bytes());
 
         // Return the weather data with payment response header
         let weather_data = json!({
@@ -79,23 +97,37 @@ async fn weather_handler(
 
         Ok((
             StatusCode::OK,
-            [(
-                axum::http::header::HeaderName::from_static("x-payment-response"),
-                payment_response_encoded,
-            )],
+            [(X_PAYMENT_RESPONSE_HEADER, payment_response_encoded)],
             Json(weather_data),
         )
             .into_response())
     } else {
         // No payment header, return 402 with payment requirements
-        let mut configs = HashMap::new();
-        configs.insert("usdc".to_string(), state.payment_config.clone());
+        payment_required(&state, &headers, None)
+    }
+}
 
-        let payment_required = create_payment_required_response(&configs, "/weather")
-            .map_err(|e| AppError::ServerError(e.to_string()))?;
+/// Builds a 402 response with the payment requirements and why payment was refused.
+fn payment_required(
+    state: &AppState,
+    headers: &HeaderMap,
+    error: Option<String>,
+) -> Result<Response, AppError> {
+    let mut configs = HashMap::new();
+    configs.insert("usdc".to_string(), state.payment_config.clone());
+
+    let accept_payment = headers
+        .get(ACCEPT_PAYMENT_HEADER)
+        .and_then(|value| value.to_str().ok());
 
-        Ok((StatusCode::PAYMENT_REQUIRED, Json(payment_required)).into_response())
+    let mut payment_required =
+        create_negotiated_payment_required_response(&configs, "/weather", accept_payment)
+            .map_err(|e| AppError::ServerError(e.to_string()))?;
+    if let Some(error) = error {
+        payment_required = payment_required.with_error(error);
     }
+
+    Ok((StatusCode::PAYMENT_REQUIRED, Json(payment_required)).into_response())
 }
 
 /// Health check endpoint (no payment required).
@@ -181,7 +213,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 // Error handling
 enum AppError {
     InvalidPayment(String),
-    PaymentFailed(String),
     ServerError(String),
 }
 
@@ -189,7 +220,6 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
             AppError::InvalidPayment(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::PaymentFailed(msg) => (StatusCode::PAYMENT_REQUIRED, msg),
             AppError::ServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
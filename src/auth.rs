@@ -0,0 +1,220 @@
+//! Shared-secret authentication of server-to-facilitator calls.
+//!
+//! A server holding an API key signs each call with a [`RequestSigner`]: an
+//! HMAC-SHA256 over the timestamp, method, path and body, sent in the
+//! `X-Facilitator-*` headers. The facilitator checks them with a
+//! [`RequestAuthenticator`] and rejects calls that are unsigned, signed with an unknown
+//! key or a wrong secret, or signed too long ago.
+
+use crate::errors::{Result, X402Error};
+use crate::types::{
+    X_FACILITATOR_KEY_ID_HEADER, X_FACILITATOR_SIGNATURE_HEADER, X_FACILITATOR_TIMESTAMP_HEADER,
+};
+use crate::utils::current_timestamp;
+use hmac::{Hmac, Mac};
+use reqwest::header::HeaderMap;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How far a signed timestamp may be from the facilitator's clock, in seconds.
+pub const DEFAULT_MAX_CLOCK_SKEW_SECONDS: u64 = 300;
+
+/// Returns the MAC over a call, keyed with `secret`.
+fn mac(secret: &[u8], timestamp: u64, method: &str, path: &str, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}\n{}\n", timestamp, method.to_uppercase(), path).as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// Signs facilitator calls with an API key.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::auth::{RequestAuthenticator, RequestSigner};
+///
+/// let signer = RequestSigner::new("server-1", "shared secret");
+/// let headers = signer.sign("POST", "/verify", br#"{"x402Version":1}"#);
+///
+/// let mut authenticator = RequestAuthenticator::new();
+/// authenticator.add_key("server-1", "shared secret");
+/// assert!(authenticator
+///     .verify("POST", "/verify", &headers, br#"{"x402Version":1}"#)
+///     .is_ok());
+/// ```
+#[derive(Clone)]
+pub struct RequestSigner {
+    key_id: String,
+    secret: Vec<u8>,
+}
+
+impl RequestSigner {
+    /// Creates a signer for the key `key_id` with its shared secret.
+    pub fn new(key_id: impl Into<String>, secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            key_id: key_id.into(),
+            secret: secret.as_ref().to_vec(),
+        }
+    }
+
+    /// Returns the key id sent with each call.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Returns the authentication headers for a call made now.
+    pub fn sign(&self, method: &str, path: &str, body: &[u8]) -> HeaderMap {
+        self.sign_at(current_timestamp(), method, path, body)
+    }
+
+    /// Returns the authentication headers for a call made at `timestamp`.
+    pub fn sign_at(&self, timestamp: u64, method: &str, path: &str, body: &[u8]) -> HeaderMap {
+        let signature = mac(&self.secret, timestamp, method, path, body)
+            .finalize()
+            .into_bytes();
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            (X_FACILITATOR_KEY_ID_HEADER, self.key_id.clone()),
+            (X_FACILITATOR_TIMESTAMP_HEADER, timestamp.to_string()),
+            (X_FACILITATOR_SIGNATURE_HEADER, hex::encode(signature)),
+        ] {
+            if let Ok(value) = value.parse() {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+}
+
+impl fmt::Debug for RequestSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep the secret out of logs
+        f.debug_struct("RequestSigner")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Checks facilitator calls were signed with a known API key.
+#[derive(Clone)]
+pub struct RequestAuthenticator {
+    keys: HashMap<String, Vec<u8>>,
+    max_clock_skew: u64,
+}
+
+impl Default for RequestAuthenticator {
+    fn default() -> Self {
+        Self {
+            keys: HashMap::new(),
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW_SECONDS,
+        }
+    }
+}
+
+impl RequestAuthenticator {
+    /// Creates an authenticator accepting no keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts calls signed with `secret` under `key_id`, replacing any previous secret.
+    pub fn add_key(&mut self, key_id: impl Into<String>, secret: impl AsRef<[u8]>) {
+        self.keys.insert(key_id.into(), secret.as_ref().to_vec());
+    }
+
+    /// Sets how far, in seconds, a signed timestamp may be from the current time.
+    pub fn with_max_clock_skew(mut self, seconds: u64) -> Self {
+        self.max_clock_skew = seconds;
+        self
+    }
+
+    /// Checks the authentication headers of a call.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::Unauthorized` if a header is missing or malformed, the key
+    /// is unknown, the timestamp is outside the allowed clock skew, or the signature
+    /// doesn't match.
+    pub fn verify(&self, method: &str, path: &str, headers: &HeaderMap, body: &[u8]) -> Result<()> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| X402Error::Unauthorized(format!("Missing {} header", name)))
+        };
+        let key_id = header(X_FACILITATOR_KEY_ID_HEADER)?;
+        let timestamp = header(X_FACILITATOR_TIMESTAMP_HEADER)?;
+        let signature = header(X_FACILITATOR_SIGNATURE_HEADER)?;
+
+        let secret = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| X402Error::Unauthorized(format!("Unknown API key: {}", key_id)))?;
+        let timestamp: u64 = timestamp
+            .parse()
+            .map_err(|_| X402Error::Unauthorized(format!("Invalid timestamp: {}", timestamp)))?;
+        if current_timestamp().abs_diff(timestamp) > self.max_clock_skew {
+            return Err(X402Error::Unauthorized(format!(
+                "Signature timestamp {} is outside the allowed clock skew",
+                timestamp
+            )));
+        }
+
+        let signature = hex::decode(signature)
+            .map_err(|_| X402Error::Unauthorized("Malformed signature".to_string()))?;
+        mac(secret, timestamp, method, path, body)
+            .verify_slice(&signature)
+            .map_err(|_| X402Error::Unauthorized("Invalid signature".to_string()))
+    }
+}
+
+impl fmt::Debug for RequestAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep secrets out of logs
+        f.debug_struct("RequestAuthenticator")
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .field("max_clock_skew", &self.max_clock_skew)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authenticator_rejects_bad_credentials() {
+        let body = br#"{"paymentHeader":"eyJ4NDAyVmVyc2lvbiI6MX0="}"#;
+        let mut authenticator = RequestAuthenticator::new();
+        authenticator.add_key("server-1", "shared secret");
+        let unauthorized = |headers: &HeaderMap, body: &[u8]| {
+            matches!(
+                authenticator.verify("POST", "/settle", headers, body),
+                Err(X402Error::Unauthorized(_))
+            )
+        };
+
+        let signed = RequestSigner::new("server-1", "shared secret").sign("POST", "/settle", body);
+        authenticator.verify("POST", "/settle", &signed, body).unwrap();
+
+        // Tampered body, wrong secret, unknown key, stale timestamp, no headers
+        assert!(unauthorized(&signed, b"{}"));
+        let wrong_secret = RequestSigner::new("server-1", "guessed").sign("POST", "/settle", body);
+        assert!(unauthorized(&wrong_secret, body));
+        let unknown = RequestSigner::new("server-2", "shared secret").sign("POST", "/settle", body);
+        assert!(unauthorized(&unknown, body));
+        let stale = RequestSigner::new("server-1", "shared secret").sign_at(
+            current_timestamp() - 3600,
+            "POST",
+            "/settle",
+            body,
+        );
+        assert!(unauthorized(&stale, body));
+        assert!(unauthorized(&HeaderMap::new(), body));
+    }
+}
@@ -0,0 +1,214 @@
+//! Shared cache with per-entry expiry.
+//!
+//! [`TtlCache`] is the building block for the crate's caches of chain and token
+//! metadata: an async map whose entries expire after a time-to-live. Clones share
+//! entries, so a cache can be handed to several schemes or tasks.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Async map whose entries expire a fixed time after they are inserted.
+///
+/// Expired entries are never returned; they stay in memory until overwritten,
+/// invalidated or removed by [`sweep`](Self::sweep).
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use x402_rs::cache::TtlCache;
+///
+/// # tokio_test::block_on(async {
+/// let cache = TtlCache::new(Duration::from_secs(60));
+/// cache.insert("8453", "base").await;
+/// assert_eq!(cache.get(&"8453").await, Some("base"));
+///
+/// cache.invalidate(&"8453").await;
+/// assert_eq!(cache.get(&"8453").await, None);
+/// # });
+/// ```
+pub struct TtlCache<K, V> {
+    entries: Arc<RwLock<HashMap<K, Entry<V>>>>,
+    ttl: Duration,
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: Instant,
+}
+
+impl<V> Entry<V> {
+    fn is_fresh(&self, now: Instant) -> bool {
+        now < self.expires_at
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    /// Creates an empty cache whose entries live for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// Sets the time-to-live of entries inserted from now on.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Returns the time-to-live of inserted entries.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the value for `key`, unless it is missing or expired.
+    pub async fn get(&self, key: &K) -> Option<V> {
+        let now = Instant::now();
+        self.entries
+            .read()
+            .await
+            .get(key)
+            .filter(|entry| entry.is_fresh(now))
+            .map(|entry| entry.value.clone())
+    }
+
+    /// Inserts `value` for `key`, replacing any previous value.
+    pub async fn insert(&self, key: K, value: V) {
+        self.insert_with_ttl(key, value, self.ttl).await;
+    }
+
+    /// Inserts `value` for `key` with its own time-to-live.
+    pub async fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) {
+        let entry = Entry {
+            value,
+            expires_at: Instant::now() + ttl,
+        };
+        self.entries.write().await.insert(key, entry);
+    }
+
+    /// Removes `key`, returning `true` if it had a fresh value.
+    pub async fn invalidate(&self, key: &K) -> bool {
+        let now = Instant::now();
+        self.entries
+            .write()
+            .await
+            .remove(key)
+            .is_some_and(|entry| entry.is_fresh(now))
+    }
+
+    /// Keeps only the entries, fresh or expired, for which `keep` returns `true`.
+    pub async fn retain(&self, mut keep: impl FnMut(&K, &V) -> bool) {
+        self.entries
+            .write()
+            .await
+            .retain(|key, entry| keep(key, &entry.value));
+    }
+
+    /// Removes every entry.
+    pub async fn clear(&self) {
+        self.entries.write().await.clear();
+    }
+
+    /// Removes expired entries, returning how many were removed.
+    pub async fn sweep(&self) -> usize {
+        let now = Instant::now();
+        let mut entries = self.entries.write().await;
+        let before = entries.len();
+        entries.retain(|_, entry| entry.is_fresh(now));
+        before - entries.len()
+    }
+
+    /// Returns the number of entries, including expired ones not yet swept.
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+
+    /// Returns `true` if the cache holds no entries, fresh or expired.
+    pub async fn is_empty(&self) -> bool {
+        self.entries.read().await.is_empty()
+    }
+}
+
+impl<K, V> Clone for TtlCache<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            ttl: self.ttl,
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for TtlCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TtlCache")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_entries_expire_and_sweep() {
+        let cache = TtlCache::new(Duration::from_millis(20));
+        cache.insert("short", 1).await;
+        cache
+            .insert_with_ttl("long", 2, Duration::from_secs(60))
+            .await;
+        assert_eq!(cache.get(&"short").await, Some(1));
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(cache.get(&"short").await, None);
+        assert_eq!(cache.get(&"long").await, Some(2));
+        assert_eq!(cache.len().await, 2);
+
+        assert_eq!(cache.sweep().await, 1);
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        cache.insert("8453", "2").await;
+        let shared = cache.clone();
+
+        assert!(shared.invalidate(&"8453").await);
+        assert!(!shared.invalidate(&"8453").await);
+        assert_eq!(cache.get(&"8453").await, None);
+
+        cache.insert("8453", "3").await;
+        cache.clear().await;
+        assert!(cache.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_access() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        let tasks: Vec<_> = (0..32u64)
+            .map(|i| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    cache.insert(i % 8, i).await;
+                    cache.get(&(i % 8)).await.is_some()
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.await.unwrap());
+        }
+
+        assert_eq!(cache.len().await, 8);
+        for key in 0..8u64 {
+            let value = cache.get(&key).await.unwrap();
+            assert_eq!(value % 8, key);
+        }
+    }
+}
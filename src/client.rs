@@ -4,12 +4,37 @@
 //! responses, generate payment payloads, and retry requests with payment.
 
 use crate::errors::{Result, X402Error};
-use crate::schemes::{exact_evm::ExactEvm, Scheme};
-use crate::types::{PaymentPayload, PaymentRequiredResponse};
-use crate::utils::{decode_payment_header, encode_payment_header};
-use reqwest::{Client, Method, Response, StatusCode};
+use crate::journal::{AuthorizationJournal, JournalEntry, MemoryJournal};
+use crate::schemes::token_client::{RpcTokenClient, TokenClient};
+use crate::schemes::{
+    allowance_evm::{AllowanceEvm, ALLOWANCE_TRANSFER_METHOD},
+    exact_evm::{ExactEvm, DEFAULT_VALID_AFTER_OFFSET},
+    is_transient_rpc_error,
+    split_evm::SplitEvm,
+    upto_evm::UptoEvm,
+    RpcRetry, Scheme, SchemeRegistry,
+};
+use crate::types::{
+    PayloadEncoding, PaymentPayload, PaymentRequiredResponse, PaymentRequirements, PaymentResponse,
+    RequiredAmount, ACCEPT_PAYMENT_HEADER, X_PAYMENT_ENCODING_HEADER, X_PAYMENT_HEADER,
+    X_PAYMENT_RESPONSE_HEADER, X_PAYMENT_TOKEN_HEADER,
+};
+use crate::utils::{
+    asset_transfer_method, check_x402_version, decode_payment_response_header,
+    encode_payment_header_with, is_split_payment, message_signature_required, parse_address,
+    sign_payment_message, string_to_u256,
+};
+use bytes::Bytes;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, U256};
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::{redirect, Client, Method, Response, StatusCode, Url};
 use serde_json::Value;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
 
 /// Configuration for x402 client requests.
 #[derive(Clone)]
@@ -28,6 +53,59 @@ pub struct X402ClientConfig {
     
     /// Preferred network (e.g., "8453" for Base mainnet)
     pub preferred_network: Option<String>,
+
+    /// Maximum number of redirects followed per request (defaults to 10)
+    pub max_redirects: usize,
+
+    /// Refuse to send payments over plain HTTP to hosts other than localhost
+    /// (defaults to `true`)
+    pub require_https: bool,
+
+    /// EIP-712 domain chain id to sign for instead of the RPC's chain id
+    pub chain_id_override: Option<u64>,
+
+    /// Optional journal recording every authorization the client signs
+    pub journal: Option<Arc<dyn AuthorizationJournal>>,
+
+    /// Caps on cumulative spend per asset (lowercased address), in the smallest unit
+    pub spend_caps: HashMap<String, U256>,
+
+    /// Serialization of the X-PAYMENT payload (defaults to JSON)
+    pub payload_encoding: PayloadEncoding,
+
+    /// Whether to pay for the cheapest matching offer instead of the first
+    pub prefer_cheapest: bool,
+
+    /// How long the requirements of a 402 are reused to pay straight away, if at all
+    pub requirements_cache_ttl: Option<Duration>,
+
+    /// Whether offers outside the preferred scheme and network are refused (default)
+    /// rather than used when nothing else is offered
+    pub strict_preferences: bool,
+
+    /// Known decimals per token contract, used to compare offers in different assets
+    pub token_decimals: HashMap<Address, u8>,
+
+    /// Amount paid when a non-`exact` offer's `maxAmountRequired` is a range, instead of
+    /// its minimum
+    pub range_amount: Option<U256>,
+
+    /// How far before the signing time authorizations become valid
+    pub valid_after_offset: Duration,
+
+    /// Retries of RPC reads failing in transport, such as the chain id
+    pub rpc_retry: RpcRetry,
+
+    /// Whether the payer's token balance is checked before an `exact` or `upto` payment
+    /// is signed
+    pub check_balance: bool,
+
+    /// Implementations of schemes other than the built-in `exact` and `upto`
+    pub schemes: SchemeRegistry,
+
+    /// Whether paid responses are checked against the requirement's `outputSchema`
+    #[cfg(feature = "schema")]
+    pub validate_output_schema: bool,
 }
 
 impl X402ClientConfig {
@@ -52,9 +130,26 @@ impl X402ClientConfig {
         Self {
             private_key: private_key.into(),
             rpc_url: rpc_url.into(),
-            http_client: Client::new(),
+            http_client: default_http_client(),
             preferred_scheme: Some("exact".to_string()),
             preferred_network: None,
+            max_redirects: 10,
+            require_https: true,
+            chain_id_override: None,
+            journal: None,
+            spend_caps: HashMap::new(),
+            payload_encoding: PayloadEncoding::Json,
+            prefer_cheapest: false,
+            strict_preferences: true,
+            requirements_cache_ttl: None,
+            token_decimals: HashMap::new(),
+            range_amount: None,
+            valid_after_offset: DEFAULT_VALID_AFTER_OFFSET,
+            rpc_retry: RpcRetry::default(),
+            check_balance: true,
+            schemes: SchemeRegistry::new(),
+            #[cfg(feature = "schema")]
+            validate_output_schema: false,
         }
     }
 
@@ -70,15 +165,841 @@ impl X402ClientConfig {
         self
     }
 
+    /// Sets whether the preferred scheme and network are required.
+    ///
+    /// With `false`, a server offering nothing that matches is paid with the best of
+    /// what it does offer instead of failing with `NoSuitableRequirement`.
+    pub fn with_strict_preferences(mut self, strict: bool) -> Self {
+        self.strict_preferences = strict;
+        self
+    }
+
+    /// Pays offers for `scheme.name()` with `scheme`, replacing the built-in
+    /// implementation if there is one.
+    ///
+    /// Without [strict preferences](Self::with_strict_preferences), a server offering
+    /// nothing in the preferred scheme is paid in a scheme the client implements.
+    pub fn with_registered_scheme(mut self, scheme: impl Scheme + 'static) -> Self {
+        self.schemes.register(scheme);
+        self
+    }
+
+    /// Returns `true` if the client can pay offers for `scheme`.
+    pub fn implements_scheme(&self, scheme: &str) -> bool {
+        matches!(scheme, "exact" | "upto") || self.schemes.contains(scheme)
+    }
+
+    /// Remembers the requirements of each 402 for `ttl`, keyed by method and URL.
+    ///
+    /// Repeat requests within the TTL send the payment with the first request instead
+    /// of waiting for another 402. If the server answers a payment made with cached
+    /// requirements with a 402, its new requirements replace the cached ones and are
+    /// paid instead.
+    pub fn with_requirements_cache(mut self, ttl: Duration) -> Self {
+        self.requirements_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the maximum number of redirects followed per request.
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Sets whether payments may only be sent over HTTPS.
+    ///
+    /// Loopback hosts are exempt so local development keeps working over `http://`.
+    pub fn require_https(mut self, require_https: bool) -> Self {
+        self.require_https = require_https;
+        self
+    }
+
+    /// Signs authorizations for `chain_id` whatever chain the RPC reports, e.g. against
+    /// a local fork of Base reporting 31337.
+    pub fn with_chain_id_override(mut self, chain_id: u64) -> Self {
+        self.chain_id_override = Some(chain_id);
+        self
+    }
+
+    /// Signs authorizations valid from `offset` before the signing time, so a chain
+    /// whose clock lags the client's still accepts them at settlement. Defaults to
+    /// [`DEFAULT_VALID_AFTER_OFFSET`]; offsets over 30 seconds are clamped to 30, as
+    /// facilitators reject longer windows.
+    pub fn with_valid_after_offset(mut self, offset: Duration) -> Self {
+        self.valid_after_offset = offset;
+        self
+    }
+
+    /// Retries RPC reads, such as the chain id, up to `max_retries` times when they fail
+    /// with a connection error or timeout, backing off exponentially from `base_delay`.
+    ///
+    /// A read still failing after every retry is reported as a `BlockchainError`
+    /// naming the attempts made.
+    pub fn with_rpc_retries(mut self, max_retries: u32, base_delay: Duration) -> Self {
+        self.rpc_retry = RpcRetry::new(max_retries, base_delay);
+        self
+    }
+
+    /// Sets whether the payer's balance of the asset is read before signing an `exact`
+    /// or `upto` payment (enabled by default).
+    ///
+    /// A payer holding less than the amount then gets `X402Error::InvalidAmount` naming
+    /// the shortfall, instead of a payment whose settlement would revert.
+    pub fn with_balance_check(mut self, enabled: bool) -> Self {
+        self.check_balance = enabled;
+        self
+    }
+
+    /// Records every authorization the client signs in `journal`.
+    ///
+    /// A payment is not sent if it cannot be recorded.
+    pub fn with_journal(mut self, journal: impl AuthorizationJournal + 'static) -> Self {
+        self.journal = Some(Arc::new(journal));
+        self
+    }
+
+    /// Refuses payments once the total authorized in `asset` would exceed `cap`, in the
+    /// asset's smallest unit.
+    ///
+    /// Spend is counted from the [journal](Self::with_journal), so with a
+    /// `FileJournal` the cap holds across restarts. Without one it is counted per
+    /// `X402Client`.
+    pub fn with_spend_cap(mut self, asset: &str, cap: U256) -> Self {
+        self.spend_caps.insert(asset.to_lowercase(), cap);
+        self
+    }
+
+    /// Pays for the cheapest matching offer instead of the first one.
+    ///
+    /// Amounts are compared in whole tokens, so offers in assets with different decimals
+    /// are compared fairly; this assumes the assets are worth the same per token, as
+    /// with USD stablecoins.
+    pub fn with_cheapest_requirement(mut self) -> Self {
+        self.prefer_cheapest = true;
+        self
+    }
+
+    /// Adds known decimals per token contract for comparing offers.
+    ///
+    /// Tokens not listed are asked for `decimals()` over the RPC.
+    pub fn with_token_decimals(mut self, decimals: HashMap<Address, u8>) -> Self {
+        self.token_decimals.extend(decimals);
+        self
+    }
+
+    /// Pays `amount` for non-`exact` offers whose `maxAmountRequired` is a `"min-max"`
+    /// range, as some non-standard servers send, instead of the minimum. Offers whose
+    /// range excludes `amount` are refused.
+    pub fn with_range_amount(mut self, amount: U256) -> Self {
+        self.range_amount = Some(amount);
+        self
+    }
+
+    /// Sends payment payloads serialized with `encoding`, named in the
+    /// `X-PAYMENT-ENCODING` header. Only use encodings the server accepts.
+    pub fn with_payload_encoding(mut self, encoding: PayloadEncoding) -> Self {
+        self.payload_encoding = encoding;
+        self
+    }
+
+    /// Checks successful paid responses against the paid requirement's `outputSchema`,
+    /// failing the request with `X402Error::InvalidResponse` if they don't conform.
+    ///
+    /// The body is read to be checked, so the returned response no longer reports the
+    /// URL it came from in [`Response::url`].
+    #[cfg(feature = "schema")]
+    pub fn with_output_schema_validation(mut self) -> Self {
+        self.validate_output_schema = true;
+        self
+    }
+
+    /// Returns `true` if payment headers may be sent to `url`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use reqwest::Url;
+    /// use x402_rs::client::X402ClientConfig;
+    ///
+    /// let config = X402ClientConfig::new("0xkey", "https://rpc.url");
+    /// assert!(config.allows_payment_to(&Url::parse("https://api.example.com").unwrap()));
+    /// assert!(config.allows_payment_to(&Url::parse("http://localhost:3000").unwrap()));
+    /// assert!(!config.allows_payment_to(&Url::parse("http://api.example.com").unwrap()));
+    /// ```
+    pub fn allows_payment_to(&self, url: &Url) -> bool {
+        let loopback = match url.host() {
+            Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+            Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+            None => false,
+        };
+        !self.require_https || url.scheme() == "https" || loopback
+    }
+
     /// Sets a custom HTTP client.
+    ///
+    /// Redirects are only handled by the x402 client, keeping payment headers on the
+    /// original origin, if `client` is built with `redirect::Policy::none()`.
     pub fn with_client(mut self, client: Client) -> Self {
         self.http_client = client;
         self
     }
+
+    /// Builds the `Accept-Payment` header value advertising the preferred scheme/network.
+    ///
+    /// Returns `None` when neither a scheme nor a network preference is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::client::X402ClientConfig;
+    ///
+    /// let config = X402ClientConfig::new("0xkey", "https://rpc.url").with_network("8453");
+    /// assert_eq!(config.accept_payment_header(), Some("exact/8453".to_string()));
+    /// ```
+    pub fn accept_payment_header(&self) -> Option<String> {
+        match (&self.preferred_scheme, &self.preferred_network) {
+            (Some(scheme), Some(network)) => Some(format!("{}/{}", scheme, network)),
+            (Some(scheme), None) => Some(scheme.clone()),
+            (None, Some(network)) => Some(format!("*/{}", network)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Builds the default HTTP client, leaving redirects to the x402 client.
+fn default_http_client() -> Client {
+    Client::builder()
+        .redirect(redirect::Policy::none())
+        .build()
+        .expect("default HTTP client")
+}
+
+/// A reusable x402 client.
+///
+/// Wraps an [`X402ClientConfig`] together with caches shared across requests (such as
+/// the RPC's chain id and access tokens granted by servers), so it should be created
+/// once and reused. Cloning is cheap and clones share their caches.
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::client::{X402Client, X402ClientConfig};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = X402Client::new(X402ClientConfig::new(
+///     "0xprivatekey",
+///     "https://mainnet.base.org",
+/// ));
+///
+/// let response = client.get("https://api.example.com/weather").await?;
+/// println!("Response: {}", response.text().await?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct X402Client {
+    config: X402ClientConfig,
+    chain_id: Arc<OnceCell<u64>>,
+    /// Access tokens granted by servers, keyed by URL
+    access_tokens: Arc<RwLock<HashMap<String, String>>>,
+    /// The configured journal, or an in-memory one tracking this client's spend
+    journal: Arc<dyn AuthorizationJournal>,
+    /// Spend of payments being signed but not yet journaled, keyed by network and asset
+    reserved: Arc<Mutex<HashMap<(String, String), U256>>>,
+    /// Requirements of recent 402s and where they were answered, keyed by method and URL
+    requirements: Arc<RwLock<HashMap<(Method, String), CachedRequirements>>>,
+}
+
+/// Spend held against a cap by [`X402Client`] while a payment is signed.
+struct SpendReservation {
+    reserved: Arc<Mutex<HashMap<(String, String), U256>>>,
+    key: (String, String),
+    amount: U256,
+}
+
+impl Drop for SpendReservation {
+    fn drop(&mut self) {
+        let mut reserved = self.reserved.lock().unwrap();
+        if let Some(pending) = reserved.get_mut(&self.key) {
+            *pending = pending.saturating_sub(self.amount);
+            if pending.is_zero() {
+                reserved.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// Requirements from a 402, remembered by [`X402Client`].
+#[derive(Clone)]
+struct CachedRequirements {
+    payment_info: PaymentRequiredResponse,
+    location: Url,
+    fetched_at: Instant,
+}
+
+impl X402Client {
+    /// Creates a client from a configuration.
+    pub fn new(config: X402ClientConfig) -> Self {
+        let journal = match &config.journal {
+            Some(journal) => journal.clone(),
+            None => Arc::new(MemoryJournal::new()),
+        };
+        Self {
+            config,
+            chain_id: Arc::new(OnceCell::new()),
+            access_tokens: Arc::new(RwLock::new(HashMap::new())),
+            journal,
+            reserved: Arc::new(Mutex::new(HashMap::new())),
+            requirements: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the total this client has authorized in `asset` on `network`.
+    pub fn total_spent(&self, network: &str, asset: &str) -> U256 {
+        self.journal.total_spent(network, asset)
+    }
+
+    /// Reserves the amount of `requirement` against the asset's spend cap, if any.
+    ///
+    /// Concurrent payments each see the others' reservations, so together they can't
+    /// exceed the cap. The reservation is released when dropped, which must happen only
+    /// once the payment is journaled or abandoned.
+    fn reserve_spend(&self, requirement: &PaymentRequirements) -> Result<Option<SpendReservation>> {
+        let asset = requirement.asset.to_lowercase();
+        let Some(cap) = self.config.spend_caps.get(&asset) else {
+            return Ok(None);
+        };
+        let amount = string_to_u256(&requirement.max_amount_required)?;
+        let key = (requirement.network.clone(), asset);
+
+        let mut reserved = self.reserved.lock().unwrap();
+        let pending = reserved.get(&key).copied().unwrap_or_default();
+        let spent = self.total_spent(&requirement.network, &requirement.asset);
+        let remaining = cap.saturating_sub(spent.saturating_add(pending));
+        if amount > remaining {
+            return Err(X402Error::PaymentExceedsBudget { remaining });
+        }
+        reserved.insert(key.clone(), pending.saturating_add(amount));
+        Ok(Some(SpendReservation {
+            reserved: self.reserved.clone(),
+            key,
+            amount,
+        }))
+    }
+
+    /// Returns the requirements cached for `method` and `url`, if still fresh.
+    fn cached_requirements(
+        &self,
+        method: &Method,
+        url: &str,
+    ) -> Option<(PaymentRequiredResponse, Url)> {
+        let ttl = self.config.requirements_cache_ttl?;
+        let key = (method.clone(), url.to_string());
+        let cached = self.requirements.read().unwrap().get(&key).cloned()?;
+        (cached.fetched_at.elapsed() < ttl).then_some((cached.payment_info, cached.location))
+    }
+
+    /// Caches the requirements of a 402 answered at `location`, if caching is enabled.
+    fn cache_requirements(
+        &self,
+        method: &Method,
+        url: &str,
+        payment_info: &PaymentRequiredResponse,
+        location: &Url,
+    ) {
+        if self.config.requirements_cache_ttl.is_none() {
+            return;
+        }
+        let cached = CachedRequirements {
+            payment_info: payment_info.clone(),
+            location: location.clone(),
+            fetched_at: Instant::now(),
+        };
+        self.requirements
+            .write()
+            .unwrap()
+            .insert((method.clone(), url.to_string()), cached);
+    }
+
+    /// Returns the access token stored for a URL, if the server granted one.
+    pub fn access_token(&self, url: &str) -> Option<String> {
+        self.access_tokens.read().unwrap().get(url).cloned()
+    }
+
+    /// Returns the client's configuration.
+    pub fn config(&self) -> &X402ClientConfig {
+        &self.config
+    }
+
+    /// Returns the chain id of the configured RPC, querying it only once.
+    pub async fn chain_id(&self) -> Result<u64> {
+        self.chain_id
+            .get_or_try_init(|| async {
+                let provider = Provider::<Http>::try_from(self.config.rpc_url.as_str())?;
+                let chain_id = self
+                    .config
+                    .rpc_retry
+                    .run(
+                        || provider.get_chainid(),
+                        is_transient_rpc_error,
+                        X402Error::from,
+                    )
+                    .await?;
+                Ok(chain_id.as_u64())
+            })
+            .await
+            .copied()
+    }
+
+    /// Makes an HTTP request with automatic x402 payment handling.
+    ///
+    /// If the server responds with 402 Payment Required, this will:
+    /// 1. Parse the payment requirements
+    /// 2. Generate a payment payload
+    /// 3. Retry the request with the X-PAYMENT header
+    ///
+    /// Redirects are followed up to `max_redirects`, with payment headers only sent to
+    /// the origin that asked for payment. If the paid request is redirected to a
+    /// resource demanding its own payment, the client pays once more for it.
+    ///
+    /// If the paid request is refused with a 402 because the authorization expired in
+    /// transit, the client signs a fresh one and retries once.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP method (GET, POST, etc.)
+    /// * `url` - Target URL
+    /// * `body` - Optional JSON request body (for POST, PUT, etc.)
+    pub async fn request(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<Value>,
+    ) -> Result<Response> {
+        let body = body.as_ref().map(RequestBody::json).transpose()?;
+        self.request_with_body(method, url, body).await
+    }
+
+    /// Makes an HTTP request with automatic x402 payment handling, sending a body of
+    /// any content type.
+    ///
+    /// Like [`request`](Self::request), but the body's bytes and content type are sent
+    /// unchanged on every attempt, including the paid retry.
+    pub async fn request_with_body(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<RequestBody>,
+    ) -> Result<Response> {
+        Ok(self.request_ex(method, url, body).await?.response)
+    }
+
+    /// Makes an HTTP request with automatic x402 payment handling, returning the decoded
+    /// `X-PAYMENT-RESPONSE` along with the response.
+    ///
+    /// Like [`request_with_body`](Self::request_with_body), but the settlement the
+    /// server reported, such as its transaction hash, is handed back in
+    /// [`PaidResponse::payment`]. It is `None` if no payment was made or the header is
+    /// missing or malformed.
+    pub async fn request_ex(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<RequestBody>,
+    ) -> Result<PaidResponse> {
+        let config = &self.config;
+
+        // Present a previously granted access token instead of paying again
+        let access_token = self.access_token(url);
+        let token_headers: Vec<_> = access_token
+            .iter()
+            .map(|token| (X_PAYMENT_TOKEN_HEADER, token.clone()))
+            .collect();
+
+        // With fresh cached requirements, skip the unpaid request and pay straight away
+        let cached = match access_token {
+            Some(_) => None,
+            None => self.cached_requirements(&method, url),
+        };
+        let from_cache = cached.is_some();
+        let (mut payment_info, mut location) = match cached {
+            Some(cached) => cached,
+            None => {
+                // Send initial request
+                let (response, location) = self
+                    .send_following_redirects(method.clone(), url, body.as_ref(), &token_headers)
+                    .await?;
+
+                // Check if payment is required
+                if response.status() != StatusCode::PAYMENT_REQUIRED {
+                    // No payment required, return original response
+                    return Ok(PaidResponse {
+                        response,
+                        payment: None,
+                    });
+                }
+
+                if access_token.is_some() {
+                    // The token expired or was rejected
+                    self.access_tokens.write().unwrap().remove(url);
+                }
+
+                // Parse 402 response
+                let payment_info: PaymentRequiredResponse = response.json().await?;
+                self.cache_requirements(&method, url, &payment_info, &location);
+                (payment_info, location)
+            }
+        };
+        let mut reissued = false;
+        let mut resigned = false;
+
+        loop {
+            check_x402_version(payment_info.x402_version)?;
+
+            // Select a suitable payment requirement
+            let mut requirement = select_requirement(&payment_info, config).await?.clone();
+            requirement.max_amount_required = amount_to_pay(&requirement, config)?.to_string();
+            let requirement = &requirement;
+
+            // Generate payment payload, within the spend cap, checking the requirement
+            // is payable before asking the RPC for the chain id
+            let reservation = self.reserve_spend(requirement)?;
+            let known_chain_id = config.chain_id_override.or(self.chain_id.get().copied());
+            let mut scheme = payment_scheme(requirement, config, known_chain_id)?;
+            scheme.prevalidate(requirement).await?;
+            if known_chain_id.is_none() {
+                // Only until the client has learned its chain id
+                scheme = payment_scheme(requirement, config, Some(self.chain_id().await?))?;
+            }
+            let payload = generate_payment_payload(scheme.as_ref(), requirement, config).await?;
+            self.journal.record(JournalEntry::for_payment(&payload, requirement)?)?;
+            // Journaled spend now counts against the cap
+            drop(reservation);
+
+            // Encode payload as Base64, naming the encoding unless it is the JSON default
+            let payment_header = encode_payment_header_with(&payload, config.payload_encoding)?;
+            let mut payment_headers = vec![(X_PAYMENT_HEADER, payment_header)];
+            if config.payload_encoding != PayloadEncoding::Json {
+                payment_headers.push((
+                    X_PAYMENT_ENCODING_HEADER,
+                    config.payload_encoding.as_str().to_string(),
+                ));
+            }
+
+            // Retry request with payment header
+            let (retry_response, final_location) = self
+                .send_following_redirects(
+                    method.clone(),
+                    location.as_str(),
+                    body.as_ref(),
+                    &payment_headers,
+                )
+                .await?;
+
+            // The resource moved somewhere that wants its own payment, or the cached
+            // requirements went stale; pay once more
+            if retry_response.status() == StatusCode::PAYMENT_REQUIRED
+                && (final_location != location || from_cache)
+                && !reissued
+            {
+                reissued = true;
+                location = final_location;
+                payment_info = retry_response.json().await?;
+                if from_cache {
+                    self.cache_requirements(&method, url, &payment_info, &location);
+                }
+                continue;
+            }
+
+            // The authorization expired in transit; sign a fresh one, but only once
+            let retry_response = match retry_response.status() {
+                StatusCode::PAYMENT_REQUIRED if !resigned => {
+                    let (response, bytes) = buffer_response(retry_response).await?;
+                    match serde_json::from_slice::<PaymentRequiredResponse>(&bytes) {
+                        Ok(refused) if refused.is_authorization_expired() => {
+                            resigned = true;
+                            if !refused.accepts.is_empty() {
+                                payment_info = refused;
+                            }
+                            continue;
+                        }
+                        _ => response,
+                    }
+                }
+                _ => retry_response,
+            };
+
+            // Check for payment response header, ignoring it if malformed
+            let payment = retry_response
+                .headers()
+                .get(X_PAYMENT_RESPONSE_HEADER)
+                .and_then(|header| header.to_str().ok())
+                .and_then(|encoded| decode_payment_response_header(encoded).ok());
+            if let Some(decoded) = &payment {
+                // Payment response received
+                #[cfg(feature = "tracing")]
+                tracing::debug!("Payment response: {:?}", decoded);
+
+                if let Some(token) = &decoded.access_token {
+                    self.access_tokens
+                        .write()
+                        .unwrap()
+                        .insert(url.to_string(), token.clone());
+                }
+            }
+
+            #[cfg(feature = "schema")]
+            if config.validate_output_schema
+                && retry_response.status().is_success()
+                && requirement.output_schema.is_some()
+            {
+                let response = validated_response(retry_response, requirement).await?;
+                return Ok(PaidResponse { response, payment });
+            }
+
+            return Ok(PaidResponse {
+                response: retry_response,
+                payment,
+            });
+        }
+    }
+
+    /// Sends a request, following up to `max_redirects` redirects.
+    ///
+    /// `payment_headers` are only sent while the location stays on the origin of `url`,
+    /// so a payment or access token is never handed to another host. A 303, or a 301/302
+    /// answering a POST, switches to a GET without body as browsers do.
+    ///
+    /// Returns the final response together with the URL that produced it.
+    async fn send_following_redirects(
+        &self,
+        mut method: Method,
+        url: &str,
+        mut body: Option<&RequestBody>,
+        payment_headers: &[(&str, String)],
+    ) -> Result<(Response, Url)> {
+        let config = &self.config;
+        let mut current = Url::parse(url)?;
+        let origin = current.origin();
+        let mut redirects = 0;
+
+        loop {
+            let mut request = config.http_client.request(method.clone(), current.clone());
+
+            if let Some(accept_payment) = config.accept_payment_header() {
+                request = request.header(ACCEPT_PAYMENT_HEADER, accept_payment);
+            }
+
+            if current.origin() == origin {
+                if !payment_headers.is_empty() && !config.allows_payment_to(&current) {
+                    return Err(X402Error::ConfigError(format!(
+                        "Refusing to send payment over plain HTTP to {}",
+                        current
+                    )));
+                }
+                for (name, value) in payment_headers {
+                    request = request.header(*name, value);
+                }
+            }
+
+            if let Some(body) = body {
+                request = request
+                    .header(CONTENT_TYPE, &body.content_type)
+                    .body(body.bytes.clone());
+            }
+
+            let response = request.send().await?;
+            let status = response.status();
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+
+            let Some(location) = location.filter(|_| status.is_redirection()) else {
+                return Ok((response, current));
+            };
+
+            if redirects == config.max_redirects {
+                return Err(X402Error::Other(format!(
+                    "Too many redirects (max {})",
+                    config.max_redirects
+                )));
+            }
+            redirects += 1;
+
+            if status == StatusCode::SEE_OTHER
+                || (method == Method::POST
+                    && matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND))
+            {
+                method = Method::GET;
+                body = None;
+            }
+            current = current.join(&location)?;
+        }
+    }
+
+    /// Makes a GET request with automatic payment handling.
+    pub async fn get(&self, url: &str) -> Result<Response> {
+        self.request(Method::GET, url, None).await
+    }
+
+    /// Makes a POST request with automatic payment handling.
+    pub async fn post(&self, url: &str, body: Value) -> Result<Response> {
+        self.request(Method::POST, url, Some(body)).await
+    }
+
+    /// Makes a PUT request with automatic payment handling.
+    pub async fn put(&self, url: &str, body: Value) -> Result<Response> {
+        self.request(Method::PUT, url, Some(body)).await
+    }
+
+    /// Fetches the payment options of `url` without paying, in the server's order.
+    ///
+    /// Returns no offers if the resource is free. With `check_balances`, the payer's
+    /// balance of each offered asset is read over the configured RPC endpoint, once per
+    /// asset, and compared to the amount the client would pay; an offer whose balance
+    /// can't be read is reported unaffordable, without a balance. Otherwise every offer
+    /// is reported affordable.
+    pub async fn probe(&self, url: &str, check_balances: bool) -> Result<Vec<ProbedOffer>> {
+        let (response, _) = self
+            .send_following_redirects(Method::GET, url, None, &[])
+            .await?;
+        if response.status() != StatusCode::PAYMENT_REQUIRED {
+            return Ok(Vec::new());
+        }
+        let payment_info: PaymentRequiredResponse = response.json().await?;
+        check_x402_version(payment_info.x402_version)?;
+
+        if !check_balances {
+            return Ok(payment_info
+                .accepts
+                .into_iter()
+                .map(|offer| ProbedOffer {
+                    offer,
+                    affordable: true,
+                    balance: None,
+                })
+                .collect());
+        }
+
+        let config = &self.config;
+        let payer = config
+            .private_key
+            .parse::<LocalWallet>()
+            .map_err(|e| X402Error::InvalidPayload(format!("Invalid private key: {}", e)))?
+            .address();
+        let token_client = RpcTokenClient::new(&config.rpc_url)?
+            .with_rpc_retries(config.rpc_retry.max_retries, config.rpc_retry.base_delay);
+        let mut balances = HashMap::new();
+        let mut offers = Vec::new();
+        for offer in payment_info.accepts {
+            // Offers in assets that aren't EVM tokens can't be checked
+            let Ok(asset) = parse_address(&offer.asset) else {
+                offers.push(ProbedOffer {
+                    offer,
+                    affordable: false,
+                    balance: None,
+                });
+                continue;
+            };
+            let balance = match balances.get(&asset) {
+                Some(&balance) => balance,
+                None => {
+                    let balance = token_client.balance_of(asset, payer, None).await.ok();
+                    balances.insert(asset, balance);
+                    balance
+                }
+            };
+            let affordable = balance.is_some_and(|balance| {
+                amount_to_pay(&offer, config).is_ok_and(|amount| balance >= amount)
+            });
+            offers.push(ProbedOffer {
+                offer,
+                affordable,
+                balance,
+            });
+        }
+        Ok(offers)
+    }
+}
+
+/// A response to a request made with payment handling, with the settlement the server
+/// reported.
+#[derive(Debug)]
+pub struct PaidResponse {
+    /// The final response
+    pub response: Response,
+
+    /// The decoded `X-PAYMENT-RESPONSE` header, if a payment was made and the server
+    /// sent a well-formed one
+    pub payment: Option<PaymentResponse>,
+}
+
+/// A payment option offered for a resource, as reported by [`X402Client::probe`].
+#[derive(Clone, Debug)]
+pub struct ProbedOffer {
+    /// The option as the server offered it
+    pub offer: PaymentRequirements,
+
+    /// Whether the payer holds enough of the asset to pay for it
+    pub affordable: bool,
+
+    /// The payer's balance of the asset, if it was read
+    pub balance: Option<U256>,
+}
+
+/// A request body and its content type, sent verbatim.
+///
+/// The body is captured once and replayed byte for byte when a request is retried
+/// with payment, so non-JSON and large bodies are neither re-serialized nor altered.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::client::RequestBody;
+///
+/// let body = RequestBody::new("text/plain", "hello");
+/// assert_eq!(body.content_type(), "text/plain");
+/// assert_eq!(body.bytes(), b"hello");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestBody {
+    content_type: String,
+    bytes: Bytes,
+}
+
+impl RequestBody {
+    /// Creates a body of `bytes` with the given content type.
+    pub fn new(content_type: impl Into<String>, bytes: impl Into<Bytes>) -> Self {
+        Self {
+            content_type: content_type.into(),
+            bytes: bytes.into(),
+        }
+    }
+
+    /// Serializes `value` as an `application/json` body.
+    pub fn json(value: &Value) -> Result<Self> {
+        Ok(Self::new("application/json", serde_json::to_vec(value)?))
+    }
+
+    /// Returns the content type.
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    /// Returns the body bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
 }
 
 /// Makes an HTTP request with automatic x402 payment handling.
 ///
+/// This is a one-shot wrapper around [`X402Client::request`]; prefer reusing an
+/// [`X402Client`] when making several requests.
+///
 /// If the server responds with 402 Payment Required, this function will:
 /// 1. Parse the payment requirements
 /// 2. Generate a payment payload
@@ -120,63 +1041,126 @@ pub async fn request_with_payment(
     url: &str,
     body: Option<Value>,
 ) -> Result<Response> {
-    // Build initial request
-    let mut request = config.http_client.request(method.clone(), url);
-
-    if let Some(body) = &body {
-        request = request.json(body);
-    }
-
-    // Send initial request
-    let response = request.send().await?;
-
-    // Check if payment is required
-    if response.status() == StatusCode::PAYMENT_REQUIRED {
-        // Parse 402 response
-        let payment_info: PaymentRequiredResponse = response.json().await?;
-
-        // Select a suitable payment requirement
-        let requirement = select_requirement(&payment_info, config)?;
-
-        // Generate payment payload
-        let payload = generate_payment_payload(requirement, config).await?;
-
-        // Encode payload as Base64
-        let payment_header = encode_payment_header(&payload)?;
+    X402Client::new(config.clone()).request(method, url, body).await
+}
 
-        // Retry request with payment header
-        let mut retry_request = config.http_client.request(method, url);
-        retry_request = retry_request.header("X-PAYMENT", payment_header);
+/// Makes an HTTP request with automatic x402 payment handling, also returning the
+/// decoded `X-PAYMENT-RESPONSE`.
+///
+/// Like [`request_with_payment`], but the settlement's transaction hash and time are
+/// available in [`PaidResponse::payment`] without decoding the header again. A missing
+/// or malformed header leaves it `None` rather than failing the request.
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::client::{X402ClientConfig, request_with_payment_ex};
+/// use reqwest::Method;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = X402ClientConfig::new("0xprivatekey", "https://mainnet.base.org");
+///
+/// let paid = request_with_payment_ex(
+///     &config,
+///     Method::GET,
+///     "https://api.example.com/weather",
+///     None,
+/// ).await?;
+///
+/// if let Some(payment) = &paid.payment {
+///     println!("Settled in {}", payment.tx_hash);
+/// }
+/// println!("Response: {}", paid.response.text().await?);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn request_with_payment_ex(
+    config: &X402ClientConfig,
+    method: Method,
+    url: &str,
+    body: Option<Value>,
+) -> Result<PaidResponse> {
+    let body = body.as_ref().map(RequestBody::json).transpose()?;
+    X402Client::new(config.clone())
+        .request_ex(method, url, body)
+        .await
+}
 
-        if let Some(body) = body {
-            retry_request = retry_request.json(&body);
-        }
+/// Checks a paid response body against the `outputSchema` of the requirement paid for.
+///
+/// Bodies paid for under requirements without an `outputSchema` always pass.
+///
+/// # Errors
+///
+/// Returns `X402Error::InvalidResponse` if the body doesn't conform to the schema, and
+/// `X402Error::ConfigError` if the schema itself is invalid.
+///
+/// # Examples
+///
+/// ```
+/// use serde_json::json;
+/// use x402_rs::client::validate_response_against_schema;
+/// # let mut requirements = x402_rs::server::create_simple_config(
+/// #     "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb", 0.01, "Weather", "https://x402.org/facilitator",
+/// # ).to_requirements("/weather").unwrap();
+///
+/// requirements.output_schema = Some(json!({
+///     "type": "object",
+///     "required": ["temperature"],
+/// }));
+/// assert!(validate_response_against_schema(&json!({ "temperature": 21 }), &requirements).is_ok());
+/// assert!(validate_response_against_schema(&json!("garbage"), &requirements).is_err());
+/// ```
+#[cfg(feature = "schema")]
+pub fn validate_response_against_schema(
+    body: &Value,
+    requirements: &PaymentRequirements,
+) -> Result<()> {
+    let Some(schema) = &requirements.output_schema else {
+        return Ok(());
+    };
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| X402Error::ConfigError(format!("Invalid output schema: {}", e)))?;
+    validator.validate(body).map_err(|e| {
+        X402Error::InvalidResponse(format!("{} at \"{}\"", e, e.instance_path))
+    })
+}
 
-        let retry_response = retry_request.send().await?;
+/// Reads a paid response, checks it against the requirement's `outputSchema`, and
+/// rebuilds it with the same status, headers and body.
+#[cfg(feature = "schema")]
+async fn validated_response(
+    response: Response,
+    requirements: &PaymentRequirements,
+) -> Result<Response> {
+    let (response, bytes) = buffer_response(response).await?;
+    let body: Value = serde_json::from_slice(&bytes)
+        .map_err(|e| X402Error::InvalidResponse(format!("Body is not JSON: {}", e)))?;
+    validate_response_against_schema(&body, requirements)?;
+    Ok(response)
+}
 
-        // Check for payment response header
-        if let Some(payment_response) = retry_response.headers().get("X-PAYMENT-RESPONSE") {
-            if let Ok(encoded) = payment_response.to_str() {
-                if let Ok(_decoded) = decode_payment_header(encoded) {
-                    // Payment response received
-                    #[cfg(feature = "tracing")]
-                    tracing::debug!("Payment response: {:?}", _decoded);
-                }
-            }
-        }
+/// Reads a response's body, returning it along with a response rebuilt with the same
+/// status, headers and body.
+async fn buffer_response(response: Response) -> Result<(Response, Bytes)> {
+    let (status, version, headers) =
+        (response.status(), response.version(), response.headers().clone());
+    let bytes = response.bytes().await?;
 
-        Ok(retry_response)
-    } else {
-        // No payment required, return original response
-        Ok(response)
-    }
+    let mut rebuilt = http::Response::new(bytes.clone());
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    Ok((Response::from(rebuilt), bytes))
 }
 
 /// Selects an appropriate payment requirement from the server's offers.
-fn select_requirement<'a>(
+///
+/// Returns the first matching offer, or the cheapest one if the client prefers it.
+async fn select_requirement<'a>(
     response: &'a PaymentRequiredResponse,
     config: &X402ClientConfig,
-) -> Result<&'a crate::types::PaymentRequirements> {
+) -> Result<&'a PaymentRequirements> {
     // Filter by preferred scheme and network if specified
     let mut candidates: Vec<_> = response.accepts.iter().collect();
 
@@ -188,27 +1172,239 @@ fn select_requirement<'a>(
         candidates.retain(|r| &r.network == network);
     }
 
-    // Return first matching requirement
-    candidates
-        .first()
-        .copied()
+    // Soft preferences only rank offers; fall back to what the server accepts, keeping
+    // to schemes the client can pay if any are offered
+    if candidates.is_empty() && !config.strict_preferences {
+        #[cfg(feature = "tracing")]
+        tracing::warn!("No offer matches the preferred scheme/network; using the best available");
+        candidates = response.accepts.iter().collect();
+        if candidates.iter().any(|r| config.implements_scheme(&r.scheme)) {
+            candidates.retain(|r| config.implements_scheme(&r.scheme));
+        }
+    }
+
+    if !config.prefer_cheapest || candidates.len() < 2 {
+        // Return first matching requirement
+        return candidates
+            .first()
+            .copied()
+            .ok_or(X402Error::NoSuitableRequirement);
+    }
+
+    // a / 10^da < b / 10^db exactly when a * 10^db < b * 10^da; ties keep server order
+    let mut cheapest: Option<(&PaymentRequirements, U256, u8)> = None;
+    for requirement in candidates {
+        let amount = amount_to_pay(requirement, config)?;
+        let decimals = asset_decimals(requirement, config).await?;
+        // The asset, chosen by the server, reports its own decimals; skip any too many
+        // to scale by
+        if decimals > MAX_COMPARABLE_DECIMALS {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Skipping offer in {} with {} decimals",
+                requirement.asset,
+                decimals
+            );
+            continue;
+        }
+        let cheaper = cheapest.map_or(true, |(_, best, best_decimals)| {
+            amount.full_mul(U256::exp10(best_decimals.into()))
+                < best.full_mul(U256::exp10(decimals.into()))
+        });
+        if cheaper {
+            cheapest = Some((requirement, amount, decimals));
+        }
+    }
+    cheapest
+        .map(|(requirement, _, _)| requirement)
         .ok_or(X402Error::NoSuitableRequirement)
 }
 
-/// Generates a payment payload for the selected requirement.
-async fn generate_payment_payload(
-    requirement: &crate::types::PaymentRequirements,
+/// Most decimals an asset can have for its offers to be compared; 10^77 is the largest
+/// power of ten a `U256` holds.
+const MAX_COMPARABLE_DECIMALS: u8 = 77;
+
+/// Returns the amount to authorize for `requirement`.
+///
+/// A `"min-max"` range in `maxAmountRequired` is refused for the `exact` scheme, which
+/// pays one fixed amount. Other schemes pay the configured range amount, or the minimum.
+fn amount_to_pay(requirement: &PaymentRequirements, config: &X402ClientConfig) -> Result<U256> {
+    let (min, max) = match requirement.required_amount()? {
+        RequiredAmount::Exact(amount) => return Ok(amount),
+        RequiredAmount::Range { min, max } => (min, max),
+    };
+    if requirement.scheme == "exact" {
+        return Err(X402Error::InvalidAmount(format!(
+            "Offer asks for a range of {} to {} but the exact scheme pays a fixed amount",
+            min, max
+        )));
+    }
+    match config.range_amount {
+        None => Ok(min),
+        Some(amount) if (min..=max).contains(&amount) => Ok(amount),
+        Some(amount) => Err(X402Error::InvalidAmount(format!(
+            "Configured amount {} is outside the offered range {} to {}",
+            amount, min, max
+        ))),
+    }
+}
+
+/// Returns the decimals of a requirement's asset, from the configuration or on-chain.
+async fn asset_decimals(
+    requirement: &PaymentRequirements,
     config: &X402ClientConfig,
-) -> Result<PaymentPayload> {
-    // Match the scheme and generate appropriate payload
-    let scheme: Arc<dyn Scheme> = match requirement.scheme.as_str() {
-        "exact" => Arc::new(ExactEvm::new()),
+) -> Result<u8> {
+    let asset = parse_address(&requirement.asset)?;
+    if let Some(&decimals) = config.token_decimals.get(&asset) {
+        return Ok(decimals);
+    }
+
+    RpcTokenClient::new(&config.rpc_url)?.decimals(asset).await
+}
+
+/// Checks the payer holds at least the requirement's amount of its asset.
+///
+/// # Errors
+///
+/// Returns `X402Error::InvalidAmount` with the shortfall if the balance is too low.
+async fn check_payer_balance(
+    requirement: &PaymentRequirements,
+    config: &X402ClientConfig,
+) -> Result<()> {
+    let payer = config
+        .private_key
+        .parse::<LocalWallet>()
+        .map_err(|e| X402Error::InvalidPayload(format!("Invalid private key: {}", e)))?
+        .address();
+    let asset = parse_address(&requirement.asset)?;
+    let amount = string_to_u256(&requirement.max_amount_required)?;
+
+    let balance = RpcTokenClient::new(&config.rpc_url)?
+        .with_rpc_retries(config.rpc_retry.max_retries, config.rpc_retry.base_delay)
+        .balance_of(asset, payer, None)
+        .await?;
+    if balance < amount {
+        return Err(X402Error::InvalidAmount(format!(
+            "Balance of {} is {} short of the {} required",
+            balance,
+            amount - balance,
+            amount
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the scheme implementation paying `requirement`.
+///
+/// A known `chain_id` skips querying the RPC for it.
+fn payment_scheme(
+    requirement: &PaymentRequirements,
+    config: &X402ClientConfig,
+    chain_id: Option<u64>,
+) -> Result<Arc<dyn Scheme>> {
+    let transfer_method = asset_transfer_method(requirement);
+    let registered = config.schemes.get(&requirement.scheme);
+    let scheme: Arc<dyn Scheme> = match (registered, requirement.scheme.as_str()) {
+        (Some(scheme), _) => scheme,
+        (None, "exact") if transfer_method == Some(ALLOWANCE_TRANSFER_METHOD) => {
+            match config.chain_id_override.or(chain_id) {
+                Some(chain_id) => Arc::new(AllowanceEvm::new().with_chain_id(chain_id)),
+                None => Arc::new(AllowanceEvm::new()),
+            }
+        }
+        (None, "exact") => {
+            let scheme = ExactEvm::new()
+                .with_valid_after_offset(config.valid_after_offset)
+                .with_rpc_retries(config.rpc_retry.max_retries, config.rpc_retry.base_delay);
+            let scheme = match config.chain_id_override.or(chain_id) {
+                Some(chain_id) => scheme.with_chain_id(chain_id),
+                None => scheme,
+            };
+            match is_split_payment(requirement) {
+                true => Arc::new(SplitEvm::new().with_exact(scheme)),
+                false => Arc::new(scheme),
+            }
+        }
+        (None, "upto") => match config.chain_id_override.or(chain_id) {
+            Some(chain_id) => Arc::new(UptoEvm::new().with_chain_id(chain_id)),
+            None => Arc::new(UptoEvm::new()),
+        },
         _ => return Err(X402Error::UnsupportedScheme(requirement.scheme.clone())),
     };
+    Ok(scheme)
+}
 
-    scheme
+/// Generates a payment payload for the selected requirement with `scheme`, which
+/// must already have prevalidated it.
+async fn generate_payment_payload(
+    scheme: &dyn Scheme,
+    requirement: &PaymentRequirements,
+    config: &X402ClientConfig,
+) -> Result<PaymentPayload> {
+    let built_in = matches!(requirement.scheme.as_str(), "exact" | "upto")
+        && !config.schemes.contains(&requirement.scheme);
+    if config.check_balance && built_in {
+        check_payer_balance(requirement, config).await?;
+    }
+
+    let mut payload = scheme
         .generate_payload(requirement, &config.private_key, &config.rpc_url)
-        .await
+        .await?;
+
+    // Echo the server's anti-probing challenge, if any
+    payload.challenge = requirement
+        .extra
+        .as_ref()
+        .and_then(|extra| extra.get("challenge"))
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    // Bind the transfer to this x402 message, once every other field is final
+    if message_signature_required(requirement) {
+        payload.message_signature = Some(sign_payment_message(&payload, &config.private_key)?);
+    }
+
+    Ok(payload)
+}
+
+/// Re-signs a payment for the same requirement with a fresh validity window and nonce.
+///
+/// Use this when a cached or pre-signed payload has expired before it could be used.
+/// The old payload must have been produced for the same scheme and network.
+///
+/// # Arguments
+///
+/// * `old_payload` - The expired payload
+/// * `requirements` - The requirement the payload was generated for
+/// * `config` - Client configuration with the signing key
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::client::{refresh_payload, X402ClientConfig};
+/// # use x402_rs::types::{PaymentPayload, PaymentRequirements};
+///
+/// # async fn example(old: PaymentPayload, requirements: PaymentRequirements) -> x402_rs::Result<()> {
+/// let config = X402ClientConfig::new("0xprivatekey", "https://mainnet.base.org");
+/// let fresh = refresh_payload(&old, &requirements, &config).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn refresh_payload(
+    old_payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+    config: &X402ClientConfig,
+) -> Result<PaymentPayload> {
+    if old_payload.scheme != requirements.scheme || old_payload.network != requirements.network {
+        return Err(X402Error::InvalidPayload(format!(
+            "Payload for {}/{} does not match requirement {}/{}",
+            old_payload.scheme, old_payload.network, requirements.scheme, requirements.network
+        )));
+    }
+
+    let scheme = payment_scheme(requirements, config, None)?;
+    scheme.prevalidate(requirements).await?;
+    generate_payment_payload(scheme.as_ref(), requirements, config).await
 }
 
 /// A simpler convenience function for GET requests.
@@ -230,7 +1426,7 @@ async fn generate_payment_payload(
 /// # }
 /// ```
 pub async fn get(config: &X402ClientConfig, url: &str) -> Result<Response> {
-    request_with_payment(config, Method::GET, url, None).await
+    X402Client::new(config.clone()).get(url).await
 }
 
 /// A simpler convenience function for POST requests.
@@ -253,13 +1449,44 @@ pub async fn get(config: &X402ClientConfig, url: &str) -> Result<Response> {
 /// # }
 /// ```
 pub async fn post(config: &X402ClientConfig, url: &str, body: Value) -> Result<Response> {
-    request_with_payment(config, Method::POST, url, Some(body)).await
+    X402Client::new(config.clone()).post(url, body).await
+}
+
+/// Fetches the payment options of `url` without paying.
+///
+/// This is a one-shot wrapper around [`X402Client::probe`], letting an agent pick an
+/// option it can afford before paying.
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::client::{X402ClientConfig, probe};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = X402ClientConfig::new(
+///     "0xprivatekey",
+///     "https://mainnet.base.org"
+/// );
+///
+/// for probed in probe(&config, "https://api.example.com/data", true).await? {
+///     println!("{} {}: {}", probed.offer.scheme, probed.offer.network, probed.affordable);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn probe(
+    config: &X402ClientConfig,
+    url: &str,
+    check_balances: bool,
+) -> Result<Vec<ProbedOffer>> {
+    X402Client::new(config.clone())
+        .probe(url, check_balances)
+        .await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::PaymentRequirements;
 
     #[test]
     fn test_client_config_creation() {
@@ -279,8 +1506,65 @@ mod tests {
         assert_eq!(config.preferred_network, Some("8453".to_string()));
     }
 
+    #[cfg(all(feature = "schema", feature = "server"))]
+    #[test]
+    fn test_validate_response_against_schema() {
+        use serde_json::json;
+
+        let mut requirements = crate::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Weather",
+            "https://x402.org/facilitator",
+        )
+        .to_requirements("/weather")
+        .unwrap();
+
+        // Without a schema anything passes
+        validate_response_against_schema(&json!("anything"), &requirements).unwrap();
+
+        requirements.output_schema = Some(json!({
+            "type": "object",
+            "properties": {
+                "city": { "type": "string" },
+                "temperature": { "type": "number" }
+            },
+            "required": ["city", "temperature"]
+        }));
+        let conforming = json!({ "city": "Lisbon", "temperature": 21.5 });
+        validate_response_against_schema(&conforming, &requirements).unwrap();
+
+        let wrong_type = json!({ "city": "Lisbon", "temperature": "warm" });
+        let err = validate_response_against_schema(&wrong_type, &requirements).unwrap_err();
+        assert!(
+            matches!(&err, X402Error::InvalidResponse(reason) if reason.contains("/temperature")),
+            "{}",
+            err
+        );
+        let missing = json!({ "city": "Lisbon" });
+        assert!(matches!(
+            validate_response_against_schema(&missing, &requirements),
+            Err(X402Error::InvalidResponse(_))
+        ));
+
+        requirements.output_schema = Some(json!({ "type": "no such type" }));
+        assert!(matches!(
+            validate_response_against_schema(&conforming, &requirements),
+            Err(X402Error::ConfigError(_))
+        ));
+    }
+
     #[test]
-    fn test_select_requirement() {
+    fn test_accept_payment_header() {
+        let config = X402ClientConfig::new("0xkey", "https://rpc.url");
+        assert_eq!(config.accept_payment_header(), Some("exact".to_string()));
+
+        let config = config.with_network("8453");
+        assert_eq!(config.accept_payment_header(), Some("exact/8453".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_select_requirement() {
         let response = PaymentRequiredResponse {
             x402_version: 1,
             accepts: vec![
@@ -288,6 +1572,7 @@ mod tests {
                     scheme: "exact".to_string(),
                     network: "8453".to_string(),
                     max_amount_required: "10000".to_string(),
+                    min_amount_required: None,
                     resource: "/api/test".to_string(),
                     description: None,
                     mime_type: None,
@@ -302,8 +1587,252 @@ mod tests {
         };
 
         let config = X402ClientConfig::new("0xkey", "https://rpc.url");
-        let requirement = select_requirement(&response, &config).unwrap();
+        let requirement = select_requirement(&response, &config).await.unwrap();
         assert_eq!(requirement.scheme, "exact");
     }
-}
 
+    #[tokio::test]
+    async fn test_soft_preferences_fall_back_to_only_offer() {
+        let response = PaymentRequiredResponse {
+            x402_version: 1,
+            accepts: vec![PaymentRequirements {
+                scheme: "exact".to_string(),
+                network: "137".to_string(),
+                max_amount_required: "10000".to_string(),
+                min_amount_required: None,
+                resource: "/api/test".to_string(),
+                description: None,
+                mime_type: None,
+                output_schema: None,
+                pay_to: "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEb".to_string(),
+                max_timeout_seconds: 300,
+                asset: "0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359".to_string(),
+                extra: None,
+            }],
+            error: None,
+        };
+
+        let strict = X402ClientConfig::new("0xkey", "https://rpc.url").with_network("8453");
+        assert!(matches!(
+            select_requirement(&response, &strict).await,
+            Err(X402Error::NoSuitableRequirement)
+        ));
+
+        let soft = strict.with_strict_preferences(false);
+        let requirement = select_requirement(&response, &soft).await.unwrap();
+        assert_eq!(requirement.network, "137");
+    }
+
+    /// Stand-in "upto" scheme that only generates payloads.
+    #[cfg(feature = "server")]
+    struct UptoScheme;
+
+    #[cfg(feature = "server")]
+    #[async_trait::async_trait]
+    impl Scheme for UptoScheme {
+        fn name(&self) -> &str {
+            "upto"
+        }
+
+        async fn generate_payload(
+            &self,
+            requirements: &PaymentRequirements,
+            _private_key: &str,
+            _rpc_url: &str,
+        ) -> Result<PaymentPayload> {
+            Ok(PaymentPayload {
+                x402_version: 1,
+                scheme: self.name().to_string(),
+                network: requirements.network.clone(),
+                payload: serde_json::json!({ "value": requirements.max_amount_required }),
+                challenge: None,
+                message_signature: None,
+            })
+        }
+
+        async fn verify(
+            &self,
+            _payload: &PaymentPayload,
+            _requirements: &PaymentRequirements,
+            _rpc_url: &str,
+        ) -> Result<bool> {
+            Err(X402Error::UnsupportedScheme("upto".to_string()))
+        }
+
+        async fn settle(
+            &self,
+            _payload: &PaymentPayload,
+            _requirements: &PaymentRequirements,
+            _rpc_url: &str,
+            _facilitator_key: &str,
+        ) -> Result<crate::types::SettlementInfo> {
+            Err(X402Error::UnsupportedScheme("upto".to_string()))
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_pays_with_registered_scheme_when_preferred_is_not_offered() {
+        let mut upto = crate::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        )
+        .to_requirements("/test")
+        .unwrap();
+        upto.scheme = "upto".to_string();
+        let mut other = upto.clone();
+        other.scheme = "stream".to_string();
+        let response = PaymentRequiredResponse {
+            x402_version: 1,
+            accepts: vec![other, upto],
+            error: None,
+        };
+
+        let strict = X402ClientConfig::new("0xkey", "https://rpc.url")
+            .with_registered_scheme(UptoScheme);
+        assert!(matches!(
+            select_requirement(&response, &strict).await,
+            Err(X402Error::NoSuitableRequirement)
+        ));
+
+        // The unknown "stream" offer comes first but can't be paid
+        let soft = strict.with_strict_preferences(false);
+        let requirement = select_requirement(&response, &soft).await.unwrap();
+        assert_eq!(requirement.scheme, "upto");
+        let scheme = payment_scheme(requirement, &soft, None).unwrap();
+        scheme.prevalidate(requirement).await.unwrap();
+        let payload = generate_payment_payload(scheme.as_ref(), requirement, &soft)
+            .await
+            .unwrap();
+        assert_eq!(payload.scheme, "upto");
+        assert_eq!(payload.payload["value"], "10000");
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_amount_range_in_max_amount_required() {
+        let offer = |scheme: &str, amount: &str| {
+            let mut requirement = crate::server::create_simple_config(
+                "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+                0.01,
+                "Test",
+                "https://facilitator.test",
+            )
+            .to_requirements("/test")
+            .unwrap();
+            requirement.scheme = scheme.to_string();
+            requirement.max_amount_required = amount.to_string();
+            requirement
+        };
+        let config = X402ClientConfig::new("0xkey", "https://rpc.url");
+
+        // A valid range: the exact scheme refuses it, upto pays the minimum or the
+        // configured amount within range
+        let err = amount_to_pay(&offer("exact", "10000-20000"), &config).unwrap_err();
+        assert!(
+            matches!(&err, X402Error::InvalidAmount(reason)
+                if reason.contains("range of 10000 to 20000")),
+            "{}",
+            err
+        );
+        let upto = offer("upto", "10000-20000");
+        assert_eq!(amount_to_pay(&upto, &config).unwrap(), U256::from(10_000));
+        let config = config.with_range_amount(U256::from(15_000));
+        assert_eq!(amount_to_pay(&upto, &config).unwrap(), U256::from(15_000));
+        let narrow = offer("upto", "20000-30000");
+        assert!(matches!(
+            amount_to_pay(&narrow, &config),
+            Err(X402Error::InvalidAmount(_))
+        ));
+        assert_eq!(
+            amount_to_pay(&offer("exact", "10000"), &config).unwrap(),
+            U256::from(10_000)
+        );
+
+        // Malformed ranges are refused whatever the scheme
+        for malformed in ["10000-", "-20000", "20000-10000", "10000-20000-30000", "0x10-0x20"] {
+            let err = amount_to_pay(&offer("upto", malformed), &config).unwrap_err();
+            assert!(matches!(err, X402Error::InvalidAmount(_)), "{}: {}", malformed, err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select_cheapest_requirement_across_decimals() {
+        let usdc: Address = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".parse().unwrap();
+        let dai: Address = "0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb".parse().unwrap();
+        let bogus: Address = "0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359".parse().unwrap();
+        let offer = |asset: Address, amount: &str| PaymentRequirements {
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            max_amount_required: amount.to_string(),
+            min_amount_required: None,
+            resource: "/api/test".to_string(),
+            description: None,
+            mime_type: None,
+            output_schema: None,
+            pay_to: "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEb".to_string(),
+            max_timeout_seconds: 300,
+            asset: format!("{:?}", asset),
+            extra: None,
+        };
+        let response = |accepts| PaymentRequiredResponse {
+            x402_version: 1,
+            accepts,
+            error: None,
+        };
+        let config = X402ClientConfig::new("0xkey", "https://rpc.url")
+            .with_cheapest_requirement()
+            .with_token_decimals(HashMap::from([(usdc, 6), (dai, 18), (bogus, 255)]));
+
+        // $0.01 in USDC against $0.009 in DAI: DAI is cheaper despite far more units
+        let offers = response(vec![offer(usdc, "10000"), offer(dai, "9000000000000000")]);
+        let requirement = select_requirement(&offers, &config).await.unwrap();
+        assert_eq!(requirement.asset, format!("{:?}", dai));
+
+        // $0.01 in both: the server's order breaks the tie
+        let offers = response(vec![offer(usdc, "10000"), offer(dai, "10000000000000000")]);
+        let requirement = select_requirement(&offers, &config).await.unwrap();
+        assert_eq!(requirement.asset, format!("{:?}", usdc));
+
+        // $0.01 in DAI against $0.005 in USDC, with USDC listed second
+        let offers = response(vec![offer(dai, "10000000000000000"), offer(usdc, "5000")]);
+        let requirement = select_requirement(&offers, &config).await.unwrap();
+        assert_eq!(requirement.asset, format!("{:?}", usdc));
+
+        // An asset claiming more decimals than a U256 can scale by is skipped
+        let offers = response(vec![offer(bogus, "1"), offer(usdc, "10000")]);
+        let requirement = select_requirement(&offers, &config).await.unwrap();
+        assert_eq!(requirement.asset, format!("{:?}", usdc));
+    }
+
+    #[test]
+    fn test_client_struct_config() {
+        let client = X402Client::new(
+            X402ClientConfig::new("0xkey", "https://rpc.url")
+                .with_scheme("upto")
+                .with_network("8453"),
+        );
+
+        assert_eq!(client.config().private_key, "0xkey");
+        assert_eq!(client.config().rpc_url, "https://rpc.url");
+        assert_eq!(client.config().accept_payment_header(), Some("upto/8453".to_string()));
+    }
+
+    #[test]
+    fn test_require_https_for_payments() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let config = X402ClientConfig::new("0xkey", "https://rpc.url");
+
+        assert!(config.require_https);
+        assert!(!config.allows_payment_to(&url("http://api.example.com/paid")));
+        assert!(config.allows_payment_to(&url("https://api.example.com/paid")));
+        assert!(config.allows_payment_to(&url("http://localhost:3000/paid")));
+        assert!(config.allows_payment_to(&url("http://127.0.0.1:3000/paid")));
+        assert!(config.allows_payment_to(&url("http://[::1]:3000/paid")));
+
+        let config = config.require_https(false);
+        assert!(config.allows_payment_to(&url("http://api.example.com/paid")));
+    }
+}
@@ -8,6 +8,7 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum X402Error {
     /// Error during HTTP request/response handling
+    #[cfg(feature = "evm")]
     #[error("HTTP error: {0}")]
     HttpError(#[from] reqwest::Error),
 
@@ -79,7 +80,35 @@ pub enum X402Error {
     #[error("Expected 402 Payment Required, got status: {0}")]
     Not402Response(u16),
 
+    /// Protocol version not supported by this implementation
+    #[error("Unsupported x402 version: {0}")]
+    UnsupportedVersion(u32),
+
+    /// The facilitator kept answering 429 Too Many Requests
+    #[error("Facilitator rate limited")]
+    RateLimited {
+        /// Last `Retry-After` delay the facilitator asked for, in seconds
+        retry_after_seconds: Option<u64>,
+    },
+
+    /// A payment would take cumulative spend past the client's configured cap
+    #[cfg(feature = "evm")]
+    #[error("Payment exceeds budget: {remaining} remaining")]
+    PaymentExceedsBudget {
+        /// Amount still spendable under the cap, in the smallest token unit
+        remaining: ethers::types::U256,
+    },
+
+    /// A facilitator call carried missing or invalid API key credentials
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// A paid response doesn't match the requirement's `outputSchema`
+    #[error("Response does not match output schema: {0}")]
+    InvalidResponse(String),
+
     /// Error parsing URL
+    #[cfg(feature = "evm")]
     #[error("URL parse error: {0}")]
     UrlParseError(#[from] url::ParseError),
 
@@ -91,12 +120,14 @@ pub enum X402Error {
 /// Result type alias for x402 operations.
 pub type Result<T> = std::result::Result<T, X402Error>;
 
+#[cfg(feature = "evm")]
 impl From<ethers::core::types::SignatureError> for X402Error {
     fn from(err: ethers::core::types::SignatureError) -> Self {
         X402Error::SignatureError(err.to_string())
     }
 }
 
+#[cfg(feature = "evm")]
 impl From<ethers::providers::ProviderError> for X402Error {
     fn from(err: ethers::providers::ProviderError) -> Self {
         X402Error::BlockchainError(err.to_string())
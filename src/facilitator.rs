@@ -4,38 +4,545 @@
 //! and settles transactions on-chain. This module provides the server endpoints
 //! needed to run a facilitator service.
 
+use crate::auth::RequestAuthenticator;
+use crate::cache::TtlCache;
 use crate::errors::{Result, X402Error};
-use crate::schemes::{exact_evm::ExactEvm, Scheme};
+use crate::finality::FinalityWatch;
+use crate::ledger::{Ledger, LedgerEntry, MemoryLedger};
+use crate::schemes::allowance_evm::{AllowanceEvm, ALLOWANCE_TRANSFER_METHOD};
+use crate::schemes::exact_evm::{ExactEvm, TokenVersionCache};
+use crate::schemes::split_evm::SplitEvm;
+use crate::schemes::upto_evm::UptoEvm;
+use crate::schemes::{RpcRetry, Scheme, SchemeCapabilities, TxType};
 use crate::types::{
-    SettlementRequest, SettlementResponse, SupportedKind, SupportedResponse, VerificationRequest,
-    VerificationResponse,
+    AllowanceTransfer, HealthResponse, HealthStatus, NetworkHealth, PayerNoncesResponse,
+    PaymentPayload, PaymentRequirements, SettlementRequest, SettlementResponse, SplitTransfer,
+    SupportedKind, SupportedResponse, TransferAuthorization, UptoAuthorization,
+    VerificationRequest, VerificationResponse, AUTHORIZATION_EXPIRED_REASON,
 };
-use std::collections::HashSet;
+use crate::utils::{
+    asset_transfer_method, current_timestamp, decode_payment_header_bounded, is_split_payment,
+    parse_address, string_to_u256, MAX_PAYMENT_PAYLOAD_BYTES,
+};
+use ethers::core::utils::keccak256;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, BlockNumber, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Thread-safe record of consumed `(payer, nonce)` pairs.
+///
+/// EIP-3009 nonces are scoped to the authorizer, so the store keys each entry by
+/// both the payer address and the nonce. Addresses and nonces are compared
+/// case-insensitively.
+///
+/// A store made with [`new`](Self::new) forgets its nonces on restart. Allowance
+/// transfers have no on-chain nonce to fall back on, so facilitators settling them
+/// should [`open`](Self::open) a store persisted to a file instead.
+#[derive(Clone, Debug, Default)]
+pub struct NonceStore {
+    entries: Arc<tokio::sync::RwLock<HashSet<(String, String)>>>,
+    file: Option<Arc<NonceFile>>,
+}
+
+/// File a persistent [`NonceStore`] appends its nonces to.
+#[derive(Debug)]
+struct NonceFile {
+    path: PathBuf,
+    file: std::sync::Mutex<File>,
+}
+
+impl NonceStore {
+    /// Creates an empty nonce store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a store persisted at `path`, one JSON `[payer, nonce]` pair per line,
+    /// creating the file if it doesn't exist. A `{"released": [payer, nonce]}` line
+    /// drops a pair recorded before it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if the file can't be opened or holds a line that
+    /// isn't a nonce entry.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use x402_rs::facilitator::{FacilitatorConfig, NonceStore};
+    ///
+    /// let mut config = FacilitatorConfig::new("0xkey", "https://mainnet.base.org");
+    /// config.used_nonces = NonceStore::open("/var/lib/facilitator/nonces.jsonl")?;
+    /// # Ok::<(), x402_rs::X402Error>(())
+    /// ```
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| {
+                X402Error::ConfigError(format!("Cannot open nonces {}: {}", path.display(), e))
+            })?;
+
+        let mut entries = HashSet::new();
+        for (i, line) in BufReader::new(&file).lines().enumerate() {
+            let line = line.map_err(|e| {
+                X402Error::ConfigError(format!("Cannot read nonces {}: {}", path.display(), e))
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: NonceLine = serde_json::from_str(&line).map_err(|e| {
+                X402Error::ConfigError(format!(
+                    "Invalid entry on line {} of nonces {}: {}",
+                    i + 1,
+                    path.display(),
+                    e
+                ))
+            })?;
+            match entry {
+                NonceLine::Used(from, nonce) => entries.insert(Self::key(&from, &nonce)),
+                NonceLine::Released {
+                    released: (from, nonce),
+                } => entries.remove(&Self::key(&from, &nonce)),
+            };
+        }
+
+        Ok(Self {
+            entries: Arc::new(tokio::sync::RwLock::new(entries)),
+            file: Some(Arc::new(NonceFile {
+                path,
+                file: std::sync::Mutex::new(file),
+            })),
+        })
+    }
+
+    /// Returns the path of the store's file, if it is persisted.
+    pub fn path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|file| file.path.as_path())
+    }
+
+    fn key(from: &str, nonce: &str) -> (String, String) {
+        (from.to_lowercase(), nonce.to_lowercase())
+    }
+
+    /// Returns `true` if the nonce has already been used by the given payer.
+    pub async fn contains(&self, from: &str, nonce: &str) -> bool {
+        self.entries.read().await.contains(&Self::key(from, nonce))
+    }
+
+    /// Records a nonce as used by the given payer, appending it to the store's file
+    /// if it has one.
+    ///
+    /// Returns `false` if the pair was already present.
+    pub async fn insert(&self, from: &str, nonce: &str) -> bool {
+        let key = Self::key(from, nonce);
+        let mut entries = self.entries.write().await;
+        if entries.contains(&key) {
+            return false;
+        }
+        if let Some(file) = &self.file {
+            if let Err(_e) = file.append(&NonceLine::Used(key.0.clone(), key.1.clone())) {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Cannot write nonces {}: {}", file.path.display(), _e);
+            }
+        }
+        entries.insert(key)
+    }
+
+    /// Forgets a nonce recorded as used by the given payer, so its authorization can
+    /// be settled again.
+    ///
+    /// Returns `false` if the pair wasn't present.
+    pub async fn remove(&self, from: &str, nonce: &str) -> bool {
+        let key = Self::key(from, nonce);
+        let mut entries = self.entries.write().await;
+        if !entries.remove(&key) {
+            return false;
+        }
+        if let Some(file) = &self.file {
+            if let Err(_e) = file.append(&NonceLine::Released { released: key }) {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Cannot write nonces {}: {}", file.path.display(), _e);
+            }
+        }
+        true
+    }
+
+    /// Lists all nonces consumed by the given payer, sorted for stable output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::facilitator::NonceStore;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let store = NonceStore::new();
+    /// store.insert("0xAbC", "0x01").await;
+    /// assert_eq!(store.list_for_payer("0xabc").await, vec!["0x01".to_string()]);
+    /// # });
+    /// ```
+    pub async fn list_for_payer(&self, from: &str) -> Vec<String> {
+        let from = from.to_lowercase();
+        let mut nonces: Vec<String> = self
+            .entries
+            .read()
+            .await
+            .iter()
+            .filter(|(payer, _)| *payer == from)
+            .map(|(_, nonce)| nonce.clone())
+            .collect();
+        nonces.sort();
+        nonces
+    }
+}
+
+/// Line of a [`NonceFile`].
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum NonceLine {
+    Used(String, String),
+    Released { released: (String, String) },
+}
+
+impl NonceFile {
+    fn append(&self, entry: &NonceLine) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+}
+
+/// Hashes of the payment headers accepted by `/verify`, keyed by `(payer, nonce)`.
+///
+/// `/settle` refuses a header for a verified authorization unless it is byte-for-byte
+/// the header first verified, so a payload can't be altered between the two calls.
+/// Entries expire with their authorization's `validBefore`.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::facilitator::HeaderBindings;
+///
+/// # tokio_test::block_on(async {
+/// let bindings = HeaderBindings::new();
+/// bindings.bind("0xAbC", "0x01", "eyJhIjoxfQ==", u64::MAX).await;
+/// assert!(bindings.matches("0xabc", "0x01", "eyJhIjoxfQ==").await);
+/// assert!(!bindings.matches("0xabc", "0x01", "eyAiYSI6MX0=").await);
+/// assert!(bindings.matches("0xabc", "0x02", "eyAiYSI6MX0=").await);
+/// # });
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HeaderBindings {
+    entries: Arc<tokio::sync::RwLock<HashMap<(String, String), BoundHeader>>>,
+}
+
+#[derive(Debug)]
+struct BoundHeader {
+    hash: [u8; 32],
+    expires_at: u64,
+}
+
+impl HeaderBindings {
+    /// Creates an empty set of bindings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds the payer's nonce to `header` until `expires_at`, unless it is already
+    /// bound to a header.
+    pub async fn bind(&self, from: &str, nonce: &str, header: &str, expires_at: u64) {
+        let now = crate::utils::current_timestamp();
+        let mut entries = self.entries.write().await;
+        entries.retain(|_, bound| now <= bound.expires_at);
+        entries
+            .entry(NonceStore::key(from, nonce))
+            .or_insert_with(|| BoundHeader {
+                hash: keccak256(header.as_bytes()),
+                expires_at,
+            });
+    }
+
+    /// Returns `true` if the payer's nonce is unbound or bound to exactly `header`.
+    pub async fn matches(&self, from: &str, nonce: &str, header: &str) -> bool {
+        let now = crate::utils::current_timestamp();
+        match self.entries.read().await.get(&NonceStore::key(from, nonce)) {
+            Some(bound) if now <= bound.expires_at => {
+                bound.hash == keccak256(header.as_bytes())
+            }
+            _ => true,
+        }
+    }
+
+    /// Drops the binding for the payer's nonce.
+    pub async fn release(&self, from: &str, nonce: &str) {
+        self.entries.write().await.remove(&NonceStore::key(from, nonce));
+    }
+}
+
+/// Cache of successful verifications, so repeated `/verify` calls for the same
+/// payment skip the RPC round trips.
+///
+/// Entries expire with their authorization's `validBefore` and are evicted as soon
+/// as the nonce they reference is marked used. Expired entries are swept whenever a
+/// new verification is cached.
+#[derive(Clone, Debug)]
+pub struct VerificationCache {
+    entries: TtlCache<String, (String, String)>,
+}
+
+impl Default for VerificationCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VerificationCache {
+    /// Creates an empty verification cache.
+    pub fn new() -> Self {
+        // Every entry gets its own time-to-live from its authorization
+        Self {
+            entries: TtlCache::new(Duration::ZERO),
+        }
+    }
+
+    /// Returns `true` if a live verification is cached under `key`.
+    pub async fn contains(&self, key: &str) -> bool {
+        self.entries.get(&key.to_string()).await.is_some()
+    }
+
+    /// Caches a successful verification of the payer's nonce until `expires_at`.
+    pub async fn insert(&self, key: impl Into<String>, from: &str, nonce: &str, expires_at: u64) {
+        self.entries.sweep().await;
+        let now = crate::utils::current_timestamp();
+        if expires_at < now {
+            return;
+        }
+        let ttl = Duration::from_secs(expires_at - now + 1);
+        let entry = NonceStore::key(from, nonce);
+        self.entries.insert_with_ttl(key.into(), entry, ttl).await;
+    }
+
+    /// Evicts every cached verification referencing the payer's nonce.
+    pub async fn invalidate_nonce(&self, from: &str, nonce: &str) {
+        let key = NonceStore::key(from, nonce);
+        self.entries.retain(|_, entry| *entry != key).await;
+    }
+
+    /// Returns the number of cached verifications, including expired ones not yet swept.
+    pub async fn len(&self) -> usize {
+        self.entries.len().await
+    }
+
+    /// Returns `true` if nothing is cached.
+    pub async fn is_empty(&self) -> bool {
+        self.entries.is_empty().await
+    }
+}
+
+/// Per-payer token bucket limiting how often one payer can hit `/verify` and `/settle`.
+///
+/// Each payer starts with `burst` requests, refilled at `per_second`. Payers are keyed
+/// by the authorization's signer, compared case-insensitively; clones share buckets.
+/// Buckets that have refilled completely are dropped, so idle payers cost nothing.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::facilitator::PayerRateLimiter;
+///
+/// # tokio_test::block_on(async {
+/// let limiter = PayerRateLimiter::new(1, 0.1);
+/// assert!(limiter.check("0xAbC").await.is_ok());
+/// assert!(limiter.check("0xabc").await.is_err());
+/// assert!(limiter.check("0xdef").await.is_ok());
+/// # });
+/// ```
+#[derive(Clone, Debug)]
+pub struct PayerRateLimiter {
+    burst: u32,
+    per_second: f64,
+    buckets: Arc<tokio::sync::Mutex<HashMap<String, TokenBucket>>>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl PayerRateLimiter {
+    /// Creates a limiter allowing `burst` requests per payer, refilled at `per_second`.
+    pub fn new(burst: u32, per_second: f64) -> Self {
+        Self {
+            burst,
+            per_second,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Takes a request from the payer's bucket.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::RateLimited` with the seconds until the next request is
+    /// allowed if the bucket is empty.
+    pub async fn check(&self, payer: &str) -> Result<()> {
+        let now = Instant::now();
+        let burst = f64::from(self.burst);
+        let mut buckets = self.buckets.lock().await;
+        // A full bucket is the same as no bucket
+        buckets.retain(|_, bucket| {
+            bucket.tokens + now.duration_since(bucket.refilled_at).as_secs_f64() * self.per_second
+                < burst
+        });
+        let bucket = buckets.entry(payer.to_lowercase()).or_insert(TokenBucket {
+            tokens: burst,
+            refilled_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(burst);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let retry_after_seconds = (self.per_second > 0.0)
+            .then(|| ((1.0 - bucket.tokens) / self.per_second).ceil() as u64);
+        Err(X402Error::RateLimited {
+            retry_after_seconds,
+        })
+    }
+}
 
 /// Configuration for a facilitator service.
 #[derive(Clone)]
 pub struct FacilitatorConfig {
-    /// Private key for the facilitator (to pay gas for settlements)
+    /// Default private key for the facilitator (to pay gas for settlements)
     pub private_key: String,
+
+    /// Signing key per network, taking precedence over `private_key`
+    pub signer_keys: HashMap<String, String>,
     
-    /// RPC URL for blockchain interactions
+    /// RPC URL for the default network, [`DEFAULT_NETWORK`]
     pub rpc_url: String,
+
+    /// RPC URL per network, taking precedence over `rpc_url`
+    pub rpc_urls: HashMap<String, String>,
     
     /// List of supported (scheme, network) combinations
     pub supported: Vec<(String, String)>,
     
-    /// Set of used nonces to prevent replay attacks
-    pub used_nonces: Arc<tokio::sync::RwLock<HashSet<String>>>,
+    /// Used `(payer, nonce)` pairs to prevent replay attacks
+    pub used_nonces: NonceStore,
+
+    /// Headers accepted by `/verify`, which `/settle` must match exactly
+    pub verified_headers: HeaderBindings,
+
+    /// Settlement transaction type per network (defaults to `TxType::Auto`)
+    pub tx_types: HashMap<String, TxType>,
+
+    /// EIP-712 domain chain id per network, used instead of the RPC's chain id
+    pub chain_id_overrides: HashMap<String, u64>,
+
+    /// Cache of successful verifications, disabled by default
+    pub verification_cache: Option<VerificationCache>,
+
+    /// Native balance in wei below which `/health` flags a signer account
+    pub min_signer_balance: U256,
+
+    /// EIP-712 domain versions read from tokens, including tokens without `version()`
+    pub token_versions: TokenVersionCache,
+
+    /// Smallest payment, in asset units, worth settling
+    pub min_profitable_amount: Option<U256>,
+
+    /// Oracle pricing gas in the payment asset, used to reject payments worth less than
+    /// their estimated settlement cost
+    pub price_oracle: Option<Arc<dyn AssetPriceOracle>>,
+
+    /// Per-payer limit on `/verify` and `/settle` requests, disabled by default
+    pub payer_rate_limiter: Option<PayerRateLimiter>,
+
+    /// Whether settlement checks the payee's balance rose by the authorized amount
+    pub payee_balance_check: bool,
+
+    /// Whether a domain separator pinned by the server must match the derived one
+    pub domain_separator_check: bool,
+
+    /// Checks payers hold the attestation a requirement declares
+    pub attestation_verifier: Option<Arc<dyn AttestationVerifier>>,
+
+    /// API keys servers must sign calls with, unauthenticated calls being accepted if unset
+    pub authenticator: Option<RequestAuthenticator>,
+
+    /// Watch run in the background on settlements of at least the paired amount
+    pub finality_watch: Option<(FinalityWatch, U256)>,
+
+    /// How long `/settle` waits for a settlement to confirm, in seconds, whatever the
+    /// authorization's validity window; unbounded by default
+    pub settle_timeout_seconds: Option<u64>,
+
+    /// How far ahead of now an authorization's `validAfter` may be; none by default
+    pub valid_after_skew: Duration,
+
+    /// Networks never verified or settled on, whatever `supported` lists
+    pub denied_networks: HashSet<String>,
+
+    /// Retries of RPC reads failing in transport; none by default
+    pub rpc_retry: RpcRetry,
+
+    /// Ledger every successful settlement is appended to, in memory by default
+    pub ledger: Arc<dyn Ledger>,
 }
 
+/// The `exact` scheme on Ethereum, Base and Polygon, mainnets and testnets.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::facilitator::{FacilitatorConfig, COMMON_EXACT_KINDS};
+///
+/// let config =
+///     FacilitatorConfig::new("0xkey", "https://rpc.url").with_supported(COMMON_EXACT_KINDS);
+/// assert!(config.is_supported("exact", "137"));
+/// ```
+pub const COMMON_EXACT_KINDS: &[(&str, &str)] = &[
+    ("exact", "1"),        // Ethereum mainnet
+    ("exact", "11155111"), // Sepolia
+    ("exact", "8453"),     // Base mainnet
+    ("exact", "84532"),    // Base Sepolia
+    ("exact", "137"),      // Polygon mainnet
+    ("exact", "80002"),    // Polygon Amoy
+];
+
+/// Network served by the default `rpc_url`: Base mainnet.
+pub const DEFAULT_NETWORK: &str = "8453";
+
+/// Default `min_signer_balance`: 0.001 ETH.
+pub const DEFAULT_MIN_SIGNER_BALANCE_WEI: u64 = 1_000_000_000_000_000;
+
 impl FacilitatorConfig {
     /// Creates a new facilitator configuration.
     ///
     /// # Arguments
     ///
     /// * `private_key` - Facilitator's private key (for paying gas)
-    /// * `rpc_url` - RPC endpoint URL
+    /// * `rpc_url` - RPC endpoint URL for [`DEFAULT_NETWORK`]
     ///
     /// # Examples
     ///
@@ -50,208 +557,1601 @@ impl FacilitatorConfig {
     pub fn new(private_key: impl Into<String>, rpc_url: impl Into<String>) -> Self {
         Self {
             private_key: private_key.into(),
+            signer_keys: HashMap::new(),
             rpc_url: rpc_url.into(),
-            supported: vec![("exact".to_string(), "8453".to_string())],
-            used_nonces: Arc::new(tokio::sync::RwLock::new(HashSet::new())),
+            rpc_urls: HashMap::new(),
+            supported: vec![("exact".to_string(), DEFAULT_NETWORK.to_string())],
+            used_nonces: NonceStore::new(),
+            verified_headers: HeaderBindings::new(),
+            tx_types: HashMap::new(),
+            chain_id_overrides: HashMap::new(),
+            verification_cache: None,
+            min_signer_balance: U256::from(DEFAULT_MIN_SIGNER_BALANCE_WEI),
+            token_versions: TokenVersionCache::new(),
+            min_profitable_amount: None,
+            price_oracle: None,
+            payer_rate_limiter: None,
+            payee_balance_check: false,
+            domain_separator_check: true,
+            attestation_verifier: None,
+            authenticator: None,
+            finality_watch: None,
+            settle_timeout_seconds: None,
+            valid_after_skew: Duration::ZERO,
+            denied_networks: HashSet::new(),
+            rpc_retry: RpcRetry::default(),
+            ledger: Arc::new(MemoryLedger::new()),
+        }
+    }
+
+    /// Creates a verify-only facilitator configuration, holding no signing key.
+    ///
+    /// Such a facilitator serves `/verify` for servers that settle payments themselves;
+    /// `/settle` reports that settlement is not supported and `/supported` marks each
+    /// kind as `verifyOnly`. Keys added later with [`set_signer_key`](Self::set_signer_key)
+    /// enable settlement on their networks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::facilitator::FacilitatorConfig;
+    ///
+    /// let config = FacilitatorConfig::verify_only("https://mainnet.base.org");
+    /// assert!(!config.can_settle("8453"));
+    /// ```
+    pub fn verify_only(rpc_url: impl Into<String>) -> Self {
+        Self::new("", rpc_url)
+    }
+
+    /// Creates a facilitator configuration from an encrypted Ethereum keystore (V3 JSON).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the keystore file
+    /// * `password` - Password used to encrypt the keystore
+    /// * `rpc_url` - RPC endpoint URL
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if the file can't be read or decrypted.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use x402_rs::facilitator::FacilitatorConfig;
+    ///
+    /// let config = FacilitatorConfig::from_keystore(
+    ///     "/etc/x402/facilitator.json",
+    ///     "keystore password",
+    ///     "https://mainnet.base.org",
+    /// )?;
+    /// # Ok::<(), x402_rs::X402Error>(())
+    /// ```
+    pub fn from_keystore(
+        path: impl AsRef<Path>,
+        password: impl AsRef<[u8]>,
+        rpc_url: impl Into<String>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let wallet = LocalWallet::decrypt_keystore(path, password).map_err(|e| {
+            X402Error::ConfigError(format!("Cannot decrypt keystore {}: {}", path.display(), e))
+        })?;
+        let private_key = format!("0x{}", hex::encode(wallet.signer().to_bytes()));
+
+        Ok(Self::new(private_key, rpc_url))
+    }
+
+    /// Adds a supported (scheme, network) combination.
+    ///
+    /// Adding a combination that is already supported has no effect.
+    pub fn add_supported(&mut self, scheme: impl Into<String>, network: impl Into<String>) {
+        let (scheme, network) = (scheme.into(), network.into());
+        if !self.supported.iter().any(|(s, n)| *s == scheme && *n == network) {
+            self.supported.push((scheme, network));
+        }
+    }
+
+    /// Replaces the supported (scheme, network) combinations with `kinds`, e.g.
+    /// [`COMMON_EXACT_KINDS`].
+    pub fn with_supported(mut self, kinds: &[(&str, &str)]) -> Self {
+        self.supported.clear();
+        for (scheme, network) in kinds {
+            self.add_supported(*scheme, *network);
+        }
+        self
+    }
+
+    /// Refuses every payment on a network, even one listed in `supported`, e.g. to
+    /// stop settling on an expensive mainnet without editing the supported kinds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::facilitator::{FacilitatorConfig, COMMON_EXACT_KINDS};
+    ///
+    /// let mut config =
+    ///     FacilitatorConfig::new("0xkey", "https://rpc.url").with_supported(COMMON_EXACT_KINDS);
+    /// config.add_denied_network("1");
+    ///
+    /// assert!(config.is_denied("1"));
+    /// assert!(!config.is_supported("exact", "1"));
+    /// assert!(config.is_supported("exact", "8453"));
+    /// ```
+    pub fn add_denied_network(&mut self, network: impl Into<String>) {
+        self.denied_networks.insert(network.into());
+    }
+
+    /// Returns `true` if payments on a network are refused.
+    pub fn is_denied(&self, network: &str) -> bool {
+        self.denied_networks.contains(network)
+    }
+
+    /// Sets the RPC URL used for payloads on a network.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::facilitator::FacilitatorConfig;
+    ///
+    /// let mut config = FacilitatorConfig::new("0xkey", "https://mainnet.base.org");
+    /// config.add_supported("exact", "137");
+    /// config.set_rpc_url("137", "https://polygon-rpc.com");
+    ///
+    /// assert_eq!(config.rpc_url_for("137").unwrap(), "https://polygon-rpc.com");
+    /// assert_eq!(config.rpc_url_for("8453").unwrap(), "https://mainnet.base.org");
+    /// assert!(config.rpc_url_for("84532").is_err());
+    /// ```
+    pub fn set_rpc_url(&mut self, network: impl Into<String>, rpc_url: impl Into<String>) {
+        self.rpc_urls.insert(network.into(), rpc_url.into());
+    }
+
+    /// Returns the RPC URL for a network, falling back to the default `rpc_url` for
+    /// [`DEFAULT_NETWORK`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if no RPC URL is configured for the network.
+    /// Other networks never fall back to `rpc_url`, which points at another chain.
+    pub fn rpc_url_for(&self, network: &str) -> Result<&str> {
+        match self.rpc_urls.get(network) {
+            Some(url) => Ok(url),
+            None if network == DEFAULT_NETWORK && !self.rpc_url.is_empty() => Ok(&self.rpc_url),
+            None => Err(X402Error::ConfigError(format!(
+                "No RPC URL configured for network {}",
+                network
+            ))),
         }
     }
 
-    /// Adds a supported (scheme, network) combination.
-    pub fn add_supported(&mut self, scheme: impl Into<String>, network: impl Into<String>) {
-        self.supported.push((scheme.into(), network.into()));
+    /// Sets the key used to sign settlements on a network.
+    ///
+    /// Useful when the facilitator keeps a separately funded account per chain.
+    pub fn set_signer_key(&mut self, network: impl Into<String>, private_key: impl Into<String>) {
+        self.signer_keys.insert(network.into(), private_key.into());
+    }
+
+    /// Returns the settlement signing key for a network, falling back to `private_key`.
+    pub fn signer_key_for(&self, network: &str) -> &str {
+        self.signer_keys
+            .get(network)
+            .map(String::as_str)
+            .unwrap_or(&self.private_key)
+    }
+
+    /// Returns `true` if a signing key is configured for settling on a network.
+    pub fn can_settle(&self, network: &str) -> bool {
+        !self.signer_key_for(network).is_empty()
+    }
+
+    /// Sets the settlement transaction type for a network.
+    pub fn set_tx_type(&mut self, network: impl Into<String>, tx_type: TxType) {
+        self.tx_types.insert(network.into(), tx_type);
+    }
+
+    /// Returns the settlement transaction type configured for a network.
+    pub fn tx_type_for(&self, network: &str) -> TxType {
+        self.tx_types.get(network).copied().unwrap_or_default()
+    }
+
+    /// Verifies authorizations on a network against `chain_id` instead of the chain id
+    /// its RPC reports, e.g. for a local fork.
+    pub fn set_chain_id_override(&mut self, network: impl Into<String>, chain_id: u64) {
+        self.chain_id_overrides.insert(network.into(), chain_id);
+    }
+
+    /// Rejects settling payments worth less than `amount` asset units.
+    pub fn set_min_profitable_amount(&mut self, amount: U256) {
+        self.min_profitable_amount = Some(amount);
+    }
+
+    /// Rejects settling payments worth less than their estimated gas cost, priced in the
+    /// payment asset by `oracle`.
+    pub fn set_price_oracle(&mut self, oracle: impl AssetPriceOracle + 'static) {
+        self.price_oracle = Some(Arc::new(oracle));
+    }
+
+    /// Checks payers hold the attestation declared in `extra.requiredAttestation` with
+    /// `verifier`.
+    ///
+    /// Without a verifier, payments for requirements declaring an attestation are
+    /// rejected.
+    pub fn set_attestation_verifier(&mut self, verifier: impl AttestationVerifier + 'static) {
+        self.attestation_verifier = Some(Arc::new(verifier));
+    }
+
+    /// Requires servers to sign calls with an API key, accepting `key_id` with its
+    /// shared secret. Call once per key.
+    ///
+    /// The HTTP layer checks calls with [`authenticator`](Self::authenticator) before
+    /// handing them to [`handle_verify`] or [`handle_settle`].
+    pub fn add_api_key(&mut self, key_id: impl Into<String>, secret: impl AsRef<[u8]>) {
+        self.authenticator
+            .get_or_insert_with(RequestAuthenticator::new)
+            .add_key(key_id, secret);
+    }
+
+    /// Watches settlements of at least `min_amount` asset units in the background until
+    /// they are final, so the watch's listeners learn of any dropped by a reorg.
+    pub fn set_finality_watch(&mut self, watch: FinalityWatch, min_amount: U256) {
+        self.finality_watch = Some((watch, min_amount));
+    }
+
+    /// Stops waiting for a settlement to confirm after `seconds`.
+    ///
+    /// Independent of the authorization's `validBefore`: a settle that times out fails
+    /// with `X402Error::TimeoutExceeded`, but its transaction may still be mined until
+    /// the authorization expires. The nonce stays marked as used, so the payment can't
+    /// be settled twice meanwhile, unless the settlement account sent nothing during
+    /// the settle: then the nonce is released and the payment can be settled again.
+    pub fn set_settle_timeout(&mut self, seconds: u64) {
+        self.settle_timeout_seconds = Some(seconds);
+    }
+
+    /// Accepts authorizations whose `validAfter` is up to `skew` in the future, for
+    /// payers whose clocks run ahead. Authorizations valid only later are rejected.
+    ///
+    /// `/settle` waits until such an authorization's `validAfter` has passed before
+    /// submitting it, since the token would revert it until then.
+    pub fn set_valid_after_skew(&mut self, skew: Duration) {
+        self.valid_after_skew = skew;
+    }
+
+    /// Retries RPC reads made while verifying and settling, such as the chain id and
+    /// nonce state, up to `max_retries` times when they fail in transport, backing off
+    /// exponentially from `base_delay`.
+    ///
+    /// Reverts and error responses from the node fail at once. A read still failing
+    /// after every retry is reported as a `BlockchainError` naming the attempts made.
+    pub fn set_rpc_retries(&mut self, max_retries: u32, base_delay: Duration) {
+        self.rpc_retry = RpcRetry::new(max_retries, base_delay);
+    }
+
+    /// Appends every successful settlement to `ledger`, e.g. a
+    /// [`FileLedger`](crate::ledger::FileLedger) for a durable record.
+    pub fn set_ledger(&mut self, ledger: impl Ledger + 'static) {
+        self.ledger = Arc::new(ledger);
+    }
+
+    /// Limits each payer to `burst` verify or settle requests, refilled at `per_second`.
+    ///
+    /// Payers are counted once their signature verifies; those over the limit get
+    /// `X402Error::RateLimited` instead of a verification or settlement.
+    pub fn set_payer_rate_limit(&mut self, burst: u32, per_second: f64) {
+        self.payer_rate_limiter = Some(PayerRateLimiter::new(burst, per_second));
+    }
+
+    /// Re-reads tokens' domain versions after `ttl` instead of the default of a day,
+    /// dropping versions cached so far.
+    pub fn set_token_version_ttl(&mut self, ttl: Duration) {
+        self.token_versions = TokenVersionCache::new().with_ttl(ttl);
+    }
+
+    /// Makes settlement fail unless the settlement receipt's `Transfer` events credit
+    /// the payee with exactly the authorized amount, as with fee-on-transfer tokens.
+    pub fn enable_payee_balance_check(&mut self) {
+        self.payee_balance_check = true;
+    }
+
+    /// Trusts an `extra.domainSeparator` pinned by the server as is, instead of
+    /// rejecting payments when it differs from the one derived from the asset, chain and
+    /// token metadata.
+    ///
+    /// Payments signed in another token's or chain's domain then verify, and revert on
+    /// settlement.
+    pub fn disable_domain_separator_check(&mut self) {
+        self.domain_separator_check = false;
+    }
+
+    /// Enables caching of successful verifications.
+    pub fn enable_verification_cache(&mut self) {
+        self.verification_cache = Some(VerificationCache::new());
+    }
+
+    /// Records a nonce as used by the given payer and evicts any cached
+    /// verification referencing it.
+    ///
+    /// Returns `false` if the nonce was already recorded.
+    pub async fn mark_nonce_used(&self, from: &str, nonce: &str) -> bool {
+        if let Some(cache) = &self.verification_cache {
+            cache.invalidate_nonce(from, nonce).await;
+        }
+        self.verified_headers.release(from, nonce).await;
+        self.used_nonces.insert(from, nonce).await
+    }
+
+    /// Checks if a (scheme, network) combination is supported.
+    ///
+    /// Combinations on a denied network never are.
+    pub fn is_supported(&self, scheme: &str, network: &str) -> bool {
+        !self.is_denied(network)
+            && self
+                .supported
+                .iter()
+                .any(|(s, n)| s == scheme && n == network)
+    }
+}
+
+/// Builder for [`FacilitatorConfig`].
+///
+/// Like [`FacilitatorConfig::new`], the built configuration supports `exact` on Base
+/// (`8453`) in addition to the combinations passed to [`support`](Self::support).
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::facilitator::FacilitatorConfigBuilder;
+///
+/// let config = FacilitatorConfigBuilder::new()
+///     .private_key("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+///     .rpc_url("https://mainnet.base.org")
+///     .support("exact", "137")
+///     .rpc("137", "https://polygon-rpc.com")
+///     .build()
+///     .unwrap();
+///
+/// assert!(config.is_supported("exact", "137"));
+/// assert_eq!(config.rpc_url_for("137").unwrap(), "https://polygon-rpc.com");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FacilitatorConfigBuilder {
+    private_key: Option<String>,
+    rpc_url: Option<String>,
+    supported: Vec<(String, String)>,
+    rpc_urls: Vec<(String, String)>,
+    signer_keys: Vec<(String, String)>,
+    tx_types: Vec<(String, TxType)>,
+    chain_id_overrides: Vec<(String, u64)>,
+    verification_cache: bool,
+    min_signer_balance: Option<U256>,
+    min_profitable_amount: Option<U256>,
+    payer_rate_limit: Option<(u32, f64)>,
+    payee_balance_check: bool,
+    skip_domain_separator_check: bool,
+    token_version_ttl: Option<Duration>,
+    api_keys: Vec<(String, String)>,
+    verify_only: bool,
+    settle_timeout_seconds: Option<u64>,
+    valid_after_skew: Option<Duration>,
+    denied_networks: Vec<String>,
+    rpc_retry: Option<RpcRetry>,
+}
+
+impl FacilitatorConfigBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the default private key used to pay for settlements.
+    pub fn private_key(mut self, private_key: impl Into<String>) -> Self {
+        self.private_key = Some(private_key.into());
+        self
+    }
+
+    /// Builds a verify-only configuration when no private key is set, instead of failing.
+    pub fn verify_only(mut self) -> Self {
+        self.verify_only = true;
+        self
+    }
+
+    /// Sets the RPC URL for [`DEFAULT_NETWORK`] (defaults to none, requiring
+    /// [`rpc`](Self::rpc) per network).
+    pub fn rpc_url(mut self, rpc_url: impl Into<String>) -> Self {
+        self.rpc_url = Some(rpc_url.into());
+        self
+    }
+
+    /// Adds a supported (scheme, network) combination.
+    pub fn support(mut self, scheme: impl Into<String>, network: impl Into<String>) -> Self {
+        self.supported.push((scheme.into(), network.into()));
+        self
+    }
+
+    /// Adds every (scheme, network) combination in `kinds`.
+    pub fn support_all(mut self, kinds: &[(&str, &str)]) -> Self {
+        self.supported.extend(
+            kinds
+                .iter()
+                .map(|(scheme, network)| (scheme.to_string(), network.to_string())),
+        );
+        self
+    }
+
+    /// Sets the RPC URL used for payloads on a network.
+    pub fn rpc(mut self, network: impl Into<String>, rpc_url: impl Into<String>) -> Self {
+        self.rpc_urls.push((network.into(), rpc_url.into()));
+        self
+    }
+
+    /// Sets the key used to sign settlements on a network.
+    pub fn signer(mut self, network: impl Into<String>, private_key: impl Into<String>) -> Self {
+        self.signer_keys.push((network.into(), private_key.into()));
+        self
+    }
+
+    /// Sets the settlement transaction type for a network.
+    pub fn tx_type(mut self, network: impl Into<String>, tx_type: TxType) -> Self {
+        self.tx_types.push((network.into(), tx_type));
+        self
+    }
+
+    /// Sets the EIP-712 domain chain id verified on a network, overriding the RPC's.
+    pub fn chain_id_override(mut self, network: impl Into<String>, chain_id: u64) -> Self {
+        self.chain_id_overrides.push((network.into(), chain_id));
+        self
+    }
+
+    /// Enables caching of successful verifications.
+    pub fn verification_cache(mut self) -> Self {
+        self.verification_cache = true;
+        self
+    }
+
+    /// Sets the smallest payment, in asset units, worth settling.
+    pub fn min_profitable_amount(mut self, amount: U256) -> Self {
+        self.min_profitable_amount = Some(amount);
+        self
+    }
+
+    /// Limits each payer to `burst` verify or settle requests, refilled at `per_second`.
+    pub fn payer_rate_limit(mut self, burst: u32, per_second: f64) -> Self {
+        self.payer_rate_limit = Some((burst, per_second));
+        self
+    }
+
+    /// Checks the payee's balance rose by the authorized amount after settlement.
+    pub fn payee_balance_check(mut self) -> Self {
+        self.payee_balance_check = true;
+        self
+    }
+
+    /// Trusts pinned domain separators without checking them against the derived ones.
+    pub fn skip_domain_separator_check(mut self) -> Self {
+        self.skip_domain_separator_check = true;
+        self
+    }
+
+    /// Stops waiting for settlements to confirm after `seconds`.
+    pub fn settle_timeout(mut self, seconds: u64) -> Self {
+        self.settle_timeout_seconds = Some(seconds);
+        self
+    }
+
+    /// Refuses every payment on a network, even one it supports.
+    pub fn deny_network(mut self, network: impl Into<String>) -> Self {
+        self.denied_networks.push(network.into());
+        self
+    }
+
+    /// Accepts authorizations valid up to `skew` in the future.
+    pub fn valid_after_skew(mut self, skew: Duration) -> Self {
+        self.valid_after_skew = Some(skew);
+        self
+    }
+
+    /// Retries RPC reads failing in transport up to `max_retries` times.
+    pub fn rpc_retries(mut self, max_retries: u32, base_delay: Duration) -> Self {
+        self.rpc_retry = Some(RpcRetry::new(max_retries, base_delay));
+        self
+    }
+
+    /// Sets how long tokens' domain versions are cached.
+    pub fn token_version_ttl(mut self, ttl: Duration) -> Self {
+        self.token_version_ttl = Some(ttl);
+        self
+    }
+
+    /// Requires servers to sign calls with the API key `key_id` or another added key.
+    pub fn api_key(mut self, key_id: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.api_keys.push((key_id.into(), secret.into()));
+        self
+    }
+
+    /// Sets the balance in wei below which `/health` flags a signer account.
+    pub fn min_signer_balance(mut self, wei: U256) -> Self {
+        self.min_signer_balance = Some(wei);
+        self
+    }
+
+    /// Builds the configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::MissingField` if no private key was set, unless the
+    /// configuration is [`verify_only`](Self::verify_only).
+    pub fn build(self) -> Result<FacilitatorConfig> {
+        let private_key = match self.private_key {
+            Some(private_key) => private_key,
+            None if self.verify_only => String::new(),
+            None => return Err(X402Error::MissingField("private_key".to_string())),
+        };
+
+        let mut config = FacilitatorConfig::new(private_key, self.rpc_url.unwrap_or_default());
+        for (scheme, network) in self.supported {
+            config.add_supported(scheme, network);
+        }
+        for (network, rpc_url) in self.rpc_urls {
+            config.set_rpc_url(network, rpc_url);
+        }
+        for (network, private_key) in self.signer_keys {
+            config.set_signer_key(network, private_key);
+        }
+        for (network, tx_type) in self.tx_types {
+            config.set_tx_type(network, tx_type);
+        }
+        for (network, chain_id) in self.chain_id_overrides {
+            config.set_chain_id_override(network, chain_id);
+        }
+        if self.verification_cache {
+            config.enable_verification_cache();
+        }
+        if let Some(wei) = self.min_signer_balance {
+            config.min_signer_balance = wei;
+        }
+        config.min_profitable_amount = self.min_profitable_amount;
+        if let Some((burst, per_second)) = self.payer_rate_limit {
+            config.set_payer_rate_limit(burst, per_second);
+        }
+        if self.payee_balance_check {
+            config.enable_payee_balance_check();
+        }
+        if self.skip_domain_separator_check {
+            config.disable_domain_separator_check();
+        }
+        if let Some(ttl) = self.token_version_ttl {
+            config.set_token_version_ttl(ttl);
+        }
+        for (key_id, secret) in self.api_keys {
+            config.add_api_key(key_id, secret);
+        }
+        config.settle_timeout_seconds = self.settle_timeout_seconds;
+        if let Some(skew) = self.valid_after_skew {
+            config.set_valid_after_skew(skew);
+        }
+        for network in self.denied_networks {
+            config.add_denied_network(network);
+        }
+        if let Some(retry) = self.rpc_retry {
+            config.set_rpc_retries(retry.max_retries, retry.base_delay);
+        }
+        Ok(config)
+    }
+}
+
+/// Handles the `/verify` endpoint.
+///
+/// Verifies a payment payload without executing it on-chain.
+///
+/// # Arguments
+///
+/// * `request` - Verification request with payment header and requirements
+/// * `config` - Facilitator configuration
+///
+/// # Returns
+///
+/// `VerificationResponse` indicating if the payment is valid
+///
+/// # Errors
+///
+/// Returns `X402Error::RateLimited` if the payer exceeded the configured rate limit.
+pub async fn handle_verify(
+    request: VerificationRequest,
+    config: &FacilitatorConfig,
+) -> Result<VerificationResponse> {
+    let verify = verify_payment(request, config);
+    #[cfg(feature = "otel")]
+    let verify = crate::telemetry::in_span(
+        "x402.facilitator.verify",
+        opentelemetry::trace::SpanKind::Server,
+        verify,
+    );
+    verify.await
+}
+
+/// Decodes an untrusted payment header, bounded by [`MAX_PAYMENT_PAYLOAD_BYTES`].
+fn decode_payload(payment_header: &str) -> Result<PaymentPayload> {
+    decode_payment_header_bounded(payment_header, MAX_PAYMENT_PAYLOAD_BYTES)
+}
+
+/// Verifies a payment for [`handle_verify`].
+async fn verify_payment(
+    request: VerificationRequest,
+    config: &FacilitatorConfig,
+) -> Result<VerificationResponse> {
+    // Decode payment header
+    let payload = match decode_payload(&request.payment_header) {
+        Ok(p) => p,
+        Err(e) => {
+            return Ok(VerificationResponse {
+                is_valid: false,
+                invalid_reason: Some(format!("Invalid payment header: {}", e)),
+            });
+        }
+    };
+
+    // Check if scheme/network is supported, denied networks first
+    if config.is_denied(&payload.network) {
+        return Ok(VerificationResponse {
+            is_valid: false,
+            invalid_reason: Some(format!("Network {} is denied", payload.network)),
+        });
+    }
+    if !config.is_supported(&payload.scheme, &payload.network) {
+        return Ok(VerificationResponse {
+            is_valid: false,
+            invalid_reason: Some(format!(
+                "Unsupported scheme/network: {}/{}",
+                payload.scheme, payload.network
+            )),
+        });
+    }
+
+    let cache_key = format!(
+        "{}|{}|{}",
+        request.payment_header,
+        serde_json::to_string(&request.payment_requirements)?,
+        request.actual_amount.as_deref().unwrap_or_default()
+    );
+    if let Some(cache) = &config.verification_cache {
+        if cache.contains(&cache_key).await {
+            return Ok(VerificationResponse {
+                is_valid: true,
+                invalid_reason: None,
+            });
+        }
+    }
+
+    let rpc_url = match config.rpc_url_for(&payload.network) {
+        Ok(url) => url,
+        Err(e) => {
+            return Ok(VerificationResponse {
+                is_valid: false,
+                invalid_reason: Some(e.to_string()),
+            });
+        }
+    };
+
+    // Get the appropriate scheme implementation
+    let transfer_method = asset_transfer_method(&request.payment_requirements);
+    let scheme: Arc<dyn Scheme> = match payload.scheme.as_str() {
+        "exact" if transfer_method == Some(ALLOWANCE_TRANSFER_METHOD) => {
+            match config.chain_id_overrides.get(&payload.network) {
+                Some(&chain_id) => Arc::new(AllowanceEvm::new().with_chain_id(chain_id)),
+                None => Arc::new(AllowanceEvm::new()),
+            }
+        }
+        "exact" => {
+            let scheme = ExactEvm::new()
+                .with_token_version_cache(config.token_versions.clone())
+                .with_valid_after_skew(config.valid_after_skew)
+                .with_domain_separator_check(config.domain_separator_check)
+                .with_rpc_retries(config.rpc_retry.max_retries, config.rpc_retry.base_delay);
+            let scheme = match config.chain_id_overrides.get(&payload.network) {
+                Some(&chain_id) => scheme.with_chain_id(chain_id),
+                None => scheme,
+            };
+            match is_split_payment(&request.payment_requirements) {
+                true => Arc::new(SplitEvm::new().with_exact(scheme)),
+                false => Arc::new(scheme),
+            }
+        }
+        "upto" => {
+            let scheme = match config.chain_id_overrides.get(&payload.network) {
+                Some(&chain_id) => UptoEvm::new().with_chain_id(chain_id),
+                None => UptoEvm::new(),
+            };
+            let scheme = match &request.actual_amount {
+                Some(amount) => scheme.with_actual_amount(string_to_u256(amount)?),
+                None => scheme,
+            };
+            // Name an over-charge rather than failing it as an invalid signature
+            let permit = payload.typed_payload::<UptoAuthorization>()?;
+            if let Some(reason) = scheme.charge_failure(&permit, &request.payment_requirements)? {
+                return Ok(VerificationResponse {
+                    is_valid: false,
+                    invalid_reason: Some(reason),
+                });
+            }
+            Arc::new(scheme)
+        }
+        _ => {
+            return Ok(VerificationResponse {
+                is_valid: false,
+                invalid_reason: Some(format!("Unsupported scheme: {}", payload.scheme)),
+            });
+        }
+    };
+
+    // Verify the payload
+    match scheme
+        .verify(&payload, &request.payment_requirements, rpc_url)
+        .await
+    {
+        Ok(true) => {
+            // Extract and check nonce to prevent replay
+            if let Some(auth) = payer_nonce(&payload, &request.payment_requirements) {
+                // The signature recovered to `from`, so a forged header can't drain
+                // another payer's bucket; cached verifications stay free
+                if let Some(limiter) = &config.payer_rate_limiter {
+                    limiter.check(&auth.from).await?;
+                }
+                if config.used_nonces.contains(&auth.from, &auth.nonce).await {
+                    return Ok(VerificationResponse {
+                        is_valid: false,
+                        invalid_reason: Some("Nonce already used".to_string()),
+                    });
+                }
+
+                // The signature proves who the payer is; now check what they hold
+                let requirements = &request.payment_requirements;
+                if let Some(reason) =
+                    missing_attestation(&payload, requirements, &auth.from, config).await
+                {
+                    return Ok(VerificationResponse {
+                        is_valid: false,
+                        invalid_reason: Some(reason),
+                    });
+                }
+
+                if let Ok(expires_at) = auth.valid_before.parse::<u64>() {
+                    config
+                        .verified_headers
+                        .bind(&auth.from, &auth.nonce, &request.payment_header, expires_at)
+                        .await;
+                    if let Some(cache) = &config.verification_cache {
+                        cache.insert(cache_key, &auth.from, &auth.nonce, expires_at).await;
+                    }
+                }
+            }
+
+            Ok(VerificationResponse {
+                is_valid: true,
+                invalid_reason: None,
+            })
+        }
+        Ok(false) => Ok(VerificationResponse {
+            is_valid: false,
+            // Name expiry, which clients recover from by signing again
+            invalid_reason: Some(match expired_at(&payload, &request.payment_requirements) {
+                Some(valid_before) => {
+                    format!("{} at {}", AUTHORIZATION_EXPIRED_REASON, valid_before)
+                }
+                None => "Verification failed".to_string(),
+            }),
+        }),
+        Err(e) => Ok(VerificationResponse {
+            is_valid: false,
+            invalid_reason: Some(e.to_string()),
+        }),
+    }
+}
+
+/// Returns the `validBefore` of a payload's authorization if it has passed.
+fn expired_at(payload: &PaymentPayload, requirements: &PaymentRequirements) -> Option<String> {
+    let auth = payer_nonce(payload, requirements)?;
+    let valid_before = auth.valid_before.parse::<u64>().ok()?;
+    (valid_before < current_timestamp()).then_some(auth.valid_before)
+}
+
+/// Payer, nonce and expiry of a payload's authorization, whichever way it moves the
+/// asset.
+struct PayerNonce {
+    from: String,
+    nonce: String,
+    valid_before: String,
+}
+
+/// Returns the payer, nonce and expiry of an EIP-3009, allowance or permit payload.
+///
+/// EIP-2612 permit nonces count up per token, so the same number recurs across tokens
+/// and chains; a permit's nonce is scoped to the network and asset of `requirements`.
+fn payer_nonce(payload: &PaymentPayload, requirements: &PaymentRequirements) -> Option<PayerNonce> {
+    if let Ok(auth) = payload.typed_payload::<TransferAuthorization>() {
+        return Some(PayerNonce {
+            from: auth.from,
+            nonce: auth.nonce,
+            valid_before: auth.valid_before,
+        });
+    }
+    if let Ok(transfer) = payload.typed_payload::<AllowanceTransfer>() {
+        return Some(PayerNonce {
+            from: transfer.from,
+            nonce: transfer.nonce,
+            valid_before: transfer.valid_before,
+        });
+    }
+    if let Ok(permit) = payload.typed_payload::<UptoAuthorization>() {
+        return Some(PayerNonce {
+            from: permit.owner,
+            nonce: format!(
+                "{}:{}:{}",
+                payload.network,
+                requirements.asset.to_lowercase(),
+                permit.nonce
+            ),
+            valid_before: permit.deadline,
+        });
+    }
+    // A split is tracked by its first leg, which no other payment can reuse
+    let split = payload.typed_payload::<SplitTransfer>().ok()?;
+    split.authorizations.into_iter().next().map(|auth| PayerNonce {
+        from: auth.from,
+        nonce: auth.nonce,
+        valid_before: auth.valid_before,
+    })
+}
+
+/// Returns the latest `validAfter` of an EIP-3009 or split payload's authorizations.
+fn latest_valid_after(payload: &PaymentPayload) -> Option<u64> {
+    if let Ok(auth) = payload.typed_payload::<TransferAuthorization>() {
+        return auth.valid_after.parse().ok();
+    }
+    let split = payload.typed_payload::<SplitTransfer>().ok()?;
+    split
+        .authorizations
+        .iter()
+        .filter_map(|auth| auth.valid_after.parse().ok())
+        .max()
+}
+
+/// Returns the capabilities of the facilitator's implementation of `scheme`, or `None`
+/// if it has none.
+pub fn scheme_capabilities(scheme: &str) -> Option<SchemeCapabilities> {
+    match scheme {
+        "exact" => Some(ExactEvm::new().capabilities()),
+        "upto" => Some(UptoEvm::new().capabilities()),
+        _ => None,
+    }
+}
+
+/// Handles the `/settle` endpoint.
+///
+/// Verifies and executes a payment on-chain.
+///
+/// The payment is always fully verified first, even if `/verify` was never called.
+/// Invalid payments are reported in `invalid_reason`, as `/verify` would, and in
+/// `error` for clients that only check it; failures on-chain set `error` alone.
+///
+/// # Arguments
+///
+/// * `request` - Settlement request with payment header and requirements
+/// * `config` - Facilitator configuration
+///
+/// # Returns
+///
+/// `SettlementResponse` with transaction hash if successful
+///
+/// # Errors
+///
+/// Returns `X402Error::RateLimited` if the payer exceeded the configured rate limit, or
+/// `X402Error::TimeoutExceeded` if the settlement isn't confirmed within the
+/// configured settle timeout, in which case it may still be mined.
+pub async fn handle_settle(
+    request: SettlementRequest,
+    config: &FacilitatorConfig,
+) -> Result<SettlementResponse> {
+    let settle = settle_payment(request, config);
+    #[cfg(feature = "otel")]
+    let settle = crate::telemetry::in_span(
+        "x402.facilitator.settle",
+        opentelemetry::trace::SpanKind::Server,
+        settle,
+    );
+    settle.await
+}
+
+/// A settlement refused because the payment is invalid, reported in both `error` and
+/// `invalid_reason`.
+fn invalid_settlement(reason: String) -> SettlementResponse {
+    SettlementResponse {
+        tx_hash: String::new(),
+        block_number: None,
+        error: Some(reason.clone()),
+        invalid_reason: Some(reason),
+        settlement: None,
+    }
+}
+
+/// Verifies and executes a payment for [`handle_settle`].
+async fn settle_payment(
+    request: SettlementRequest,
+    config: &FacilitatorConfig,
+) -> Result<SettlementResponse> {
+    // A verify-only facilitator has nothing to settle with
+    if let Ok(payload) = decode_payload(&request.payment_header) {
+        if config.is_denied(&payload.network) {
+            return Ok(invalid_settlement(format!(
+                "Network {} is denied",
+                payload.network
+            )));
+        }
+        if scheme_capabilities(&payload.scheme).is_some_and(|c| !c.settle) {
+            return Ok(SettlementResponse {
+                tx_hash: String::new(),
+                block_number: None,
+                error: Some(format!("Settlement not supported by scheme {}", payload.scheme)),
+                invalid_reason: None,
+                settlement: None,
+            });
+        }
+        if !config.can_settle(&payload.network) {
+            return Ok(SettlementResponse {
+                tx_hash: String::new(),
+                block_number: None,
+                error: Some(format!(
+                    "Settlement not supported: facilitator is verify-only on network {}",
+                    payload.network
+                )),
+                invalid_reason: None,
+                settlement: None,
+            });
+        }
+
+        // Settle only the exact header `/verify` accepted for this authorization
+        if let Some(auth) = payer_nonce(&payload, &request.payment_requirements) {
+            if !config
+                .verified_headers
+                .matches(&auth.from, &auth.nonce, &request.payment_header)
+                .await
+            {
+                return Ok(invalid_settlement(
+                    "Payment header differs from the verified header".to_string(),
+                ));
+            }
+        }
+    }
+
+    // First verify the payment
+    let verify_request = VerificationRequest {
+        payment_header: request.payment_header.clone(),
+        payment_requirements: request.payment_requirements.clone(),
+        actual_amount: request.actual_amount.clone(),
+    };
+
+    let verification = handle_verify(verify_request, config).await?;
+
+    if !verification.is_valid {
+        let reason = verification.invalid_reason;
+        return Ok(invalid_settlement(
+            reason.unwrap_or_else(|| "Verification failed".to_string()),
+        ));
+    }
+
+    // Decode payload
+    let payload = decode_payload(&request.payment_header)?;
+
+    // Get the scheme implementation
+    let transfer_method = asset_transfer_method(&request.payment_requirements);
+    let scheme: Arc<dyn Scheme> = match payload.scheme.as_str() {
+        "exact" if transfer_method == Some(ALLOWANCE_TRANSFER_METHOD) => {
+            let scheme = AllowanceEvm::new().with_tx_type(config.tx_type_for(&payload.network));
+            match config.chain_id_overrides.get(&payload.network) {
+                Some(&chain_id) => Arc::new(scheme.with_chain_id(chain_id)),
+                None => Arc::new(scheme),
+            }
+        }
+        "exact" => {
+            let scheme = ExactEvm::new()
+                .with_tx_type(config.tx_type_for(&payload.network))
+                .with_payee_balance_check(config.payee_balance_check)
+                .with_rpc_retries(config.rpc_retry.max_retries, config.rpc_retry.base_delay);
+            match is_split_payment(&request.payment_requirements) {
+                true => Arc::new(SplitEvm::new().with_exact(scheme)),
+                false => Arc::new(scheme),
+            }
+        }
+        "upto" => {
+            let scheme = UptoEvm::new().with_tx_type(config.tx_type_for(&payload.network));
+            let scheme = match &request.actual_amount {
+                Some(amount) => scheme.with_actual_amount(string_to_u256(amount)?),
+                None => scheme,
+            };
+            // Refuse an over-charge before the nonce is spent on it
+            let permit = payload.typed_payload::<UptoAuthorization>()?;
+            if let Some(reason) = scheme.charge_failure(&permit, &request.payment_requirements)? {
+                return Ok(invalid_settlement(reason));
+            }
+            Arc::new(scheme)
+        }
+        _ => {
+            return Ok(SettlementResponse {
+                tx_hash: String::new(),
+                block_number: None,
+                error: Some(format!("Unsupported scheme: {}", payload.scheme)),
+                invalid_reason: None,
+                settlement: None,
+            });
+        }
+    };
+
+    let rpc_url = config.rpc_url_for(&payload.network)?;
+
+    // Refuse payments worth less than settling them costs
+    match unprofitable_reason(&request, config).await {
+        Ok(None) => {}
+        Ok(Some(reason)) => return Ok(invalid_settlement(reason)),
+        Err(e) => {
+            return Ok(SettlementResponse {
+                tx_hash: String::new(),
+                block_number: None,
+                error: Some(format!("Cannot estimate settlement cost: {}", e)),
+                invalid_reason: None,
+                settlement: None,
+            });
+        }
+    }
+
+    // Mark nonce as used
+    let auth = payer_nonce(&payload, &request.payment_requirements);
+    if let Some(auth) = &auth {
+        config.mark_nonce_used(&auth.from, &auth.nonce).await;
+    }
+
+    // An authorization accepted within `valid_after_skew` would revert until its
+    // `validAfter` has passed
+    if let Some(valid_after) = latest_valid_after(&payload) {
+        let now = current_timestamp();
+        if valid_after >= now {
+            tokio::time::sleep(Duration::from_secs(valid_after + 1 - now)).await;
+        }
+    }
+
+    // Settle the payment, within the facilitator's own time bound if any
+    let signer_key = config.signer_key_for(&payload.network);
+    let settlement = scheme.settle(&payload, &request.payment_requirements, rpc_url, signer_key);
+    let settlement = match config.settle_timeout_seconds {
+        Some(seconds) => {
+            let sent_before = sent_transaction_count(rpc_url, signer_key).await;
+            match tokio::time::timeout(Duration::from_secs(seconds), settlement).await {
+                Ok(settlement) => settlement,
+                Err(_) => {
+                    // Nothing left the settlement account, so the authorization is unspent
+                    let sent_after = sent_transaction_count(rpc_url, signer_key).await;
+                    if let (Some(auth), Some(before)) = (&auth, sent_before) {
+                        if sent_after == Some(before) {
+                            config.used_nonces.remove(&auth.from, &auth.nonce).await;
+                        }
+                    }
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        "Settlement not confirmed within {}s; the authorization may still settle",
+                        seconds
+                    );
+                    return Err(X402Error::TimeoutExceeded);
+                }
+            }
+        }
+        None => settlement.await,
+    };
+    match settlement {
+        Ok(info) => {
+            // The payment has moved on-chain, so a failed write doesn't fail the settle
+            if let Err(_e) = config.ledger.append(LedgerEntry::for_settlement(&info)) {
+                #[cfg(feature = "tracing")]
+                tracing::error!(
+                    "Cannot record settlement {} in ledger: {}",
+                    info.tx_hash,
+                    _e
+                );
+            }
+            watch_finality(config, &payload, rpc_url, &info.tx_hash);
+            Ok(SettlementResponse {
+                tx_hash: info.tx_hash.clone(),
+                block_number: info.block_number,
+                error: None,
+                invalid_reason: None,
+                settlement: Some(info),
+            })
+        }
+        Err(e) => Ok(SettlementResponse {
+            tx_hash: String::new(),
+            block_number: None,
+            error: Some(e.to_string()),
+            invalid_reason: None,
+            settlement: None,
+        }),
+    }
+}
+
+/// Returns how many transactions the settlement account has sent, pending ones
+/// included, or `None` if the node can't tell.
+async fn sent_transaction_count(rpc_url: &str, signer_key: &str) -> Option<U256> {
+    let signer = signer_key.parse::<LocalWallet>().ok()?.address();
+    let provider = Provider::<Http>::try_from(rpc_url).ok()?;
+    provider
+        .get_transaction_count(signer, Some(BlockNumber::Pending.into()))
+        .await
+        .ok()
+}
+
+/// Starts the configured finality watch on a settlement worth watching.
+fn watch_finality(
+    config: &FacilitatorConfig,
+    payload: &PaymentPayload,
+    rpc_url: &str,
+    tx_hash: &str,
+) {
+    let Some((watch, min_amount)) = &config.finality_watch else {
+        return;
+    };
+    let Ok(auth) = payload.typed_payload::<TransferAuthorization>() else {
+        return;
+    };
+    let (Ok(value), Ok(tx_hash)) = (
+        crate::utils::string_to_u256(&auth.value),
+        tx_hash.parse::<H256>(),
+    ) else {
+        return;
+    };
+    if value < *min_amount {
+        return;
+    }
+
+    let (watch, rpc_url) = (watch.clone(), rpc_url.to_string());
+    tokio::spawn(async move {
+        if let Err(_e) = watch.watch(&rpc_url, tx_hash).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Finality watch for {:?} failed: {}", tx_hash, _e);
+        }
+    });
+}
+
+/// Settles a batch of payments, returning one response per request in order.
+///
+/// Each payment goes through [`handle_settle`] on its own, so one bad item never
+/// aborts the batch: failures, including those `handle_settle` would return as
+/// errors, are reported in that item's `error` or `invalid_reason`. Items are settled
+/// one after another, so an authorization appearing twice in a batch settles once
+/// and the repeat is rejected as a used nonce.
+///
+/// # Arguments
+///
+/// * `requests` - Settlement requests to process
+/// * `config` - Facilitator configuration
+pub async fn handle_settle_batch(
+    requests: Vec<SettlementRequest>,
+    config: &FacilitatorConfig,
+) -> Vec<SettlementResponse> {
+    let mut responses = Vec::with_capacity(requests.len());
+    for request in requests {
+        let response = match handle_settle(request, config).await {
+            Ok(response) => response,
+            Err(e) => SettlementResponse {
+                tx_hash: String::new(),
+                block_number: None,
+                error: Some(e.to_string()),
+                invalid_reason: None,
+                settlement: None,
+            },
+        };
+        responses.push(response);
+    }
+    responses
+}
+
+/// Explains why a payment is not worth settling, if it isn't.
+///
+/// Checks the payment's value against `min_profitable_amount` and, with a price oracle
+/// configured, against the estimated settlement cost in the payment asset. Payments
+/// whose settlement can't be simulated are only checked against the minimum.
+async fn unprofitable_reason(
+    request: &SettlementRequest,
+    config: &FacilitatorConfig,
+) -> Result<Option<String>> {
+    if config.min_profitable_amount.is_none() && config.price_oracle.is_none() {
+        return Ok(None);
+    }
+
+    let payload = decode_payload(&request.payment_header)?;
+    let value = settled_value(&payload, request)?;
+
+    if let Some(minimum) = config.min_profitable_amount.filter(|minimum| value < *minimum) {
+        return Ok(Some(format!(
+            "Payment of {} is below the minimum profitable amount of {}",
+            value, minimum
+        )));
+    }
+
+    let oracle = config
+        .price_oracle
+        .as_ref()
+        .filter(|_| can_estimate_cost(&payload, &request.payment_requirements));
+    if let Some(oracle) = oracle {
+        let cost = estimate_settlement_cost(request, config, Some(oracle.as_ref())).await?;
+        if value < cost {
+            return Ok(Some(format!(
+                "Payment of {} is below the estimated settlement cost of {}",
+                value, cost
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the amount settling `payload` would move, in the smallest unit of the asset.
+///
+/// For `upto`, that is the server's `actualAmount`, or the whole permit without one,
+/// and for a split the sum of its legs.
+fn settled_value(payload: &PaymentPayload, request: &SettlementRequest) -> Result<U256> {
+    let requirements = &request.payment_requirements;
+    match payload.scheme.as_str() {
+        "upto" => match &request.actual_amount {
+            Some(amount) => string_to_u256(amount),
+            None => string_to_u256(&payload.typed_payload::<UptoAuthorization>()?.value),
+        },
+        "exact" if asset_transfer_method(requirements) == Some(ALLOWANCE_TRANSFER_METHOD) => {
+            string_to_u256(&payload.typed_payload::<AllowanceTransfer>()?.value)
+        }
+        "exact" if is_split_payment(requirements) => {
+            let split = payload.typed_payload::<SplitTransfer>()?;
+            split
+                .authorizations
+                .iter()
+                .try_fold(U256::zero(), |total, auth| {
+                    Ok(total.saturating_add(string_to_u256(&auth.value)?))
+                })
+        }
+        _ => string_to_u256(&payload.typed_payload::<TransferAuthorization>()?.value),
+    }
+}
+
+/// Returns `true` if [`estimate_settlement_cost`] can simulate settling `payload`.
+///
+/// Only single EIP-3009 transfers are simulated; allowance and split payments aren't.
+fn can_estimate_cost(payload: &PaymentPayload, requirements: &PaymentRequirements) -> bool {
+    scheme_capabilities(&payload.scheme).is_some_and(|c| c.simulate)
+        && asset_transfer_method(requirements) != Some(ALLOWANCE_TRANSFER_METHOD)
+        && !is_split_payment(requirements)
+}
+
+/// Handles the `/supported` endpoint.
+///
+/// Returns the list of supported (scheme, network) combinations, marking those on
+/// networks without a signing key, or of schemes that can't settle, as `verifyOnly`.
+///
+/// # Arguments
+///
+/// * `config` - Facilitator configuration
+///
+/// # Returns
+///
+/// `SupportedResponse` with the list of supported payment kinds
+pub async fn handle_supported(config: &FacilitatorConfig) -> Result<SupportedResponse> {
+    // `supported` is public and may have been extended directly, so skip repeats here too
+    let mut seen = HashSet::new();
+    let supported = config
+        .supported
+        .iter()
+        .filter(|(_, network)| !config.is_denied(network))
+        .filter(|entry| seen.insert(*entry))
+        .map(|(scheme, network)| SupportedKind {
+            verify_only: !config.can_settle(network)
+                || scheme_capabilities(scheme).is_some_and(|c| !c.settle),
+            ..SupportedKind::from((scheme.as_str(), network.as_str()))
+        })
+        .collect();
+
+    Ok(SupportedResponse { supported })
+}
+
+/// Handles the `/nonces/{payer}` endpoint.
+///
+/// Lists the nonces the facilitator has recorded as consumed by a payer, for
+/// auditing and dispute resolution.
+///
+/// # Arguments
+///
+/// * `payer` - Address of the payer to query
+/// * `config` - Facilitator configuration
+///
+/// # Returns
+///
+/// `PayerNoncesResponse` with the payer's used nonces
+pub async fn handle_payer_nonces(
+    payer: &str,
+    config: &FacilitatorConfig,
+) -> Result<PayerNoncesResponse> {
+    parse_address(payer)?;
+
+    Ok(PayerNoncesResponse {
+        payer: payer.to_string(),
+        nonces: config.used_nonces.list_for_payer(payer).await,
+    })
+}
+
+/// Decides whether a payer holds an attestation, e.g. an on-chain SBT or an EAS
+/// attestation, for requirements declaring `extra.requiredAttestation`.
+#[async_trait::async_trait]
+pub trait AttestationVerifier: Send + Sync {
+    /// Returns whether `payer` holds `attestation` on `network`.
+    async fn has_attestation(&self, network: &str, payer: &str, attestation: &str)
+        -> Result<bool>;
+}
+
+/// Explains why a payer fails the attestation `requirements` declare, if they do.
+async fn missing_attestation(
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+    payer: &str,
+    config: &FacilitatorConfig,
+) -> Option<String> {
+    let attestation = crate::utils::required_attestation(requirements)?;
+    let Some(verifier) = &config.attestation_verifier else {
+        return Some(format!(
+            "Attestation {} required but no attestation verifier is configured",
+            attestation
+        ));
+    };
+    match verifier.has_attestation(&payload.network, payer, attestation).await {
+        Ok(true) => None,
+        Ok(false) => Some(format!("Payer {} lacks attestation {}", payer, attestation)),
+        Err(e) => Some(format!("Cannot check attestation {}: {}", attestation, e)),
     }
+}
 
-    /// Checks if a (scheme, network) combination is supported.
-    pub fn is_supported(&self, scheme: &str, network: &str) -> bool {
-        self.supported.iter().any(|(s, n)| s == scheme && n == network)
-    }
+/// Converts a native gas cost into units of a payment asset.
+///
+/// Used by [`estimate_settlement_cost`] to express settlement costs in the token being
+/// paid, e.g. backed by a price feed.
+#[async_trait::async_trait]
+pub trait AssetPriceOracle: Send + Sync {
+    /// Converts `wei` of the network's native currency into the smallest unit of `asset`.
+    async fn native_to_asset(&self, network: &str, asset: &str, wei: U256) -> Result<U256>;
 }
 
-/// Handles the `/verify` endpoint.
+/// Estimates what settling a payment would cost in gas.
 ///
-/// Verifies a payment payload without executing it on-chain.
+/// Estimates the gas of the settlement transaction and multiplies it by the current gas
+/// price, so operators can skip settling payments worth less than their gas.
 ///
 /// # Arguments
 ///
-/// * `request` - Verification request with payment header and requirements
+/// * `request` - Settlement request with payment header and requirements
 /// * `config` - Facilitator configuration
+/// * `oracle` - Optional oracle converting the cost into the payment asset
 ///
 /// # Returns
 ///
-/// `VerificationResponse` indicating if the payment is valid
-pub async fn handle_verify(
-    request: VerificationRequest,
+/// The cost in wei, or in the smallest unit of the payment asset if `oracle` is given
+pub async fn estimate_settlement_cost(
+    request: &SettlementRequest,
     config: &FacilitatorConfig,
-) -> Result<VerificationResponse> {
-    // Decode payment header
-    let payload = match crate::utils::decode_payment_header(&request.payment_header) {
-        Ok(p) => p,
-        Err(e) => {
-            return Ok(VerificationResponse {
-                is_valid: false,
-                invalid_reason: Some(format!("Invalid payment header: {}", e)),
-            });
-        }
-    };
-
-    // Check if scheme/network is supported
-    if !config.is_supported(&payload.scheme, &payload.network) {
-        return Ok(VerificationResponse {
-            is_valid: false,
-            invalid_reason: Some(format!(
-                "Unsupported scheme/network: {}/{}",
-                payload.scheme, payload.network
-            )),
-        });
+    oracle: Option<&dyn AssetPriceOracle>,
+) -> Result<U256> {
+    let payload = decode_payload(&request.payment_header)?;
+    if !can_estimate_cost(&payload, &request.payment_requirements) {
+        return Err(X402Error::UnsupportedScheme(payload.scheme));
     }
 
-    // Get the appropriate scheme implementation
-    let scheme: Arc<dyn Scheme> = match payload.scheme.as_str() {
-        "exact" => Arc::new(ExactEvm::new()),
-        _ => {
-            return Ok(VerificationResponse {
-                is_valid: false,
-                invalid_reason: Some(format!("Unsupported scheme: {}", payload.scheme)),
-            });
-        }
-    };
+    let requirements = &request.payment_requirements;
+    let rpc_url = config.rpc_url_for(&payload.network)?;
+    let gas = ExactEvm::new()
+        .with_tx_type(config.tx_type_for(&payload.network))
+        .with_rpc_retries(config.rpc_retry.max_retries, config.rpc_retry.base_delay)
+        .estimate_settlement_gas(
+            &payload,
+            requirements,
+            rpc_url,
+            config.signer_key_for(&payload.network),
+        )
+        .await?;
 
-    // Verify the payload
-    match scheme
-        .verify(&payload, &request.payment_requirements, &config.rpc_url)
-        .await
-    {
-        Ok(true) => {
-            // Extract and check nonce to prevent replay
-            if let Ok(auth) = serde_json::from_value::<crate::types::TransferAuthorization>(
-                payload.payload.clone(),
-            ) {
-                let mut nonces = config.used_nonces.write().await;
-                if nonces.contains(&auth.nonce) {
-                    return Ok(VerificationResponse {
-                        is_valid: false,
-                        invalid_reason: Some("Nonce already used".to_string()),
-                    });
-                }
-            }
+    let provider = Provider::<Http>::try_from(rpc_url)
+        .map_err(|e| X402Error::ConfigError(format!("Invalid RPC URL: {}", e)))?;
+    let cost = gas.saturating_mul(provider.get_gas_price().await?);
 
-            Ok(VerificationResponse {
-                is_valid: true,
-                invalid_reason: None,
-            })
+    match oracle {
+        Some(oracle) => {
+            oracle
+                .native_to_asset(&payload.network, &requirements.asset, cost)
+                .await
         }
-        Ok(false) => Ok(VerificationResponse {
-            is_valid: false,
-            invalid_reason: Some("Verification failed".to_string()),
-        }),
-        Err(e) => Ok(VerificationResponse {
-            is_valid: false,
-            invalid_reason: Some(e.to_string()),
-        }),
+        None => Ok(cost),
     }
 }
 
-/// Handles the `/settle` endpoint.
+/// Handles the `/health` endpoint.
 ///
-/// Verifies and executes a payment on-chain.
+/// Queries the native balance of the settlement signer on every supported network, so
+/// operators notice before the facilitator can no longer pay gas. Verify-only networks
+/// are left out.
 ///
 /// # Arguments
 ///
-/// * `request` - Settlement request with payment header and requirements
 /// * `config` - Facilitator configuration
 ///
 /// # Returns
 ///
-/// `SettlementResponse` with transaction hash if successful
-pub async fn handle_settle(
-    request: SettlementRequest,
-    config: &FacilitatorConfig,
-) -> Result<SettlementResponse> {
-    // First verify the payment
-    let verify_request = VerificationRequest {
-        payment_header: request.payment_header.clone(),
-        payment_requirements: request.payment_requirements.clone(),
-    };
+/// `HealthResponse` that is `Degraded` when a signer is below `min_signer_balance` and
+/// `Unhealthy` when a balance can't be queried
+pub async fn handle_health(config: &FacilitatorConfig) -> Result<HealthResponse> {
+    let mut seen = HashSet::new();
+    let mut networks = Vec::new();
 
-    let verification = handle_verify(verify_request, config).await?;
+    for (_, network) in &config.supported {
+        // Verify-only and denied networks have no signer to fund
+        if !seen.insert(network.as_str())
+            || !config.can_settle(network)
+            || config.is_denied(network)
+        {
+            continue;
+        }
 
-    if !verification.is_valid {
-        return Ok(SettlementResponse {
-            tx_hash: String::new(),
-            block_number: None,
-            error: verification.invalid_reason,
+        let signer = config
+            .signer_key_for(network)
+            .parse::<LocalWallet>()
+            .ok()
+            .map(|wallet| wallet.address());
+        let balance = match signer {
+            Some(address) => signer_balance(config, network, address).await,
+            None => Err(X402Error::ConfigError("Invalid facilitator key".to_string())),
+        };
+
+        networks.push(NetworkHealth {
+            network: network.clone(),
+            signer: signer.map(|a| format!("{:?}", a)).unwrap_or_default(),
+            low_balance: balance
+                .as_ref()
+                .is_ok_and(|balance| *balance < config.min_signer_balance),
+            balance: balance.as_ref().ok().map(|b| b.to_string()),
+            error: balance.err().map(|e| e.to_string()),
         });
     }
 
-    // Decode payload
-    let payload = crate::utils::decode_payment_header(&request.payment_header)?;
-
-    // Get the scheme implementation
-    let scheme: Arc<dyn Scheme> = match payload.scheme.as_str() {
-        "exact" => Arc::new(ExactEvm::new()),
-        _ => {
-            return Ok(SettlementResponse {
-                tx_hash: String::new(),
-                block_number: None,
-                error: Some(format!("Unsupported scheme: {}", payload.scheme)),
-            });
-        }
+    let status = if networks.iter().any(|n| n.error.is_some()) {
+        HealthStatus::Unhealthy
+    } else if networks.iter().any(|n| n.low_balance) {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
     };
 
-    // Mark nonce as used
-    if let Ok(auth) =
-        serde_json::from_value::<crate::types::TransferAuthorization>(payload.payload.clone())
-    {
-        let mut nonces = config.used_nonces.write().await;
-        nonces.insert(auth.nonce.clone());
-    }
+    Ok(HealthResponse { status, networks })
+}
 
-    // Settle the payment
-    match scheme
-        .settle(
-            &payload,
-            &request.payment_requirements,
-            &config.rpc_url,
-            &config.private_key,
-        )
-        .await
-    {
-        Ok(tx_hash) => Ok(SettlementResponse {
-            tx_hash,
-            block_number: None,
-            error: None,
-        }),
-        Err(e) => Ok(SettlementResponse {
-            tx_hash: String::new(),
-            block_number: None,
-            error: Some(e.to_string()),
-        }),
-    }
+/// Queries the native balance of a signer on a network.
+async fn signer_balance(
+    config: &FacilitatorConfig,
+    network: &str,
+    address: Address,
+) -> Result<U256> {
+    let provider = Provider::<Http>::try_from(config.rpc_url_for(network)?)
+        .map_err(|e| X402Error::ConfigError(format!("Invalid RPC URL: {}", e)))?;
+    Ok(provider.get_balance(address, None).await?)
 }
 
-/// Handles the `/supported` endpoint.
+/// Facilitator running inside the server process, without HTTP.
 ///
-/// Returns the list of supported (scheme, network) combinations.
+/// Calls [`handle_verify`] and [`handle_settle`] directly, so it can be injected in
+/// place of a `FacilitatorClient` for tests and single-binary deployments. Clones
+/// share the configuration, including its `NonceStore`.
 ///
-/// # Arguments
-///
-/// * `config` - Facilitator configuration
+/// # Examples
 ///
-/// # Returns
+/// ```
+/// use x402_rs::facilitator::{FacilitatorConfig, InProcessFacilitator};
+/// use x402_rs::server::create_simple_config;
 ///
-/// `SupportedResponse` with the list of supported payment kinds
-pub async fn handle_supported(config: &FacilitatorConfig) -> Result<SupportedResponse> {
-    let supported = config
-        .supported
-        .iter()
-        .map(|(scheme, network)| SupportedKind {
-            scheme: scheme.clone(),
-            network: network.clone(),
-            assets: None, // Can be extended to list specific assets
-        })
-        .collect();
+/// let facilitator = InProcessFacilitator::new(FacilitatorConfig::new(
+///     "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+///     "https://mainnet.base.org",
+/// ));
+/// let config = create_simple_config(
+///     "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+///     0.01,
+///     "API access",
+///     "",
+/// )
+/// .with_facilitator(facilitator);
+/// ```
+#[cfg(feature = "in-process")]
+#[derive(Clone)]
+pub struct InProcessFacilitator {
+    config: Arc<FacilitatorConfig>,
+}
 
-    Ok(SupportedResponse { supported })
+#[cfg(feature = "in-process")]
+impl InProcessFacilitator {
+    /// Creates an in-process facilitator from a configuration.
+    pub fn new(config: FacilitatorConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+
+    /// Returns the facilitator configuration.
+    pub fn config(&self) -> &FacilitatorConfig {
+        &self.config
+    }
+}
+
+#[cfg(feature = "in-process")]
+impl std::fmt::Debug for InProcessFacilitator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Keep signing keys out of logs
+        f.debug_struct("InProcessFacilitator")
+            .field("supported", &self.config.supported)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "in-process")]
+#[async_trait::async_trait]
+impl crate::facilitator_client::Facilitator for InProcessFacilitator {
+    async fn verify(
+        &self,
+        payment_header: &str,
+        requirements: &crate::types::PaymentRequirements,
+        request_id: &str,
+    ) -> Result<VerificationResponse> {
+        self.verify_with_amount(payment_header, requirements, None, request_id)
+            .await
+    }
+
+    async fn verify_with_amount(
+        &self,
+        payment_header: &str,
+        requirements: &crate::types::PaymentRequirements,
+        actual_amount: Option<&str>,
+        _request_id: &str,
+    ) -> Result<VerificationResponse> {
+        let request = VerificationRequest {
+            payment_header: payment_header.to_string(),
+            payment_requirements: requirements.clone(),
+            actual_amount: actual_amount.map(str::to_string),
+        };
+        handle_verify(request, &self.config).await
+    }
+
+    async fn settle(
+        &self,
+        payment_header: &str,
+        requirements: &crate::types::PaymentRequirements,
+        request_id: &str,
+    ) -> Result<SettlementResponse> {
+        self.settle_with_amount(payment_header, requirements, None, request_id)
+            .await
+    }
+
+    async fn settle_with_amount(
+        &self,
+        payment_header: &str,
+        requirements: &crate::types::PaymentRequirements,
+        actual_amount: Option<&str>,
+        _request_id: &str,
+    ) -> Result<SettlementResponse> {
+        let request = SettlementRequest {
+            payment_header: payment_header.to_string(),
+            payment_requirements: requirements.clone(),
+            actual_amount: actual_amount.map(str::to_string),
+        };
+        handle_settle(request, &self.config).await
+    }
+
+    async fn supported(&self) -> Result<SupportedResponse> {
+        handle_supported(&self.config).await
+    }
 }
 
 #[cfg(test)]
@@ -267,6 +2167,205 @@ mod tests {
         assert!(!config.is_supported("upto", "8453"));
     }
 
+    #[test]
+    fn test_supported_kinds_from_table() {
+        let config = FacilitatorConfig::new("0xkey", "https://rpc.url")
+            .with_supported(&[("exact", "137"), ("exact", "84532"), ("exact", "137")]);
+        assert_eq!(
+            config.supported,
+            vec![
+                ("exact".to_string(), "137".to_string()),
+                ("exact".to_string(), "84532".to_string()),
+            ]
+        );
+        assert!(!config.is_supported("exact", "8453"));
+
+        let built = FacilitatorConfigBuilder::new()
+            .private_key("0xkey")
+            .support_all(COMMON_EXACT_KINDS)
+            .build()
+            .unwrap();
+        assert_eq!(built.supported.len(), COMMON_EXACT_KINDS.len());
+        for (scheme, network) in COMMON_EXACT_KINDS {
+            assert!(built.is_supported(scheme, network));
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_settle_without_verify_reports_invalid_reason() {
+        let config = FacilitatorConfig::new("0xkey", "https://rpc.url");
+        let request = SettlementRequest {
+            payment_header: "not a payment".to_string(),
+            payment_requirements: crate::server::create_simple_config(
+                "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+                0.01,
+                "Test",
+                "https://facilitator.test",
+            )
+            .to_requirements("/test")
+            .unwrap(),
+            actual_amount: None,
+        };
+
+        let response = handle_settle(request, &config).await.unwrap();
+        assert!(response.tx_hash.is_empty());
+        assert!(response
+            .invalid_reason
+            .as_ref()
+            .is_some_and(|reason| reason.starts_with("Invalid payment header")));
+        // Clients checking only `error` still see the failure
+        assert_eq!(response.error, response.invalid_reason);
+    }
+
+    #[tokio::test]
+    async fn test_nonce_store_reloads_from_file() {
+        let path = std::env::temp_dir().join(format!(
+            "x402-nonces-{}-{}.jsonl",
+            std::process::id(),
+            current_timestamp()
+        ));
+
+        let store = NonceStore::open(&path).unwrap();
+        assert_eq!(store.path(), Some(path.as_path()));
+        assert!(store.insert("0xAbC", "0x01").await);
+        assert!(!store.insert("0xabc", "0x01").await);
+        assert!(store.insert("0xabc", "0x02").await);
+
+        // A restarted facilitator still refuses both nonces
+        let reopened = NonceStore::open(&path).unwrap();
+        assert!(reopened.contains("0xabc", "0x01").await);
+        assert_eq!(reopened.list_for_payer("0xABC").await, vec!["0x01", "0x02"]);
+        assert!(!reopened.insert("0xabc", "0x02").await);
+
+        // A released nonce stays released across restarts
+        assert!(reopened.remove("0xABC", "0x01").await);
+        assert!(!reopened.remove("0xabc", "0x01").await);
+        let reopened = NonceStore::open(&path).unwrap();
+        assert_eq!(reopened.list_for_payer("0xabc").await, vec!["0x02"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verification_cache_sweeps_expired_entries() {
+        let cache = VerificationCache::new();
+        let now = current_timestamp();
+        cache.insert("expired", "0xpayer", "0x01", now - 1).await;
+        assert!(cache.is_empty().await);
+
+        cache.insert("short", "0xpayer", "0x02", now).await;
+        assert!(cache.contains("short").await);
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(!cache.contains("short").await);
+
+        cache.insert("long", "0xPAYER", "0x03", now + 60).await;
+        assert_eq!(cache.len().await, 1);
+
+        cache.invalidate_nonce("0xpayer", "0x03").await;
+        assert!(cache.is_empty().await);
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_verify_rejects_oversized_payload() {
+        let config = FacilitatorConfig::new("0xkey", "https://rpc.url");
+        let payload = PaymentPayload {
+            x402_version: 1,
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            payload: serde_json::json!({ "padding": "a".repeat(MAX_PAYMENT_PAYLOAD_BYTES) }),
+            challenge: None,
+            message_signature: None,
+        };
+        let request = VerificationRequest {
+            payment_header: crate::utils::encode_payment_header(&payload).unwrap(),
+            payment_requirements: crate::server::create_simple_config(
+                "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+                0.01,
+                "Test",
+                "https://facilitator.test",
+            )
+            .to_requirements("/test")
+            .unwrap(),
+            actual_amount: None,
+        };
+
+        let response = handle_verify(request, &config).await.unwrap();
+        assert!(!response.is_valid);
+        assert!(response
+            .invalid_reason
+            .is_some_and(|reason| reason.contains("exceeds")));
+    }
+
+    #[test]
+    fn test_builder_matches_constructor() {
+        let mut expected = FacilitatorConfig::new("0xkey", "https://rpc.url");
+        expected.add_supported("exact", "137");
+        expected.set_rpc_url("137", "https://polygon-rpc.com");
+        expected.set_signer_key("137", "0xpolygon");
+
+        let built = FacilitatorConfigBuilder::new()
+            .private_key("0xkey")
+            .rpc_url("https://rpc.url")
+            .support("exact", "137")
+            .support("exact", "137")
+            .rpc("137", "https://polygon-rpc.com")
+            .signer("137", "0xpolygon")
+            .build()
+            .unwrap();
+
+        assert_eq!(built.private_key, expected.private_key);
+        assert_eq!(built.rpc_url, expected.rpc_url);
+        assert_eq!(built.supported, expected.supported);
+        assert_eq!(built.rpc_urls, expected.rpc_urls);
+        assert_eq!(built.signer_keys, expected.signer_keys);
+        assert_eq!(built.tx_types, expected.tx_types);
+        assert!(built.verification_cache.is_none());
+    }
+
+    #[test]
+    fn test_builder_requires_private_key() {
+        let result = FacilitatorConfigBuilder::new().rpc_url("https://rpc.url").build();
+        assert!(matches!(result, Err(X402Error::MissingField(f)) if f == "private_key"));
+    }
+
+    #[test]
+    fn test_from_keystore() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/keystore.json");
+
+        let config =
+            FacilitatorConfig::from_keystore(path, "x402-test-password", "https://rpc.url").unwrap();
+        assert_eq!(
+            config.private_key,
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        );
+
+        let wrong = FacilitatorConfig::from_keystore(path, "wrong", "https://rpc.url");
+        assert!(matches!(wrong, Err(X402Error::ConfigError(_))));
+
+        let missing = FacilitatorConfig::from_keystore("/nonexistent.json", "x", "https://rpc.url");
+        assert!(matches!(missing, Err(X402Error::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_tx_type_per_network() {
+        let mut config = FacilitatorConfig::new("0xkey", "https://rpc.url");
+        config.set_tx_type("137", TxType::Legacy);
+
+        assert_eq!(config.tx_type_for("137"), TxType::Legacy);
+        assert_eq!(config.tx_type_for("8453"), TxType::Auto);
+    }
+
+    #[test]
+    fn test_signer_key_per_network() {
+        let mut config = FacilitatorConfig::new("0xdefault", "https://mainnet.base.org");
+        config.set_signer_key("137", "0xpolygon");
+
+        assert_eq!(config.signer_key_for("137"), "0xpolygon");
+        assert_eq!(config.signer_key_for("8453"), "0xdefault");
+    }
+
     #[test]
     fn test_add_supported() {
         let mut config = FacilitatorConfig::new("0xkey", "https://rpc.url");
@@ -282,5 +2381,235 @@ mod tests {
         let response = handle_supported(&config).await.unwrap();
         assert_eq!(response.supported.len(), 2);
     }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_verify_rejects_unsupported_version() {
+        let config = FacilitatorConfig::new("0xkey", "https://rpc.url");
+        let payload = crate::types::PaymentPayload {
+            x402_version: 2,
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            payload: serde_json::json!({}),
+            challenge: None,
+            message_signature: None,
+        };
+        let requirements = crate::server::create_simple_config("0xpay_to", 0.01, "Test", "")
+            .to_requirements("/test")
+            .unwrap();
+
+        let response = handle_verify(
+            VerificationRequest {
+                payment_header: crate::utils::encode_payment_header(&payload).unwrap(),
+                payment_requirements: requirements,
+                actual_amount: None,
+            },
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert!(!response.is_valid);
+        assert!(response.invalid_reason.unwrap().contains("Unsupported x402 version: 2"));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_verify_names_expired_authorization() {
+        let config = FacilitatorConfig::new("0xkey", "http://127.0.0.1:1");
+        let requirements = crate::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "",
+        )
+        .to_requirements("/test")
+        .unwrap();
+        // Signed long ago, so its window closed long ago too
+        let payload = ExactEvm::new()
+            .with_clock(|| 1_000_000)
+            .generate_payload_offline(
+                &requirements,
+                "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                8453,
+                "USD Coin",
+                "2",
+            )
+            .unwrap();
+
+        let response = handle_verify(
+            VerificationRequest {
+                payment_header: crate::utils::encode_payment_header(&payload).unwrap(),
+                payment_requirements: requirements,
+                actual_amount: None,
+            },
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert!(!response.is_valid);
+        assert_eq!(
+            response.invalid_reason.unwrap(),
+            format!("{} at 1000300", AUTHORIZATION_EXPIRED_REASON)
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_denied_network_rejected_even_if_supported() {
+        let config = FacilitatorConfigBuilder::new()
+            .private_key("0xkey")
+            .rpc_url("http://127.0.0.1:1")
+            .support_all(COMMON_EXACT_KINDS)
+            .deny_network("8453")
+            .build()
+            .unwrap();
+        assert!(config.supported.contains(&("exact".to_string(), "8453".to_string())));
+        assert!(!config.is_supported("exact", "8453"));
+
+        let requirements = crate::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "",
+        )
+        .to_requirements("/test")
+        .unwrap();
+        let payload = ExactEvm::new()
+            .generate_payload_offline(
+                &requirements,
+                "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                8453,
+                "USD Coin",
+                "2",
+            )
+            .unwrap();
+        let payment_header = crate::utils::encode_payment_header(&payload).unwrap();
+
+        // Rejected before any RPC call, which would fail on this endpoint
+        let verify = handle_verify(
+            VerificationRequest {
+                payment_header: payment_header.clone(),
+                payment_requirements: requirements.clone(),
+                actual_amount: None,
+            },
+            &config,
+        )
+        .await
+        .unwrap();
+        assert!(!verify.is_valid);
+        assert_eq!(
+            verify.invalid_reason.as_deref(),
+            Some("Network 8453 is denied")
+        );
+
+        let settle = handle_settle(
+            SettlementRequest {
+                payment_header,
+                payment_requirements: requirements,
+                actual_amount: None,
+            },
+            &config,
+        )
+        .await
+        .unwrap();
+        assert!(settle.tx_hash.is_empty());
+        assert_eq!(
+            settle.invalid_reason.as_deref(),
+            Some("Network 8453 is denied")
+        );
+
+        let supported = handle_supported(&config).await.unwrap();
+        assert!(supported
+            .supported
+            .iter()
+            .all(|kind| kind.network != "8453"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_supported_listed_once() {
+        let mut config = FacilitatorConfig::new("0xkey", "https://rpc.url");
+        config.add_supported("exact", "8453"); // already the default
+        config.add_supported("exact", "8453");
+
+        let response = handle_supported(&config).await.unwrap();
+        assert_eq!(response.supported.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_nonces_for_payer() {
+        let config = FacilitatorConfig::new("0xkey", "https://rpc.url");
+        let alice = "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb";
+        let bob = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913";
+
+        config.used_nonces.insert(alice, "0x02").await;
+        config.used_nonces.insert(alice, "0x01").await;
+        config.used_nonces.insert(bob, "0x03").await;
+
+        assert!(config.used_nonces.contains(alice, "0x01").await);
+        assert!(!config.used_nonces.contains(bob, "0x01").await);
+
+        let response = handle_payer_nonces(alice, &config).await.unwrap();
+        assert_eq!(response.nonces, vec!["0x01".to_string(), "0x02".to_string()]);
+        assert!(handle_payer_nonces("invalid", &config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_drops_refilled_buckets() {
+        let limiter = PayerRateLimiter::new(1, 1000.0);
+        limiter.check("0xabc").await.unwrap();
+        assert_eq!(limiter.buckets.lock().await.len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        limiter.check("0xdef").await.unwrap();
+        let buckets = limiter.buckets.lock().await;
+        assert!(!buckets.contains_key("0xabc"));
+        assert!(buckets.contains_key("0xdef"));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_permit_nonce_scoped_to_asset_and_network() {
+        let requirements = crate::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "",
+        )
+        .with_upto("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266")
+        .to_requirements("/test")
+        .unwrap();
+        let permit = |network: &str| PaymentPayload {
+            x402_version: 1,
+            scheme: "upto".to_string(),
+            network: network.to_string(),
+            payload: serde_json::json!({
+                "owner": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "spender": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+                "value": "10000",
+                "nonce": "0",
+                "deadline": "4000000000",
+                "signature": "0x",
+                "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+                "resource": "/test",
+                "paymentSignature": "0x",
+            }),
+            challenge: None,
+            message_signature: None,
+        };
+        let mut other_token = requirements.clone();
+        other_token.asset = "0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb".to_string();
+
+        // Each token and chain counts permit nonces from zero
+        let key = |payload: &PaymentPayload, requirements| {
+            let auth = payer_nonce(payload, requirements).unwrap();
+            NonceStore::key(&auth.from, &auth.nonce)
+        };
+        let base = key(&permit("8453"), &requirements);
+        assert_ne!(base, key(&permit("8453"), &other_token));
+        assert_ne!(base, key(&permit("84532"), &requirements));
+        assert_eq!(base, key(&permit("8453"), &requirements));
+    }
 }
 
@@ -0,0 +1,850 @@
+//! HTTP client for talking to a remote facilitator.
+//!
+//! Servers use [`FacilitatorClient`] to call a facilitator's `/verify` and `/settle`
+//! endpoints. An optional [`CircuitBreaker`] stops hammering a facilitator that is
+//! down, failing fast instead of waiting for every request to time out, and an
+//! optional [`RetryConfig`] retries calls failing with a transient status.
+//!
+//! Both the HTTP client and the in-process facilitator implement [`Facilitator`], so a
+//! server can be pointed at either one.
+
+use crate::auth::RequestSigner;
+use crate::errors::{Result, X402Error};
+use crate::types::{
+    PaymentRequirements, SettlementRequest, SettlementResponse, SupportedResponse,
+    VerificationRequest, VerificationResponse, X_REQUEST_ID_HEADER,
+};
+use async_trait::async_trait;
+use rand::Rng;
+use reqwest::header::{CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default User-Agent for facilitator calls.
+pub const DEFAULT_USER_AGENT: &str = concat!("x402-rs/", env!("CARGO_PKG_VERSION"));
+
+/// Verification and settlement backend used by servers.
+///
+/// Implemented by [`FacilitatorClient`] for remote facilitators and, with the
+/// `in-process` feature, by `facilitator::InProcessFacilitator`.
+#[async_trait]
+pub trait Facilitator: Send + Sync + fmt::Debug {
+    /// Verifies a payment header against requirements.
+    async fn verify(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        request_id: &str,
+    ) -> Result<VerificationResponse>;
+
+    /// Verifies a payment header, checking that `actual_amount` can be charged if given.
+    ///
+    /// Backends that can't check a chosen amount verify the payment alone, and
+    /// [`settle_with_amount`](Self::settle_with_amount) refuses the amount later.
+    async fn verify_with_amount(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        _actual_amount: Option<&str>,
+        request_id: &str,
+    ) -> Result<VerificationResponse> {
+        self.verify(payment_header, requirements, request_id).await
+    }
+
+    /// Settles a payment header on-chain.
+    async fn settle(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        request_id: &str,
+    ) -> Result<SettlementResponse>;
+
+    /// Settles a payment header on-chain, charging `actual_amount` if given.
+    ///
+    /// `actual_amount`, in the smallest token unit, is charged instead of the full
+    /// authorized amount by schemes that settle a server-chosen amount, such as `upto`.
+    /// Without an amount this is [`settle`](Self::settle); backends that can't charge
+    /// a chosen amount refuse one rather than settling the full authorization.
+    async fn settle_with_amount(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        actual_amount: Option<&str>,
+        request_id: &str,
+    ) -> Result<SettlementResponse> {
+        match actual_amount {
+            None => self.settle(payment_header, requirements, request_id).await,
+            Some(_) => Err(X402Error::SettlementError(
+                "Facilitator can't settle a chosen amount".to_string(),
+            )),
+        }
+    }
+
+    /// Returns the payment kinds the facilitator supports.
+    async fn supported(&self) -> Result<SupportedResponse>;
+}
+
+/// State of a [`CircuitBreaker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through normally
+    Closed,
+    /// Calls fail fast until the cooldown elapses
+    Open,
+    /// The cooldown elapsed; a single trial call closes or re-opens the circuit while
+    /// other calls keep failing fast
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_in_flight: bool,
+}
+
+/// Circuit breaker tripping after consecutive facilitator failures.
+///
+/// After `failure_threshold` consecutive failures the circuit opens and calls fail with
+/// `X402Error::VerificationFailed("facilitator circuit open")` until `cooldown` has
+/// passed. Then exactly one trial call is let through: its success closes the circuit
+/// and its failure re-opens it for another `cooldown`. Clones share state.
+///
+/// Only transport errors and non-2xx responses count as failures; a facilitator
+/// answering that a payment is invalid is working correctly.
+#[derive(Clone, Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed circuit breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `failure_threshold` - Consecutive failures that open the circuit
+    /// * `cooldown` - How long the circuit stays open before allowing a trial call
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Arc::new(Mutex::new(BreakerState::default())),
+        }
+    }
+
+    /// Returns the current state of the circuit.
+    pub fn state(&self) -> CircuitState {
+        match self.state.lock().unwrap().opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Fails fast if the circuit is open, or half-open with a trial call in flight.
+    ///
+    /// A call let through while half-open is the trial, and must report its outcome
+    /// with [`record_success`](Self::record_success) or
+    /// [`record_failure`](Self::record_failure) before another call is admitted.
+    pub fn check(&self) -> Result<()> {
+        self.admit().map(|_| ())
+    }
+
+    /// Admits a call, returning whether it is the half-open trial.
+    fn admit(&self) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        let Some(opened_at) = state.opened_at else {
+            return Ok(false);
+        };
+        if opened_at.elapsed() < self.cooldown || state.trial_in_flight {
+            return Err(X402Error::VerificationFailed(
+                "facilitator circuit open".to_string(),
+            ));
+        }
+        state.trial_in_flight = true;
+        Ok(true)
+    }
+
+    /// Records a successful call, closing the circuit.
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    /// Records a failed call, opening the circuit once the threshold is reached.
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold {
+            // Also restarts the cooldown after a failed half-open trial
+            state.opened_at = Some(Instant::now());
+        }
+        state.trial_in_flight = false;
+    }
+}
+
+/// Lets another trial through if a half-open trial call is dropped before reporting.
+struct TrialRelease<'a>(&'a CircuitBreaker);
+
+impl Drop for TrialRelease<'_> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().trial_in_flight = false;
+    }
+}
+
+/// Bounds on retrying facilitator calls answered with 429 Too Many Requests.
+///
+/// The client waits for the facilitator's `Retry-After` delay (one second if absent)
+/// and retries, giving up with `X402Error::RateLimited` once `max_retries` retries are
+/// spent or the requested delay exceeds `max_wait`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimitRetry {
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u32,
+    /// Longest `Retry-After` delay the client is willing to wait
+    pub max_wait: Duration,
+}
+
+/// Retrying of facilitator calls that fail with a transient status.
+///
+/// Calls answered with one of `retryable_statuses` (by default 429 and the 5xx
+/// statuses of an overloaded or restarting facilitator) are retried after an
+/// exponential backoff from `base_delay`, until `max_attempts` calls have been made.
+/// Other statuses, such as 400, fail at once. A `Retry-After` header on the response
+/// is honoured instead of the backoff, unless it asks for more than `max_delay`, in
+/// which case the call fails at once.
+///
+/// Each delay is shortened by a random fraction of up to `jitter`, so many servers
+/// retrying together don't hit the facilitator in lockstep.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use x402_rs::facilitator_client::{FacilitatorClient, RetryConfig};
+///
+/// let client = FacilitatorClient::new("https://facilitator.example.com")
+///     .unwrap()
+///     .with_retry(
+///         RetryConfig::new()
+///             .with_max_attempts(4)
+///             .with_base_delay(Duration::from_millis(100)),
+///     );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryConfig {
+    /// Maximum number of calls, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each retry after it
+    pub base_delay: Duration,
+    /// Largest fraction, from 0 to 1, randomly taken off each delay
+    pub jitter: f64,
+    /// Longest delay before a retry; backoff is capped to it
+    pub max_delay: Duration,
+    /// Response statuses worth retrying
+    pub retryable_statuses: Vec<u16>,
+}
+
+impl RetryConfig {
+    /// Creates a configuration making up to 3 attempts, 200ms apart at first, with
+    /// half the delay jittered and none over 30s, retrying 429, 500, 502, 503 and 504.
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            jitter: 0.5,
+            max_delay: Duration::from_secs(30),
+            retryable_statuses: vec![429, 500, 502, 503, 504],
+        }
+    }
+
+    /// Sets the maximum number of calls, including the first.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets the largest fraction of each delay taken off at random, clamped to [0, 1].
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the longest delay before a retry.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the response statuses worth retrying.
+    pub fn with_retryable_statuses(mut self, statuses: impl IntoIterator<Item = u16>) -> Self {
+        self.retryable_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Returns `true` if a call answered with `status` may be retried.
+    pub fn is_retryable(&self, status: StatusCode) -> bool {
+        self.retryable_statuses.contains(&status.as_u16())
+    }
+
+    /// Returns the jittered delay before retry number `retry`, counting from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+        backoff.mul_f64(1.0 - rand::rng().random_range(0.0..=jitter))
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses a `Retry-After` header given as delay-seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.signed_duration_since(chrono::Utc::now());
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// A facilitator call and its answer, as sent and received over HTTP.
+///
+/// Captured by a [`FacilitatorClient`] built with
+/// [`with_debug_capture`](FacilitatorClient::with_debug_capture).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawExchange {
+    /// HTTP method of the call
+    pub method: String,
+
+    /// URL called
+    pub url: String,
+
+    /// Request body, lossily decoded as UTF-8
+    pub request_body: String,
+
+    /// Response status code
+    pub status: u16,
+
+    /// Response body, lossily decoded as UTF-8
+    pub response_body: String,
+}
+
+/// Last exchange retained by a client in debug mode.
+#[derive(Default)]
+struct ExchangeCapture {
+    last: Mutex<Option<RawExchange>>,
+}
+
+impl fmt::Debug for ExchangeCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep payment headers out of logs
+        f.debug_struct("ExchangeCapture").finish_non_exhaustive()
+    }
+}
+
+/// Client for a facilitator's `/verify` and `/settle` endpoints.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use x402_rs::facilitator_client::{CircuitBreaker, FacilitatorClient};
+///
+/// let client = FacilitatorClient::new("https://facilitator.example.com")
+///     .unwrap()
+///     .with_circuit_breaker(CircuitBreaker::new(5, Duration::from_secs(30)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct FacilitatorClient {
+    url: String,
+    http: Client,
+    circuit_breaker: Option<CircuitBreaker>,
+    rate_limit_retry: Option<RateLimitRetry>,
+    retry: Option<RetryConfig>,
+    request_signer: Option<RequestSigner>,
+    capture: Option<Arc<ExchangeCapture>>,
+}
+
+impl FacilitatorClient {
+    /// Creates a client for the facilitator at `url`.
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        Self::with_user_agent(url, DEFAULT_USER_AGENT)
+    }
+
+    /// Creates a client sending a custom User-Agent.
+    pub fn with_user_agent(url: impl Into<String>, user_agent: &str) -> Result<Self> {
+        Ok(Self {
+            url: url.into(),
+            http: Client::builder().user_agent(user_agent).build()?,
+            circuit_breaker: None,
+            rate_limit_retry: None,
+            retry: None,
+            request_signer: None,
+            capture: None,
+        })
+    }
+
+    /// Guards calls with a circuit breaker.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    /// Retries calls answered with 429 Too Many Requests within `retry`'s bounds.
+    ///
+    /// Without this, a 429 fails immediately with `X402Error::RateLimited`.
+    pub fn with_rate_limit_retry(mut self, retry: RateLimitRetry) -> Self {
+        self.rate_limit_retry = Some(retry);
+        self
+    }
+
+    /// Retries verify and settle calls failing with a transient status within `retry`'s
+    /// bounds.
+    ///
+    /// A 429 is governed by [`with_rate_limit_retry`](Self::with_rate_limit_retry)
+    /// instead when that is also set.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Signs every call with an API key, for facilitators requiring authentication.
+    pub fn with_request_signer(mut self, signer: RequestSigner) -> Self {
+        self.request_signer = Some(signer);
+        self
+    }
+
+    /// Retains the raw request and response of the last call, for debugging failures.
+    ///
+    /// Off by default: the bodies hold payment headers and settlement details. Clones
+    /// share the captured exchange.
+    pub fn with_debug_capture(mut self) -> Self {
+        self.capture = Some(Arc::default());
+        self
+    }
+
+    /// Returns the last call's raw request and response, if debug capture is enabled
+    /// and a response was received.
+    pub fn last_exchange(&self) -> Option<RawExchange> {
+        let capture = self.capture.as_ref()?;
+        capture.last.lock().unwrap().clone()
+    }
+
+    /// Records an exchange if debug capture is enabled.
+    fn record_exchange(
+        &self,
+        method: &str,
+        url: &reqwest::Url,
+        request_body: &[u8],
+        status: StatusCode,
+        response_body: &[u8],
+    ) {
+        if let Some(capture) = &self.capture {
+            *capture.last.lock().unwrap() = Some(RawExchange {
+                method: method.to_string(),
+                url: url.to_string(),
+                request_body: String::from_utf8_lossy(request_body).into_owned(),
+                status: status.as_u16(),
+                response_body: String::from_utf8_lossy(response_body).into_owned(),
+            });
+        }
+    }
+
+    /// Returns the facilitator's base URL.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Calls `/verify`, returning the facilitator's verdict.
+    pub async fn verify(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        request_id: &str,
+    ) -> Result<VerificationResponse> {
+        self.verify_with_amount(payment_header, requirements, None, request_id)
+            .await
+    }
+
+    /// Calls `/verify` for charging `actual_amount`, returning the facilitator's verdict.
+    ///
+    /// `actual_amount` is sent as the request's `actualAmount`, for schemes that settle
+    /// less than was authorized.
+    pub async fn verify_with_amount(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        actual_amount: Option<&str>,
+        request_id: &str,
+    ) -> Result<VerificationResponse> {
+        let request = VerificationRequest {
+            payment_header: payment_header.to_string(),
+            payment_requirements: requirements.clone(),
+            actual_amount: actual_amount.map(str::to_string),
+        };
+        let call = self.post("verify", &request, request_id, || {
+            X402Error::VerificationFailed("Facilitator verification failed".to_string())
+        });
+        #[cfg(feature = "otel")]
+        let call = crate::telemetry::in_span(
+            "x402.facilitator_client.verify",
+            opentelemetry::trace::SpanKind::Client,
+            call,
+        );
+        call.await
+    }
+
+    /// Calls `/settle`, returning the facilitator's settlement response.
+    pub async fn settle(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        request_id: &str,
+    ) -> Result<SettlementResponse> {
+        self.settle_with_amount(payment_header, requirements, None, request_id)
+            .await
+    }
+
+    /// Calls `/settle` charging `actual_amount`, returning the settlement response.
+    ///
+    /// `actual_amount` is sent as the request's `actualAmount`, for schemes that settle
+    /// less than was authorized.
+    pub async fn settle_with_amount(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        actual_amount: Option<&str>,
+        request_id: &str,
+    ) -> Result<SettlementResponse> {
+        let request = SettlementRequest {
+            payment_header: payment_header.to_string(),
+            payment_requirements: requirements.clone(),
+            actual_amount: actual_amount.map(str::to_string),
+        };
+        let call = self.post("settle", &request, request_id, || {
+            X402Error::SettlementError("Facilitator settlement failed".to_string())
+        });
+        #[cfg(feature = "otel")]
+        let call = crate::telemetry::in_span(
+            "x402.facilitator_client.settle",
+            opentelemetry::trace::SpanKind::Client,
+            call,
+        );
+        call.await
+    }
+
+    /// Calls `/supported`, returning the payment kinds the facilitator handles.
+    pub async fn supported(&self) -> Result<SupportedResponse> {
+        let url = reqwest::Url::parse(&format!("{}/supported", self.url))?;
+        let mut request = self.http.get(url.clone());
+        if let Some(signer) = &self.request_signer {
+            request = request.headers(signer.sign("GET", url.path(), b""));
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let response_body = response.bytes().await?;
+        self.record_exchange("GET", &url, b"", status, &response_body);
+        if !status.is_success() {
+            return Err(X402Error::InvalidResponse(format!(
+                "Facilitator /supported answered {}",
+                status
+            )));
+        }
+        Ok(serde_json::from_slice(&response_body)?)
+    }
+
+    /// Posts `body` to an endpoint, feeding the outcome to the circuit breaker.
+    async fn post<B: Serialize, R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: &B,
+        request_id: &str,
+        status_error: fn() -> X402Error,
+    ) -> Result<R> {
+        let _trial = match &self.circuit_breaker {
+            Some(breaker) => breaker.admit()?.then(|| TrialRelease(breaker)),
+            None => None,
+        };
+
+        let result = async {
+            let url = reqwest::Url::parse(&format!("{}/{}", self.url, endpoint))?;
+            let body = serde_json::to_vec(body)?;
+            let mut retries = 0;
+            let mut attempts = 1;
+            loop {
+                let mut request = self
+                    .http
+                    .post(url.clone())
+                    .header(X_REQUEST_ID_HEADER, request_id)
+                    .header(CONTENT_TYPE, "application/json");
+                if let Some(signer) = &self.request_signer {
+                    request = request.headers(signer.sign("POST", url.path(), &body));
+                }
+                #[cfg(feature = "otel")]
+                if let Some(traceparent) =
+                    crate::telemetry::traceparent(&opentelemetry::Context::current())
+                {
+                    request = request.header(crate::telemetry::TRACEPARENT_HEADER, traceparent);
+                }
+                let response = request.body(body.clone()).send().await?;
+                let status = response.status();
+                let retry_after = response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_retry_after);
+                let response_body = response.bytes().await?;
+                self.record_exchange("POST", &url, &body, status, &response_body);
+
+                let rate_limited = status == StatusCode::TOO_MANY_REQUESTS;
+                if let (true, Some(retry)) = (rate_limited, self.rate_limit_retry) {
+                    let delay = retry_after.unwrap_or(Duration::from_secs(1));
+                    if retries < retry.max_retries && delay <= retry.max_wait {
+                        retries += 1;
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                } else if let Some(retry) = &self.retry {
+                    let delay = retry_after.unwrap_or_else(|| retry.delay(attempts));
+                    if retry.is_retryable(status)
+                        && attempts < retry.max_attempts
+                        && delay <= retry.max_delay
+                    {
+                        tokio::time::sleep(delay).await;
+                        attempts += 1;
+                        continue;
+                    }
+                }
+                if rate_limited {
+                    return Err(X402Error::RateLimited {
+                        retry_after_seconds: retry_after.map(|d| d.as_secs()),
+                    });
+                }
+
+                if !status.is_success() {
+                    return Err(status_error());
+                }
+                return Ok(serde_json::from_slice(&response_body)?);
+            }
+        }
+        .await;
+
+        if let Some(breaker) = &self.circuit_breaker {
+            match result {
+                Ok(_) => breaker.record_success(),
+                Err(_) => breaker.record_failure(),
+            }
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl Facilitator for FacilitatorClient {
+    async fn verify(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        request_id: &str,
+    ) -> Result<VerificationResponse> {
+        FacilitatorClient::verify(self, payment_header, requirements, request_id).await
+    }
+
+    async fn verify_with_amount(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        actual_amount: Option<&str>,
+        request_id: &str,
+    ) -> Result<VerificationResponse> {
+        FacilitatorClient::verify_with_amount(
+            self,
+            payment_header,
+            requirements,
+            actual_amount,
+            request_id,
+        )
+        .await
+    }
+
+    async fn settle(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        request_id: &str,
+    ) -> Result<SettlementResponse> {
+        FacilitatorClient::settle(self, payment_header, requirements, request_id).await
+    }
+
+    async fn settle_with_amount(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+        actual_amount: Option<&str>,
+        request_id: &str,
+    ) -> Result<SettlementResponse> {
+        FacilitatorClient::settle_with_amount(
+            self,
+            payment_header,
+            requirements,
+            actual_amount,
+            request_id,
+        )
+        .await
+    }
+
+    async fn supported(&self) -> Result<SupportedResponse> {
+        FacilitatorClient::supported(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_half_opens_after_cooldown() {
+        let breaker = CircuitBreaker::new(2, Duration::ZERO);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_breaker_admits_one_half_open_trial() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        // Only the first call is let through while the trial is in flight
+        breaker.check().unwrap();
+        assert!(breaker.check().is_err());
+        assert!(breaker.clone().check().is_err());
+
+        // A failed trial re-opens the circuit for the next trial
+        breaker.record_failure();
+        breaker.check().unwrap();
+        assert!(breaker.check().is_err());
+
+        breaker.record_success();
+        breaker.check().unwrap();
+        breaker.check().unwrap();
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_with_jitter() {
+        let retry = RetryConfig::new()
+            .with_base_delay(Duration::from_millis(100))
+            .with_jitter(0.0);
+        assert_eq!(retry.delay(1), Duration::from_millis(100));
+        assert_eq!(retry.delay(3), Duration::from_millis(400));
+        let capped = retry.clone().with_max_delay(Duration::from_millis(150));
+        assert_eq!(capped.delay(3), Duration::from_millis(150));
+        assert!(retry.is_retryable(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!retry.is_retryable(StatusCode::BAD_REQUEST));
+
+        let retry = retry.with_jitter(0.5);
+        for _ in 0..20 {
+            let delay = retry.delay(2);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("2"), Some(Duration::from_secs(2)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[cfg(feature = "server")]
+    #[derive(Debug)]
+    struct FullAmountFacilitator;
+
+    #[cfg(feature = "server")]
+    #[async_trait]
+    impl Facilitator for FullAmountFacilitator {
+        async fn verify(
+            &self,
+            _payment_header: &str,
+            _requirements: &PaymentRequirements,
+            _request_id: &str,
+        ) -> Result<VerificationResponse> {
+            unimplemented!()
+        }
+
+        async fn settle(
+            &self,
+            _payment_header: &str,
+            _requirements: &PaymentRequirements,
+            _request_id: &str,
+        ) -> Result<SettlementResponse> {
+            Ok(SettlementResponse {
+                tx_hash: "0xabc".to_string(),
+                block_number: None,
+                invalid_reason: None,
+                error: None,
+                settlement: None,
+            })
+        }
+
+        async fn supported(&self) -> Result<SupportedResponse> {
+            unimplemented!()
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_settle_with_amount_refused_by_default() {
+        let requirements = crate::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        )
+        .to_requirements("/test")
+        .unwrap();
+        let facilitator = FullAmountFacilitator;
+
+        let settled = facilitator
+            .settle_with_amount("header", &requirements, None, "req-1")
+            .await
+            .unwrap();
+        assert_eq!(settled.tx_hash, "0xabc");
+
+        let refused = facilitator
+            .settle_with_amount("header", &requirements, Some("5000"), "req-2")
+            .await;
+        assert!(matches!(refused, Err(X402Error::SettlementError(_))));
+    }
+}
@@ -0,0 +1,195 @@
+//! Watching settlements until they are final.
+//!
+//! A settlement's receipt only shows the transaction was mined; a reorg can still drop
+//! it. For high-value payments a [`FinalityWatch`] keeps polling until the transaction
+//! has the required confirmations, or reports it as dropped, notifying its listeners
+//! either way.
+
+use crate::errors::{Result, X402Error};
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::H256;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default delay between polls of the transaction's status.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Default time to wait for finality before giving up.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Outcome of watching a settlement transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinalityEvent {
+    /// The transaction reached the required number of confirmations
+    Finalized {
+        /// Settlement transaction hash
+        tx_hash: H256,
+        /// Block the transaction is final in
+        block_number: u64,
+        /// Confirmations counted, including the transaction's own block
+        confirmations: u64,
+    },
+    /// The transaction is no longer part of the chain, e.g. after a reorg
+    Dropped {
+        /// Settlement transaction hash
+        tx_hash: H256,
+        /// Block the transaction was last seen in
+        last_block_number: Option<u64>,
+    },
+}
+
+type Listener = Arc<dyn Fn(&FinalityEvent) + Send + Sync>;
+
+/// Polls a settlement transaction until it is final or dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use x402_rs::finality::{FinalityEvent, FinalityWatch};
+///
+/// # async fn example(tx_hash: ethers::types::H256) -> x402_rs::Result<()> {
+/// let watch = FinalityWatch::new(12)
+///     .with_poll_interval(Duration::from_secs(12))
+///     .on_event(|event| {
+///         if let FinalityEvent::Dropped { tx_hash, .. } = event {
+///             eprintln!("settlement {:?} was reorged out", tx_hash);
+///         }
+///     });
+/// let event = watch.watch("https://mainnet.base.org", tx_hash).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FinalityWatch {
+    confirmations: u64,
+    poll_interval: Duration,
+    timeout: Duration,
+    listeners: Vec<Listener>,
+}
+
+impl FinalityWatch {
+    /// Creates a watch requiring `confirmations` blocks, counting the transaction's own.
+    pub fn new(confirmations: u64) -> Self {
+        Self {
+            confirmations: confirmations.max(1),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Sets the delay between polls (defaults to 2 seconds).
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets how long to wait for finality (defaults to 10 minutes).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Calls `listener` with the outcome of every watched transaction.
+    pub fn on_event(mut self, listener: impl Fn(&FinalityEvent) + Send + Sync + 'static) -> Self {
+        self.listeners.push(Arc::new(listener));
+        self
+    }
+
+    /// Returns the number of confirmations required.
+    pub fn confirmations(&self) -> u64 {
+        self.confirmations
+    }
+
+    /// Watches `tx_hash` over the RPC endpoint at `rpc_url`.
+    ///
+    /// # Errors
+    ///
+    /// See [`watch_with`](Self::watch_with).
+    pub async fn watch(&self, rpc_url: &str, tx_hash: H256) -> Result<FinalityEvent> {
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        self.watch_with(&provider, tx_hash).await
+    }
+
+    /// Watches `tx_hash` through `provider`, returning once it is final or dropped.
+    ///
+    /// The transaction counts as dropped as soon as the node no longer returns its
+    /// receipt. If a reorg moves it to another block, confirmations are counted from
+    /// the new block.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::BlockchainError` if the RPC fails, or
+    /// `X402Error::SettlementError` if the transaction is neither final nor dropped
+    /// before the timeout.
+    pub async fn watch_with<M: Middleware>(
+        &self,
+        provider: &M,
+        tx_hash: H256,
+    ) -> Result<FinalityEvent> {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let mut last_block_number = None;
+
+        loop {
+            let receipt = provider
+                .get_transaction_receipt(tx_hash)
+                .await
+                .map_err(|e| X402Error::BlockchainError(e.to_string()))?;
+
+            let included = receipt.and_then(|receipt| receipt.block_number);
+            let Some(block_number) = included.map(|block| block.as_u64()) else {
+                return Ok(self.emit(FinalityEvent::Dropped {
+                    tx_hash,
+                    last_block_number,
+                }));
+            };
+            last_block_number = Some(block_number);
+
+            let head = provider
+                .get_block_number()
+                .await
+                .map_err(|e| X402Error::BlockchainError(e.to_string()))?
+                .as_u64();
+            let confirmations = head.saturating_sub(block_number) + 1;
+            if confirmations >= self.confirmations {
+                return Ok(self.emit(FinalityEvent::Finalized {
+                    tx_hash,
+                    block_number,
+                    confirmations,
+                }));
+            }
+
+            if tokio::time::Instant::now() + self.poll_interval > deadline {
+                return Err(X402Error::SettlementError(format!(
+                    "Transaction {:?} has {} of {} confirmations after {:?}",
+                    tx_hash, confirmations, self.confirmations, self.timeout
+                )));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    fn emit(&self, event: FinalityEvent) -> FinalityEvent {
+        #[cfg(feature = "tracing")]
+        if let FinalityEvent::Dropped { tx_hash, .. } = &event {
+            tracing::warn!("Settlement {:?} dropped from the chain", tx_hash);
+        }
+        for listener in &self.listeners {
+            listener(&event);
+        }
+        event
+    }
+}
+
+impl fmt::Debug for FinalityWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FinalityWatch")
+            .field("confirmations", &self.confirmations)
+            .field("poll_interval", &self.poll_interval)
+            .field("timeout", &self.timeout)
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
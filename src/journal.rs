@@ -0,0 +1,280 @@
+//! Client-side journal of signed payment authorizations.
+//!
+//! An [`AuthorizationJournal`] records every authorization an
+//! [`X402Client`](crate::client::X402Client) signs, so agents can audit their payments
+//! and track cumulative spend. [`MemoryJournal`] keeps entries for the life of the
+//! process, while [`FileJournal`] appends them to a JSONL file and reloads them on
+//! startup, so spend survives restarts.
+
+use crate::errors::{Result, X402Error};
+use crate::types::{PaymentPayload, PaymentRequirements};
+use crate::utils::{current_timestamp, string_to_u256};
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+/// A signed authorization, as recorded by an [`AuthorizationJournal`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// Network the authorization was signed for
+    pub network: String,
+
+    /// Token contract address
+    pub asset: String,
+
+    /// Address receiving the payment
+    #[serde(rename = "payTo")]
+    pub pay_to: String,
+
+    /// Resource the payment was made for
+    pub resource: String,
+
+    /// Authorized amount in the smallest token unit
+    pub amount: String,
+
+    /// Authorization nonce
+    pub nonce: String,
+
+    /// Unix timestamp at which the authorization was recorded
+    pub timestamp: u64,
+}
+
+impl JournalEntry {
+    /// Builds the entry for a payload signed for `requirements`.
+    ///
+    /// The payee and amount are taken from `requirements`, whose `maxAmountRequired`
+    /// holds the amount the client settled on, so payloads of any scheme are recorded.
+    /// The nonce is read from the payload's `nonce` field, or that of a split's first
+    /// authorization, and left empty for schemes without one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::InvalidAmount` if `maxAmountRequired` is not a plain amount.
+    pub fn for_payment(
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<Self> {
+        let amount = string_to_u256(&requirements.max_amount_required)?;
+        let nonce = payload
+            .payload
+            .get("nonce")
+            .or_else(|| payload.payload.pointer("/authorizations/0/nonce"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        Ok(Self {
+            network: payload.network.clone(),
+            asset: requirements.asset.clone(),
+            pay_to: requirements.pay_to.clone(),
+            resource: requirements.resource.clone(),
+            amount: amount.to_string(),
+            nonce: nonce.to_string(),
+            timestamp: current_timestamp(),
+        })
+    }
+}
+
+/// Record of the authorizations a client has signed.
+///
+/// Implemented by [`MemoryJournal`] and [`FileJournal`]; attach one with
+/// [`X402ClientConfig::with_journal`](crate::client::X402ClientConfig::with_journal).
+pub trait AuthorizationJournal: Send + Sync + fmt::Debug {
+    /// Records a signed authorization.
+    fn record(&self, entry: JournalEntry) -> Result<()>;
+
+    /// Returns every recorded authorization, oldest first.
+    fn entries(&self) -> Vec<JournalEntry>;
+
+    /// Returns the total amount authorized in `asset` on `network`, in the smallest
+    /// token unit. Assets are compared case-insensitively.
+    fn total_spent(&self, network: &str, asset: &str) -> U256 {
+        self.entries()
+            .iter()
+            .filter(|entry| entry.network == network && entry.asset.eq_ignore_ascii_case(asset))
+            .filter_map(|entry| string_to_u256(&entry.amount).ok())
+            .fold(U256::zero(), |total, amount| total.saturating_add(amount))
+    }
+}
+
+/// In-memory [`AuthorizationJournal`], lost when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryJournal {
+    entries: RwLock<Vec<JournalEntry>>,
+}
+
+impl MemoryJournal {
+    /// Creates an empty journal.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AuthorizationJournal for MemoryJournal {
+    fn record(&self, entry: JournalEntry) -> Result<()> {
+        self.entries.write().unwrap().push(entry);
+        Ok(())
+    }
+
+    fn entries(&self) -> Vec<JournalEntry> {
+        self.entries.read().unwrap().clone()
+    }
+}
+
+/// [`AuthorizationJournal`] persisted as one JSON entry per line.
+///
+/// Entries already in the file are loaded when it is opened, and each new entry is
+/// appended and flushed before `record` returns.
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::client::X402ClientConfig;
+/// use x402_rs::journal::FileJournal;
+///
+/// let journal = FileJournal::open("/var/lib/agent/x402-journal.jsonl")?;
+/// let config = X402ClientConfig::new("0xprivatekey", "https://mainnet.base.org")
+///     .with_journal(journal);
+/// # Ok::<(), x402_rs::X402Error>(())
+/// ```
+#[derive(Debug)]
+pub struct FileJournal {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: MemoryJournal,
+}
+
+impl FileJournal {
+    /// Opens the journal at `path`, creating the file if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if the file can't be opened or holds a line that
+    /// isn't a journal entry.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| {
+                X402Error::ConfigError(format!("Cannot open journal {}: {}", path.display(), e))
+            })?;
+
+        let entries = MemoryJournal::new();
+        for (i, line) in BufReader::new(&file).lines().enumerate() {
+            let line = line.map_err(|e| {
+                X402Error::ConfigError(format!("Cannot read journal {}: {}", path.display(), e))
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|e| {
+                X402Error::ConfigError(format!(
+                    "Invalid entry on line {} of journal {}: {}",
+                    i + 1,
+                    path.display(),
+                    e
+                ))
+            })?;
+            entries.record(entry)?;
+        }
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            entries,
+        })
+    }
+
+    /// Returns the path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AuthorizationJournal for FileJournal {
+    fn record(&self, entry: JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| {
+                X402Error::Other(format!("Cannot write journal {}: {}", self.path.display(), e))
+            })?;
+        self.entries.record(entry)
+    }
+
+    fn entries(&self) -> Vec<JournalEntry> {
+        self.entries.entries()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913";
+
+    fn entry(amount: &str, nonce: &str) -> JournalEntry {
+        JournalEntry {
+            network: "8453".to_string(),
+            asset: USDC.to_string(),
+            pay_to: "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb".to_string(),
+            resource: "/weather".to_string(),
+            amount: amount.to_string(),
+            nonce: nonce.to_string(),
+            timestamp: 1_735_689_600,
+        }
+    }
+
+    #[test]
+    fn test_file_journal_reloads_spend() {
+        let path = std::env::temp_dir().join(format!(
+            "x402-journal-{}-{}.jsonl",
+            std::process::id(),
+            current_timestamp()
+        ));
+
+        let journal = FileJournal::open(&path).unwrap();
+        journal.record(entry("10000", "0x01")).unwrap();
+        journal.record(entry("25000", "0x02")).unwrap();
+        drop(journal);
+
+        let reloaded = FileJournal::open(&path).unwrap();
+        assert_eq!(reloaded.entries(), vec![entry("10000", "0x01"), entry("25000", "0x02")]);
+        assert_eq!(
+            reloaded.total_spent("8453", &USDC.to_lowercase()),
+            U256::from(35_000u64)
+        );
+        assert_eq!(reloaded.total_spent("84532", USDC), U256::zero());
+
+        reloaded.record(entry("5000", "0x03")).unwrap();
+        drop(reloaded);
+        let reloaded = FileJournal::open(&path).unwrap();
+        assert_eq!(reloaded.total_spent("8453", USDC), U256::from(40_000u64));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_journal_rejects_corrupt_line() {
+        let path = std::env::temp_dir().join(format!(
+            "x402-journal-corrupt-{}-{}.jsonl",
+            std::process::id(),
+            current_timestamp()
+        ));
+        std::fs::write(&path, "not json\n").unwrap();
+
+        let err = FileJournal::open(&path).unwrap_err();
+        assert!(matches!(err, X402Error::ConfigError(reason) if reason.contains("line 1")));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+//! Facilitator-side ledger of settled payments.
+//!
+//! A [`Ledger`] receives an entry for every payment a facilitator settles, for
+//! accounting and reconciliation against the chain. [`MemoryLedger`], the default,
+//! keeps entries for the life of the process, while [`FileLedger`] appends them to a
+//! JSONL file and reloads them on startup.
+
+use crate::errors::{Result, X402Error};
+use crate::types::SettlementInfo;
+use crate::utils::{current_timestamp, string_to_u256};
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+/// A settled payment, as recorded by a [`Ledger`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    /// Address that paid
+    pub payer: String,
+
+    /// Address that was paid
+    #[serde(rename = "payTo")]
+    pub pay_to: String,
+
+    /// Settled amount in the smallest token unit
+    pub amount: String,
+
+    /// Token contract address
+    pub asset: String,
+
+    /// Network the payment settled on
+    pub network: String,
+
+    /// Hash of the settlement transaction
+    #[serde(rename = "txHash")]
+    pub tx_hash: String,
+
+    /// Unix timestamp at which the settlement was recorded
+    pub timestamp: u64,
+}
+
+impl LedgerEntry {
+    /// Builds the entry for a settlement, timestamped now.
+    pub fn for_settlement(info: &SettlementInfo) -> Self {
+        Self {
+            payer: info.payer.clone(),
+            pay_to: info.pay_to.clone(),
+            amount: info.amount.clone(),
+            asset: info.asset.clone(),
+            network: info.network.clone(),
+            tx_hash: info.tx_hash.clone(),
+            timestamp: current_timestamp(),
+        }
+    }
+}
+
+/// Append-only record of the payments a facilitator has settled.
+///
+/// Implemented by [`MemoryLedger`] and [`FileLedger`]; attach one with
+/// [`FacilitatorConfig::set_ledger`](crate::facilitator::FacilitatorConfig::set_ledger).
+pub trait Ledger: Send + Sync + fmt::Debug {
+    /// Appends a settled payment.
+    fn append(&self, entry: LedgerEntry) -> Result<()>;
+
+    /// Returns every recorded settlement, oldest first.
+    fn entries(&self) -> Vec<LedgerEntry>;
+
+    /// Returns the total amount settled in `asset` on `network`, in the smallest token
+    /// unit. Assets are compared case-insensitively.
+    fn volume(&self, network: &str, asset: &str) -> U256 {
+        self.entries()
+            .iter()
+            .filter(|entry| entry.network == network && entry.asset.eq_ignore_ascii_case(asset))
+            .filter_map(|entry| string_to_u256(&entry.amount).ok())
+            .fold(U256::zero(), |total, amount| total.saturating_add(amount))
+    }
+}
+
+/// In-memory [`Ledger`], lost when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryLedger {
+    entries: RwLock<Vec<LedgerEntry>>,
+}
+
+impl MemoryLedger {
+    /// Creates an empty ledger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Ledger for MemoryLedger {
+    fn append(&self, entry: LedgerEntry) -> Result<()> {
+        self.entries.write().unwrap().push(entry);
+        Ok(())
+    }
+
+    fn entries(&self) -> Vec<LedgerEntry> {
+        self.entries.read().unwrap().clone()
+    }
+}
+
+/// [`Ledger`] persisted as one JSON entry per line.
+///
+/// Entries already in the file are loaded when it is opened, and each new entry is
+/// appended and flushed before `append` returns.
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::facilitator::FacilitatorConfig;
+/// use x402_rs::ledger::FileLedger;
+///
+/// let mut config = FacilitatorConfig::new("0xkey", "https://mainnet.base.org");
+/// config.set_ledger(FileLedger::open("/var/lib/facilitator/settlements.jsonl")?);
+/// # Ok::<(), x402_rs::X402Error>(())
+/// ```
+#[derive(Debug)]
+pub struct FileLedger {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: MemoryLedger,
+}
+
+impl FileLedger {
+    /// Opens the ledger at `path`, creating the file if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if the file can't be opened or holds a line that
+    /// isn't a ledger entry.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| {
+                X402Error::ConfigError(format!("Cannot open ledger {}: {}", path.display(), e))
+            })?;
+
+        let entries = MemoryLedger::new();
+        for (i, line) in BufReader::new(&file).lines().enumerate() {
+            let line = line.map_err(|e| {
+                X402Error::ConfigError(format!("Cannot read ledger {}: {}", path.display(), e))
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|e| {
+                X402Error::ConfigError(format!(
+                    "Invalid entry on line {} of ledger {}: {}",
+                    i + 1,
+                    path.display(),
+                    e
+                ))
+            })?;
+            entries.append(entry)?;
+        }
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            entries,
+        })
+    }
+
+    /// Returns the path of the ledger file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Ledger for FileLedger {
+    fn append(&self, entry: LedgerEntry) -> Result<()> {
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| {
+                X402Error::Other(format!(
+                    "Cannot write ledger {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            })?;
+        self.entries.append(entry)
+    }
+
+    fn entries(&self) -> Vec<LedgerEntry> {
+        self.entries.entries()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913";
+
+    fn entry(amount: &str, tx_hash: &str) -> LedgerEntry {
+        LedgerEntry {
+            payer: "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".to_string(),
+            pay_to: "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb".to_string(),
+            amount: amount.to_string(),
+            asset: USDC.to_string(),
+            network: "8453".to_string(),
+            tx_hash: tx_hash.to_string(),
+            timestamp: 1_735_689_600,
+        }
+    }
+
+    #[test]
+    fn test_file_ledger_reloads_entries() {
+        let path = std::env::temp_dir().join(format!(
+            "x402-ledger-{}-{}.jsonl",
+            std::process::id(),
+            current_timestamp()
+        ));
+
+        let ledger = FileLedger::open(&path).unwrap();
+        ledger.append(entry("10000", "0x01")).unwrap();
+        ledger.append(entry("25000", "0x02")).unwrap();
+        drop(ledger);
+
+        let reloaded = FileLedger::open(&path).unwrap();
+        assert_eq!(
+            reloaded.entries(),
+            vec![entry("10000", "0x01"), entry("25000", "0x02")]
+        );
+        assert_eq!(
+            reloaded.volume("8453", &USDC.to_lowercase()),
+            U256::from(35_000u64)
+        );
+        assert_eq!(reloaded.volume("84532", USDC), U256::zero());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -14,20 +14,34 @@
 //! - **EVM Chains**: Full support for EVM-compatible chains using EIP-3009
 //! - **Extensible**: Easy to add new payment schemes and blockchain networks
 //!
+//! ## Cargo Features
+//!
+//! - `types`: protocol types and the `utils` header encoding, with no EVM or HTTP
+//!   dependencies, for embedded and wasm targets
+//! - `evm`: `ethers`, `reqwest` and `tokio`, the schemes and the EVM helpers in `utils`
+//! - `client`, `server`, `facilitator`: the corresponding modules (each enables `evm`)
+//! - `in-process`: an in-memory facilitator servers can call without HTTP
+//! - `otel`: OpenTelemetry spans around facilitator calls, propagated in the
+//!   `traceparent` header
+//!
+//! `client`, `server`, `facilitator` and `in-process` are enabled by default. Use
+//! `default-features = false, features = ["types"]` for the types alone.
+//!
 //! ## Quick Start
 //!
 //! ### Client Example
 //!
 //! ```rust,no_run
-//! use x402_rs::client::{X402ClientConfig, get};
-//!
+//! # #[cfg(feature = "client")]
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-//! let config = X402ClientConfig::new(
+//! use x402_rs::client::{X402Client, X402ClientConfig};
+//!
+//! let client = X402Client::new(X402ClientConfig::new(
 //!     "0xYOUR_PRIVATE_KEY",
 //!     "https://mainnet.base.org"
-//! );
+//! ));
 //!
-//! let response = get(&config, "https://api.example.com/weather").await?;
+//! let response = client.get("https://api.example.com/weather").await?;
 //! println!("Response: {}", response.text().await?);
 //! # Ok(())
 //! # }
@@ -36,6 +50,8 @@
 //! ### Server Example
 //!
 //! ```rust,no_run
+//! # #[cfg(feature = "server")]
+//! # fn main() {
 //! use x402_rs::server::PaymentConfig;
 //!
 //! let config = PaymentConfig::new(
@@ -48,6 +64,9 @@
 //!     "API access fee",
 //!     "https://facilitator.example.com",
 //! );
+//! # }
+//! # #[cfg(not(feature = "server"))]
+//! # fn main() {}
 //! ```
 //!
 //! ## Protocol Overview
@@ -92,20 +111,41 @@
 #![warn(missing_docs)]
 #![warn(rustdoc::missing_crate_level_docs)]
 
+#[cfg(feature = "evm")]
+pub mod auth;
+#[cfg(feature = "evm")]
+pub mod cache;
+#[cfg(feature = "client")]
 pub mod client;
 pub mod errors;
+#[cfg(feature = "facilitator")]
 pub mod facilitator;
+#[cfg(feature = "evm")]
+pub mod facilitator_client;
+#[cfg(feature = "evm")]
+pub mod finality;
+#[cfg(feature = "client")]
+pub mod journal;
+#[cfg(feature = "facilitator")]
+pub mod ledger;
+#[cfg(feature = "evm")]
 pub mod schemes;
+#[cfg(feature = "server")]
 pub mod server;
+#[cfg(feature = "server")]
+pub mod settlement_queue;
+#[cfg(feature = "otel")]
+pub mod telemetry;
 pub mod types;
 pub mod utils;
 
 // Re-export commonly used items
 pub use errors::{Result, X402Error};
 pub use types::{
-    PaymentPayload, PaymentRequiredResponse, PaymentRequirements, SettlementRequest,
-    SettlementResponse, SupportedKind, SupportedResponse, TransferAuthorization,
-    VerificationRequest, VerificationResponse, X402_VERSION,
+    PaymentPayload, PaymentRequiredResponse, PaymentRequirements, SettlementInfo,
+    SettlementRequest, SettlementResponse, SupportedKind, SupportedResponse, TransferAuthorization,
+    VerificationRequest, VerificationResponse, ACCEPT_PAYMENT_HEADER, X402_VERSION,
+    X_PAYMENT_HEADER, X_PAYMENT_RESPONSE_HEADER, X_PAYMENT_TOKEN_HEADER, X_REQUEST_ID_HEADER,
 };
 
 #[cfg(test)]
@@ -117,6 +157,7 @@ mod tests {
         assert_eq!(X402_VERSION, 1);
     }
 
+    #[cfg(all(feature = "client", feature = "server", feature = "facilitator"))]
     #[test]
     fn test_module_accessibility() {
         // Ensure all modules are accessible
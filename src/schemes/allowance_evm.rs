@@ -0,0 +1,420 @@
+//! The "exact" scheme paid from an ERC-20 allowance.
+//!
+//! Tokens without EIP-3009 or EIP-2612 cannot be moved with a signature alone. A payer
+//! who has already `approve`d the facilitator instead signs the x402 payload, and the
+//! facilitator settles with `transferFrom(from, to, value)`. Servers opt in by setting
+//! `extra.assetTransferMethod` to `"allowance"` and `extra.spender` to the
+//! facilitator's address.
+//!
+//! The payer signs the asset, spender and chain id along with the transfer, so a
+//! payload can only be settled against the token and chain it was made for. Nothing
+//! on-chain tracks its nonce, so facilitators should keep used nonces in a persistent
+//! [`NonceStore`](crate::facilitator::NonceStore).
+
+use crate::errors::{Result, X402Error};
+use crate::schemes::exact_evm::{decode_revert_reason, wait_for_receipt};
+use crate::schemes::{apply_tx_type, Scheme, SchemePayload, TxType};
+use crate::types::{
+    AllowanceTransfer, PaymentPayload, PaymentRequirements, SettlementInfo, X402_VERSION,
+};
+use crate::utils::{
+    amount_range, current_timestamp, generate_nonce, parse_address, sign_payment_message,
+    string_to_u256,
+};
+use async_trait::async_trait;
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Signature, U256, U64};
+use serde_json::json;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// `extra.assetTransferMethod` selecting this scheme.
+pub const ALLOWANCE_TRANSFER_METHOD: &str = "allowance";
+
+// Defaults for waiting on settlement receipts
+const DEFAULT_RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+const DEFAULT_RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Slack allowed on top of `maxTimeoutSeconds` for clock differences
+const VALIDITY_WINDOW_SKEW_SECONDS: u64 = 30;
+
+mod bindings {
+    #![allow(missing_docs)]
+    use ethers::contract::abigen;
+
+    abigen!(
+        ERC20Allowance,
+        r#"[
+            function allowance(address owner, address spender) external view returns (uint256)
+            function balanceOf(address account) external view returns (uint256)
+            function transferFrom(address from, address to, uint256 value) external returns (bool)
+        ]"#
+    );
+}
+
+pub use bindings::ERC20Allowance;
+
+/// Implementation of the "exact" scheme settled with `transferFrom`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::schemes::{allowance_evm::AllowanceEvm, Scheme};
+/// # use x402_rs::types::PaymentRequirements;
+///
+/// # async fn example(requirements: PaymentRequirements) -> x402_rs::Result<()> {
+/// let scheme = AllowanceEvm::new();
+/// let payload = scheme
+///     .generate_payload(&requirements, "0xprivatekey", "https://mainnet.base.org")
+///     .await?;
+/// let valid = scheme
+///     .verify(&payload, &requirements, "https://mainnet.base.org")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AllowanceEvm {
+    chain_id: Option<u64>,
+    receipt_timeout: Duration,
+    receipt_poll_interval: Duration,
+    tx_type: TxType,
+}
+
+impl AllowanceEvm {
+    /// Creates a new instance of the allowance scheme.
+    pub fn new() -> Self {
+        Self {
+            chain_id: None,
+            receipt_timeout: DEFAULT_RECEIPT_TIMEOUT,
+            receipt_poll_interval: DEFAULT_RECEIPT_POLL_INTERVAL,
+            tx_type: TxType::Auto,
+        }
+    }
+
+    /// Sets the chain id payers sign for, instead of querying the RPC for it.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Sets the transaction type used for settlement.
+    pub fn with_tx_type(mut self, tx_type: TxType) -> Self {
+        self.tx_type = tx_type;
+        self
+    }
+
+    /// Sets how long to wait for a settlement receipt.
+    pub fn with_receipt_timeout(mut self, timeout: Duration) -> Self {
+        self.receipt_timeout = timeout;
+        self
+    }
+
+    /// Sets how often to poll for a settlement receipt.
+    pub fn with_receipt_poll_interval(mut self, interval: Duration) -> Self {
+        self.receipt_poll_interval = interval;
+        self
+    }
+
+    /// Returns the account the payer approved, from `extra.spender`.
+    pub(crate) fn spender(requirements: &PaymentRequirements) -> Result<Address> {
+        let spender = requirements
+            .extra
+            .as_ref()
+            .and_then(|extra| extra.get("spender"))
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| X402Error::MissingField("extra.spender".to_string()))?;
+        parse_address(spender)
+    }
+
+    /// Returns the chain id payers sign for, querying the RPC unless known.
+    async fn signed_chain_id(&self, provider: &Provider<Http>) -> Result<u64> {
+        match self.chain_id {
+            Some(chain_id) => Ok(chain_id),
+            None => Ok(provider.get_chainid().await?.as_u64()),
+        }
+    }
+
+    /// Returns how the asset, spender and chain the payer signed for differ from
+    /// `requirements` and `chain_id`.
+    fn binding_failures(
+        transfer: &AllowanceTransfer,
+        requirements: &PaymentRequirements,
+        chain_id: u64,
+    ) -> Result<Vec<String>> {
+        let mut failures = Vec::new();
+        let asset = parse_address(&requirements.asset)?;
+        if parse_address(&transfer.asset)? != asset {
+            failures.push(format!(
+                "Asset mismatch: expected {:?}, got {}",
+                asset, transfer.asset
+            ));
+        }
+        let spender = Self::spender(requirements)?;
+        if parse_address(&transfer.spender)? != spender {
+            failures.push(format!(
+                "Spender mismatch: expected {:?}, got {}",
+                spender, transfer.spender
+            ));
+        }
+        if transfer.chain_id != chain_id {
+            failures.push(format!(
+                "Chain mismatch: expected {}, got {}",
+                chain_id, transfer.chain_id
+            ));
+        }
+        Ok(failures)
+    }
+
+    /// Recovers the signer of the transfer's signature over the payload.
+    fn recover_signer(payload: &PaymentPayload, transfer: &AllowanceTransfer) -> Result<Address> {
+        let signature = Signature::from_str(&transfer.signature)?;
+        Ok(signature.recover(payload.signing_message()?)?)
+    }
+}
+
+impl Default for AllowanceEvm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Scheme for AllowanceEvm {
+    fn name(&self) -> &str {
+        "exact"
+    }
+
+    async fn generate_payload(
+        &self,
+        requirements: &PaymentRequirements,
+        private_key: &str,
+        rpc_url: &str,
+    ) -> Result<PaymentPayload> {
+        let wallet = private_key
+            .parse::<LocalWallet>()
+            .map_err(|e| X402Error::InvalidPayload(format!("Invalid private key: {}", e)))?;
+        let to = parse_address(&requirements.pay_to)?;
+        let value = string_to_u256(&requirements.max_amount_required)?;
+        let asset = parse_address(&requirements.asset)?;
+        let spender = Self::spender(requirements)?;
+        let chain_id = self
+            .signed_chain_id(&Provider::<Http>::try_from(rpc_url)?)
+            .await?;
+
+        // The signature covers every other field, including the server's challenge, so
+        // it is filled in last
+        let mut transfer = AllowanceTransfer {
+            from: format!("{:?}", wallet.address()),
+            to: format!("{:?}", to),
+            value: value.to_string(),
+            asset: format!("{:?}", asset),
+            spender: format!("{:?}", spender),
+            chain_id,
+            valid_before: (current_timestamp() + requirements.max_timeout_seconds).to_string(),
+            nonce: generate_nonce(),
+            signature: String::new(),
+        };
+        let mut payload = PaymentPayload {
+            x402_version: X402_VERSION,
+            scheme: self.name().to_string(),
+            network: requirements.network.clone(),
+            payload: json!(transfer),
+            challenge: requirements
+                .extra
+                .as_ref()
+                .and_then(|extra| extra.get("challenge"))
+                .and_then(|v| v.as_str())
+                .map(String::from),
+            message_signature: None,
+        };
+        transfer.signature = sign_payment_message(&payload, private_key)?;
+        payload.payload = json!(transfer);
+
+        Ok(payload)
+    }
+
+    async fn verify(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+    ) -> Result<bool> {
+        Ok(self
+            .verify_detailed(payload, requirements, rpc_url)
+            .await?
+            .is_empty())
+    }
+
+    async fn verify_detailed(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+    ) -> Result<Vec<String>> {
+        let transfer = AllowanceTransfer::from_value(&payload.payload)?;
+        let mut failures = Vec::new();
+
+        if payload.scheme != self.name() {
+            failures.push(format!(
+                "Scheme mismatch: expected {}, got {}",
+                self.name(),
+                payload.scheme
+            ));
+        }
+        if payload.network != requirements.network {
+            failures.push(format!(
+                "Network mismatch: expected {}, got {}",
+                requirements.network, payload.network
+            ));
+        }
+
+        let from = parse_address(&transfer.from)?;
+        let to = parse_address(&transfer.to)?;
+        let value = string_to_u256(&transfer.value)?;
+        let expected_to = parse_address(&requirements.pay_to)?;
+        let (min_value, max_value) = amount_range(requirements)?;
+        let asset = parse_address(&requirements.asset)?;
+        let spender = Self::spender(requirements)?;
+        let valid_before = string_to_u256(&transfer.valid_before)?;
+
+        if to != expected_to {
+            failures.push(format!("Payee mismatch: expected {:?}, got {:?}", expected_to, to));
+        }
+        if value < min_value || value > max_value {
+            failures.push(format!(
+                "Amount {} outside accepted range [{}, {}]",
+                value, min_value, max_value
+            ));
+        }
+
+        let now = current_timestamp();
+        let latest = now
+            .saturating_add(requirements.max_timeout_seconds)
+            .saturating_add(VALIDITY_WINDOW_SKEW_SECONDS);
+        if U256::from(now) > valid_before {
+            failures.push(format!("Authorization expired at {}", valid_before));
+        } else if valid_before > U256::from(latest) {
+            failures.push(format!(
+                "Authorization window of {}s exceeds maxTimeoutSeconds {}",
+                valid_before - U256::from(now),
+                requirements.max_timeout_seconds
+            ));
+        }
+
+        match Self::recover_signer(payload, &transfer) {
+            Ok(recovered) if recovered == from => {}
+            Ok(recovered) => failures.push(format!(
+                "Signature recovers to {:?}, not payer {:?}",
+                recovered, from
+            )),
+            Err(e) => failures.push(e.to_string()),
+        }
+
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let chain_id = self.signed_chain_id(&provider).await?;
+        failures.extend(Self::binding_failures(&transfer, requirements, chain_id)?);
+
+        // The payer must have approved enough for the facilitator, and hold it
+        let token = ERC20Allowance::new(asset, Arc::new(provider));
+        let allowance = token
+            .allowance(from, spender)
+            .call()
+            .await
+            .map_err(|e| X402Error::BlockchainError(e.to_string()))?;
+        if allowance < value {
+            failures.push(format!(
+                "Allowance of {} for spender {:?} is below {}",
+                allowance, spender, value
+            ));
+        }
+        let balance = token
+            .balance_of(from)
+            .call()
+            .await
+            .map_err(|e| X402Error::BlockchainError(e.to_string()))?;
+        if balance < value {
+            failures.push(format!("Balance of {} is below {}", balance, value));
+        }
+
+        Ok(failures)
+    }
+
+    async fn settle(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+        facilitator_key: &str,
+    ) -> Result<SettlementInfo> {
+        let transfer = AllowanceTransfer::from_value(&payload.payload)?;
+        let from = parse_address(&transfer.from)?;
+        let to = parse_address(&transfer.to)?;
+        let value = string_to_u256(&transfer.value)?;
+        let asset = parse_address(&requirements.asset)?;
+
+        // Only the approved spender can move the payer's tokens
+        let wallet = facilitator_key
+            .parse::<LocalWallet>()
+            .map_err(|e| X402Error::ConfigError(format!("Invalid facilitator key: {}", e)))?;
+        let spender = Self::spender(requirements)?;
+        if wallet.address() != spender {
+            return Err(X402Error::ConfigError(format!(
+                "Facilitator {:?} is not the approved spender {:?}",
+                wallet.address(),
+                spender
+            )));
+        }
+
+        // Only the token and chain the payer signed for can be drawn on
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let signed_chain_id = self.signed_chain_id(&provider).await?;
+        let failures = Self::binding_failures(&transfer, requirements, signed_chain_id)?;
+        if let Some(failure) = failures.into_iter().next() {
+            return Err(X402Error::VerificationFailed(failure));
+        }
+
+        let chain_id = provider.get_chainid().await?.as_u64();
+        let client = SignerMiddleware::new(provider.clone(), wallet.with_chain_id(chain_id));
+        let call = ERC20Allowance::new(asset, Arc::new(client)).transfer_from(from, to, value);
+        let call = apply_tx_type(call, self.tx_type, chain_id);
+        let pending_tx = call
+            .send()
+            .await
+            .map_err(|e| {
+                let reason = e.as_revert().and_then(|data| decode_revert_reason(data));
+                X402Error::SettlementError(match reason {
+                    Some(reason) => format!("Transaction reverted: {}", reason),
+                    None => format!("Transaction failed: {}", e),
+                })
+            })?;
+        let tx_hash = pending_tx.tx_hash();
+
+        let receipt = wait_for_receipt(
+            &provider,
+            tx_hash,
+            self.receipt_poll_interval,
+            self.receipt_timeout,
+        )
+        .await?;
+        if receipt.status == Some(U64::zero()) {
+            return Err(X402Error::SettlementError(format!(
+                "Transaction {:?} reverted",
+                tx_hash
+            )));
+        }
+
+        Ok(SettlementInfo {
+            payer: format!("{:?}", from),
+            pay_to: format!("{:?}", to),
+            amount: value.to_string(),
+            asset: format!("{:?}", asset),
+            network: payload.network.clone(),
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+            block_number: receipt.block_number.map(|n| n.as_u64()),
+        })
+    }
+}
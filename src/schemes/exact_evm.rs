@@ -4,52 +4,644 @@
 //! The payer signs an authorization that allows the facilitator to execute the transfer
 //! on their behalf without requiring the payer to have ETH for gas.
 
+use crate::cache::TtlCache;
 use crate::errors::{Result, X402Error};
-use crate::schemes::Scheme;
-use crate::types::{PaymentPayload, PaymentRequirements, TransferAuthorization, X402_VERSION};
-use crate::utils::{current_timestamp, generate_nonce, parse_address, string_to_u256};
+use crate::schemes::token_client::{RpcTokenClient, SignedTransfer, TokenClient};
+use crate::schemes::{
+    is_transient_rpc_error, RpcRetry, Scheme, SchemeCapabilities, SchemePayload, TxType,
+};
+use crate::types::{
+    PaymentPayload, PaymentRequirements, SettlementInfo, TransferAuthorization,
+    AUTHORIZATION_EXPIRED_REASON, X402_VERSION,
+};
+use crate::utils::{
+    amount_range, current_timestamp, generate_nonce, message_signature_required, parse_address,
+    recover_message_signer, string_to_u256,
+};
 use async_trait::async_trait;
 use ethers::abi::Token;
-use ethers::contract::abigen;
-use ethers::core::k256::ecdsa::SigningKey;
 use ethers::core::utils::keccak256;
 use ethers::prelude::*;
 use ethers::providers::{Http, Provider};
 use ethers::signers::{LocalWallet, Signer};
-use ethers::types::{transaction::eip712::Eip712, Signature, H256, U256};
+use ethers::types::{Signature, H256, U256};
 use serde_json::json;
 use std::sync::Arc;
+use std::time::Duration;
 
 // Define the EIP-3009 domain and types for EIP-712 signing
 const EIP712_DOMAIN_NAME: &str = "USD Coin";
 const EIP712_DOMAIN_VERSION: &str = "2";
 
+/// EIP-712 type string of the EIP-3009 authorization signed by payers.
+///
+/// Tokens naming the struct or its fields differently can override it with
+/// `extra.authorizationType` or [`ExactEvm::with_authorization_type`].
+pub const DEFAULT_AUTHORIZATION_TYPE: &str = "TransferWithAuthorization(address from,address to,\
+uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)";
+
+// Field types every authorization type string must declare, in order
+const AUTHORIZATION_FIELD_TYPES: [&str; 6] =
+    ["address", "address", "uint256", "uint256", "uint256", "bytes32"];
+
+// Defaults for waiting on settlement receipts
+const DEFAULT_RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+const DEFAULT_RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a token's probed domain version is trusted before it is read again.
+pub const DEFAULT_TOKEN_VERSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Slack allowed on top of `maxTimeoutSeconds` for clock differences
+const VALIDITY_WINDOW_SKEW_SECONDS: u64 = 30;
+
+// Defaults for retrying `authorizationState` reads that fail transiently
+const DEFAULT_NONCE_STATE_RETRIES: u32 = 2;
+const DEFAULT_NONCE_STATE_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// How far before the signing time authorizations become valid, so a chain whose clock
+/// lags the payer's doesn't reject them as not yet valid.
+pub const DEFAULT_VALID_AFTER_OFFSET: Duration = Duration::from_secs(10);
+
 // ABI for EIP-3009 compliant ERC-20 token
-abigen!(
-    EIP3009Token,
-    r#"[
-        function transferWithAuthorization(address from, address to, uint256 value, uint256 validAfter, uint256 validBefore, bytes32 nonce, uint8 v, bytes32 r, bytes32 s) external
-        function authorizationState(address authorizer, bytes32 nonce) external view returns (bool)
-        function decimals() external view returns (uint8)
-        function name() external view returns (string)
-        function version() external view returns (string)
-    ]"#
-);
+mod bindings {
+    #![allow(missing_docs)]
+    use ethers::contract::abigen;
+
+    abigen!(
+        EIP3009Token,
+        r#"[
+            function transferWithAuthorization(address from, address to, uint256 value, uint256 validAfter, uint256 validBefore, bytes32 nonce, uint8 v, bytes32 r, bytes32 s) external
+            function authorizationState(address authorizer, bytes32 nonce) external view returns (bool)
+            function balanceOf(address account) external view returns (uint256)
+            function decimals() external view returns (uint8)
+            function name() external view returns (string)
+            function version() external view returns (string)
+        ]"#
+    );
+}
+
+pub use bindings::EIP3009Token;
+
+/// Cache of EIP-712 domain versions read from tokens' `version()`.
+///
+/// Tokens without `version()` are cached as `None`, so they are only probed once and
+/// then use the configured or default version. Keyed by chain id and token address;
+/// clones share entries.
+///
+/// Entries expire after a TTL, [`DEFAULT_TOKEN_VERSION_TTL`] unless set with
+/// [`with_ttl`](Self::with_ttl), so a token that upgrades its `version()` is picked
+/// up eventually. Call [`invalidate`](Self::invalidate) to pick it up immediately.
+#[derive(Clone, Debug)]
+pub struct TokenVersionCache {
+    entries: TtlCache<TokenKey, Option<String>>,
+}
+
+/// Chain id and address identifying a token.
+type TokenKey = (U256, Address);
+
+impl Default for TokenVersionCache {
+    fn default() -> Self {
+        Self {
+            entries: TtlCache::new(DEFAULT_TOKEN_VERSION_TTL),
+        }
+    }
+}
+
+impl TokenVersionCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how long a probed version is trusted before the token is probed again.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.entries = self.entries.with_ttl(ttl);
+        self
+    }
+
+    /// Returns the cached probe result for a token, `Some(None)` meaning it has no
+    /// `version()`. Expired entries are treated as missing.
+    pub async fn get(&self, chain_id: U256, asset: Address) -> Option<Option<String>> {
+        self.entries.get(&(chain_id, asset)).await
+    }
+
+    /// Records a probe result for a token.
+    pub async fn insert(&self, chain_id: U256, asset: Address, version: Option<String>) {
+        self.entries.insert((chain_id, asset), version).await;
+    }
+
+    /// Drops the cached version of a token, so the next verification probes it again.
+    pub async fn invalidate(&self, chain_id: U256, asset: Address) {
+        self.entries.invalidate(&(chain_id, asset)).await;
+    }
+
+    /// Drops every cached version.
+    pub async fn clear(&self) {
+        self.entries.clear().await;
+    }
+
+    /// Returns the number of probed tokens, including expired entries.
+    pub async fn len(&self) -> usize {
+        self.entries.len().await
+    }
+
+    /// Returns `true` if no token has been probed.
+    pub async fn is_empty(&self) -> bool {
+        self.entries.is_empty().await
+    }
+}
 
 /// Implementation of the "exact" scheme for EVM chains.
 ///
 /// This scheme requires the payer to pay exactly the `maxAmountRequired` using
 /// EIP-3009 signed authorization.
-pub struct ExactEvm;
+pub struct ExactEvm {
+    receipt_timeout: Duration,
+    receipt_poll_interval: Duration,
+    tx_type: TxType,
+    chain_id: Option<u64>,
+    token_versions: Option<TokenVersionCache>,
+    authorization_type: Option<String>,
+    payee_balance_check: bool,
+    token_client: Option<Arc<dyn TokenClient>>,
+    valid_after_offset: Duration,
+    valid_after_skew: Duration,
+    clock: Option<Arc<dyn Fn() -> u64 + Send + Sync>>,
+    nonce_generator: Option<Arc<dyn Fn() -> H256 + Send + Sync>>,
+    nonce_state_retries: u32,
+    nonce_state_retry_delay: Duration,
+    rpc_retry: RpcRetry,
+    domain_separator_check: bool,
+}
 
 impl ExactEvm {
     /// Creates a new instance of the ExactEvm scheme.
     pub fn new() -> Self {
-        Self
+        Self {
+            receipt_timeout: DEFAULT_RECEIPT_TIMEOUT,
+            receipt_poll_interval: DEFAULT_RECEIPT_POLL_INTERVAL,
+            tx_type: TxType::Auto,
+            chain_id: None,
+            token_versions: None,
+            authorization_type: None,
+            payee_balance_check: false,
+            token_client: None,
+            valid_after_offset: DEFAULT_VALID_AFTER_OFFSET,
+            valid_after_skew: Duration::ZERO,
+            clock: None,
+            nonce_generator: None,
+            nonce_state_retries: DEFAULT_NONCE_STATE_RETRIES,
+            nonce_state_retry_delay: DEFAULT_NONCE_STATE_RETRY_DELAY,
+            rpc_retry: RpcRetry::default(),
+            domain_separator_check: true,
+        }
+    }
+
+    /// Reads the domain version from the token's `version()` during verification when
+    /// `extra` doesn't name one, caching results in `cache`.
+    pub fn with_token_version_cache(mut self, cache: TokenVersionCache) -> Self {
+        self.token_versions = Some(cache);
+        self
+    }
+
+    /// Signs and verifies authorizations for `chain_id` instead of querying the RPC.
+    ///
+    /// It needn't match the chain the RPC reports, e.g. on a local fork of Base
+    /// reporting 31337. Only the EIP-712 domain uses it; settlement transactions still
+    /// target the RPC's own chain.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Signs authorizations valid from `offset` before the signing time (defaults to
+    /// 10 seconds).
+    ///
+    /// Facilitators reject windows longer than `maxTimeoutSeconds` plus 30 seconds of
+    /// skew, so the offset is clamped to those 30 seconds.
+    pub fn with_valid_after_offset(mut self, offset: Duration) -> Self {
+        self.valid_after_offset = offset.min(Duration::from_secs(VALIDITY_WINDOW_SKEW_SECONDS));
+        self
+    }
+
+    /// Accepts authorizations whose `validAfter` is up to `skew` ahead of the
+    /// verifier's clock (defaults to none).
+    ///
+    /// Allows for a payer whose clock runs ahead of the facilitator's. Authorizations
+    /// valid only further in the future are rejected: they can't be settled now, and
+    /// could be held back and settled later.
+    pub fn with_valid_after_skew(mut self, skew: Duration) -> Self {
+        self.valid_after_skew = skew;
+        self
+    }
+
+    /// Sets the transaction type used for settlement.
+    pub fn with_tx_type(mut self, tx_type: TxType) -> Self {
+        self.tx_type = tx_type;
+        self
+    }
+
+    /// Sets the EIP-712 type string for tokens with a variant EIP-3009 struct.
+    ///
+    /// `extra.authorizationType` in the requirements takes precedence. The type must
+    /// declare the same field types in the same order as [`DEFAULT_AUTHORIZATION_TYPE`].
+    pub fn with_authorization_type(mut self, type_string: impl Into<String>) -> Self {
+        self.authorization_type = Some(type_string.into());
+        self
+    }
+
+    /// Makes `settle` confirm the payee received exactly the authorized amount, catching
+    /// fee-on-transfer tokens.
+    ///
+    /// Sums the token's `Transfer` events from payer to payee in the settlement receipt,
+    /// so transfers by anyone else in the same block don't count.
+    pub fn with_payee_balance_check(mut self, enabled: bool) -> Self {
+        self.payee_balance_check = enabled;
+        self
+    }
+
+    /// Makes token calls through `client` instead of an [`RpcTokenClient`] for the RPC
+    /// passed to each call.
+    ///
+    /// Settlement still waits for receipts over the RPC.
+    pub fn with_token_client(mut self, client: Arc<dyn TokenClient>) -> Self {
+        self.token_client = Some(client);
+        self
+    }
+
+    /// Reads the current Unix time from `clock` when signing and verifying, instead of
+    /// the system clock.
+    ///
+    /// Meant for tests and reproducible signatures.
+    pub fn with_clock(mut self, clock: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Draws authorization nonces from `generator` instead of a random source.
+    ///
+    /// Meant for tests and reproducible signatures: a nonce can only be used once per
+    /// payer, so `generator` must not repeat itself in production.
+    pub fn with_nonce_generator(
+        mut self,
+        generator: impl Fn() -> H256 + Send + Sync + 'static,
+    ) -> Self {
+        self.nonce_generator = Some(Arc::new(generator));
+        self
+    }
+
+    /// Retries a failed `authorizationState` read up to `retries` times, `delay` apart,
+    /// before treating the nonce as used (defaults to 2 retries, 250ms apart).
+    ///
+    /// Some nodes briefly fail the read for a nonce they haven't seen yet.
+    pub fn with_nonce_state_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.nonce_state_retries = retries;
+        self.nonce_state_retry_delay = delay;
+        self
+    }
+
+    /// Retries RPC reads failing in transport, such as the chain id and the nonce state,
+    /// up to `max_retries` times with exponential backoff from `base_delay` (defaults
+    /// to no retries).
+    ///
+    /// Reverts and error responses from the node are not retried. Applies to the
+    /// [`RpcTokenClient`] used unless one is set with
+    /// [`with_token_client`](Self::with_token_client).
+    pub fn with_rpc_retries(mut self, max_retries: u32, base_delay: Duration) -> Self {
+        self.rpc_retry = RpcRetry::new(max_retries, base_delay);
+        self
+    }
+
+    /// Sets whether verification rejects a domain separator pinned in
+    /// `extra.domainSeparator` that differs from the one derived from the asset, chain
+    /// and token metadata.
+    ///
+    /// On by default. Turned off, a pinned separator is trusted as is and the chain id
+    /// and token version aren't looked up, but payments signed in another token's or
+    /// chain's domain verify and then revert on settlement.
+    pub fn with_domain_separator_check(mut self, enabled: bool) -> Self {
+        self.domain_separator_check = enabled;
+        self
+    }
+
+    /// Queries `provider` for its chain id, retrying transport failures.
+    async fn rpc_chain_id(&self, provider: &Provider<Http>) -> Result<U256> {
+        self.rpc_retry
+            .run(
+                || provider.get_chainid(),
+                is_transient_rpc_error,
+                X402Error::from,
+            )
+            .await
+    }
+
+    /// Reads whether `from` has used `nonce` on `asset`, retrying failed reads.
+    async fn authorization_state(
+        &self,
+        token: &dyn TokenClient,
+        asset: Address,
+        from: Address,
+        nonce: H256,
+    ) -> Result<bool> {
+        let mut retries = 0;
+        loop {
+            match token.authorization_state(asset, from, nonce).await {
+                Err(_) if retries < self.nonce_state_retries => {
+                    retries += 1;
+                    tokio::time::sleep(self.nonce_state_retry_delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the current Unix time from the configured clock.
+    fn now(&self) -> u64 {
+        match &self.clock {
+            Some(clock) => clock(),
+            None => current_timestamp(),
+        }
+    }
+
+    /// Returns the latest `validAfter` accepted at `now`.
+    fn latest_valid_after(&self, now: U256) -> U256 {
+        now.saturating_add(U256::from(self.valid_after_skew.as_secs()))
+    }
+
+    /// Returns a fresh authorization nonce from the configured generator.
+    fn next_nonce(&self) -> Result<H256> {
+        match &self.nonce_generator {
+            Some(generator) => Ok(generator()),
+            None => Self::parse_nonce(&generate_nonce()),
+        }
+    }
+
+    /// Sets how long `settle` waits for a transaction receipt before giving up.
+    pub fn with_receipt_timeout(mut self, timeout: Duration) -> Self {
+        self.receipt_timeout = timeout;
+        self
+    }
+
+    /// Sets how often `settle` polls for a transaction receipt.
+    pub fn with_receipt_poll_interval(mut self, interval: Duration) -> Self {
+        self.receipt_poll_interval = interval;
+        self
+    }
+
+    /// Estimates the gas a settlement would use, without sending it.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The verified payment payload
+    /// * `requirements` - The payment requirements
+    /// * `rpc_url` - RPC endpoint used for the estimate
+    /// * `facilitator_key` - Private key of the account that would send the settlement
+    pub async fn estimate_settlement_gas(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+        facilitator_key: &str,
+    ) -> Result<U256> {
+        let asset = parse_address(&requirements.asset)?;
+        self.rpc_settlement_client(rpc_url, facilitator_key)?
+            .estimate_transfer_gas(asset, &Self::signed_transfer(payload)?)
+            .await
+    }
+
+    /// Returns the injected token client, or one reading from `rpc_url`.
+    fn token_client(&self, rpc_url: &str) -> Result<Arc<dyn TokenClient>> {
+        match &self.token_client {
+            Some(client) => Ok(client.clone()),
+            None => Ok(Arc::new(RpcTokenClient::new(rpc_url)?.with_rpc_retries(
+                self.rpc_retry.max_retries,
+                self.rpc_retry.base_delay,
+            ))),
+        }
+    }
+
+    /// Returns a token client sending settlements from the facilitator's account.
+    fn rpc_settlement_client(
+        &self,
+        rpc_url: &str,
+        facilitator_key: &str,
+    ) -> Result<RpcTokenClient> {
+        Ok(RpcTokenClient::new(rpc_url)?
+            .with_signer(facilitator_key)?
+            .with_tx_type(self.tx_type)
+            .with_rpc_retries(self.rpc_retry.max_retries, self.rpc_retry.base_delay))
+    }
+
+    /// Submits `transfers` to `asset` and waits for the receipt.
+    ///
+    /// A single transfer is sent on its own; several are batched into one transaction
+    /// that settles all of them or none.
+    pub(crate) async fn settle_transfers(
+        &self,
+        transfers: &[SignedTransfer],
+        asset: Address,
+        rpc_url: &str,
+        facilitator_key: &str,
+    ) -> Result<TransactionReceipt> {
+        let token: Arc<dyn TokenClient> = match &self.token_client {
+            Some(client) => client.clone(),
+            None => Arc::new(self.rpc_settlement_client(rpc_url, facilitator_key)?),
+        };
+
+        // Send transferWithAuthorization
+        let tx_hash = match transfers {
+            [transfer] => token.transfer_with_authorization(asset, transfer).await?,
+            _ => token.transfer_batch_with_authorization(asset, transfers).await?,
+        };
+
+        // Wait for confirmation, tolerating transient `None` receipts while the tx is pending
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let receipt = wait_for_receipt(
+            &provider,
+            tx_hash,
+            self.receipt_poll_interval,
+            self.receipt_timeout,
+        )
+        .await?;
+
+        if self.payee_balance_check {
+            check_payee_transfers(&receipt, asset, transfers)?;
+        }
+
+        Ok(receipt)
+    }
+
+    /// Parses the signed transfer carried by `payload`.
+    fn signed_transfer(payload: &PaymentPayload) -> Result<SignedTransfer> {
+        Self::authorized_transfer(&TransferAuthorization::from_value(&payload.payload)?)
+    }
+
+    /// Parses the fields of `auth` into a transfer ready to submit.
+    pub(crate) fn authorized_transfer(auth: &TransferAuthorization) -> Result<SignedTransfer> {
+        Ok(SignedTransfer {
+            from: parse_address(&auth.from)?,
+            to: parse_address(&auth.to)?,
+            value: string_to_u256(&auth.value)?,
+            valid_after: string_to_u256(&auth.valid_after)?,
+            valid_before: string_to_u256(&auth.valid_before)?,
+            nonce: Self::parse_nonce(&auth.nonce)?,
+            signature: Self::parse_signature(&auth.signature)?,
+        })
+    }
+
+    /// Signs a payment for `requirements` without any network access, e.g. on an
+    /// air-gapped machine.
+    ///
+    /// The chain id and the token's EIP-712 domain `name` and `version` are taken as
+    /// given rather than read from the RPC or `extra`. The resulting payload can be
+    /// carried to an online machine and sent as-is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::schemes::exact_evm::ExactEvm;
+    /// use x402_rs::types::PaymentRequirements;
+    ///
+    /// let requirements: PaymentRequirements = serde_json::from_value(serde_json::json!({
+    ///     "scheme": "exact",
+    ///     "network": "8453",
+    ///     "maxAmountRequired": "10000",
+    ///     "resource": "/weather",
+    ///     "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+    ///     "maxTimeoutSeconds": 300,
+    ///     "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+    /// }))?;
+    ///
+    /// let payload = ExactEvm::new().generate_payload_offline(
+    ///     &requirements,
+    ///     "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+    ///     8453,
+    ///     "USD Coin",
+    ///     "2",
+    /// )?;
+    /// assert_eq!(payload.network, "8453");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn generate_payload_offline(
+        &self,
+        requirements: &PaymentRequirements,
+        private_key: &str,
+        chain_id: u64,
+        token_name: &str,
+        token_version: &str,
+    ) -> Result<PaymentPayload> {
+        // Parse addresses and amounts
+        let to = parse_address(&requirements.pay_to)?;
+        let value = string_to_u256(&requirements.max_amount_required)?;
+        let asset = parse_address(&requirements.asset)?;
+
+        // Create wallet from private key
+        let wallet = private_key
+            .parse::<LocalWallet>()
+            .map_err(|e| X402Error::InvalidPayload(format!("Invalid private key: {}", e)))?;
+        let from = wallet.address();
+
+        // Generate nonce and timestamps
+        let nonce = self.next_nonce()?;
+
+        let now = self.now();
+        let valid_after = U256::from(now.saturating_sub(self.valid_after_offset.as_secs()));
+        let valid_before = U256::from(now + requirements.max_timeout_seconds);
+
+        // Get the authorization type from extra field or use the default
+        let authorization_type = self.authorization_type(requirements)?;
+
+        // Create domain separator, refusing one pinned by the server for another token
+        // or chain, and authorization hash
+        let domain_separator =
+            Self::create_domain_separator(asset, U256::from(chain_id), token_name, token_version);
+        Self::check_pinned_domain_separator(requirements, domain_separator)?;
+
+        let message_hash = Self::create_authorization_hash(
+            &authorization_type,
+            from,
+            to,
+            value,
+            valid_after,
+            valid_before,
+            nonce,
+            domain_separator,
+        );
+
+        // Sign the hash
+        let signature = wallet.sign_hash(message_hash)
+            .map_err(|e| X402Error::SignatureError(e.to_string()))?;
+
+        // Create the authorization object
+        // Convert r and s from U256 to [u8; 32]
+        let mut r_bytes = [0u8; 32];
+        signature.r.to_big_endian(&mut r_bytes);
+        let mut s_bytes = [0u8; 32];
+        signature.s.to_big_endian(&mut s_bytes);
+        
+        let mut sig_bytes = Vec::with_capacity(65);
+        sig_bytes.extend_from_slice(&r_bytes);
+        sig_bytes.extend_from_slice(&s_bytes);
+        sig_bytes.push(signature.v as u8);
+        
+        let authorization = TransferAuthorization {
+            from: format!("{:?}", from),
+            to: format!("{:?}", to),
+            value: value.to_string(),
+            valid_after: valid_after.to_string(),
+            valid_before: valid_before.to_string(),
+            nonce: format!("0x{}", hex::encode(nonce.as_bytes())),
+            signature: format!("0x{}", hex::encode(sig_bytes)),
+        };
+
+        Ok(PaymentPayload {
+            x402_version: X402_VERSION,
+            scheme: self.name().to_string(),
+            network: requirements.network.clone(),
+            payload: json!(authorization),
+            challenge: None,
+            message_signature: None,
+        })
+    }
+
+    /// Returns the authorization type string for `requirements`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if the type string doesn't declare the EIP-3009
+    /// fields in order.
+    fn authorization_type(&self, requirements: &PaymentRequirements) -> Result<String> {
+        let type_string = requirements
+            .extra
+            .as_ref()
+            .and_then(|extra| extra.get("authorizationType"))
+            .and_then(|v| v.as_str())
+            .or(self.authorization_type.as_deref())
+            .unwrap_or(DEFAULT_AUTHORIZATION_TYPE);
+
+        let field_types: Option<Vec<&str>> = type_string
+            .strip_suffix(')')
+            .and_then(|s| s.split_once('('))
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(_, fields)| {
+                fields
+                    .split(',')
+                    .map(|field| field.split_whitespace().next().unwrap_or_default())
+                    .collect()
+            });
+        if field_types.as_deref() != Some(AUTHORIZATION_FIELD_TYPES.as_slice()) {
+            return Err(X402Error::ConfigError(format!(
+                "Unsupported authorization type: {}",
+                type_string
+            )));
+        }
+        Ok(type_string.to_string())
     }
 
     /// Creates the EIP-712 typed data hash for the transfer authorization.
+    #[allow(clippy::too_many_arguments)]
     fn create_authorization_hash(
+        authorization_type: &str,
         from: Address,
         to: Address,
         value: U256,
@@ -58,14 +650,12 @@ impl ExactEvm {
         nonce: H256,
         domain_separator: H256,
     ) -> H256 {
-        // EIP-712 type hash for TransferWithAuthorization
-        let type_hash = keccak256(
-            b"TransferWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)"
-        );
+        // EIP-712 type hash, `TransferWithAuthorization` unless overridden
+        let type_hash = keccak256(authorization_type.as_bytes());
 
         // Encode the struct data
         let struct_hash = keccak256(
-            &ethers::abi::encode(&[
+            ethers::abi::encode(&[
                 Token::FixedBytes(type_hash.to_vec()),
                 Token::Address(from),
                 Token::Address(to),
@@ -85,8 +675,199 @@ impl ExactEvm {
         H256::from(keccak256(&message))
     }
 
+    /// Returns the EIP-712 domain name and version from `extra`, or the USDC defaults.
+    pub(crate) fn token_metadata(requirements: &PaymentRequirements) -> (String, String) {
+        let field = |key: &str, default: &str| {
+            requirements
+                .extra
+                .as_ref()
+                .and_then(|extra| extra.get(key))
+                .and_then(|v| v.as_str())
+                .unwrap_or(default)
+                .to_string()
+        };
+        (
+            field("name", EIP712_DOMAIN_NAME),
+            field("version", EIP712_DOMAIN_VERSION),
+        )
+    }
+
+    /// Returns the chain id of the EIP-712 domain, querying the RPC unless known.
+    async fn domain_chain_id(&self, provider: &Provider<Http>) -> Result<U256> {
+        match self.chain_id {
+            Some(chain_id) => Ok(U256::from(chain_id)),
+            None => self.rpc_chain_id(provider).await,
+        }
+    }
+
+    /// Returns the domain separator pinned by the server in `extra.domainSeparator`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if it isn't 32 bytes of hex.
+    fn pinned_domain_separator(requirements: &PaymentRequirements) -> Result<Option<H256>> {
+        let Some(pinned) = requirements
+            .extra
+            .as_ref()
+            .and_then(|extra| extra.get("domainSeparator"))
+            .and_then(|v| v.as_str())
+        else {
+            return Ok(None);
+        };
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(pinned.trim_start_matches("0x"), &mut bytes)
+            .map_err(|e| X402Error::ConfigError(format!("Invalid domainSeparator: {}", e)))?;
+        Ok(Some(H256::from(bytes)))
+    }
+
+    /// Fails if `requirements` pin a domain separator other than `derived`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` on a mismatch or a malformed pinned separator.
+    fn check_pinned_domain_separator(
+        requirements: &PaymentRequirements,
+        derived: H256,
+    ) -> Result<()> {
+        match Self::pinned_domain_separator(requirements)? {
+            Some(pinned) if pinned != derived => Err(X402Error::ConfigError(format!(
+                "Domain separator mismatch: pinned {:?}, derived {:?}",
+                pinned, derived
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the domain separator a payment must be signed in, and why it is wrong,
+    /// if it is.
+    ///
+    /// A separator pinned in `extra.domainSeparator` is used as is. With the domain
+    /// separator check on, it must also match the one derived from the asset, chain and
+    /// token metadata.
+    async fn verification_domain_separator(
+        &self,
+        requirements: &PaymentRequirements,
+        provider: &Provider<Http>,
+        asset: Address,
+    ) -> Result<(H256, Option<String>)> {
+        let pinned = Self::pinned_domain_separator(requirements)?;
+        if let Some(pinned) = pinned.filter(|_| !self.domain_separator_check) {
+            return Ok((pinned, None));
+        }
+
+        let chain_id = self.domain_chain_id(provider).await?;
+        let (token_name, token_version) = self
+            .domain_metadata(requirements, provider, chain_id, asset)
+            .await;
+        let derived = Self::create_domain_separator(asset, chain_id, &token_name, &token_version);
+        Ok(match pinned {
+            Some(pinned) if pinned != derived => (
+                pinned,
+                Some(format!(
+                    "Domain separator mismatch: pinned {:?}, derived {:?}",
+                    pinned, derived
+                )),
+            ),
+            _ => (derived, None),
+        })
+    }
+
+    /// Returns the EIP-712 domain name and version used to verify a payment.
+    ///
+    /// Like [`token_metadata`](Self::token_metadata), but with a version cache attached
+    /// a version missing from `extra` is read from the token's `version()` first.
+    async fn domain_metadata(
+        &self,
+        requirements: &PaymentRequirements,
+        provider: &Provider<Http>,
+        chain_id: U256,
+        asset: Address,
+    ) -> (String, String) {
+        let (name, default_version) = Self::token_metadata(requirements);
+        let explicit = requirements
+            .extra
+            .as_ref()
+            .is_some_and(|extra| extra.get("version").is_some());
+        let Some(cache) = self.token_versions.as_ref().filter(|_| !explicit) else {
+            return (name, default_version);
+        };
+
+        if let Some(cached) = cache.get(chain_id, asset).await {
+            return (name, cached.unwrap_or(default_version));
+        }
+
+        let token = EIP3009Token::new(asset, Arc::new(provider.clone()));
+        let version = match token.version().call().await {
+            Ok(version) if !version.is_empty() => Some(version),
+            Ok(_) => None,
+            // The token has no usable `version()`
+            Err(
+                ContractError::Revert(_)
+                | ContractError::DecodingError(_)
+                | ContractError::AbiError(_)
+                | ContractError::DetokenizationError(_),
+            ) => None,
+            // Transient RPC failure; probe again next time
+            Err(_) => return (name, default_version),
+        };
+        cache.insert(chain_id, asset, version.clone()).await;
+        (name, version.unwrap_or(default_version))
+    }
+
+    /// Checks that the authorization's validity window is no longer than the
+    /// requirement's `maxTimeoutSeconds`, allowing for clock skew.
+    fn window_within_timeout(
+        valid_after: U256,
+        valid_before: U256,
+        requirements: &PaymentRequirements,
+    ) -> bool {
+        let max_window = requirements
+            .max_timeout_seconds
+            .saturating_add(VALIDITY_WINDOW_SKEW_SECONDS);
+        valid_before.saturating_sub(valid_after) <= U256::from(max_window)
+    }
+
+    /// Parses a 32-byte hex nonce.
+    fn parse_nonce(nonce: &str) -> Result<H256> {
+        let mut nonce_bytes = [0u8; 32];
+        hex::decode_to_slice(nonce.trim_start_matches("0x"), &mut nonce_bytes)
+            .map_err(|e| X402Error::InvalidPayload(format!("Invalid nonce: {}", e)))?;
+        Ok(H256::from(nonce_bytes))
+    }
+
+    /// Parses a hex signature, either 65 bytes (`r || s || v`) or 64-byte compact
+    /// EIP-2098 (`r || yParityAndS`), which is expanded to `r`, `s` and `v`.
+    fn parse_signature(signature: &str) -> Result<Signature> {
+        let sig_hex = signature.trim_start_matches("0x");
+        // 65 or 64 bytes * 2 hex chars
+        if sig_hex.len() != 130 && sig_hex.len() != 128 {
+            return Err(X402Error::SignatureError(format!(
+                "Expected 65-byte or 64-byte compact signature, got {} hex chars",
+                sig_hex.len()
+            )));
+        }
+
+        let mut sig_bytes = hex::decode(sig_hex)
+            .map_err(|e| X402Error::InvalidPayload(format!("Invalid signature: {}", e)))?;
+
+        if sig_bytes.len() == 64 {
+            // The top bit of `s` carries yParity
+            let y_parity = sig_bytes[32] >> 7;
+            sig_bytes[32] &= 0x7f;
+            sig_bytes.push(27 + y_parity);
+        }
+
+        Signature::try_from(sig_bytes.as_slice())
+            .map_err(|e| X402Error::SignatureError(e.to_string()))
+    }
+
+    /// Recovers the signer of `message_hash` from a hex signature.
+    fn recover_signer(signature: &str, message_hash: H256) -> Result<Address> {
+        Ok(Self::parse_signature(signature)?.recover(message_hash)?)
+    }
+
     /// Creates the domain separator for EIP-712.
-    fn create_domain_separator(
+    pub(crate) fn create_domain_separator(
         token_address: Address,
         chain_id: U256,
         name: &str,
@@ -97,7 +878,7 @@ impl ExactEvm {
         );
 
         H256::from(keccak256(
-            &ethers::abi::encode(&[
+            ethers::abi::encode(&[
                 Token::FixedBytes(type_hash.to_vec()),
                 Token::FixedBytes(keccak256(name.as_bytes()).to_vec()),
                 Token::FixedBytes(keccak256(version.as_bytes()).to_vec()),
@@ -108,6 +889,11 @@ impl ExactEvm {
     }
 }
 
+/// Error for a requirement whose asset has no contract code.
+fn not_a_contract(asset: Address) -> X402Error {
+    X402Error::ConfigError(format!("Asset {:?} is not a contract", asset))
+}
+
 impl Default for ExactEvm {
     fn default() -> Self {
         Self::new()
@@ -120,107 +906,80 @@ impl Scheme for ExactEvm {
         "exact"
     }
 
-    async fn generate_payload(
-        &self,
-        requirements: &PaymentRequirements,
-        private_key: &str,
-        rpc_url: &str,
-    ) -> Result<PaymentPayload> {
-        // Parse addresses and amounts
-        let to = parse_address(&requirements.pay_to)?;
-        let value = string_to_u256(&requirements.max_amount_required)?;
-        let asset = parse_address(&requirements.asset)?;
-
-        // Create wallet from private key
-        let wallet = private_key
-            .parse::<LocalWallet>()
-            .map_err(|e| X402Error::InvalidPayload(format!("Invalid private key: {}", e)))?;
-        let from = wallet.address();
-
-        // Connect to provider to get chain ID
-        let provider = Provider::<Http>::try_from(rpc_url)?;
-        let chain_id = provider.get_chainid().await?;
-
-        // Generate nonce and timestamps
-        let nonce_bytes: [u8; 32] = {
-            let nonce_str = generate_nonce();
-            let nonce_hex = nonce_str.trim_start_matches("0x");
-            let mut bytes = [0u8; 32];
-            hex::decode_to_slice(nonce_hex, &mut bytes)
-                .map_err(|e| X402Error::InvalidPayload(format!("Invalid nonce: {}", e)))?;
-            bytes
-        };
-        let nonce = H256::from(nonce_bytes);
-
-        let now = current_timestamp();
-        let valid_after = U256::from(now);
-        let valid_before = U256::from(now + requirements.max_timeout_seconds);
-
-        // Get token name and version from extra field or use defaults
-        let (token_name, token_version) = if let Some(extra) = &requirements.extra {
-            let name = extra
-                .get("name")
-                .and_then(|v| v.as_str())
-                .unwrap_or(EIP712_DOMAIN_NAME);
-            let version = extra
-                .get("version")
-                .and_then(|v| v.as_str())
-                .unwrap_or(EIP712_DOMAIN_VERSION);
-            (name.to_string(), version.to_string())
-        } else {
-            (EIP712_DOMAIN_NAME.to_string(), EIP712_DOMAIN_VERSION.to_string())
-        };
-
-        // Create domain separator and authorization hash
-        let domain_separator = Self::create_domain_separator(
-            asset,
-            chain_id,
-            &token_name,
-            &token_version,
-        );
+    fn capabilities(&self) -> SchemeCapabilities {
+        // Settlement is simulated with `eth_estimateGas`; verifying needs the nonce
+        // state on-chain
+        SchemeCapabilities {
+            settle: true,
+            simulate: true,
+            ..SchemeCapabilities::default()
+        }
+    }
 
-        let message_hash = Self::create_authorization_hash(
-            from,
-            to,
-            value,
-            valid_after,
-            valid_before,
-            nonce,
-            domain_separator,
-        );
+    async fn prevalidate(&self, requirements: &PaymentRequirements) -> Result<()> {
+        if requirements.scheme != self.name() {
+            return Err(X402Error::UnsupportedScheme(requirements.scheme.clone()));
+        }
 
-        // Sign the hash
-        let signature = wallet.sign_hash(message_hash)
-            .map_err(|e| X402Error::SignatureError(e.to_string()))?;
+        for (field, address) in [
+            ("payTo", &requirements.pay_to),
+            ("asset", &requirements.asset),
+        ] {
+            if parse_address(address)?.is_zero() {
+                return Err(X402Error::InvalidAddress(format!(
+                    "{} is the zero address",
+                    field
+                )));
+            }
+        }
+        if string_to_u256(&requirements.max_amount_required)?.is_zero() {
+            return Err(X402Error::InvalidAmount(
+                "maxAmountRequired must be greater than zero".to_string(),
+            ));
+        }
 
-        // Create the authorization object
-        // Convert r and s from U256 to [u8; 32]
-        let mut r_bytes = [0u8; 32];
-        signature.r.to_big_endian(&mut r_bytes);
-        let mut s_bytes = [0u8; 32];
-        signature.s.to_big_endian(&mut s_bytes);
-        
-        let mut sig_bytes = Vec::with_capacity(65);
-        sig_bytes.extend_from_slice(&r_bytes);
-        sig_bytes.extend_from_slice(&s_bytes);
-        sig_bytes.push(signature.v as u8);
-        
-        let authorization = TransferAuthorization {
-            from: format!("{:?}", from),
-            to: format!("{:?}", to),
-            value: value.to_string(),
-            valid_after: valid_after.to_string(),
-            valid_before: valid_before.to_string(),
-            nonce: format!("0x{}", hex::encode(nonce_bytes)),
-            signature: format!("0x{}", hex::encode(sig_bytes)),
+        // The token must take an EIP-3009 authorization in a domain we can sign in,
+        // which is checked against a pinned one once the chain id is known
+        self.authorization_type(requirements)?;
+        Self::pinned_domain_separator(requirements)?;
+        if let Some(chain_id) = self.chain_id {
+            let (token_name, token_version) = Self::token_metadata(requirements);
+            let derived = Self::create_domain_separator(
+                parse_address(&requirements.asset)?,
+                U256::from(chain_id),
+                &token_name,
+                &token_version,
+            );
+            Self::check_pinned_domain_separator(requirements, derived)?;
+        }
+        Ok(())
+    }
+
+    async fn generate_payload(
+        &self,
+        requirements: &PaymentRequirements,
+        private_key: &str,
+        rpc_url: &str,
+    ) -> Result<PaymentPayload> {
+        // Connect to provider to get chain ID, unless it is already known
+        let chain_id = match self.chain_id {
+            Some(chain_id) => chain_id,
+            None => {
+                let provider = Provider::<Http>::try_from(rpc_url)?;
+                self.rpc_chain_id(&provider).await?.as_u64()
+            }
         };
 
-        Ok(PaymentPayload {
-            x402_version: X402_VERSION,
-            scheme: self.name().to_string(),
-            network: requirements.network.clone(),
-            payload: json!(authorization),
-        })
+        // Get token name and version from extra field or use defaults
+        let (token_name, token_version) = Self::token_metadata(requirements);
+
+        self.generate_payload_offline(
+            requirements,
+            private_key,
+            chain_id,
+            &token_name,
+            &token_version,
+        )
     }
 
     async fn verify(
@@ -230,8 +989,7 @@ impl Scheme for ExactEvm {
         rpc_url: &str,
     ) -> Result<bool> {
         // Parse the authorization from payload
-        let auth: TransferAuthorization = serde_json::from_value(payload.payload.clone())
-            .map_err(|e| X402Error::InvalidPayload(format!("Invalid authorization: {}", e)))?;
+        let auth = TransferAuthorization::from_value(&payload.payload)?;
 
         // Verify scheme and network match
         if payload.scheme != self.name() {
@@ -246,73 +1004,62 @@ impl Scheme for ExactEvm {
         let to = parse_address(&auth.to)?;
         let value = string_to_u256(&auth.value)?;
         let expected_to = parse_address(&requirements.pay_to)?;
-        let expected_value = string_to_u256(&requirements.max_amount_required)?;
+        let (min_value, max_value) = amount_range(requirements)?;
         let asset = parse_address(&requirements.asset)?;
 
-        // Verify to and value match requirements
+        // Verify payee matches and value is within the accepted range
         if to != expected_to {
             return Ok(false);
         }
-        if value != expected_value {
+        if value < min_value || value > max_value {
             return Ok(false);
         }
 
         // Verify timestamps
         let valid_after = string_to_u256(&auth.valid_after)?;
         let valid_before = string_to_u256(&auth.valid_before)?;
-        let now = U256::from(current_timestamp());
+        let now = U256::from(self.now());
 
-        if now < valid_after || now > valid_before {
+        if valid_after > self.latest_valid_after(now) || now > valid_before {
+            return Ok(false);
+        }
+        if !Self::window_within_timeout(valid_after, valid_before, requirements) {
             return Ok(false);
         }
 
-        // Connect to provider
-        let provider = Provider::<Http>::try_from(rpc_url)?;
-        let chain_id = provider.get_chainid().await?;
+        // Check the asset is a token contract; a misconfigured address would otherwise
+        // make every nonce look used. An unreachable node fails the nonce check below.
+        let token_client = self.token_client(rpc_url)?;
+        if let Ok(false) = token_client.has_code(asset).await {
+            return Err(not_a_contract(asset));
+        }
 
-        // Get token name and version
-        let (token_name, token_version) = if let Some(extra) = &requirements.extra {
-            let name = extra
-                .get("name")
-                .and_then(|v| v.as_str())
-                .unwrap_or(EIP712_DOMAIN_NAME);
-            let version = extra
-                .get("version")
-                .and_then(|v| v.as_str())
-                .unwrap_or(EIP712_DOMAIN_VERSION);
-            (name, version)
-        } else {
-            (EIP712_DOMAIN_NAME, EIP712_DOMAIN_VERSION)
-        };
+        // Connect to provider and find the domain the authorization must be signed in
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let (domain_separator, mismatch) = self
+            .verification_domain_separator(requirements, &provider, asset)
+            .await?;
+        if mismatch.is_some() {
+            return Ok(false);
+        }
+        let authorization_type = self.authorization_type(requirements)?;
 
         // Parse nonce
-        let nonce_hex = auth.nonce.trim_start_matches("0x");
-        let mut nonce_bytes = [0u8; 32];
-        hex::decode_to_slice(nonce_hex, &mut nonce_bytes)
-            .map_err(|e| X402Error::InvalidPayload(format!("Invalid nonce: {}", e)))?;
-        let nonce = H256::from(nonce_bytes);
+        let nonce = Self::parse_nonce(&auth.nonce)?;
 
         // Check if nonce was already used on-chain
-        let token_contract = EIP3009Token::new(asset, Arc::new(provider.clone()));
-        let is_used = token_contract
-            .authorization_state(from, nonce.into())
-            .call()
+        let is_used = self
+            .authorization_state(token_client.as_ref(), asset, from, nonce)
             .await
-            .unwrap_or(true); // Assume used if call fails
+            .unwrap_or(true); // Assume used if every attempt fails
 
         if is_used {
             return Err(X402Error::NonceUsed(auth.nonce.clone()));
         }
 
         // Verify signature
-        let domain_separator = Self::create_domain_separator(
-            asset,
-            chain_id,
-            token_name,
-            token_version,
-        );
-
         let message_hash = Self::create_authorization_hash(
+            &authorization_type,
             from,
             to,
             value,
@@ -322,103 +1069,285 @@ impl Scheme for ExactEvm {
             domain_separator,
         );
 
-        // Parse signature
-        let sig_hex = auth.signature.trim_start_matches("0x");
-        if sig_hex.len() != 130 {
-            // 65 bytes * 2 hex chars
-            return Ok(false);
-        }
-
-        let sig_bytes = hex::decode(sig_hex)
-            .map_err(|e| X402Error::InvalidPayload(format!("Invalid signature: {}", e)))?;
-
-        let signature = Signature::try_from(sig_bytes.as_slice())
-            .map_err(|e| X402Error::SignatureError(e.to_string()))?;
-
         // Recover signer from signature
-        let recovered = signature.recover(message_hash)?;
+        match Self::recover_signer(&auth.signature, message_hash) {
+            Ok(recovered) if recovered != from => return Ok(false),
+            Ok(_) => {}
+            Err(X402Error::SignatureError(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        }
 
-        Ok(recovered == from)
+        // Check the payer also signed the surrounding x402 message, if required
+        if !message_signature_required(requirements) {
+            return Ok(true);
+        }
+        match recover_message_signer(payload) {
+            Ok(recovered) => Ok(recovered == from),
+            Err(X402Error::SignatureError(_) | X402Error::MissingField(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
-    async fn settle(
+    async fn verify_detailed(
         &self,
         payload: &PaymentPayload,
         requirements: &PaymentRequirements,
         rpc_url: &str,
-        facilitator_key: &str,
-    ) -> Result<String> {
-        // Parse the authorization
-        let auth: TransferAuthorization = serde_json::from_value(payload.payload.clone())
-            .map_err(|e| X402Error::InvalidPayload(format!("Invalid authorization: {}", e)))?;
-
-        // Parse signature components
-        let sig_hex = auth.signature.trim_start_matches("0x");
-        let sig_bytes = hex::decode(sig_hex)
-            .map_err(|e| X402Error::InvalidPayload(format!("Invalid signature: {}", e)))?;
+    ) -> Result<Vec<String>> {
+        let auth = TransferAuthorization::from_value(&payload.payload)?;
+        let mut failures = Vec::new();
 
-        let r = H256::from_slice(&sig_bytes[0..32]);
-        let s = H256::from_slice(&sig_bytes[32..64]);
-        let v = sig_bytes[64];
+        if payload.scheme != self.name() {
+            failures.push(format!(
+                "Scheme mismatch: expected {}, got {}",
+                self.name(),
+                payload.scheme
+            ));
+        }
+        if payload.network != requirements.network {
+            failures.push(format!(
+                "Network mismatch: expected {}, got {}",
+                requirements.network, payload.network
+            ));
+        }
 
-        // Parse addresses and values
         let from = parse_address(&auth.from)?;
         let to = parse_address(&auth.to)?;
         let value = string_to_u256(&auth.value)?;
+        let expected_to = parse_address(&requirements.pay_to)?;
+        let (min_value, max_value) = amount_range(requirements)?;
         let asset = parse_address(&requirements.asset)?;
-
-        let nonce_hex = auth.nonce.trim_start_matches("0x");
-        let mut nonce_bytes = [0u8; 32];
-        hex::decode_to_slice(nonce_hex, &mut nonce_bytes)
-            .map_err(|e| X402Error::InvalidPayload(format!("Invalid nonce: {}", e)))?;
-        let nonce = H256::from(nonce_bytes);
-
+        let nonce = Self::parse_nonce(&auth.nonce)?;
         let valid_after = string_to_u256(&auth.valid_after)?;
         let valid_before = string_to_u256(&auth.valid_before)?;
 
-        // Create wallet and provider
-        let wallet = facilitator_key
-            .parse::<LocalWallet>()
-            .map_err(|e| X402Error::ConfigError(format!("Invalid facilitator key: {}", e)))?;
+        if to != expected_to {
+            failures.push(format!("Payee mismatch: expected {:?}, got {:?}", expected_to, to));
+        }
+        if value < min_value || value > max_value {
+            failures.push(format!(
+                "Amount {} outside accepted range [{}, {}]",
+                value, min_value, max_value
+            ));
+        }
+
+        let now = U256::from(self.now());
+        if valid_after > self.latest_valid_after(now) {
+            failures.push(format!("Authorization not valid until {}", valid_after));
+        } else if now > valid_before {
+            failures.push(format!("{} at {}", AUTHORIZATION_EXPIRED_REASON, valid_before));
+        }
+        if !Self::window_within_timeout(valid_after, valid_before, requirements) {
+            failures.push(format!(
+                "Authorization window of {}s exceeds maxTimeoutSeconds {}",
+                valid_before.saturating_sub(valid_after),
+                requirements.max_timeout_seconds
+            ));
+        }
+
         let provider = Provider::<Http>::try_from(rpc_url)?;
-        let chain_id = provider.get_chainid().await?;
-        let client = SignerMiddleware::new(provider, wallet.with_chain_id(chain_id.as_u64()));
-        let client = Arc::new(client);
+        let (domain_separator, mismatch) = self
+            .verification_domain_separator(requirements, &provider, asset)
+            .await?;
+        failures.extend(mismatch);
+
+        let token_client = self.token_client(rpc_url)?;
+        if let Ok(false) = token_client.has_code(asset).await {
+            failures.push(not_a_contract(asset).to_string());
+        }
 
-        // Create contract instance
-        let token_contract = EIP3009Token::new(asset, client);
+        match self
+            .authorization_state(token_client.as_ref(), asset, from, nonce)
+            .await
+        {
+            Ok(true) => failures.push(format!("Nonce already used: {}", auth.nonce)),
+            Ok(false) => {}
+            Err(e) => failures.push(format!("Nonce state unavailable: {}", e)),
+        }
 
-        // Call transferWithAuthorization and get pending transaction
-        let call = token_contract.transfer_with_authorization(
+        let authorization_type = self.authorization_type(requirements)?;
+        let message_hash = Self::create_authorization_hash(
+            &authorization_type,
             from,
             to,
             value,
             valid_after,
             valid_before,
-            nonce.into(),
-            v,
-            r.into(),
-            s.into(),
+            nonce,
+            domain_separator,
         );
 
-        let pending_tx = call
-            .send()
-            .await
-            .map_err(|e| X402Error::SettlementError(format!("Transaction failed: {}", e)))?;
+        match Self::recover_signer(&auth.signature, message_hash) {
+            Ok(recovered) if recovered == from => {}
+            Ok(recovered) => failures.push(format!(
+                "Signature recovers to {:?}, not payer {:?}",
+                recovered, from
+            )),
+            Err(e) => failures.push(e.to_string()),
+        }
 
-        // Wait for confirmation
-        let receipt = pending_tx
+        if message_signature_required(requirements) {
+            match recover_message_signer(payload) {
+                Ok(recovered) if recovered == from => {}
+                Ok(recovered) => failures.push(format!(
+                    "Message signature recovers to {:?}, not payer {:?}",
+                    recovered, from
+                )),
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+
+        Ok(failures)
+    }
+
+    async fn settle(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+        facilitator_key: &str,
+    ) -> Result<SettlementInfo> {
+        let transfer = Self::signed_transfer(payload)?;
+        let asset = parse_address(&requirements.asset)?;
+        let receipt = self
+            .settle_transfers(std::slice::from_ref(&transfer), asset, rpc_url, facilitator_key)
+            .await?;
+
+        Ok(SettlementInfo {
+            payer: format!("{:?}", transfer.from),
+            pay_to: format!("{:?}", transfer.to),
+            amount: transfer.value.to_string(),
+            asset: format!("{:?}", asset),
+            network: payload.network.clone(),
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+            block_number: receipt.block_number.map(|n| n.as_u64()),
+        })
+    }
+}
+
+/// Checks that the `Transfer` events of `asset` in `receipt` credit each payee with
+/// exactly the value transferred to them.
+fn check_payee_transfers(
+    receipt: &TransactionReceipt,
+    asset: Address,
+    transfers: &[SignedTransfer],
+) -> Result<()> {
+    let transfer_topic = H256::from(keccak256("Transfer(address,address,uint256)"));
+    let sum_between = |from: Address, to: Address| {
+        receipt
+            .logs
+            .iter()
+            .filter(|log| log.address == asset && log.topics.len() == 3)
+            .filter(|log| log.topics[0] == transfer_topic)
+            .filter(|log| Address::from(log.topics[1]) == from)
+            .filter(|log| Address::from(log.topics[2]) == to)
+            .fold(U256::zero(), |sum, log| {
+                sum.saturating_add(U256::from_big_endian(&log.data))
+            })
+    };
+
+    for transfer in transfers {
+        let (from, to) = (transfer.from, transfer.to);
+        let expected = transfers
+            .iter()
+            .filter(|other| other.from == from && other.to == to)
+            .fold(U256::zero(), |sum, other| sum.saturating_add(other.value));
+        let received = sum_between(from, to);
+        if received != expected {
+            let tx_hash = receipt.transaction_hash;
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Payee {:?} received {} instead of {} in {:?}",
+                to,
+                received,
+                expected,
+                tx_hash
+            );
+            return Err(X402Error::SettlementError(format!(
+                "Payee transfer mismatch in {:?}: {:?} received {} instead of {}",
+                tx_hash, to, received, expected
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Polls for a transaction receipt until one is available or the timeout elapses.
+///
+/// Providers return `None` while a transaction is still in the mempool, so a missing
+/// receipt is retried rather than treated as a failure.
+pub(crate) async fn wait_for_receipt<M: Middleware>(
+    provider: &M,
+    tx_hash: H256,
+    poll_interval: Duration,
+    timeout: Duration,
+) -> Result<TransactionReceipt> {
+    let deadline = tokio::time::Instant::now() + timeout;
+
+    loop {
+        let receipt = provider
+            .get_transaction_receipt(tx_hash)
             .await
-            .map_err(|e| X402Error::SettlementError(format!("Receipt error: {}", e)))?
-            .ok_or_else(|| X402Error::SettlementError("No receipt".to_string()))?;
+            .map_err(|e| X402Error::SettlementError(format!("Receipt error: {}", e)))?;
+
+        if let Some(receipt) = receipt {
+            return Ok(receipt);
+        }
+
+        if tokio::time::Instant::now() + poll_interval > deadline {
+            return Err(X402Error::SettlementError(format!(
+                "No receipt for {:?} after {:?}",
+                tx_hash, timeout
+            )));
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Decodes `Error(string)` and `Panic(uint256)` revert data into a readable reason.
+///
+/// Returns `None` for empty data and custom errors.
+pub(crate) fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+    const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
 
-        Ok(format!("{:?}", receipt.transaction_hash))
+    if data.len() < 4 {
+        return None;
     }
+    let (selector, args) = data.split_at(4);
+
+    if selector == ERROR_SELECTOR {
+        let tokens = ethers::abi::decode(&[ethers::abi::ParamType::String], args).ok()?;
+        return tokens.into_iter().next()?.into_string();
+    }
+
+    if selector == PANIC_SELECTOR {
+        let tokens = ethers::abi::decode(&[ethers::abi::ParamType::Uint(256)], args).ok()?;
+        let code = tokens.into_iter().next()?.into_uint()?;
+        let description = match code.low_u64() {
+            0x01 => "assertion failed",
+            0x11 => "arithmetic overflow or underflow",
+            0x12 => "division or modulo by zero",
+            0x21 => "invalid enum value",
+            0x22 => "invalid storage byte array",
+            0x31 => "pop on empty array",
+            0x32 => "array index out of bounds",
+            0x41 => "out of memory",
+            0x51 => "call to uninitialized function",
+            _ => "unknown panic",
+        };
+        return Some(format!("panic 0x{:02x} ({})", code, description));
+    }
+
+    None
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::schemes::apply_tx_type;
+    use ethers::types::transaction::eip2718::TypedTransaction;
 
     #[test]
     fn test_exact_evm_name() {
@@ -426,6 +1355,645 @@ mod tests {
         assert_eq!(scheme.name(), "exact");
     }
 
+    #[test]
+    fn test_exact_evm_capabilities() {
+        let capabilities = ExactEvm::new().capabilities();
+        assert_eq!(
+            capabilities,
+            SchemeCapabilities {
+                settle: true,
+                batch_settle: false,
+                cancel: false,
+                simulate: true,
+                offline_verify: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_revert_reason() {
+        let reason = "FiatTokenV2: authorization is used or canceled";
+        let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+        data.extend(ethers::abi::encode(&[Token::String(reason.to_string())]));
+        assert_eq!(decode_revert_reason(&data).as_deref(), Some(reason));
+
+        let mut data = vec![0x4e, 0x48, 0x7b, 0x71];
+        data.extend(ethers::abi::encode(&[Token::Uint(U256::from(0x11))]));
+        assert_eq!(
+            decode_revert_reason(&data).as_deref(),
+            Some("panic 0x11 (arithmetic overflow or underflow)")
+        );
+
+        assert_eq!(decode_revert_reason(&[0xde, 0xad, 0xbe, 0xef]), None);
+    }
+
+    #[test]
+    fn test_compact_signature_recovers_same_address() {
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let wallet: LocalWallet = key.parse().unwrap();
+        let hash = H256::from(keccak256(b"x402"));
+        let signature = wallet.sign_hash(hash).unwrap();
+        let expanded = format!("0x{}", hex::encode(signature.to_vec()));
+
+        // EIP-2098: fold yParity into the top bit of `s`
+        let mut compact = signature.to_vec();
+        compact.pop();
+        if signature.v == 28 {
+            compact[32] |= 0x80;
+        }
+        let compact = format!("0x{}", hex::encode(compact));
+
+        assert_eq!(ExactEvm::parse_signature(&compact).unwrap(), signature);
+        assert_eq!(ExactEvm::recover_signer(&compact, hash).unwrap(), wallet.address());
+        assert_eq!(ExactEvm::recover_signer(&expanded, hash).unwrap(), wallet.address());
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_over_long_window() {
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }))
+        .unwrap();
+        let now = current_timestamp();
+        let payload = PaymentPayload {
+            x402_version: 1,
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            payload: json!(TransferAuthorization {
+                from: "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".to_string(),
+                to: requirements.pay_to.clone(),
+                value: "10000".to_string(),
+                valid_after: now.to_string(),
+                valid_before: (now + 86_400).to_string(),
+                nonce: format!("0x{}", "11".repeat(32)),
+                signature: format!("0x{}", "00".repeat(65)),
+            }),
+            challenge: None,
+            message_signature: None,
+        };
+
+        // Rejected before any RPC call, so the endpoint is never contacted
+        let valid = ExactEvm::new()
+            .verify(&payload, &requirements, "http://127.0.0.1:1")
+            .await
+            .unwrap();
+        assert!(!valid);
+    }
+
+    /// Token answering `authorizationState` from a fixed set of used nonces.
+    #[derive(Default)]
+    struct MockToken {
+        used: std::sync::Mutex<Vec<(Address, H256)>>,
+        // Number of `authorizationState` reads to fail before answering
+        state_errors: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait]
+    impl TokenClient for MockToken {
+        async fn authorization_state(
+            &self,
+            _asset: Address,
+            authorizer: Address,
+            nonce: H256,
+        ) -> Result<bool> {
+            use std::sync::atomic::Ordering;
+            let failing = self
+                .state_errors
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing {
+                return Err(X402Error::BlockchainError("nonce not found".to_string()));
+            }
+            Ok(self.used.lock().unwrap().contains(&(authorizer, nonce)))
+        }
+
+        async fn balance_of(&self, _: Address, _: Address, _: Option<U64>) -> Result<U256> {
+            Ok(U256::zero())
+        }
+
+        async fn decimals(&self, _asset: Address) -> Result<u8> {
+            Ok(6)
+        }
+
+        async fn has_code(&self, _asset: Address) -> Result<bool> {
+            Ok(true)
+        }
+
+        async fn transfer_with_authorization(
+            &self,
+            _asset: Address,
+            transfer: &SignedTransfer,
+        ) -> Result<H256> {
+            self.used.lock().unwrap().push((transfer.from, transfer.nonce));
+            Ok(H256::repeat_byte(0xab))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_with_mock_token_client() {
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }))
+        .unwrap();
+        // No RPC is reachable here: every chain read goes to the mock
+        let rpc_url = "http://127.0.0.1:1";
+        let payload = ExactEvm::new()
+            .with_chain_id(8453)
+            .generate_payload(
+                &requirements,
+                "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                rpc_url,
+            )
+            .await
+            .unwrap();
+        let transfer = ExactEvm::signed_transfer(&payload).unwrap();
+
+        let token = Arc::new(MockToken::default());
+        let scheme = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_token_client(token.clone());
+        assert!(scheme.verify(&payload, &requirements, rpc_url).await.unwrap());
+        assert!(scheme
+            .verify_detailed(&payload, &requirements, rpc_url)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Signed for another chain, the authorization no longer recovers to the payer
+        let other_chain = ExactEvm::new()
+            .with_chain_id(84532)
+            .with_token_client(token.clone());
+        assert!(!other_chain.verify(&payload, &requirements, rpc_url).await.unwrap());
+
+        // Once the nonce is used on-chain, verification refuses the replay
+        token.used.lock().unwrap().push((transfer.from, transfer.nonce));
+        assert!(matches!(
+            scheme.verify(&payload, &requirements, rpc_url).await,
+            Err(X402Error::NonceUsed(_))
+        ));
+        let failures = scheme
+            .verify_detailed(&payload, &requirements, rpc_url)
+            .await
+            .unwrap();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("Nonce already used"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_against_pinned_domain_separator() {
+        let asset = parse_address("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913").unwrap();
+        let pinned_requirements = |domain_separator: H256| -> PaymentRequirements {
+            serde_json::from_value(json!({
+                "scheme": "exact",
+                "network": "8453",
+                "maxAmountRequired": "10000",
+                "resource": "/test",
+                "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+                "maxTimeoutSeconds": 300,
+                "asset": format!("{:?}", asset),
+                "extra": { "domainSeparator": format!("{:?}", domain_separator) }
+            }))
+            .unwrap()
+        };
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let rpc_url = "http://127.0.0.1:1";
+        let base = ExactEvm::create_domain_separator(
+            asset,
+            U256::from(8453),
+            EIP712_DOMAIN_NAME,
+            EIP712_DOMAIN_VERSION,
+        );
+        let sepolia = ExactEvm::create_domain_separator(
+            asset,
+            U256::from(84532),
+            EIP712_DOMAIN_NAME,
+            EIP712_DOMAIN_VERSION,
+        );
+        let scheme = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_token_client(Arc::new(MockToken::default()));
+        let unchecked = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_token_client(Arc::new(MockToken::default()))
+            .with_domain_separator_check(false);
+
+        // Pinned to the domain derived from the asset and chain
+        let requirements = pinned_requirements(base);
+        scheme.prevalidate(&requirements).await.unwrap();
+        let payload = scheme
+            .generate_payload_offline(&requirements, key, 8453, "USD Coin", "2")
+            .unwrap();
+        assert!(scheme.verify(&payload, &requirements, rpc_url).await.unwrap());
+        assert!(scheme
+            .verify_detailed(&payload, &requirements, rpc_url)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Pinned to another chain's domain, the payer refuses to sign in it
+        let requirements = pinned_requirements(sepolia);
+        let err = scheme.prevalidate(&requirements).await.unwrap_err();
+        assert!(err.to_string().contains("Domain separator mismatch"));
+        let err = scheme
+            .generate_payload_offline(&requirements, key, 8453, "USD Coin", "2")
+            .unwrap_err();
+        assert!(err.to_string().contains("Domain separator mismatch"));
+
+        // A payment signed in that domain anyway is refused unless the check is off
+        let payload = scheme
+            .generate_payload_offline(&requirements, key, 84532, "USD Coin", "2")
+            .unwrap();
+        assert!(!scheme.verify(&payload, &requirements, rpc_url).await.unwrap());
+        let failures = scheme
+            .verify_detailed(&payload, &requirements, rpc_url)
+            .await
+            .unwrap();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("Domain separator mismatch"));
+        assert!(unchecked.verify(&payload, &requirements, rpc_url).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_retries_failed_nonce_state() {
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }))
+        .unwrap();
+        let rpc_url = "http://127.0.0.1:1";
+        let payload = ExactEvm::new()
+            .generate_payload_offline(
+                &requirements,
+                "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                8453,
+                "USD Coin",
+                "2",
+            )
+            .unwrap();
+
+        // The node fails the first read, then answers `false`
+        let token = Arc::new(MockToken::default());
+        token.state_errors.store(1, std::sync::atomic::Ordering::SeqCst);
+        let scheme = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_nonce_state_retries(2, Duration::ZERO)
+            .with_token_client(token.clone());
+        assert!(scheme.verify(&payload, &requirements, rpc_url).await.unwrap());
+
+        // Failing every attempt, the nonce is still assumed used
+        token.state_errors.store(3, std::sync::atomic::Ordering::SeqCst);
+        assert!(matches!(
+            scheme.verify(&payload, &requirements, rpc_url).await,
+            Err(X402Error::NonceUsed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_generate_payload_offline() {
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+            "extra": { "name": "USD Coin", "version": "2" }
+        }))
+        .unwrap();
+
+        // Signed with no RPC at all
+        let payload = ExactEvm::new()
+            .generate_payload_offline(
+                &requirements,
+                "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                8453,
+                "USD Coin",
+                "2",
+            )
+            .unwrap();
+        let auth = payload.typed_payload::<TransferAuthorization>().unwrap();
+        assert_eq!(auth.from, "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+        assert_eq!(auth.value, "10000");
+
+        // Carried online, it verifies for Base; a different domain rejects it
+        let rpc_url = "http://127.0.0.1:1";
+        let scheme = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_token_client(Arc::new(MockToken::default()));
+        assert!(scheme.verify(&payload, &requirements, rpc_url).await.unwrap());
+
+        let mut renamed = requirements.clone();
+        renamed.extra = Some(json!({ "name": "Bridged USDC", "version": "2" }));
+        assert!(!scheme.verify(&payload, &renamed, rpc_url).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_valid_after_offset() {
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }))
+        .unwrap();
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let valid_after = |scheme: ExactEvm| {
+            let before = current_timestamp();
+            let payload = scheme
+                .generate_payload_offline(&requirements, key, 8453, "USD Coin", "2")
+                .unwrap();
+            let auth = payload.typed_payload::<TransferAuthorization>().unwrap();
+            let valid_after: u64 = auth.valid_after.parse().unwrap();
+            let valid_before: u64 = auth.valid_before.parse().unwrap();
+            (before - valid_after, valid_before - valid_after)
+        };
+
+        // Allow for the clock ticking over while signing
+        let (behind, window) = valid_after(ExactEvm::new());
+        assert!((9..=10).contains(&behind), "behind by {}", behind);
+        assert!((310..=311).contains(&window));
+
+        let offset = Duration::from_secs(25);
+        let (behind, _) = valid_after(ExactEvm::new().with_valid_after_offset(offset));
+        assert!((24..=25).contains(&behind), "behind by {}", behind);
+
+        // Clamped to the facilitator's allowance for skew, so it still verifies
+        let offset = Duration::from_secs(120);
+        let (behind, _) = valid_after(ExactEvm::new().with_valid_after_offset(offset));
+        assert!((29..=30).contains(&behind), "behind by {}", behind);
+        let scheme = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_valid_after_offset(offset)
+            .with_token_client(Arc::new(MockToken::default()));
+        let payload = scheme
+            .generate_payload_offline(&requirements, key, 8453, "USD Coin", "2")
+            .unwrap();
+        assert!(scheme.verify(&payload, &requirements, "http://127.0.0.1:1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_accepts_amounts_within_range() {
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "20000",
+            "minAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }))
+        .unwrap();
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let rpc_url = "http://127.0.0.1:1";
+        let scheme = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_token_client(Arc::new(MockToken::default()));
+        let verifies = |value: u64| {
+            let mut signed = requirements.clone();
+            signed.max_amount_required = value.to_string();
+            let payload = scheme
+                .generate_payload_offline(&signed, key, 8453, "USD Coin", "2")
+                .unwrap();
+            let (scheme, requirements) = (&scheme, &requirements);
+            async move {
+                scheme
+                    .verify(&payload, requirements, rpc_url)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert!(!verifies(9999).await);
+        assert!(verifies(10000).await);
+        assert!(verifies(15000).await);
+        assert!(verifies(20000).await);
+        assert!(!verifies(20001).await);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_future_valid_after() {
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }))
+        .unwrap();
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let rpc_url = "http://127.0.0.1:1";
+        let now = 1_700_000_000;
+        let signed_at = |at: u64| {
+            ExactEvm::new()
+                .with_clock(move || at)
+                .with_valid_after_offset(Duration::ZERO)
+                .generate_payload_offline(&requirements, key, 8453, "USD Coin", "2")
+                .unwrap()
+        };
+        let scheme = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_clock(move || now)
+            .with_valid_after_skew(Duration::from_secs(30))
+            .with_token_client(Arc::new(MockToken::default()));
+
+        // Valid an hour from now
+        let payload = signed_at(now + 3600);
+        assert!(!scheme.verify(&payload, &requirements, rpc_url).await.unwrap());
+        let failures = scheme
+            .verify_detailed(&payload, &requirements, rpc_url)
+            .await
+            .unwrap();
+        assert_eq!(
+            failures,
+            vec![format!("Authorization not valid until {}", now + 3600)]
+        );
+
+        // A payer clock running ahead within the skew is tolerated
+        let payload = signed_at(now + 20);
+        assert!(scheme.verify(&payload, &requirements, rpc_url).await.unwrap());
+        let strict = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_clock(move || now)
+            .with_token_client(Arc::new(MockToken::default()));
+        assert!(!strict.verify(&payload, &requirements, rpc_url).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_custom_authorization_type_recovers_signer() {
+        let custom = "ReceiveWithAuthorization(address from,address to,uint256 value,\
+                      uint256 validAfter,uint256 validBefore,bytes32 nonce)";
+        let mut requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+            "extra": { "authorizationType": custom }
+        }))
+        .unwrap();
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let scheme = ExactEvm::new().with_chain_id(8453);
+        let payload = scheme
+            .generate_payload(&requirements, key, "http://127.0.0.1:1")
+            .await
+            .unwrap();
+        let auth: TransferAuthorization = payload.typed_payload().unwrap();
+
+        let hash_for = |type_string: &str| {
+            let domain = ExactEvm::create_domain_separator(
+                parse_address(&requirements.asset).unwrap(),
+                U256::from(8453u64),
+                EIP712_DOMAIN_NAME,
+                EIP712_DOMAIN_VERSION,
+            );
+            ExactEvm::create_authorization_hash(
+                type_string,
+                parse_address(&auth.from).unwrap(),
+                parse_address(&auth.to).unwrap(),
+                string_to_u256(&auth.value).unwrap(),
+                string_to_u256(&auth.valid_after).unwrap(),
+                string_to_u256(&auth.valid_before).unwrap(),
+                ExactEvm::parse_nonce(&auth.nonce).unwrap(),
+                domain,
+            )
+        };
+        let signer = parse_address(&auth.from).unwrap();
+        let recovered = ExactEvm::recover_signer(&auth.signature, hash_for(custom)).unwrap();
+        assert_eq!(recovered, signer);
+        let default_hash = hash_for(DEFAULT_AUTHORIZATION_TYPE);
+        let default = ExactEvm::recover_signer(&auth.signature, default_hash).unwrap();
+        assert_ne!(default, signer);
+
+        requirements.extra = Some(json!({ "authorizationType": "Transfer(address from)" }));
+        let err = scheme
+            .generate_payload(&requirements, key, "http://127.0.0.1:1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, X402Error::ConfigError(_)));
+    }
+
+    #[test]
+    fn test_sign_typed_data_v4_vector() {
+        // A wallet's `eth_signTypedData_v4` request and signature for USDC on Base
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/sign_typed_data_v4.json");
+        let vector: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let request = &vector["request"];
+        let (domain, message) = (&request["domain"], &request["message"]);
+        let field = |key: &str| message[key].as_str().unwrap();
+
+        // Same struct name, field order and types as the wallet was asked to sign
+        let fields: Vec<String> = request["types"]["TransferWithAuthorization"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| format!("{} {}", f["type"].as_str().unwrap(), f["name"].as_str().unwrap()))
+            .collect();
+        let type_string = format!("TransferWithAuthorization({})", fields.join(","));
+        assert_eq!(type_string, DEFAULT_AUTHORIZATION_TYPE);
+
+        let domain_separator = ExactEvm::create_domain_separator(
+            parse_address(domain["verifyingContract"].as_str().unwrap()).unwrap(),
+            U256::from(domain["chainId"].as_u64().unwrap()),
+            domain["name"].as_str().unwrap(),
+            domain["version"].as_str().unwrap(),
+        );
+        let from = parse_address(field("from")).unwrap();
+        let digest = ExactEvm::create_authorization_hash(
+            DEFAULT_AUTHORIZATION_TYPE,
+            from,
+            parse_address(field("to")).unwrap(),
+            string_to_u256(field("value")).unwrap(),
+            string_to_u256(field("validAfter")).unwrap(),
+            string_to_u256(field("validBefore")).unwrap(),
+            ExactEvm::parse_nonce(field("nonce")).unwrap(),
+            domain_separator,
+        );
+        assert_eq!(format!("{:?}", digest), vector["digest"].as_str().unwrap());
+
+        let signature = vector["signature"].as_str().unwrap();
+        assert_eq!(ExactEvm::recover_signer(signature, digest).unwrap(), from);
+    }
+
+    #[tokio::test]
+    async fn test_generate_payload_golden_vector() {
+        // Signs the fixture's message with a fixed clock and nonce; RFC 6979 signatures
+        // are deterministic, so any change to the wire format shows up here
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/sign_typed_data_v4.json");
+        let vector: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let message = &vector["request"]["message"];
+        let field = |key: &str| message[key].as_str().unwrap().to_string();
+
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": field("value"),
+            "resource": "/test",
+            "payTo": field("to"),
+            "maxTimeoutSeconds": 3600,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }))
+        .unwrap();
+        let signed_at: u64 = field("validAfter").parse().unwrap();
+        let nonce = ExactEvm::parse_nonce(&field("nonce")).unwrap();
+        let scheme = ExactEvm::new()
+            .with_chain_id(8453)
+            .with_clock(move || signed_at)
+            .with_nonce_generator(move || nonce)
+            .with_valid_after_offset(Duration::ZERO)
+            .with_token_client(Arc::new(MockToken::default()));
+
+        let payload = scheme
+            .generate_payload_offline(
+                &requirements,
+                "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                8453,
+                "USD Coin",
+                "2",
+            )
+            .unwrap();
+        let auth = payload.typed_payload::<TransferAuthorization>().unwrap();
+        assert_eq!(auth.from, field("from").to_lowercase());
+        assert_eq!(auth.valid_after, field("validAfter"));
+        assert_eq!(auth.valid_before, field("validBefore"));
+        assert_eq!(auth.nonce, field("nonce"));
+        assert_eq!(auth.signature, vector["signature"].as_str().unwrap());
+
+        // Verified at signing time, the signature recovers to the payer
+        let valid = scheme
+            .verify(&payload, &requirements, "http://127.0.0.1:1")
+            .await
+            .unwrap();
+        assert!(valid);
+    }
+
     #[test]
     fn test_domain_separator() {
         let token = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".parse().unwrap();
@@ -440,5 +2008,77 @@ mod tests {
         
         assert_ne!(domain, H256::zero());
     }
+
+    #[test]
+    fn test_legacy_tx_type_builds_type0_transaction() {
+        let (provider, _mock) = Provider::mocked();
+        let token = EIP3009Token::new(Address::zero(), Arc::new(provider));
+        let call = || {
+            token.transfer_with_authorization(
+                Address::zero(),
+                Address::zero(),
+                U256::one(),
+                U256::zero(),
+                U256::MAX,
+                [0u8; 32],
+                27,
+                [0u8; 32],
+                [0u8; 32],
+            )
+        };
+
+        let legacy = apply_tx_type(call(), TxType::Legacy, 8453);
+        assert!(matches!(legacy.tx, TypedTransaction::Legacy(_)));
+
+        let eip1559 = apply_tx_type(call(), TxType::Eip1559, 8453);
+        assert!(matches!(eip1559.tx, TypedTransaction::Eip1559(_)));
+
+        let auto_unknown_chain = apply_tx_type(call(), TxType::Auto, 999_999);
+        assert!(matches!(auto_unknown_chain.tx, TypedTransaction::Legacy(_)));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_receipt_retries_pending() {
+        let (provider, mock) = Provider::mocked();
+        let tx_hash = H256::repeat_byte(0x11);
+        let receipt = TransactionReceipt {
+            transaction_hash: tx_hash,
+            ..Default::default()
+        };
+
+        // Responses are popped in LIFO order: None, None, then the receipt
+        mock.push::<Option<TransactionReceipt>, _>(Some(receipt)).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+
+        let result = wait_for_receipt(
+            &provider,
+            tx_hash,
+            Duration::from_millis(1),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.transaction_hash, tx_hash);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_receipt_times_out() {
+        let (provider, mock) = Provider::mocked();
+        for _ in 0..10 {
+            mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        }
+
+        let result = wait_for_receipt(
+            &provider,
+            H256::zero(),
+            Duration::from_millis(1),
+            Duration::from_millis(3),
+        )
+        .await;
+
+        assert!(matches!(result, Err(X402Error::SettlementError(_))));
+    }
 }
 
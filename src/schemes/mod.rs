@@ -3,11 +3,198 @@
 //! This module contains the trait definition for payment schemes and concrete
 //! implementations for different blockchain networks.
 
+pub mod allowance_evm;
 pub mod exact_evm;
+pub mod split_evm;
+pub mod token_client;
+pub mod upto_evm;
 
-use crate::errors::Result;
-use crate::types::{PaymentPayload, PaymentRequirements};
+pub use crate::types::SchemePayload;
+
+use crate::errors::{Result, X402Error};
+use crate::types::{PaymentPayload, PaymentRequirements, SettlementInfo};
 use async_trait::async_trait;
+use ethers::abi::Detokenize;
+use ethers::contract::{ContractCall, ContractError};
+use ethers::providers::{Middleware, ProviderError};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Envelope used for settlement transactions on EVM chains.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxType {
+    /// Type-0 transactions with a single gas price
+    Legacy,
+    /// Type-2 transactions with base/priority fees
+    Eip1559,
+    /// Pick based on the chain id
+    #[default]
+    Auto,
+}
+
+impl TxType {
+    /// Chains known to accept EIP-1559 transactions.
+    const EIP1559_CHAINS: &'static [u64] = &[
+        1,        // Ethereum mainnet
+        11155111, // Sepolia
+        8453,     // Base mainnet
+        84532,    // Base Sepolia
+        10,       // Optimism
+        42161,    // Arbitrum One
+        137,      // Polygon mainnet
+        80002,    // Polygon Amoy
+    ];
+
+    /// Resolves `Auto` to a concrete type for the given chain.
+    ///
+    /// Unknown chains fall back to `Legacy`, which every EVM chain accepts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::schemes::TxType;
+    ///
+    /// assert_eq!(TxType::Auto.resolve(8453), TxType::Eip1559);
+    /// assert_eq!(TxType::Auto.resolve(999_999), TxType::Legacy);
+    /// assert_eq!(TxType::Legacy.resolve(8453), TxType::Legacy);
+    /// ```
+    pub fn resolve(self, chain_id: u64) -> TxType {
+        match self {
+            TxType::Auto if Self::EIP1559_CHAINS.contains(&chain_id) => TxType::Eip1559,
+            TxType::Auto => TxType::Legacy,
+            concrete => concrete,
+        }
+    }
+}
+
+/// Converts a contract call to the transaction type resolved for `chain_id`.
+pub(crate) fn apply_tx_type<M: Middleware, D: Detokenize>(
+    call: ContractCall<M, D>,
+    tx_type: TxType,
+    chain_id: u64,
+) -> ContractCall<M, D> {
+    match tx_type.resolve(chain_id) {
+        TxType::Legacy => call.legacy(),
+        _ => call,
+    }
+}
+
+/// Retry policy for read-only JSON-RPC calls, such as the chain id and a nonce's state.
+///
+/// Only transport failures are retried: connection errors and timeouts. Error
+/// responses from the node, reverts and malformed replies fail at once. By default
+/// nothing is retried.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use x402_rs::schemes::RpcRetry;
+///
+/// let retry = RpcRetry::new(3, Duration::from_millis(100));
+/// assert!(retry.delay(1) <= Duration::from_millis(100));
+/// assert!(retry.delay(3) >= Duration::from_millis(200));
+/// assert_eq!(RpcRetry::default().max_retries, 0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RpcRetry {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry, doubling with each further one
+    pub base_delay: Duration,
+}
+
+impl RpcRetry {
+    /// Retries a failed call up to `max_retries` times, backing off from `base_delay`.
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+        }
+    }
+
+    /// Returns the delay before retry number `retry`, counting from 1.
+    ///
+    /// The exponential backoff is shortened by a random amount of up to half, so
+    /// clients failing together don't retry together.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        backoff.mul_f64(1.0 - rand::rng().random_range(0.0..=0.5))
+    }
+
+    /// Runs `call`, retrying it while it fails with an error `is_transient` accepts.
+    ///
+    /// Once retried, the final error is a `BlockchainError` naming the number of
+    /// attempts; a call that fails at the first attempt is mapped with `into_error`.
+    pub(crate) async fn run<T, E, Fut>(
+        &self,
+        mut call: impl FnMut() -> Fut,
+        is_transient: impl Fn(&E) -> bool,
+        into_error: impl FnOnce(E) -> X402Error,
+    ) -> Result<T>
+    where
+        E: fmt::Display,
+        Fut: Future<Output = std::result::Result<T, E>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt <= self.max_retries && is_transient(&e) => {
+                    tokio::time::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) if attempt > 1 => {
+                    return Err(X402Error::BlockchainError(format!(
+                        "RPC call failed after {} attempts: {}",
+                        attempt, e
+                    )))
+                }
+                Err(e) => return Err(into_error(e)),
+            }
+        }
+    }
+}
+
+/// Returns `true` if `e` is a transport failure, such as a refused connection or a
+/// timeout, rather than an answer from the node.
+pub fn is_transient_rpc_error(e: &ProviderError) -> bool {
+    matches!(e, ProviderError::HTTPError(_))
+}
+
+/// Returns `true` if a contract call failed in transport rather than reverting.
+pub(crate) fn is_transient_contract_error<M>(e: &ContractError<M>) -> bool
+where
+    M: Middleware<Error = ProviderError>,
+{
+    e.as_provider_error()
+        .or_else(|| e.as_middleware_error())
+        .is_some_and(is_transient_rpc_error)
+}
+
+/// Operations a [`Scheme`] supports beyond generating and verifying payloads.
+///
+/// Lets a facilitator advertise only what it can do and refuse other operations
+/// before doing any work.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchemeCapabilities {
+    /// Payments can be settled on-chain
+    pub settle: bool,
+    /// Several payments can be settled in one transaction
+    pub batch_settle: bool,
+    /// Signed authorizations can be cancelled before settlement
+    pub cancel: bool,
+    /// Settlement can be simulated, e.g. to estimate its gas
+    pub simulate: bool,
+    /// Payloads can be verified without an RPC endpoint
+    pub offline_verify: bool,
+}
 
 /// Trait for implementing different payment schemes.
 ///
@@ -18,6 +205,25 @@ pub trait Scheme: Send + Sync {
     /// Returns the name of this scheme (e.g., "exact").
     fn name(&self) -> &str;
 
+    /// Returns the operations this scheme supports.
+    ///
+    /// The default reports settlement only.
+    fn capabilities(&self) -> SchemeCapabilities {
+        SchemeCapabilities {
+            settle: true,
+            ..SchemeCapabilities::default()
+        }
+    }
+
+    /// Checks that `requirements` can be paid, before anything is signed or sent.
+    ///
+    /// Meant for cheap checks that need no network, such as address formats and a
+    /// non-zero amount, so a malformed offer fails fast with a precise error. The
+    /// default accepts everything.
+    async fn prevalidate(&self, _requirements: &PaymentRequirements) -> Result<()> {
+        Ok(())
+    }
+
     /// Generates a payment payload for the given requirements.
     ///
     /// # Arguments
@@ -54,6 +260,28 @@ pub trait Scheme: Send + Sync {
         rpc_url: &str,
     ) -> Result<bool>;
 
+    /// Verifies a payment payload and reports every failed check.
+    ///
+    /// Unlike `verify`, which stops at the first failure, this collects all reasons
+    /// so client implementations can be debugged in one round trip. The default
+    /// implementation wraps `verify` and can only report a single reason.
+    ///
+    /// # Returns
+    ///
+    /// An empty list if valid, otherwise a description of each failed check
+    async fn verify_detailed(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+    ) -> Result<Vec<String>> {
+        match self.verify(payload, requirements, rpc_url).await {
+            Ok(true) => Ok(Vec::new()),
+            Ok(false) => Ok(vec!["Verification failed".to_string()]),
+            Err(e) => Ok(vec![e.to_string()]),
+        }
+    }
+
     /// Settles a payment on-chain.
     ///
     /// # Arguments
@@ -65,13 +293,58 @@ pub trait Scheme: Send + Sync {
     ///
     /// # Returns
     ///
-    /// Transaction hash of the settlement
+    /// Details of the settled payment, including the transaction hash
     async fn settle(
         &self,
         payload: &PaymentPayload,
         requirements: &PaymentRequirements,
         rpc_url: &str,
         facilitator_key: &str,
-    ) -> Result<String>;
+    ) -> Result<SettlementInfo>;
+}
+
+
+/// Scheme implementations available to a client, keyed by scheme name.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::schemes::{exact_evm::ExactEvm, SchemeRegistry};
+///
+/// let mut registry = SchemeRegistry::new();
+/// registry.register(ExactEvm::new());
+/// assert!(registry.contains("exact"));
+/// assert!(!registry.contains("upto"));
+/// ```
+#[derive(Clone, Default)]
+pub struct SchemeRegistry {
+    schemes: HashMap<String, Arc<dyn Scheme>>,
 }
 
+impl SchemeRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `scheme` under its name, replacing any previous implementation.
+    pub fn register(&mut self, scheme: impl Scheme + 'static) {
+        self.schemes.insert(scheme.name().to_string(), Arc::new(scheme));
+    }
+
+    /// Returns the implementation registered for `name`.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Scheme>> {
+        self.schemes.get(name).cloned()
+    }
+
+    /// Returns `true` if an implementation is registered for `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.schemes.contains_key(name)
+    }
+}
+
+impl fmt::Debug for SchemeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.schemes.keys()).finish()
+    }
+}
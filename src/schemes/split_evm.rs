@@ -0,0 +1,247 @@
+//! The "exact" scheme split across several recipients.
+//!
+//! A single `transferWithAuthorization` pays one address, so providers sharing revenue
+//! list their recipients and basis-point shares in `extra.splits`. The payer signs one
+//! EIP-3009 authorization per recipient, and the facilitator submits them together
+//! through a Multicall3 contract, so either every leg settles or none does. Servers
+//! opt in with [`PaymentConfig::with_split`](crate::server::PaymentConfig::with_split).
+
+use crate::errors::{Result, X402Error};
+use crate::schemes::exact_evm::ExactEvm;
+use crate::schemes::{Scheme, SchemeCapabilities, SchemePayload};
+use crate::types::{
+    PaymentPayload, PaymentRequirements, SettlementInfo, SplitTransfer, TransferAuthorization,
+    X402_VERSION,
+};
+use crate::utils::{
+    message_signature_required, parse_address, recover_message_signer, split_amounts,
+    string_to_u256,
+};
+use async_trait::async_trait;
+use ethers::types::U256;
+use serde_json::json;
+use std::collections::HashSet;
+
+/// Implementation of the "exact" scheme paying each recipient of `extra.splits`.
+///
+/// Every leg is an ordinary EIP-3009 authorization, generated, verified and submitted
+/// by the wrapped [`ExactEvm`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::schemes::{exact_evm::ExactEvm, split_evm::SplitEvm, Scheme};
+/// # use x402_rs::types::PaymentRequirements;
+///
+/// # async fn example(requirements: PaymentRequirements) -> x402_rs::Result<()> {
+/// let scheme = SplitEvm::new().with_exact(ExactEvm::new().with_chain_id(8453));
+/// let payload = scheme
+///     .generate_payload(&requirements, "0xprivatekey", "https://mainnet.base.org")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SplitEvm {
+    exact: ExactEvm,
+}
+
+impl SplitEvm {
+    /// Creates a split scheme signing and settling legs with a default [`ExactEvm`].
+    pub fn new() -> Self {
+        Self {
+            exact: ExactEvm::new(),
+        }
+    }
+
+    /// Signs, verifies and settles every leg with `exact`, e.g. to set its chain id,
+    /// token client or transaction type.
+    pub fn with_exact(mut self, exact: ExactEvm) -> Self {
+        self.exact = exact;
+        self
+    }
+
+    /// Returns the requirements of each leg, paying its recipient its share.
+    fn legs(requirements: &PaymentRequirements) -> Result<Vec<PaymentRequirements>> {
+        let amounts = split_amounts(requirements)?
+            .ok_or_else(|| X402Error::MissingField("extra.splits".to_string()))?;
+
+        Ok(amounts
+            .into_iter()
+            .map(|(pay_to, amount)| {
+                let mut leg = requirements.clone();
+                leg.pay_to = format!("{:?}", pay_to);
+                leg.max_amount_required = amount.to_string();
+                leg.min_amount_required = None;
+                // The message signature covers the whole split, not each leg
+                if let Some(extra) = leg.extra.as_mut().and_then(|e| e.as_object_mut()) {
+                    extra.remove("splits");
+                    extra.remove("requireMessageSignature");
+                }
+                leg
+            })
+            .collect())
+    }
+}
+
+impl Default for SplitEvm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Scheme for SplitEvm {
+    fn name(&self) -> &str {
+        "exact"
+    }
+
+    fn capabilities(&self) -> SchemeCapabilities {
+        SchemeCapabilities {
+            settle: true,
+            batch_settle: true,
+            ..SchemeCapabilities::default()
+        }
+    }
+
+    async fn prevalidate(&self, requirements: &PaymentRequirements) -> Result<()> {
+        // A leg may round down to nothing, so only the total must be non-zero
+        Self::legs(requirements)?;
+        self.exact.prevalidate(requirements).await
+    }
+
+    async fn generate_payload(
+        &self,
+        requirements: &PaymentRequirements,
+        private_key: &str,
+        rpc_url: &str,
+    ) -> Result<PaymentPayload> {
+        let mut authorizations = Vec::new();
+        for leg in Self::legs(requirements)? {
+            let payload = self
+                .exact
+                .generate_payload(&leg, private_key, rpc_url)
+                .await?;
+            authorizations.push(TransferAuthorization::from_value(&payload.payload)?);
+        }
+
+        Ok(PaymentPayload {
+            x402_version: X402_VERSION,
+            scheme: self.name().to_string(),
+            network: requirements.network.clone(),
+            payload: json!(SplitTransfer { authorizations }),
+            challenge: None,
+            message_signature: None,
+        })
+    }
+
+    async fn verify(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+    ) -> Result<bool> {
+        Ok(self
+            .verify_detailed(payload, requirements, rpc_url)
+            .await?
+            .is_empty())
+    }
+
+    async fn verify_detailed(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+    ) -> Result<Vec<String>> {
+        let split = SplitTransfer::from_value(&payload.payload)?;
+        let legs = Self::legs(requirements)?;
+        if split.authorizations.len() != legs.len() {
+            return Ok(vec![format!(
+                "Expected {} split authorizations, got {}",
+                legs.len(),
+                split.authorizations.len()
+            )]);
+        }
+
+        let payer = parse_address(&split.authorizations[0].from)?;
+        let mut failures = Vec::new();
+        let mut nonces = HashSet::new();
+        for (i, (auth, leg)) in split.authorizations.iter().zip(&legs).enumerate() {
+            let from = parse_address(&auth.from)?;
+            if from != payer {
+                failures.push(format!(
+                    "Split leg {} is paid by {:?}, not {:?}",
+                    i, from, payer
+                ));
+            }
+            // The batch reverts if two legs share a nonce
+            if !nonces.insert(auth.nonce.to_lowercase()) {
+                failures.push(format!("Split leg {} reuses nonce {}", i, auth.nonce));
+            }
+
+            let leg_payload = PaymentPayload {
+                payload: json!(auth),
+                message_signature: None,
+                ..payload.clone()
+            };
+            for failure in self
+                .exact
+                .verify_detailed(&leg_payload, leg, rpc_url)
+                .await?
+            {
+                failures.push(format!("Split leg {}: {}", i, failure));
+            }
+        }
+
+        if message_signature_required(requirements) {
+            match recover_message_signer(payload) {
+                Ok(recovered) if recovered == payer => {}
+                Ok(recovered) => failures.push(format!(
+                    "Message signature recovers to {:?}, not payer {:?}",
+                    recovered, payer
+                )),
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+
+        Ok(failures)
+    }
+
+    async fn settle(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+        facilitator_key: &str,
+    ) -> Result<SettlementInfo> {
+        let split = SplitTransfer::from_value(&payload.payload)?;
+        let transfers = split
+            .authorizations
+            .iter()
+            .map(ExactEvm::authorized_transfer)
+            .collect::<Result<Vec<_>>>()?;
+        let payer = transfers
+            .first()
+            .ok_or_else(|| X402Error::InvalidPayload("Split has no authorizations".to_string()))?
+            .from;
+        let asset = parse_address(&requirements.asset)?;
+
+        let receipt = self
+            .exact
+            .settle_transfers(&transfers, asset, rpc_url, facilitator_key)
+            .await?;
+
+        let mut amount = U256::zero();
+        for auth in &split.authorizations {
+            amount += string_to_u256(&auth.value)?;
+        }
+        Ok(SettlementInfo {
+            payer: format!("{:?}", payer),
+            pay_to: requirements.pay_to.clone(),
+            amount: amount.to_string(),
+            asset: format!("{:?}", asset),
+            network: payload.network.clone(),
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+            block_number: receipt.block_number.map(|n| n.as_u64()),
+        })
+    }
+}
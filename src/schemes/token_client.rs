@@ -0,0 +1,328 @@
+//! Access to EIP-3009 token contracts.
+//!
+//! [`ExactEvm`] reads and settles through a [`TokenClient`]. [`RpcTokenClient`] calls
+//! the token over JSON-RPC; tests can inject their own implementation with
+//! [`ExactEvm::with_token_client`] to exercise verification without a chain.
+//!
+//! [`ExactEvm`]: super::exact_evm::ExactEvm
+//! [`ExactEvm::with_token_client`]: super::exact_evm::ExactEvm::with_token_client
+
+use crate::errors::{Result, X402Error};
+use crate::schemes::exact_evm::{decode_revert_reason, EIP3009Token};
+use crate::schemes::{
+    apply_tx_type, is_transient_contract_error, is_transient_rpc_error, RpcRetry, TxType,
+};
+use async_trait::async_trait;
+use ethers::contract::{ContractCall, ContractError, Multicall, MulticallError, MULTICALL_ADDRESS};
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Signature, H256, U256, U64};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A signed EIP-3009 `transferWithAuthorization`, ready to submit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTransfer {
+    /// Payer authorizing the transfer
+    pub from: Address,
+
+    /// Recipient of the transfer
+    pub to: Address,
+
+    /// Amount in the token's smallest unit
+    pub value: U256,
+
+    /// Unix time after which the authorization is valid
+    pub valid_after: U256,
+
+    /// Unix time before which the authorization is valid
+    pub valid_before: U256,
+
+    /// Authorization nonce
+    pub nonce: H256,
+
+    /// Payer's signature over the authorization
+    pub signature: Signature,
+}
+
+/// The EIP-3009 token calls made by the `exact` scheme.
+#[async_trait]
+pub trait TokenClient: Send + Sync {
+    /// Returns `true` if `authorizer` has already used `nonce` on `asset`.
+    async fn authorization_state(
+        &self,
+        asset: Address,
+        authorizer: Address,
+        nonce: H256,
+    ) -> Result<bool>;
+
+    /// Returns `account`'s balance of `asset` at `block`, or at the latest block.
+    async fn balance_of(&self, asset: Address, account: Address, block: Option<U64>)
+        -> Result<U256>;
+
+    /// Returns the number of decimals of `asset`.
+    async fn decimals(&self, asset: Address) -> Result<u8>;
+
+    /// Returns `true` if contract code is deployed at `asset`.
+    async fn has_code(&self, asset: Address) -> Result<bool>;
+
+    /// Submits `transfer` to `asset`, returning the transaction hash without waiting
+    /// for it to be mined.
+    async fn transfer_with_authorization(
+        &self,
+        asset: Address,
+        transfer: &SignedTransfer,
+    ) -> Result<H256>;
+
+    /// Submits all of `transfers` to `asset` in one transaction, so either every
+    /// transfer lands or none does, returning the transaction hash.
+    ///
+    /// The default implementation can't batch and returns
+    /// `X402Error::SettlementError`.
+    async fn transfer_batch_with_authorization(
+        &self,
+        _asset: Address,
+        _transfers: &[SignedTransfer],
+    ) -> Result<H256> {
+        Err(X402Error::SettlementError(
+            "Token client cannot submit batched transfers".to_string(),
+        ))
+    }
+}
+
+/// Middleware sending settlements from the facilitator's account.
+type SettlementClient = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+/// [`TokenClient`] calling the token contract over JSON-RPC.
+///
+/// Reads need only an RPC endpoint; submitting transfers also needs the key of the
+/// account paying for gas, set with [`with_signer`](Self::with_signer). Batched
+/// transfers go through the chain's Multicall3 contract.
+///
+/// Reads are retried on transport failures as set with
+/// [`with_rpc_retries`](Self::with_rpc_retries); transfers are sent once.
+#[derive(Clone, Debug)]
+pub struct RpcTokenClient {
+    provider: Provider<Http>,
+    signer: Option<LocalWallet>,
+    tx_type: TxType,
+    multicall: Address,
+    rpc_retry: RpcRetry,
+}
+
+impl RpcTokenClient {
+    /// Creates a client for the RPC endpoint at `rpc_url`.
+    pub fn new(rpc_url: &str) -> Result<Self> {
+        Ok(Self {
+            provider: Provider::<Http>::try_from(rpc_url)?,
+            signer: None,
+            tx_type: TxType::Auto,
+            multicall: MULTICALL_ADDRESS,
+            rpc_retry: RpcRetry::default(),
+        })
+    }
+
+    /// Submits transfers from the account of `private_key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if the key is invalid.
+    pub fn with_signer(mut self, private_key: &str) -> Result<Self> {
+        let wallet = private_key
+            .parse::<LocalWallet>()
+            .map_err(|e| X402Error::ConfigError(format!("Invalid facilitator key: {}", e)))?;
+        self.signer = Some(wallet);
+        Ok(self)
+    }
+
+    /// Sets the transaction type of submitted transfers.
+    pub fn with_tx_type(mut self, tx_type: TxType) -> Self {
+        self.tx_type = tx_type;
+        self
+    }
+
+    /// Sets the Multicall3 contract batched transfers are sent through (defaults to
+    /// its canonical deployment address).
+    pub fn with_multicall_address(mut self, multicall: Address) -> Self {
+        self.multicall = multicall;
+        self
+    }
+
+    /// Retries reads failing in transport up to `max_retries` times, with exponential
+    /// backoff from `base_delay` (defaults to no retries).
+    pub fn with_rpc_retries(mut self, max_retries: u32, base_delay: Duration) -> Self {
+        self.rpc_retry = RpcRetry::new(max_retries, base_delay);
+        self
+    }
+
+    /// Estimates the gas `transfer` would use, without sending it.
+    pub async fn estimate_transfer_gas(
+        &self,
+        asset: Address,
+        transfer: &SignedTransfer,
+    ) -> Result<U256> {
+        let call = self.transfer_call(asset, transfer).await?;
+        call.estimate_gas().await.map_err(settlement_error)
+    }
+
+    fn token(&self, asset: Address) -> EIP3009Token<Provider<Http>> {
+        EIP3009Token::new(asset, Arc::new(self.provider.clone()))
+    }
+
+    /// Returns a client sending from the signer's account, and the chain id.
+    async fn settlement_client(&self) -> Result<(Arc<SettlementClient>, u64)> {
+        let wallet = self.signer.clone().ok_or_else(|| {
+            X402Error::ConfigError("No signer key configured for settlement".to_string())
+        })?;
+        let chain_id = self.provider.get_chainid().await?.as_u64();
+        let client = SignerMiddleware::new(self.provider.clone(), wallet.with_chain_id(chain_id));
+        Ok((Arc::new(client), chain_id))
+    }
+
+    /// Builds the `transferWithAuthorization` call sent from the signer's account.
+    async fn transfer_call(
+        &self,
+        asset: Address,
+        transfer: &SignedTransfer,
+    ) -> Result<ContractCall<SettlementClient, ()>> {
+        let (client, chain_id) = self.settlement_client().await?;
+        let call = authorization_call(client, asset, transfer);
+        Ok(apply_tx_type(call, self.tx_type, chain_id))
+    }
+}
+
+/// Builds the `transferWithAuthorization` call for `transfer`.
+fn authorization_call(
+    client: Arc<SettlementClient>,
+    asset: Address,
+    transfer: &SignedTransfer,
+) -> ContractCall<SettlementClient, ()> {
+    let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+    transfer.signature.r.to_big_endian(&mut r);
+    transfer.signature.s.to_big_endian(&mut s);
+
+    EIP3009Token::new(asset, client).transfer_with_authorization(
+        transfer.from,
+        transfer.to,
+        transfer.value,
+        transfer.valid_after,
+        transfer.valid_before,
+        transfer.nonce.into(),
+        transfer.signature.v as u8,
+        r,
+        s,
+    )
+}
+
+#[async_trait]
+impl TokenClient for RpcTokenClient {
+    async fn authorization_state(
+        &self,
+        asset: Address,
+        authorizer: Address,
+        nonce: H256,
+    ) -> Result<bool> {
+        let call = self
+            .token(asset)
+            .authorization_state(authorizer, nonce.into());
+        self.rpc_retry
+            .run(
+                || call.call(),
+                is_transient_contract_error,
+                |e| X402Error::BlockchainError(e.to_string()),
+            )
+            .await
+    }
+
+    async fn balance_of(
+        &self,
+        asset: Address,
+        account: Address,
+        block: Option<U64>,
+    ) -> Result<U256> {
+        let call = self.token(asset).balance_of(account);
+        let call = match block {
+            Some(block) => call.block(block),
+            None => call,
+        };
+        self.rpc_retry
+            .run(
+                || call.call(),
+                is_transient_contract_error,
+                |e| X402Error::BlockchainError(e.to_string()),
+            )
+            .await
+    }
+
+    async fn decimals(&self, asset: Address) -> Result<u8> {
+        let call = self.token(asset).decimals();
+        self.rpc_retry
+            .run(
+                || call.call(),
+                is_transient_contract_error,
+                |e| X402Error::BlockchainError(e.to_string()),
+            )
+            .await
+    }
+
+    async fn has_code(&self, asset: Address) -> Result<bool> {
+        self.rpc_retry
+            .run(
+                || self.provider.get_code(asset, None),
+                is_transient_rpc_error,
+                |e| X402Error::BlockchainError(e.to_string()),
+            )
+            .await
+            .map(|code| !code.is_empty())
+    }
+
+    async fn transfer_with_authorization(
+        &self,
+        asset: Address,
+        transfer: &SignedTransfer,
+    ) -> Result<H256> {
+        let call = self.transfer_call(asset, transfer).await?;
+        let pending_tx = call.send().await.map_err(settlement_error)?;
+        Ok(pending_tx.tx_hash())
+    }
+
+    async fn transfer_batch_with_authorization(
+        &self,
+        asset: Address,
+        transfers: &[SignedTransfer],
+    ) -> Result<H256> {
+        let (client, chain_id) = self.settlement_client().await?;
+        let mut multicall = Multicall::new(client.clone(), Some(self.multicall))
+            .await
+            .map_err(batch_settlement_error)?;
+        if self.tx_type.resolve(chain_id) == TxType::Legacy {
+            multicall = multicall.legacy();
+        }
+        // No call may fail, so one bad leg reverts the whole batch
+        for transfer in transfers {
+            multicall.add_call(authorization_call(client.clone(), asset, transfer), false);
+        }
+
+        let pending_tx = multicall.send().await.map_err(batch_settlement_error)?;
+        Ok(pending_tx.tx_hash())
+    }
+}
+
+/// Maps a failed batched settlement to an error, decoding the revert reason if any.
+fn batch_settlement_error(e: MulticallError<SettlementClient>) -> X402Error {
+    let reason = e.as_revert().and_then(|data| decode_revert_reason(data));
+    X402Error::SettlementError(match reason {
+        Some(reason) => format!("Batch reverted: {}", reason),
+        None => format!("Batch failed: {}", e),
+    })
+}
+
+/// Maps a failed settlement call to an error, decoding the revert reason if any.
+fn settlement_error(e: ContractError<SettlementClient>) -> X402Error {
+    let reason = e.as_revert().and_then(|data| decode_revert_reason(data));
+    X402Error::SettlementError(match reason {
+        Some(reason) => format!("Transaction reverted: {}", reason),
+        None => format!("Transaction failed: {}", e),
+    })
+}
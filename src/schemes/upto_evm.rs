@@ -0,0 +1,636 @@
+//! Implementation of the "upto" payment scheme for EVM-compatible chains.
+//!
+//! For resources priced by usage, the payer signs an EIP-2612 `permit` letting the
+//! facilitator's settlement account spend up to `maxAmountRequired`. Once the resource
+//! is served, the server names the amount actually owed in the settle request's
+//! `actualAmount`, and the facilitator submits the permit and moves that amount with
+//! `transferFrom`. Since the permit doesn't name a recipient, the payer also signs an
+//! [`UPTO_PAYMENT_TYPE`] message binding it to `payTo`, the asset and the resource,
+//! which the facilitator checks before verifying or settling. Servers opt in with
+//! [`PaymentConfig::with_upto`](crate::server::PaymentConfig::with_upto).
+//!
+//! The permit's signature covers its whole `value`, so it can only be submitted as
+//! signed: charging less leaves the facilitator's account an allowance of the
+//! difference, and a `transferFrom` failing after the permit lands leaves all of it.
+//! Only the owner can lower an allowance, so payers should treat an upto payment as
+//! spendable up to `maxAmountRequired` by that account until they revoke it.
+
+use crate::errors::{Result, X402Error};
+use crate::schemes::allowance_evm::AllowanceEvm;
+use crate::schemes::exact_evm::{decode_revert_reason, wait_for_receipt, ExactEvm};
+use crate::schemes::{apply_tx_type, Scheme, SchemeCapabilities, SchemePayload, TxType};
+use crate::types::{
+    PaymentPayload, PaymentRequirements, SettlementInfo, UptoAuthorization, X402_VERSION,
+};
+use crate::utils::{amount_range, current_timestamp, parse_address, string_to_u256};
+use async_trait::async_trait;
+use ethers::abi::Token;
+use ethers::contract::ContractError;
+use ethers::core::utils::keccak256;
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Signature, TransactionReceipt, H256, U256, U64};
+use serde_json::json;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// EIP-712 type string of the EIP-2612 permit signed by payers.
+pub const PERMIT_TYPE: &str =
+    "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)";
+
+/// EIP-712 type string of the message binding a permit to its recipient and resource,
+/// signed by payers in the token's domain alongside the permit.
+pub const UPTO_PAYMENT_TYPE: &str = "UptoPayment(address owner,address payTo,address asset,\
+string resource,uint256 value,uint256 nonce,uint256 deadline)";
+
+// Defaults for waiting on settlement receipts
+const DEFAULT_RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+const DEFAULT_RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Slack allowed on top of `maxTimeoutSeconds` for clock differences
+const VALIDITY_WINDOW_SKEW_SECONDS: u64 = 30;
+
+mod bindings {
+    #![allow(missing_docs)]
+    use ethers::contract::abigen;
+
+    abigen!(
+        EIP2612Token,
+        r#"[
+            function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external
+            function nonces(address owner) external view returns (uint256)
+            function balanceOf(address account) external view returns (uint256)
+            function transferFrom(address from, address to, uint256 value) external returns (bool)
+        ]"#
+    );
+}
+
+pub use bindings::EIP2612Token;
+
+/// Implementation of the "upto" scheme, charging at most the permitted amount.
+///
+/// Facilitators set the amount to charge with
+/// [`with_actual_amount`](Self::with_actual_amount) before verifying or settling;
+/// without it the full permitted amount is charged.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ethers::types::U256;
+/// use x402_rs::schemes::{upto_evm::UptoEvm, Scheme};
+/// # use x402_rs::types::PaymentRequirements;
+///
+/// # async fn example(requirements: PaymentRequirements) -> x402_rs::Result<()> {
+/// let payload = UptoEvm::new()
+///     .generate_payload(&requirements, "0xprivatekey", "https://mainnet.base.org")
+///     .await?;
+///
+/// // The request used 2500 of the permitted amount
+/// let scheme = UptoEvm::new().with_actual_amount(U256::from(2500));
+/// let valid = scheme
+///     .verify(&payload, &requirements, "https://mainnet.base.org")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UptoEvm {
+    chain_id: Option<u64>,
+    actual_amount: Option<U256>,
+    receipt_timeout: Duration,
+    receipt_poll_interval: Duration,
+    tx_type: TxType,
+}
+
+impl UptoEvm {
+    /// Creates a new instance of the upto scheme.
+    pub fn new() -> Self {
+        Self {
+            chain_id: None,
+            actual_amount: None,
+            receipt_timeout: DEFAULT_RECEIPT_TIMEOUT,
+            receipt_poll_interval: DEFAULT_RECEIPT_POLL_INTERVAL,
+            tx_type: TxType::Auto,
+        }
+    }
+
+    /// Sets the chain id of the EIP-712 domain, instead of querying the RPC for it.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Sets the amount to charge, which must not exceed `maxAmountRequired` or the
+    /// permitted amount.
+    pub fn with_actual_amount(mut self, amount: U256) -> Self {
+        self.actual_amount = Some(amount);
+        self
+    }
+
+    /// Sets the transaction type used for settlement.
+    pub fn with_tx_type(mut self, tx_type: TxType) -> Self {
+        self.tx_type = tx_type;
+        self
+    }
+
+    /// Sets how long to wait for each settlement receipt.
+    pub fn with_receipt_timeout(mut self, timeout: Duration) -> Self {
+        self.receipt_timeout = timeout;
+        self
+    }
+
+    /// Sets how often to poll for a settlement receipt.
+    pub fn with_receipt_poll_interval(mut self, interval: Duration) -> Self {
+        self.receipt_poll_interval = interval;
+        self
+    }
+
+    /// Returns the amount to charge for `permit`.
+    fn charge(&self, permit: &UptoAuthorization) -> Result<U256> {
+        match self.actual_amount {
+            Some(amount) => Ok(amount),
+            None => string_to_u256(&permit.value),
+        }
+    }
+
+    /// Returns why the amount to charge can't be taken under `permit`, if it can't.
+    pub(crate) fn charge_failure(
+        &self,
+        permit: &UptoAuthorization,
+        requirements: &PaymentRequirements,
+    ) -> Result<Option<String>> {
+        let charge = self.charge(permit)?;
+        let value = string_to_u256(&permit.value)?;
+        let (_, max_amount) = amount_range(requirements)?;
+        Ok(if charge > max_amount {
+            Some(format!(
+                "Actual amount {} exceeds maxAmountRequired {}",
+                charge, max_amount
+            ))
+        } else if charge > value {
+            Some(format!(
+                "Actual amount {} exceeds permitted {}",
+                charge, value
+            ))
+        } else {
+            None
+        })
+    }
+
+    /// Returns why `permit` isn't bound to `requirements`' recipient and resource, if
+    /// it isn't.
+    fn binding_failure(
+        permit: &UptoAuthorization,
+        requirements: &PaymentRequirements,
+        domain_separator: H256,
+    ) -> Result<Option<String>> {
+        let pay_to = parse_address(&requirements.pay_to)?;
+        if parse_address(&permit.pay_to)? != pay_to {
+            return Ok(Some(format!(
+                "Recipient mismatch: expected {:?}, got {}",
+                pay_to, permit.pay_to
+            )));
+        }
+        if permit.resource != requirements.resource {
+            return Ok(Some(format!(
+                "Resource mismatch: expected {}, got {}",
+                requirements.resource, permit.resource
+            )));
+        }
+
+        let owner = parse_address(&permit.owner)?;
+        let message_hash = Self::create_payment_hash(
+            owner,
+            pay_to,
+            parse_address(&requirements.asset)?,
+            &requirements.resource,
+            string_to_u256(&permit.value)?,
+            string_to_u256(&permit.nonce)?,
+            string_to_u256(&permit.deadline)?,
+            domain_separator,
+        );
+        Ok(
+            match Signature::from_str(&permit.payment_signature).map(|s| s.recover(message_hash)) {
+                Ok(Ok(recovered)) if recovered == owner => None,
+                Ok(Ok(recovered)) => Some(format!(
+                    "Payment signature recovers to {:?}, not payer {:?}",
+                    recovered, owner
+                )),
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(e) => Some(e.to_string()),
+            },
+        )
+    }
+
+    /// Returns the chain id of the EIP-712 domain, querying the RPC unless known.
+    async fn domain_chain_id(&self, provider: &Provider<Http>) -> Result<U256> {
+        match self.chain_id {
+            Some(chain_id) => Ok(U256::from(chain_id)),
+            None => Ok(provider.get_chainid().await?),
+        }
+    }
+
+    /// Waits for the receipt of `tx_hash`, failing if the transaction reverted.
+    async fn confirm(
+        &self,
+        provider: &Provider<Http>,
+        tx_hash: H256,
+    ) -> Result<TransactionReceipt> {
+        let receipt = wait_for_receipt(
+            provider,
+            tx_hash,
+            self.receipt_poll_interval,
+            self.receipt_timeout,
+        )
+        .await?;
+        if receipt.status == Some(U64::zero()) {
+            return Err(X402Error::SettlementError(format!(
+                "Transaction {:?} reverted",
+                tx_hash
+            )));
+        }
+        Ok(receipt)
+    }
+
+    /// Creates the EIP-712 typed data hash for the permit.
+    fn create_permit_hash(
+        owner: Address,
+        spender: Address,
+        value: U256,
+        nonce: U256,
+        deadline: U256,
+        domain_separator: H256,
+    ) -> H256 {
+        let struct_hash = keccak256(ethers::abi::encode(&[
+            Token::FixedBytes(keccak256(PERMIT_TYPE.as_bytes()).to_vec()),
+            Token::Address(owner),
+            Token::Address(spender),
+            Token::Uint(value),
+            Token::Uint(nonce),
+            Token::Uint(deadline),
+        ]));
+
+        // EIP-712 final hash: "\x19\x01" ‖ domainSeparator ‖ hashStruct(message)
+        let mut message = Vec::new();
+        message.extend_from_slice(b"\x19\x01");
+        message.extend_from_slice(domain_separator.as_bytes());
+        message.extend_from_slice(&struct_hash);
+
+        H256::from(keccak256(&message))
+    }
+
+    /// Creates the EIP-712 typed data hash binding a permit to its recipient and
+    /// resource.
+    #[allow(clippy::too_many_arguments)]
+    fn create_payment_hash(
+        owner: Address,
+        pay_to: Address,
+        asset: Address,
+        resource: &str,
+        value: U256,
+        nonce: U256,
+        deadline: U256,
+        domain_separator: H256,
+    ) -> H256 {
+        let struct_hash = keccak256(ethers::abi::encode(&[
+            Token::FixedBytes(keccak256(UPTO_PAYMENT_TYPE.as_bytes()).to_vec()),
+            Token::Address(owner),
+            Token::Address(pay_to),
+            Token::Address(asset),
+            Token::FixedBytes(keccak256(resource.as_bytes()).to_vec()),
+            Token::Uint(value),
+            Token::Uint(nonce),
+            Token::Uint(deadline),
+        ]));
+
+        let mut message = Vec::new();
+        message.extend_from_slice(b"\x19\x01");
+        message.extend_from_slice(domain_separator.as_bytes());
+        message.extend_from_slice(&struct_hash);
+
+        H256::from(keccak256(&message))
+    }
+}
+
+impl Default for UptoEvm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Scheme for UptoEvm {
+    fn name(&self) -> &str {
+        "upto"
+    }
+
+    fn capabilities(&self) -> SchemeCapabilities {
+        SchemeCapabilities {
+            settle: true,
+            ..SchemeCapabilities::default()
+        }
+    }
+
+    async fn prevalidate(&self, requirements: &PaymentRequirements) -> Result<()> {
+        if requirements.scheme != self.name() {
+            return Err(X402Error::UnsupportedScheme(requirements.scheme.clone()));
+        }
+
+        for (field, address) in [
+            ("payTo", &requirements.pay_to),
+            ("asset", &requirements.asset),
+        ] {
+            if parse_address(address)?.is_zero() {
+                return Err(X402Error::InvalidAddress(format!(
+                    "{} is the zero address",
+                    field
+                )));
+            }
+        }
+        if amount_range(requirements)?.1.is_zero() {
+            return Err(X402Error::InvalidAmount(
+                "maxAmountRequired must be greater than zero".to_string(),
+            ));
+        }
+
+        AllowanceEvm::spender(requirements)?;
+        Ok(())
+    }
+
+    async fn generate_payload(
+        &self,
+        requirements: &PaymentRequirements,
+        private_key: &str,
+        rpc_url: &str,
+    ) -> Result<PaymentPayload> {
+        let wallet = private_key
+            .parse::<LocalWallet>()
+            .map_err(|e| X402Error::InvalidPayload(format!("Invalid private key: {}", e)))?;
+        let owner = wallet.address();
+        let spender = AllowanceEvm::spender(requirements)?;
+        let value = string_to_u256(&requirements.max_amount_required)?;
+        let asset = parse_address(&requirements.asset)?;
+        let deadline = U256::from(current_timestamp() + requirements.max_timeout_seconds);
+
+        // Permits are ordered by the token's per-owner nonce
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let chain_id = self.domain_chain_id(&provider).await?;
+        let nonce = EIP2612Token::new(asset, Arc::new(provider))
+            .nonces(owner)
+            .call()
+            .await
+            .map_err(|e| X402Error::BlockchainError(e.to_string()))?;
+
+        let (token_name, token_version) = ExactEvm::token_metadata(requirements);
+        let domain_separator =
+            ExactEvm::create_domain_separator(asset, chain_id, &token_name, &token_version);
+        let message_hash =
+            Self::create_permit_hash(owner, spender, value, nonce, deadline, domain_separator);
+        let signature = wallet
+            .sign_hash(message_hash)
+            .map_err(|e| X402Error::SignatureError(e.to_string()))?;
+        let pay_to = parse_address(&requirements.pay_to)?;
+        let payment_hash = Self::create_payment_hash(
+            owner,
+            pay_to,
+            asset,
+            &requirements.resource,
+            value,
+            nonce,
+            deadline,
+            domain_separator,
+        );
+        let payment_signature = wallet
+            .sign_hash(payment_hash)
+            .map_err(|e| X402Error::SignatureError(e.to_string()))?;
+
+        let permit = UptoAuthorization {
+            owner: format!("{:?}", owner),
+            spender: format!("{:?}", spender),
+            value: value.to_string(),
+            nonce: nonce.to_string(),
+            deadline: deadline.to_string(),
+            signature: format!("0x{}", hex::encode(signature.to_vec())),
+            pay_to: format!("{:?}", pay_to),
+            resource: requirements.resource.clone(),
+            payment_signature: format!("0x{}", hex::encode(payment_signature.to_vec())),
+        };
+
+        Ok(PaymentPayload {
+            x402_version: X402_VERSION,
+            scheme: self.name().to_string(),
+            network: requirements.network.clone(),
+            payload: json!(permit),
+            challenge: None,
+            message_signature: None,
+        })
+    }
+
+    async fn verify(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+    ) -> Result<bool> {
+        Ok(self
+            .verify_detailed(payload, requirements, rpc_url)
+            .await?
+            .is_empty())
+    }
+
+    async fn verify_detailed(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+    ) -> Result<Vec<String>> {
+        let permit = UptoAuthorization::from_value(&payload.payload)?;
+        let mut failures = Vec::new();
+
+        if payload.scheme != self.name() {
+            failures.push(format!(
+                "Scheme mismatch: expected {}, got {}",
+                self.name(),
+                payload.scheme
+            ));
+        }
+        if payload.network != requirements.network {
+            failures.push(format!(
+                "Network mismatch: expected {}, got {}",
+                requirements.network, payload.network
+            ));
+        }
+
+        let owner = parse_address(&permit.owner)?;
+        let spender = parse_address(&permit.spender)?;
+        let value = string_to_u256(&permit.value)?;
+        let nonce = string_to_u256(&permit.nonce)?;
+        let deadline = string_to_u256(&permit.deadline)?;
+        let expected_spender = AllowanceEvm::spender(requirements)?;
+        let (_, max_amount) = amount_range(requirements)?;
+        let asset = parse_address(&requirements.asset)?;
+
+        if spender != expected_spender {
+            failures.push(format!(
+                "Spender mismatch: expected {:?}, got {:?}",
+                expected_spender, spender
+            ));
+        }
+        if value > max_amount {
+            failures.push(format!(
+                "Permitted {} exceeds maxAmountRequired {}",
+                value, max_amount
+            ));
+        }
+        if let Some(failure) = self.charge_failure(&permit, requirements)? {
+            failures.push(failure);
+        }
+
+        let now = current_timestamp();
+        let latest = now
+            .saturating_add(requirements.max_timeout_seconds)
+            .saturating_add(VALIDITY_WINDOW_SKEW_SECONDS);
+        if U256::from(now) > deadline {
+            failures.push(format!("Permit expired at {}", deadline));
+        } else if deadline > U256::from(latest) {
+            failures.push(format!(
+                "Permit window of {}s exceeds maxTimeoutSeconds {}",
+                deadline - U256::from(now),
+                requirements.max_timeout_seconds
+            ));
+        }
+
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let chain_id = self.domain_chain_id(&provider).await?;
+        let (token_name, token_version) = ExactEvm::token_metadata(requirements);
+        let domain_separator =
+            ExactEvm::create_domain_separator(asset, chain_id, &token_name, &token_version);
+        let message_hash =
+            Self::create_permit_hash(owner, spender, value, nonce, deadline, domain_separator);
+        match Signature::from_str(&permit.signature).map(|s| s.recover(message_hash)) {
+            Ok(Ok(recovered)) if recovered == owner => {}
+            Ok(Ok(recovered)) => failures.push(format!(
+                "Signature recovers to {:?}, not payer {:?}",
+                recovered, owner
+            )),
+            Ok(Err(e)) => failures.push(e.to_string()),
+            Err(e) => failures.push(e.to_string()),
+        }
+        if let Some(failure) = Self::binding_failure(&permit, requirements, domain_separator)? {
+            failures.push(failure);
+        }
+
+        // The permit must be the payer's next one, and the payer must hold the charge
+        let token = EIP2612Token::new(asset, Arc::new(provider));
+        let next_nonce = token
+            .nonces(owner)
+            .call()
+            .await
+            .map_err(|e| X402Error::BlockchainError(e.to_string()))?;
+        if nonce != next_nonce {
+            failures.push(format!(
+                "Permit nonce {} is not the payer's next nonce {}",
+                nonce, next_nonce
+            ));
+        }
+        let charge = self.charge(&permit)?;
+        let balance = token
+            .balance_of(owner)
+            .call()
+            .await
+            .map_err(|e| X402Error::BlockchainError(e.to_string()))?;
+        if balance < charge {
+            failures.push(format!("Balance of {} is below {}", balance, charge));
+        }
+
+        Ok(failures)
+    }
+
+    async fn settle(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        rpc_url: &str,
+        facilitator_key: &str,
+    ) -> Result<SettlementInfo> {
+        let permit = UptoAuthorization::from_value(&payload.payload)?;
+        if let Some(failure) = self.charge_failure(&permit, requirements)? {
+            return Err(X402Error::InvalidAmount(failure));
+        }
+        let owner = parse_address(&permit.owner)?;
+        let value = string_to_u256(&permit.value)?;
+        let deadline = string_to_u256(&permit.deadline)?;
+        let signature = Signature::from_str(&permit.signature)?;
+        let pay_to = parse_address(&requirements.pay_to)?;
+        let asset = parse_address(&requirements.asset)?;
+        let charge = self.charge(&permit)?;
+
+        // Only the permitted spender can move the payer's tokens
+        let wallet = facilitator_key
+            .parse::<LocalWallet>()
+            .map_err(|e| X402Error::ConfigError(format!("Invalid facilitator key: {}", e)))?;
+        let spender = parse_address(&permit.spender)?;
+        if wallet.address() != spender {
+            return Err(X402Error::ConfigError(format!(
+                "Facilitator {:?} is not the permitted spender {:?}",
+                wallet.address(),
+                spender
+            )));
+        }
+
+        // Only the recipient and resource the payer signed for can be charged
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let (token_name, token_version) = ExactEvm::token_metadata(requirements);
+        let domain_separator = ExactEvm::create_domain_separator(
+            asset,
+            self.domain_chain_id(&provider).await?,
+            &token_name,
+            &token_version,
+        );
+        if let Some(failure) = Self::binding_failure(&permit, requirements, domain_separator)? {
+            return Err(X402Error::VerificationFailed(failure));
+        }
+
+        let chain_id = provider.get_chainid().await?.as_u64();
+        let client = SignerMiddleware::new(provider.clone(), wallet.with_chain_id(chain_id));
+        let token = EIP2612Token::new(asset, Arc::new(client));
+
+        // The permit has to land before the transfer can draw on it
+        let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+        signature.r.to_big_endian(&mut r);
+        signature.s.to_big_endian(&mut s);
+        let call = token.permit(owner, spender, value, deadline, signature.v as u8, r, s);
+        let call = apply_tx_type(call, self.tx_type, chain_id);
+        let pending_tx = call.send().await.map_err(settlement_error)?;
+        self.confirm(&provider, pending_tx.tx_hash()).await?;
+
+        let call = apply_tx_type(
+            token.transfer_from(owner, pay_to, charge),
+            self.tx_type,
+            chain_id,
+        );
+        let pending_tx = call.send().await.map_err(settlement_error)?;
+        let receipt = self.confirm(&provider, pending_tx.tx_hash()).await?;
+
+        Ok(SettlementInfo {
+            payer: format!("{:?}", owner),
+            pay_to: format!("{:?}", pay_to),
+            amount: charge.to_string(),
+            asset: format!("{:?}", asset),
+            network: payload.network.clone(),
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+            block_number: receipt.block_number.map(|n| n.as_u64()),
+        })
+    }
+}
+
+/// Maps a failed settlement call to an error, decoding the revert reason if any.
+fn settlement_error<M: Middleware>(e: ContractError<M>) -> X402Error {
+    let reason = e.as_revert().and_then(|data| decode_revert_reason(data));
+    X402Error::SettlementError(match reason {
+        Some(reason) => format!("Transaction reverted: {}", reason),
+        None => format!("Transaction failed: {}", e),
+    })
+}
@@ -3,12 +3,353 @@
 //! This module provides middleware and helpers for integrating x402 payment requirements
 //! into web servers, particularly with the Axum framework.
 
+use crate::auth::RequestSigner;
+use crate::cache::TtlCache;
 use crate::errors::{Result, X402Error};
-use crate::types::{PaymentRequiredResponse, PaymentRequirements, SettlementRequest, VerificationRequest};
-use crate::utils::{decode_payment_header, dollar_to_token_amount};
-use reqwest::Client;
+use crate::facilitator_client::{
+    CircuitBreaker, Facilitator, FacilitatorClient, RateLimitRetry, RetryConfig,
+};
+use crate::schemes::allowance_evm::ALLOWANCE_TRANSFER_METHOD;
+use crate::settlement_queue::SettlementQueue;
+use crate::types::{
+    PaymentRequiredResponse, PaymentRequirements, SettlementInfo, SettlementRequest,
+    SplitRecipient, SupportedResponse, TransferAuthorization, ACCEPT_PAYMENT_HEADER,
+    X_PAYMENT_HEADER,
+};
+use crate::utils::{
+    current_timestamp, decimal_to_token_amount, decode_payment_header_bounded,
+    dollar_to_token_amount, generate_request_id, MAX_PAYMENT_PAYLOAD_BYTES,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::header::HeaderMap;
+use ethers::types::U256;
+use hmac::{Hmac, Mac};
 use serde_json::json;
+use sha2::Sha256;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+pub use crate::facilitator_client::DEFAULT_USER_AGENT;
+
+/// Signs and opens compact JWT-like tokens (`<claims>.<mac>`, HMAC-SHA256) bound to a
+/// resource.
+#[derive(Clone, Debug)]
+struct TokenSigner {
+    secret: Vec<u8>,
+}
+
+impl TokenSigner {
+    /// Returns the HMAC-SHA256 over encoded `claims`, keyed with the secret.
+    fn mac(&self, claims: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(claims.as_bytes());
+        mac
+    }
+
+    /// Signs `claims`, which must include `res` (resource) and `exp` (expiry).
+    fn sign(&self, claims: &serde_json::Value) -> String {
+        let claims = URL_SAFE_NO_PAD.encode(claims.to_string());
+        let mac = URL_SAFE_NO_PAD.encode(self.mac(&claims).finalize().into_bytes());
+        format!("{}.{}", claims, mac)
+    }
+
+    /// Checks a token's signature, resource and expiry, returning its claims.
+    ///
+    /// `kind` names the token in error messages.
+    fn open(&self, token: &str, resource: &str, kind: &str) -> Result<serde_json::Value> {
+        let invalid =
+            |reason: &str| X402Error::VerificationFailed(format!("{} {}", kind, reason));
+
+        let (claims, mac) = token.split_once('.').ok_or_else(|| invalid("malformed"))?;
+        let mac = URL_SAFE_NO_PAD
+            .decode(mac)
+            .map_err(|_| invalid("malformed"))?;
+        // Compared in constant time
+        self.mac(claims)
+            .verify_slice(&mac)
+            .map_err(|_| invalid("signature mismatch"))?;
+
+        let claims: serde_json::Value = URL_SAFE_NO_PAD
+            .decode(claims)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| invalid("malformed"))?;
+        let exp = claims["exp"].as_u64().ok_or_else(|| invalid("malformed"))?;
+
+        // `res` is a single resource or, for bundles, a list of them
+        let covers = match &claims["res"] {
+            serde_json::Value::String(res) => res == resource,
+            serde_json::Value::Array(bundle) => bundle.iter().any(|r| r.as_str() == Some(resource)),
+            _ => false,
+        };
+        if !covers {
+            return Err(invalid("issued for another resource"));
+        }
+        if current_timestamp() > exp {
+            return Err(invalid("expired"));
+        }
+        Ok(claims)
+    }
+}
+
+/// Issues and checks short-lived, single-use challenges bound into 402 responses.
+///
+/// A challenge is a compact signed token (`<claims>.<mac>`, JWT-like) naming the
+/// resource, an expiry and a unique id, and optionally the HTTP method. Clients echo it
+/// in `PaymentPayload.challenge`, so a payment can only be redeemed against a 402 the
+/// server actually issued.
+#[derive(Clone, Debug)]
+pub struct ChallengeIssuer {
+    signer: TokenSigner,
+    ttl_seconds: u64,
+    /// Redeemed challenge ids and their expiry, pruned as they lapse
+    redeemed: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl ChallengeIssuer {
+    /// Creates an issuer signing challenges with `secret`, valid for `ttl_seconds`.
+    pub fn new(secret: impl Into<Vec<u8>>, ttl_seconds: u64) -> Self {
+        Self {
+            signer: TokenSigner {
+                secret: secret.into(),
+            },
+            ttl_seconds,
+            redeemed: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Issues a fresh challenge for a resource.
+    pub fn issue(&self, resource: &str) -> String {
+        self.issue_claims(json!(resource), None)
+    }
+
+    /// Issues a fresh challenge for a resource, redeemable only by requests made with
+    /// the HTTP `method`.
+    pub fn issue_for_method(&self, method: &str, resource: &str) -> String {
+        self.issue_claims(json!(resource), Some(method))
+    }
+
+    /// Issues a fresh challenge redeemable for any one of a bundle of resources.
+    pub fn issue_bundle(&self, resources: &[String]) -> String {
+        self.issue_claims(json!(resources), None)
+    }
+
+    fn issue_claims(&self, resources: serde_json::Value, method: Option<&str>) -> String {
+        let mut claims = json!({
+            "res": resources,
+            "exp": current_timestamp() + self.ttl_seconds,
+            "jti": generate_request_id(),
+        });
+        if let Some(method) = method {
+            claims["mth"] = json!(method.to_uppercase());
+        }
+        self.signer.sign(&claims)
+    }
+
+    /// Checks that a challenge was issued for `resource`, is unexpired and unused,
+    /// then marks it redeemed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::server::ChallengeIssuer;
+    ///
+    /// let issuer = ChallengeIssuer::new("secret", 60);
+    /// let challenge = issuer.issue("/weather");
+    ///
+    /// assert!(issuer.redeem(&challenge, "/weather").is_ok());
+    /// assert!(issuer.redeem(&challenge, "/weather").is_err()); // single use
+    /// ```
+    pub fn redeem(&self, challenge: &str, resource: &str) -> Result<()> {
+        self.redeem_checked(challenge, None, resource)
+    }
+
+    /// Like [`redeem`](Self::redeem), for a request made with the HTTP `method`.
+    ///
+    /// A challenge issued for a method is only redeemable with that method, so a
+    /// payment made for a `GET` can't be replayed on a `DELETE` of the same resource.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::server::ChallengeIssuer;
+    ///
+    /// let issuer = ChallengeIssuer::new("secret", 60);
+    /// let challenge = issuer.issue_for_method("GET", "/weather");
+    ///
+    /// assert!(issuer.redeem_for_method(&challenge, "DELETE", "/weather").is_err());
+    /// assert!(issuer.redeem(&challenge, "/weather").is_err());
+    /// assert!(issuer.redeem_for_method(&challenge, "get", "/weather").is_ok());
+    /// ```
+    pub fn redeem_for_method(&self, challenge: &str, method: &str, resource: &str) -> Result<()> {
+        self.redeem_checked(challenge, Some(method), resource)
+    }
+
+    fn redeem_checked(&self, challenge: &str, method: Option<&str>, resource: &str) -> Result<()> {
+        let (jti, exp) = self.check(challenge, method, resource)?;
+        let now = current_timestamp();
+        let mut redeemed = self.redeemed.lock().unwrap();
+        redeemed.retain(|_, expiry| *expiry >= now);
+        if redeemed.insert(jti, exp).is_some() {
+            return Err(X402Error::VerificationFailed(
+                "Challenge already used".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks a challenge as [`redeem`](Self::redeem) does without marking it redeemed,
+    /// returning its id and expiry.
+    fn check(
+        &self,
+        challenge: &str,
+        method: Option<&str>,
+        resource: &str,
+    ) -> Result<(String, u64)> {
+        let claims = self.signer.open(challenge, resource, "Challenge")?;
+        let (Some(exp), Some(jti)) = (claims["exp"].as_u64(), claims["jti"].as_str()) else {
+            return Err(X402Error::VerificationFailed("Challenge malformed".to_string()));
+        };
+        if let Some(bound) = claims["mth"].as_str() {
+            if !method.is_some_and(|method| method.eq_ignore_ascii_case(bound)) {
+                return Err(X402Error::VerificationFailed(
+                    "Challenge issued for another method".to_string(),
+                ));
+            }
+        }
+
+        if self.redeemed.lock().unwrap().contains_key(jti) {
+            return Err(X402Error::VerificationFailed(
+                "Challenge already used".to_string(),
+            ));
+        }
+        Ok((jti.to_string(), exp))
+    }
+}
+
+/// Issues and checks time-boxed access tokens ("pay once, access many").
+///
+/// After a payment settles, the server can mint a token for the resource and return it
+/// in `PaymentResponse.access_token`. Clients present it in the `X-PAYMENT-TOKEN`
+/// header on later requests, which are served without payment until it expires.
+#[derive(Clone, Debug)]
+pub struct AccessTokenIssuer {
+    signer: TokenSigner,
+    ttl_seconds: u64,
+}
+
+impl AccessTokenIssuer {
+    /// Creates an issuer signing tokens with `secret`, valid for `ttl_seconds`.
+    pub fn new(secret: impl Into<Vec<u8>>, ttl_seconds: u64) -> Self {
+        Self {
+            signer: TokenSigner {
+                secret: secret.into(),
+            },
+            ttl_seconds,
+        }
+    }
+
+    /// Issues an access token to `payer` for a resource.
+    pub fn issue(&self, resource: &str, payer: &str) -> String {
+        self.signer.sign(&json!({
+            "res": resource,
+            "exp": current_timestamp() + self.ttl_seconds,
+            "sub": payer,
+        }))
+    }
+
+    /// Checks that a token grants access to `resource`, returning the payer it was
+    /// issued to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::server::AccessTokenIssuer;
+    ///
+    /// let issuer = AccessTokenIssuer::new("secret", 3600);
+    /// let token = issuer.issue("/weather", "0xpayer");
+    ///
+    /// assert_eq!(issuer.verify(&token, "/weather").unwrap(), "0xpayer");
+    /// assert!(issuer.verify(&token, "/premium").is_err());
+    /// ```
+    pub fn verify(&self, token: &str, resource: &str) -> Result<String> {
+        let claims = self.signer.open(token, resource, "Access token")?;
+        Ok(claims["sub"].as_str().unwrap_or_default().to_string())
+    }
+}
+
+/// Rules applied to a resource path before it is bound into payment requirements.
+///
+/// Applying the same rules when generating and verifying requirements keeps
+/// `/weather`, `/weather/` and `/Weather` from being treated as different resources.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceNormalization {
+    /// Remove a trailing `/` (the root path `/` is kept)
+    pub strip_trailing_slash: bool,
+
+    /// Lowercase the resource
+    pub lowercase: bool,
+}
+
+impl ResourceNormalization {
+    /// Normalization that strips trailing slashes and lowercases.
+    pub fn lenient() -> Self {
+        Self {
+            strip_trailing_slash: true,
+            lowercase: true,
+        }
+    }
+
+    /// Applies the configured rules to a resource.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::server::ResourceNormalization;
+    ///
+    /// let rules = ResourceNormalization::lenient();
+    /// assert_eq!(rules.normalize("/Weather/"), "/weather");
+    /// assert_eq!(rules.normalize("/"), "/");
+    /// ```
+    pub fn normalize(&self, resource: &str) -> String {
+        let mut normalized = resource;
+        if self.strip_trailing_slash {
+            let trimmed = normalized.trim_end_matches('/');
+            normalized = if trimmed.is_empty() && !resource.is_empty() { "/" } else { trimmed };
+        }
+
+        if self.lowercase {
+            normalized.to_lowercase()
+        } else {
+            normalized.to_string()
+        }
+    }
+}
+
+/// Prices a resource in USD per request, e.g. for surge or per-caller pricing.
+#[derive(Clone)]
+pub struct PriceHook(Arc<dyn Fn(&str) -> f64 + Send + Sync>);
+
+impl PriceHook {
+    /// Wraps a function mapping a resource path to its price in USD.
+    pub fn new(price: impl Fn(&str) -> f64 + Send + Sync + 'static) -> Self {
+        Self(Arc::new(price))
+    }
+
+    /// Returns the price in USD for a resource.
+    pub fn price(&self, resource: &str) -> f64 {
+        (self.0)(resource)
+    }
+}
+
+impl std::fmt::Debug for PriceHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PriceHook(..)")
+    }
+}
 
 /// Configuration for payment requirements on a server endpoint.
 #[derive(Clone, Debug)]
@@ -40,9 +381,77 @@ pub struct PaymentConfig {
     /// Maximum timeout in seconds for payment validity
     pub max_timeout_seconds: u64,
     
-    /// Token name and version for EIP-712 (optional)
+    /// Token name for EIP-712 (optional)
     pub token_name: Option<String>,
+
+    /// Token version for EIP-712 (optional)
     pub token_version: Option<String>,
+
+    /// EIP-712 type string of the token's authorization, if not `TransferWithAuthorization`
+    pub authorization_type: Option<String>,
+
+    /// Precomputed EIP-712 domain separator payers must sign in, as hex
+    pub domain_separator: Option<String>,
+
+    /// Whether payers must also sign the x402 message around their authorization
+    pub require_message_signature: bool,
+
+    /// Attestation payers must hold, checked by the facilitator
+    pub required_attestation: Option<String>,
+
+    /// Facilitator account payers approve, when paying from an allowance
+    pub allowance_spender: Option<String>,
+
+    /// Facilitator account payers permit to charge them, for the "upto" scheme
+    pub upto_spender: Option<String>,
+
+    /// Recipients the payment is split between, if not all of it goes to `pay_to`
+    pub splits: Vec<SplitRecipient>,
+
+    /// User-Agent sent on facilitator calls
+    pub user_agent: String,
+
+    /// Normalization applied to resources when building requirements
+    pub resource_normalization: ResourceNormalization,
+
+    /// Optional issuer binding 402 responses to single-use challenges
+    pub challenge: Option<ChallengeIssuer>,
+
+    /// Optional per-resource pricing, overriding `price_usd`
+    pub price_hook: Option<PriceHook>,
+
+    /// Optional issuer of access tokens minted on settlement
+    pub access_tokens: Option<AccessTokenIssuer>,
+
+    /// Exact decimal price in USD (e.g. `"0.001"`), used instead of `price_usd`
+    pub price: Option<String>,
+
+    /// Optional circuit breaker shared by all facilitator calls made with this config,
+    /// unless a `facilitator` is injected
+    pub circuit_breaker: Option<CircuitBreaker>,
+
+    /// Optional retrying of facilitator calls answered with 429 Too Many Requests,
+    /// unless a `facilitator` is injected
+    pub rate_limit_retry: Option<RateLimitRetry>,
+
+    /// Optional retrying of facilitator calls failing with a transient status
+    pub facilitator_retry: Option<RetryConfig>,
+
+    /// Optional API key signing calls to a facilitator requiring authentication
+    pub facilitator_api_key: Option<RequestSigner>,
+
+    /// Resources unlocked together by a single payment
+    pub bundled_resources: Vec<String>,
+
+    /// Facilitator used instead of calling `facilitator_url` over HTTP
+    pub facilitator: Option<Arc<dyn Facilitator>>,
+
+    /// Cache of the facilitator's supported kinds, when 402 responses only offer what
+    /// it can settle
+    pub supported_kinds: Option<TtlCache<String, SupportedResponse>>,
+
+    /// HTTP method of the request being served, bound into challenges
+    pub request_method: Option<String>,
 }
 
 impl PaymentConfig {
@@ -64,6 +473,7 @@ impl PaymentConfig {
     ///     "https://facilitator.example.com",
     /// );
     /// ```
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         pay_to: impl Into<String>,
         asset: impl Into<String>,
@@ -86,6 +496,27 @@ impl PaymentConfig {
             max_timeout_seconds: 300,
             token_name: None,
             token_version: None,
+            authorization_type: None,
+            domain_separator: None,
+            require_message_signature: false,
+            required_attestation: None,
+            allowance_spender: None,
+            upto_spender: None,
+            splits: Vec::new(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            resource_normalization: ResourceNormalization::default(),
+            challenge: None,
+            price_hook: None,
+            access_tokens: None,
+            price: None,
+            circuit_breaker: None,
+            rate_limit_retry: None,
+            facilitator_retry: None,
+            facilitator_api_key: None,
+            bundled_resources: Vec::new(),
+            facilitator: None,
+            supported_kinds: None,
+            request_method: None,
         }
     }
 
@@ -102,9 +533,336 @@ impl PaymentConfig {
         self
     }
 
+    /// Sets the EIP-712 authorization type string for tokens with a variant EIP-3009
+    /// struct, sent to clients as `extra.authorizationType`.
+    pub fn with_authorization_type(mut self, type_string: impl Into<String>) -> Self {
+        self.authorization_type = Some(type_string.into());
+        self
+    }
+
+    /// Pins the token's EIP-712 domain to a precomputed separator, sent to clients as
+    /// `extra.domainSeparator`. Clients and facilitators still derive the domain from
+    /// the asset, chain and token metadata, and refuse a separator that differs.
+    pub fn with_domain_separator(mut self, domain_separator: impl Into<String>) -> Self {
+        self.domain_separator = Some(domain_separator.into());
+        self
+    }
+
+    /// Requires payers to sign the whole x402 message, not just the transfer, so their
+    /// authorization cannot be replayed outside x402. Sent as
+    /// `extra.requireMessageSignature`.
+    pub fn with_message_signature(mut self) -> Self {
+        self.require_message_signature = true;
+        self
+    }
+
+    /// Requires payers to hold `attestation`, e.g. an EAS schema or SBT contract, for
+    /// sybil resistance. Sent as `extra.requiredAttestation`; the facilitator rejects
+    /// payers its [`AttestationVerifier`](crate::facilitator::AttestationVerifier)
+    /// doesn't vouch for.
+    pub fn with_required_attestation(mut self, attestation: impl Into<String>) -> Self {
+        self.required_attestation = Some(attestation.into());
+        self
+    }
+
+    /// Takes payment from an ERC-20 allowance the payer granted `spender`, the
+    /// facilitator's settlement account, for tokens without EIP-3009. Sent as
+    /// `extra.assetTransferMethod` and `extra.spender`.
+    pub fn with_allowance_transfer(mut self, spender: impl Into<String>) -> Self {
+        self.allowance_spender = Some(spender.into());
+        self
+    }
+
+    /// Charges by usage with the "upto" scheme: payers permit `spender`, the
+    /// facilitator's settlement account, to take up to the price, and the facilitator
+    /// settles the `actualAmount` named in the settle request. Sent as `extra.spender`.
+    pub fn with_upto(mut self, spender: impl Into<String>) -> Self {
+        self.scheme = "upto".to_string();
+        self.upto_spender = Some(spender.into());
+        self
+    }
+
+    /// Splits the payment, sending `recipient` a share of `bps` basis points. Sent as
+    /// `extra.splits`; the shares of all recipients must add up to 10000, and the last
+    /// recipient also receives any rounding remainder.
+    ///
+    /// Payers sign one authorization per recipient, which the facilitator settles
+    /// together with [`SplitEvm`](crate::schemes::split_evm::SplitEvm).
+    pub fn with_split(mut self, recipient: impl Into<String>, bps: u16) -> Self {
+        self.splits.push(SplitRecipient {
+            pay_to: recipient.into(),
+            bps,
+        });
+        self
+    }
+
+    /// Offers this option in [`create_supported_payment_required_response`] only if the
+    /// facilitator's `/supported` says it can settle it, re-fetching that list after
+    /// `ttl`.
+    pub fn with_supported_filter(mut self, ttl: Duration) -> Self {
+        self.supported_kinds = Some(TtlCache::new(ttl));
+        self
+    }
+
+    /// Sets the User-Agent sent on facilitator calls.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets the normalization applied to resources when building and verifying requirements.
+    pub fn with_resource_normalization(mut self, normalization: ResourceNormalization) -> Self {
+        self.resource_normalization = normalization;
+        self
+    }
+
+    /// Requires payments to echo a challenge issued with the 402 response.
+    ///
+    /// Challenges are signed with `secret` and expire after `ttl_seconds`.
+    pub fn with_challenge(mut self, secret: impl Into<Vec<u8>>, ttl_seconds: u64) -> Self {
+        self.challenge = Some(ChallengeIssuer::new(secret, ttl_seconds));
+        self
+    }
+
+    /// Binds challenges to the HTTP method of the request being served.
+    ///
+    /// Set per request on a copy of the configuration, both when answering with a 402
+    /// and when verifying the payment, so a payment made for one method is refused on
+    /// another at the same resource. Only takes effect with
+    /// [challenges](Self::with_challenge); copies share redeemed challenges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::server::create_simple_config;
+    ///
+    /// let config = create_simple_config("0xpay_to", 0.01, "API", "https://facilitator.example.com")
+    ///     .with_challenge("server-secret", 60);
+    /// let get_config = config.clone().with_request_method("GET");
+    /// # assert_eq!(get_config.request_method.as_deref(), Some("GET"));
+    /// ```
+    pub fn with_request_method(mut self, method: impl Into<String>) -> Self {
+        self.request_method = Some(method.into());
+        self
+    }
+
+    /// Fails facilitator calls fast for `cooldown` after `failure_threshold` consecutive
+    /// facilitator failures. Ignored when a facilitator is injected with
+    /// [`with_facilitator`](Self::with_facilitator).
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cooldown: Duration) -> Self {
+        self.circuit_breaker = Some(CircuitBreaker::new(failure_threshold, cooldown));
+        self
+    }
+
+    /// Retries facilitator calls answered with 429 Too Many Requests, honouring
+    /// `Retry-After` up to `max_wait` per retry and `max_retries` retries in total.
+    /// Ignored when a facilitator is injected with
+    /// [`with_facilitator`](Self::with_facilitator).
+    pub fn with_rate_limit_retry(mut self, max_retries: u32, max_wait: Duration) -> Self {
+        self.rate_limit_retry = Some(RateLimitRetry {
+            max_retries,
+            max_wait,
+        });
+        self
+    }
+
+    /// Retries facilitator verify and settle calls failing with a transient status,
+    /// such as 503, with jittered backoff within `retry`'s bounds.
+    pub fn with_facilitator_retry(mut self, retry: RetryConfig) -> Self {
+        self.facilitator_retry = Some(retry);
+        self
+    }
+
+    /// Signs facilitator calls with the API key `key_id` and its shared secret.
+    pub fn with_facilitator_api_key(
+        mut self,
+        key_id: impl Into<String>,
+        secret: impl AsRef<[u8]>,
+    ) -> Self {
+        self.facilitator_api_key = Some(RequestSigner::new(key_id, secret));
+        self
+    }
+
+    /// Lets a single payment unlock any of `resources`.
+    ///
+    /// Requirements for a bundled resource list the whole bundle in `extra.resources`,
+    /// and with challenges enabled a challenge issued for one of them can be redeemed
+    /// at any other, but not at resources outside the bundle.
+    pub fn with_bundled_resources<I, S>(mut self, resources: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.bundled_resources = resources.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns the normalized bundle containing `resource`, if it is bundled.
+    fn bundle_for(&self, resource: &str) -> Option<Vec<String>> {
+        let bundle: Vec<String> = self
+            .bundled_resources
+            .iter()
+            .map(|r| self.resource_normalization.normalize(r))
+            .collect();
+        bundle.iter().any(|r| r == resource).then_some(bundle)
+    }
+
+    /// Sends verification and settlement to `facilitator` instead of `facilitator_url`.
+    ///
+    /// Useful with an in-process facilitator for tests and single-binary deployments.
+    /// Settings for the HTTP client built from `facilitator_url`, such as
+    /// [`with_circuit_breaker`](Self::with_circuit_breaker) and
+    /// [`with_rate_limit_retry`](Self::with_rate_limit_retry), don't apply to
+    /// `facilitator`; pass a [`FacilitatorClient`] configured with them instead.
+    pub fn with_facilitator(mut self, facilitator: impl Facilitator + 'static) -> Self {
+        self.facilitator = Some(Arc::new(facilitator));
+        self
+    }
+
+    /// Mints an access token on settlement that grants the payer access to the same
+    /// resource for `ttl_seconds` without paying again.
+    pub fn with_access_tokens(mut self, secret: impl Into<Vec<u8>>, ttl_seconds: u64) -> Self {
+        self.access_tokens = Some(AccessTokenIssuer::new(secret, ttl_seconds));
+        self
+    }
+
+    /// Issues an access token for a settled payment, if access tokens are enabled.
+    pub fn issue_access_token(
+        &self,
+        resource: &str,
+        settlement: &SettlementInfo,
+    ) -> Option<String> {
+        let resource = self.resource_normalization.normalize(resource);
+        self.access_tokens
+            .as_ref()
+            .map(|issuer| issuer.issue(&resource, &settlement.payer))
+    }
+
+    /// Returns `true` if `token` is a live access token for the resource.
+    pub fn has_access(&self, token: &str, resource: &str) -> bool {
+        let resource = self.resource_normalization.normalize(resource);
+        self.access_tokens
+            .as_ref()
+            .is_some_and(|issuer| issuer.verify(token, &resource).is_ok())
+    }
+
+    /// Prices each resource dynamically instead of using the static `price_usd`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::server::create_simple_config;
+    ///
+    /// let config = create_simple_config("0xpay_to", 0.01, "API", "https://facilitator.example.com")
+    ///     .with_price_hook(|resource| {
+    ///         if resource.starts_with("/premium") { 0.10 } else { 0.01 }
+    ///     });
+    ///
+    /// assert_eq!(config.price_for("/premium/report"), 0.10);
+    /// ```
+    pub fn with_price_hook(mut self, price: impl Fn(&str) -> f64 + Send + Sync + 'static) -> Self {
+        self.price_hook = Some(PriceHook::new(price));
+        self
+    }
+
+    /// Returns the price in USD for a resource, consulting the price hook if set.
+    pub fn price_for(&self, resource: &str) -> f64 {
+        self.price_hook
+            .as_ref()
+            .map_or(self.price_usd, |hook| hook.price(resource))
+    }
+
+    /// Builds the requirements advertised in a 402 response, attaching a fresh
+    /// challenge when one is configured.
+    fn to_offered_requirements(&self, resource: &str) -> Result<PaymentRequirements> {
+        let mut requirements = self.to_requirements(resource)?;
+        if let Some(issuer) = &self.challenge {
+            let resources = match self.bundle_for(&requirements.resource) {
+                Some(bundle) => json!(bundle),
+                None => json!(requirements.resource),
+            };
+            let challenge = issuer.issue_claims(resources, self.request_method.as_deref());
+            requirements
+                .extra
+                .get_or_insert_with(|| json!({}))["challenge"] = json!(challenge);
+        }
+        Ok(requirements)
+    }
+
+    /// Returns the facilitator's supported kinds if the supported filter is enabled,
+    /// fetching them unless cached. `None` if disabled or the fetch failed.
+    async fn facilitator_supported(&self) -> Option<SupportedResponse> {
+        let cache = self.supported_kinds.as_ref()?;
+        if let Some(supported) = cache.get(&self.facilitator_url).await {
+            return Some(supported);
+        }
+
+        let fetched = match facilitator_client(self) {
+            Ok(facilitator) => facilitator.supported().await,
+            Err(e) => Err(e),
+        };
+        match fetched {
+            Ok(supported) => {
+                cache.insert(self.facilitator_url.clone(), supported.clone()).await;
+                Some(supported)
+            }
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    "Cannot fetch supported kinds from {}: {}",
+                    self.facilitator_url,
+                    _e
+                );
+                None
+            }
+        }
+    }
+
+    /// Checks the challenge echoed in a payment header, if challenges are enabled,
+    /// without redeeming it.
+    fn check_challenge(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+    ) -> Result<()> {
+        let Some(issuer) = &self.challenge else {
+            return Ok(());
+        };
+        let challenge = Self::challenge_of(payment_header)?;
+        let method = self.request_method.as_deref();
+        issuer.check(&challenge, method, &requirements.resource)?;
+        Ok(())
+    }
+
+    /// Redeems the challenge echoed in a payment header, if challenges are enabled.
+    ///
+    /// Called once the facilitator has verified the payment, so a payment failing
+    /// verification leaves the challenge usable for a corrected one.
+    fn redeem_challenge(
+        &self,
+        payment_header: &str,
+        requirements: &PaymentRequirements,
+    ) -> Result<()> {
+        let Some(issuer) = &self.challenge else {
+            return Ok(());
+        };
+        let challenge = Self::challenge_of(payment_header)?;
+        issuer.redeem_checked(&challenge, self.request_method.as_deref(), &requirements.resource)
+    }
+
+    fn challenge_of(payment_header: &str) -> Result<String> {
+        decode_payment_header_bounded(payment_header, MAX_PAYMENT_PAYLOAD_BYTES)?
+            .challenge
+            .ok_or_else(|| X402Error::VerificationFailed("Missing payment challenge".to_string()))
+    }
+
     /// Converts the configuration to payment requirements.
     pub fn to_requirements(&self, resource: &str) -> Result<PaymentRequirements> {
-        let amount_str = dollar_to_token_amount(self.price_usd, self.decimals, 1.0)?;
+        let resource = self.resource_normalization.normalize(resource);
+        let amount_str = match (&self.price_hook, &self.price) {
+            (None, Some(price)) => decimal_to_token_amount(price, self.decimals)?,
+            _ => dollar_to_token_amount(self.price_for(&resource), self.decimals, 1.0)?,
+        };
 
         let mut extra = json!({});
         if let Some(name) = &self.token_name {
@@ -113,12 +871,38 @@ impl PaymentConfig {
         if let Some(version) = &self.token_version {
             extra["version"] = json!(version);
         }
+        if let Some(authorization_type) = &self.authorization_type {
+            extra["authorizationType"] = json!(authorization_type);
+        }
+        if let Some(domain_separator) = &self.domain_separator {
+            extra["domainSeparator"] = json!(domain_separator);
+        }
+        if self.require_message_signature {
+            extra["requireMessageSignature"] = json!(true);
+        }
+        if let Some(attestation) = &self.required_attestation {
+            extra["requiredAttestation"] = json!(attestation);
+        }
+        if let Some(spender) = &self.allowance_spender {
+            extra["assetTransferMethod"] = json!(ALLOWANCE_TRANSFER_METHOD);
+            extra["spender"] = json!(spender);
+        }
+        if let Some(spender) = &self.upto_spender {
+            extra["spender"] = json!(spender);
+        }
+        if !self.splits.is_empty() {
+            extra["splits"] = json!(self.splits);
+        }
+        if let Some(bundle) = self.bundle_for(&resource) {
+            extra["resources"] = json!(bundle);
+        }
 
         Ok(PaymentRequirements {
             scheme: self.scheme.clone(),
             network: self.network.clone(),
             max_amount_required: amount_str,
-            resource: resource.to_string(),
+            min_amount_required: None,
+            resource,
             description: Some(self.description.clone()),
             mime_type: Some("application/json".to_string()),
             output_schema: None,
@@ -134,6 +918,53 @@ impl PaymentConfig {
     }
 }
 
+/// Returns the facilitator to call, building an HTTP client unless one was injected.
+fn facilitator_client(config: &PaymentConfig) -> Result<Arc<dyn Facilitator>> {
+    if let Some(facilitator) = &config.facilitator {
+        return Ok(facilitator.clone());
+    }
+    let client = FacilitatorClient::with_user_agent(&config.facilitator_url, &config.user_agent)?;
+    let client = match &config.circuit_breaker {
+        Some(breaker) => client.with_circuit_breaker(breaker.clone()),
+        None => client,
+    };
+    let client = match &config.facilitator_api_key {
+        Some(signer) => client.with_request_signer(signer.clone()),
+        None => client,
+    };
+    let client = match &config.facilitator_retry {
+        Some(retry) => client.with_retry(retry.clone()),
+        None => client,
+    };
+    Ok(match config.rate_limit_retry {
+        Some(retry) => Arc::new(client.with_rate_limit_retry(retry)),
+        None => Arc::new(client),
+    })
+}
+
+/// Verifies a payment header with the facilitator's `/verify` endpoint.
+async fn verify_with_facilitator(
+    client: &dyn Facilitator,
+    payment_header: &str,
+    requirements: &PaymentRequirements,
+    actual_amount: Option<&str>,
+    request_id: &str,
+) -> Result<()> {
+    let verification = client
+        .verify_with_amount(payment_header, requirements, actual_amount, request_id)
+        .await?;
+
+    if !verification.is_valid {
+        return Err(X402Error::VerificationFailed(
+            verification
+                .invalid_reason
+                .unwrap_or_else(|| "Unknown reason".to_string()),
+        ));
+    }
+
+    Ok(())
+}
+
 /// Checks if a request has a valid payment header.
 ///
 /// # Arguments
@@ -144,70 +975,188 @@ impl PaymentConfig {
 ///
 /// # Returns
 ///
-/// `Ok(tx_hash)` if payment is valid and settled, `Err` otherwise
+/// Details of the settled payment if it is valid and settled, `Err` otherwise. If the
+/// facilitator omits them, they are reconstructed from the payment header.
+///
+/// Both facilitator calls carry the configured User-Agent and a shared `X-Request-Id`
+/// so the facilitator can correlate them.
 pub async fn verify_and_settle_payment(
     payment_header: &str,
     config: &PaymentConfig,
     resource: &str,
-) -> Result<String> {
+) -> Result<SettlementInfo> {
+    verify_and_settle(payment_header, config, resource, None).await
+}
+
+/// Checks if a request has a valid payment header, and settles only `actual_amount`.
+///
+/// Like [`verify_and_settle_payment`], but for schemes where the server chooses what
+/// to charge within the authorized amount, such as `upto` (see
+/// [`PaymentConfig::with_upto`]). The facilitator refuses an amount above what the
+/// payer authorized.
+///
+/// # Arguments
+///
+/// * `payment_header` - The X-PAYMENT header value (Base64 encoded)
+/// * `config` - Payment configuration
+/// * `resource` - The requested resource path
+/// * `actual_amount` - Amount to charge, in the smallest token unit
+pub async fn verify_and_charge_payment(
+    payment_header: &str,
+    config: &PaymentConfig,
+    resource: &str,
+    actual_amount: U256,
+) -> Result<SettlementInfo> {
+    verify_and_settle(payment_header, config, resource, Some(actual_amount)).await
+}
+
+async fn verify_and_settle(
+    payment_header: &str,
+    config: &PaymentConfig,
+    resource: &str,
+    actual_amount: Option<U256>,
+) -> Result<SettlementInfo> {
+    let actual_amount = actual_amount.map(|amount| amount.to_string());
     let requirements = config.to_requirements(resource)?;
+    config.check_challenge(payment_header, &requirements)?;
+    let request_id = generate_request_id();
+    let client = facilitator_client(config)?;
 
-    // Verify payment with facilitator
-    let client = Client::new();
-    let verify_request = VerificationRequest {
-        payment_header: payment_header.to_string(),
-        payment_requirements: requirements.clone(),
-    };
+    // Verify payment with facilitator, then spend the challenge
+    verify_with_facilitator(
+        client.as_ref(),
+        payment_header,
+        &requirements,
+        actual_amount.as_deref(),
+        &request_id,
+    )
+    .await?;
+    config.redeem_challenge(payment_header, &requirements)?;
 
-    let verify_url = format!("{}/verify", config.facilitator_url);
-    let verify_response = client
-        .post(&verify_url)
-        .json(&verify_request)
-        .send()
+    // Settle payment with facilitator
+    let settlement = client
+        .settle_with_amount(
+            payment_header,
+            &requirements,
+            actual_amount.as_deref(),
+            &request_id,
+        )
         .await?;
 
-    if !verify_response.status().is_success() {
-        return Err(X402Error::VerificationFailed(
-            "Facilitator verification failed".to_string(),
-        ));
+    if let Some(reason) = settlement.invalid_reason {
+        return Err(X402Error::VerificationFailed(reason));
     }
-
-    let verification: crate::types::VerificationResponse = verify_response.json().await?;
-
-    if !verification.is_valid {
-        return Err(X402Error::VerificationFailed(
-            verification
-                .invalid_reason
-                .unwrap_or_else(|| "Unknown reason".to_string()),
-        ));
+    if let Some(error) = settlement.error {
+        return Err(X402Error::SettlementError(error));
     }
 
-    // Settle payment with facilitator
-    let settle_request = SettlementRequest {
-        payment_header: payment_header.to_string(),
-        payment_requirements: requirements,
-    };
+    Ok(settlement.settlement.unwrap_or_else(|| {
+        // Older facilitators only return the transaction hash
+        let auth = decode_payment_header_bounded(payment_header, MAX_PAYMENT_PAYLOAD_BYTES)
+            .ok()
+            .and_then(|payload| payload.typed_payload::<TransferAuthorization>().ok());
+        SettlementInfo {
+            payer: auth.as_ref().map(|a| a.from.clone()).unwrap_or_default(),
+            pay_to: requirements.pay_to.clone(),
+            amount: actual_amount
+                .or(auth.map(|a| a.value))
+                .unwrap_or(requirements.max_amount_required.clone()),
+            asset: requirements.asset.clone(),
+            network: requirements.network.clone(),
+            tx_hash: settlement.tx_hash,
+            block_number: settlement.block_number,
+        }
+    }))
+}
 
-    let settle_url = format!("{}/settle", config.facilitator_url);
-    let settle_response = client
-        .post(&settle_url)
-        .json(&settle_request)
-        .send()
-        .await?;
+/// Verifies a payment and hands settlement off to an out-of-band settler.
+///
+/// Instead of calling the facilitator's `/settle` endpoint, the settlement request is
+/// sent on `queue` once verification succeeds, and the function returns immediately.
+///
+/// # Arguments
+///
+/// * `payment_header` - The X-PAYMENT header value (Base64 encoded)
+/// * `config` - Payment configuration
+/// * `resource` - The requested resource path
+/// * `actual_amount` - Amount to charge for schemes such as `upto`, or `None` to settle
+///   the full authorized amount
+/// * `queue` - Channel drained by the settler
+///
+/// # Returns
+///
+/// `Ok(())` once the payment is verified and queued, `Err` otherwise
+pub async fn verify_and_enqueue_settlement(
+    payment_header: &str,
+    config: &PaymentConfig,
+    resource: &str,
+    actual_amount: Option<U256>,
+    queue: &mpsc::Sender<SettlementRequest>,
+) -> Result<()> {
+    let actual_amount = actual_amount.map(|amount| amount.to_string());
+    let requirements = config.to_requirements(resource)?;
+    config.check_challenge(payment_header, &requirements)?;
+    let request_id = generate_request_id();
+    let client = facilitator_client(config)?;
 
-    if !settle_response.status().is_success() {
-        return Err(X402Error::SettlementError(
-            "Facilitator settlement failed".to_string(),
-        ));
-    }
+    verify_with_facilitator(
+        client.as_ref(),
+        payment_header,
+        &requirements,
+        actual_amount.as_deref(),
+        &request_id,
+    )
+    .await?;
+    config.redeem_challenge(payment_header, &requirements)?;
 
-    let settlement: crate::types::SettlementResponse = settle_response.json().await?;
+    queue
+        .send(SettlementRequest {
+            payment_header: payment_header.to_string(),
+            payment_requirements: requirements,
+            actual_amount,
+        })
+        .await
+        .map_err(|_| X402Error::SettlementError("Settlement queue closed".to_string()))
+}
 
-    if let Some(error) = settlement.error {
-        return Err(X402Error::SettlementError(error));
-    }
+/// Verifies a payment and adds its settlement to a durable queue.
+///
+/// Like [`verify_and_enqueue_settlement`], but the settlement is stored in `queue`, for
+/// example a [`FileQueue`](crate::settlement_queue::FileQueue), and drained by a
+/// [`SettlementWorker`](crate::settlement_queue::SettlementWorker), so it survives a
+/// restart.
+///
+/// # Returns
+///
+/// The queued settlement's id once the payment is verified and queued, `Err` otherwise
+pub async fn verify_and_queue_settlement(
+    payment_header: &str,
+    config: &PaymentConfig,
+    resource: &str,
+    actual_amount: Option<U256>,
+    queue: &dyn SettlementQueue,
+) -> Result<u64> {
+    let actual_amount = actual_amount.map(|amount| amount.to_string());
+    let requirements = config.to_requirements(resource)?;
+    config.check_challenge(payment_header, &requirements)?;
+    let request_id = generate_request_id();
+    let client = facilitator_client(config)?;
+
+    verify_with_facilitator(
+        client.as_ref(),
+        payment_header,
+        &requirements,
+        actual_amount.as_deref(),
+        &request_id,
+    )
+    .await?;
+    config.redeem_challenge(payment_header, &requirements)?;
 
-    Ok(settlement.tx_hash)
+    queue.enqueue(SettlementRequest {
+        payment_header: payment_header.to_string(),
+        payment_requirements: requirements,
+        actual_amount,
+    })
 }
 
 /// Creates a 402 Payment Required response.
@@ -244,7 +1193,7 @@ pub fn create_payment_required_response(
 ) -> Result<PaymentRequiredResponse> {
     let accepts: Result<Vec<_>> = configs
         .values()
-        .map(|config| config.to_requirements(resource))
+        .map(|config| config.to_offered_requirements(resource))
         .collect();
 
     Ok(PaymentRequiredResponse {
@@ -254,6 +1203,275 @@ pub fn create_payment_required_response(
     })
 }
 
+/// Creates a 402 Payment Required response leaving out options the facilitator can't
+/// settle.
+///
+/// Options of configs with [`PaymentConfig::with_supported_filter`] are offered only if
+/// their facilitator's `/supported` lists a kind covering them that isn't verify-only.
+/// If `/supported` can't be fetched the option is kept, so a facilitator outage doesn't
+/// empty the 402. Other options are always offered.
+///
+/// # Arguments
+///
+/// * `configs` - Map of payment configurations
+/// * `resource` - The requested resource path
+pub async fn create_supported_payment_required_response(
+    configs: &HashMap<String, PaymentConfig>,
+    resource: &str,
+) -> Result<PaymentRequiredResponse> {
+    let mut accepts = Vec::with_capacity(configs.len());
+    for config in configs.values() {
+        let requirements = config.to_offered_requirements(resource)?;
+        if let Some(supported) = config.facilitator_supported().await {
+            let settleable = supported
+                .supported
+                .iter()
+                .any(|kind| !kind.verify_only && kind.covers(&requirements));
+            if !settleable {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    "Facilitator {} cannot settle {}/{}; not offering it",
+                    config.facilitator_url,
+                    requirements.scheme,
+                    requirements.network
+                );
+                continue;
+            }
+        }
+        accepts.push(requirements);
+    }
+
+    Ok(PaymentRequiredResponse {
+        x402_version: 1,
+        accepts,
+        error: None,
+    })
+}
+
+/// Returns the request's X-PAYMENT header, if it sent one.
+///
+/// A request carrying the header more than once, whether duplicated by a proxy or
+/// crafted to make the server and facilitator read different payments, is rejected
+/// rather than paid with whichever copy comes first.
+///
+/// # Errors
+///
+/// Returns `X402Error::InvalidPayload` if the header appears more than once or isn't
+/// valid ASCII.
+///
+/// # Examples
+///
+/// ```
+/// use reqwest::header::HeaderMap;
+/// use x402_rs::server::payment_header;
+/// use x402_rs::X_PAYMENT_HEADER;
+///
+/// let mut headers = HeaderMap::new();
+/// assert_eq!(payment_header(&headers).unwrap(), None);
+///
+/// headers.insert(X_PAYMENT_HEADER, "eyJ4NDAyVmVyc2lvbiI6MX0=".parse().unwrap());
+/// assert_eq!(payment_header(&headers).unwrap(), Some("eyJ4NDAyVmVyc2lvbiI6MX0="));
+///
+/// headers.append(X_PAYMENT_HEADER, "eyJ4NDAyVmVyc2lvbiI6Mn0=".parse().unwrap());
+/// assert!(payment_header(&headers).is_err());
+/// ```
+pub fn payment_header(headers: &HeaderMap) -> Result<Option<&str>> {
+    let mut values = headers.get_all(X_PAYMENT_HEADER).iter();
+    let Some(value) = values.next() else {
+        return Ok(None);
+    };
+    if values.next().is_some() {
+        return Err(X402Error::InvalidPayload(format!(
+            "Multiple {} headers",
+            X_PAYMENT_HEADER
+        )));
+    }
+    value
+        .to_str()
+        .map(Some)
+        .map_err(|_| X402Error::InvalidPayload("Invalid payment header encoding".to_string()))
+}
+
+/// Parses an `Accept-Payment` header into `(scheme, network)` entries.
+///
+/// The header is a comma-separated list of `scheme` or `scheme/network` items, where
+/// `*` matches any scheme and an omitted network matches any network.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::server::parse_accept_payment;
+///
+/// let entries = parse_accept_payment("exact/8453, upto");
+/// assert_eq!(entries[0], ("exact".to_string(), Some("8453".to_string())));
+/// assert_eq!(entries[1], ("upto".to_string(), None));
+/// ```
+pub fn parse_accept_payment(header: &str) -> Vec<(String, Option<String>)> {
+    header
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| match item.split_once('/') {
+            Some((scheme, network)) => (scheme.trim().to_string(), Some(network.trim().to_string())),
+            None => (item.to_string(), None),
+        })
+        .collect()
+}
+
+/// Creates a 402 Payment Required response filtered by the client's `Accept-Payment` header.
+///
+/// Options whose scheme/network the client did not advertise are omitted. Without a
+/// header, this behaves like [`create_payment_required_response`].
+///
+/// # Arguments
+///
+/// * `configs` - Map of payment configurations
+/// * `resource` - The requested resource path
+/// * `accept_payment` - The raw `Accept-Payment` header value, if the client sent one
+pub fn create_negotiated_payment_required_response(
+    configs: &HashMap<String, PaymentConfig>,
+    resource: &str,
+    accept_payment: Option<&str>,
+) -> Result<PaymentRequiredResponse> {
+    let mut response = create_payment_required_response(configs, resource)?;
+
+    if let Some(header) = accept_payment {
+        let accepted = parse_accept_payment(header);
+        response.accepts.retain(|requirement| {
+            accepted.iter().any(|(scheme, network)| {
+                (scheme == "*" || scheme == &requirement.scheme)
+                    && network.as_ref().map_or(true, |n| n == &requirement.network)
+            })
+        });
+
+        if response.accepts.is_empty() {
+            response.error = Some(format!("No payment option matches {}", ACCEPT_PAYMENT_HEADER));
+        }
+    }
+
+    Ok(response)
+}
+
+/// Builder for [`PaymentConfig`] taking the price as an exact decimal string.
+///
+/// `f64` prices can't represent values like $0.001 exactly; the builder parses the
+/// price string and converts it with integer math instead.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::server::PaymentConfigBuilder;
+///
+/// let config = PaymentConfigBuilder::new()
+///     .pay_to("0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb")
+///     .asset("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913")
+///     .decimals(6)
+///     .network("8453")
+///     .price("0.001")
+///     .description("Sub-cent API call")
+///     .facilitator_url("https://facilitator.example.com")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(config.to_requirements("/api").unwrap().max_amount_required, "1000");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PaymentConfigBuilder {
+    pay_to: Option<String>,
+    asset: Option<String>,
+    decimals: Option<u8>,
+    network: Option<String>,
+    scheme: Option<String>,
+    price: Option<String>,
+    description: Option<String>,
+    facilitator_url: Option<String>,
+}
+
+impl PaymentConfigBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the recipient address.
+    pub fn pay_to(mut self, pay_to: impl Into<String>) -> Self {
+        self.pay_to = Some(pay_to.into());
+        self
+    }
+
+    /// Sets the token contract address.
+    pub fn asset(mut self, asset: impl Into<String>) -> Self {
+        self.asset = Some(asset.into());
+        self
+    }
+
+    /// Sets the token decimals.
+    pub fn decimals(mut self, decimals: u8) -> Self {
+        self.decimals = Some(decimals);
+        self
+    }
+
+    /// Sets the network identifier.
+    pub fn network(mut self, network: impl Into<String>) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    /// Sets the payment scheme (defaults to `"exact"`).
+    pub fn scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.scheme = Some(scheme.into());
+        self
+    }
+
+    /// Sets the price in USD as a decimal string, e.g. `"0.001"`.
+    pub fn price(mut self, price: impl Into<String>) -> Self {
+        self.price = Some(price.into());
+        self
+    }
+
+    /// Sets the human-readable description (defaults to empty).
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the facilitator URL.
+    pub fn facilitator_url(mut self, facilitator_url: impl Into<String>) -> Self {
+        self.facilitator_url = Some(facilitator_url.into());
+        self
+    }
+
+    /// Builds the configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::MissingField` if a required field is unset, or
+    /// `X402Error::InvalidAmount` if the price can't be represented exactly in the
+    /// token's decimals.
+    pub fn build(self) -> Result<PaymentConfig> {
+        let missing = |field: &str| X402Error::MissingField(field.to_string());
+
+        let decimals = self.decimals.ok_or_else(|| missing("decimals"))?;
+        let price = self.price.ok_or_else(|| missing("price"))?;
+        // Validate up front so misconfiguration fails at startup, not per request
+        decimal_to_token_amount(&price, decimals)?;
+
+        let mut config = PaymentConfig::new(
+            self.pay_to.ok_or_else(|| missing("pay_to"))?,
+            self.asset.ok_or_else(|| missing("asset"))?,
+            decimals,
+            self.network.ok_or_else(|| missing("network"))?,
+            self.scheme.unwrap_or_else(|| "exact".to_string()),
+            // Approximation kept for display; amounts use `price`
+            price.trim().parse().unwrap_or_default(),
+            self.description.unwrap_or_default(),
+            self.facilitator_url.ok_or_else(|| missing("facilitator_url"))?,
+        );
+        config.price = Some(price);
+        Ok(config)
+    }
+}
+
 /// Helper to create a simple single-payment configuration.
 ///
 /// # Examples
@@ -327,6 +1545,31 @@ mod tests {
         assert_eq!(requirements.max_amount_required, "10000"); // $0.01 in USDC (6 decimals)
     }
 
+    #[test]
+    fn test_resource_normalization() {
+        let config = create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        );
+
+        // Default keeps resources verbatim
+        assert_eq!(config.to_requirements("/Weather/").unwrap().resource, "/Weather/");
+
+        let config = config.with_resource_normalization(ResourceNormalization::lenient());
+        for variant in ["/weather", "/weather/", "/Weather", "/WEATHER//"] {
+            assert_eq!(config.to_requirements(variant).unwrap().resource, "/weather");
+        }
+        assert_eq!(config.to_requirements("/").unwrap().resource, "/");
+
+        let strip_only = ResourceNormalization {
+            strip_trailing_slash: true,
+            lowercase: false,
+        };
+        assert_eq!(strip_only.normalize("/Weather/"), "/Weather");
+    }
+
     #[test]
     fn test_create_payment_required_response() {
         let mut configs = HashMap::new();
@@ -348,5 +1591,201 @@ mod tests {
         assert_eq!(response.x402_version, 1);
         assert_eq!(response.accepts.len(), 1);
     }
-}
 
+    #[test]
+    fn test_accept_payment_filters_unsupported_scheme() {
+        let mut configs = HashMap::new();
+        let exact = create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        );
+        let mut upto = exact.clone();
+        upto.scheme = "upto".to_string();
+        configs.insert("exact".to_string(), exact);
+        configs.insert("upto".to_string(), upto);
+
+        let response =
+            create_negotiated_payment_required_response(&configs, "/test", Some("exact/8453")).unwrap();
+        assert_eq!(response.accepts.len(), 1);
+        assert_eq!(response.accepts[0].scheme, "exact");
+
+        let response = create_negotiated_payment_required_response(&configs, "/test", None).unwrap();
+        assert_eq!(response.accepts.len(), 2);
+
+        let response =
+            create_negotiated_payment_required_response(&configs, "/test", Some("exact/137")).unwrap();
+        assert!(response.accepts.is_empty());
+        assert!(response.error.is_some());
+    }
+
+    #[test]
+    fn test_bundled_payment_accepted_for_listed_resources() {
+        use crate::types::PaymentPayload;
+        use crate::utils::encode_payment_header;
+
+        let config = create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        )
+        .with_challenge("server-secret", 60)
+        .with_bundled_resources(["/reports/daily", "/reports/weekly"]);
+        let configs = HashMap::from([("usdc".to_string(), config.clone())]);
+
+        // A payment made against the 402 for one bundled resource
+        let paid_header = || {
+            let response = create_payment_required_response(&configs, "/reports/daily").unwrap();
+            let extra = response.accepts[0].extra.clone().unwrap();
+            assert_eq!(extra["resources"], json!(["/reports/daily", "/reports/weekly"]));
+            encode_payment_header(&PaymentPayload {
+                x402_version: 1,
+                scheme: "exact".to_string(),
+                network: "8453".to_string(),
+                payload: json!({}),
+                challenge: extra["challenge"].as_str().map(String::from),
+                message_signature: None,
+            })
+            .unwrap()
+        };
+        let check = |header: &str, resource: &str| {
+            config.redeem_challenge(header, &config.to_requirements(resource).unwrap())
+        };
+
+        assert!(check(&paid_header(), "/reports/daily").is_ok());
+        assert!(check(&paid_header(), "/reports/weekly").is_ok());
+        assert!(check(&paid_header(), "/reports/monthly").is_err());
+        assert!(config.to_requirements("/reports/monthly").unwrap().extra.unwrap()["resources"]
+            .is_null());
+    }
+
+    #[test]
+    fn test_stale_or_missing_challenge_rejected() {
+        use crate::types::PaymentPayload;
+        use crate::utils::encode_payment_header;
+
+        let config = create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        )
+        .with_challenge("server-secret", 60);
+        let issuer = config.challenge.as_ref().unwrap();
+        let requirements = config.to_requirements("/weather").unwrap();
+
+        let header_with = |challenge: Option<String>| {
+            encode_payment_header(&PaymentPayload {
+                x402_version: 1,
+                scheme: "exact".to_string(),
+                network: "8453".to_string(),
+                payload: json!({}),
+                challenge,
+                message_signature: None,
+            })
+            .unwrap()
+        };
+
+        // Missing
+        assert!(config.redeem_challenge(&header_with(None), &requirements).is_err());
+
+        // Expired, though correctly signed
+        let expired = issuer
+            .signer
+            .sign(&json!({ "res": "/weather", "exp": current_timestamp() - 1, "jti": "old" }));
+        assert!(config.redeem_challenge(&header_with(Some(expired)), &requirements).is_err());
+
+        // Fresh, but with a forged MAC
+        let claims = URL_SAFE_NO_PAD.encode(
+            json!({ "res": "/weather", "exp": current_timestamp() + 60, "jti": "forged" })
+                .to_string(),
+        );
+        let forged = format!("{}.{}", claims, URL_SAFE_NO_PAD.encode([0u8; 32]));
+        assert!(config.redeem_challenge(&header_with(Some(forged)), &requirements).is_err());
+
+        // Fresh challenge from a 402 is accepted once, then rejected on reuse
+        let configs = HashMap::from([("usdc".to_string(), config.clone())]);
+        let response = create_payment_required_response(&configs, "/weather").unwrap();
+        let challenge = response.accepts[0].extra.as_ref().unwrap()["challenge"]
+            .as_str()
+            .map(String::from);
+        let header = header_with(challenge);
+        assert!(config.redeem_challenge(&header, &requirements).is_ok());
+        assert!(config.redeem_challenge(&header, &requirements).is_err());
+    }
+
+    #[test]
+    fn test_challenge_bound_to_request_method() {
+        use crate::types::PaymentPayload;
+        use crate::utils::encode_payment_header;
+
+        let config = create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        )
+        .with_challenge("server-secret", 60);
+        let requirements = config.to_requirements("/weather").unwrap();
+
+        // A payment made against the 402 answering a GET
+        let get = config.clone().with_request_method("GET");
+        let configs = HashMap::from([("usdc".to_string(), get.clone())]);
+        let response = create_payment_required_response(&configs, "/weather").unwrap();
+        let header = encode_payment_header(&PaymentPayload {
+            x402_version: 1,
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            payload: json!({}),
+            challenge: response.accepts[0].extra.as_ref().unwrap()["challenge"]
+                .as_str()
+                .map(String::from),
+            message_signature: None,
+        })
+        .unwrap();
+
+        // Presented on a POST, or without a method, it is refused and stays redeemable
+        let post = config.clone().with_request_method("POST");
+        let err = post.redeem_challenge(&header, &requirements).unwrap_err();
+        assert!(err.to_string().contains("another method"), "{}", err);
+        assert!(config.redeem_challenge(&header, &requirements).is_err());
+        assert!(get.redeem_challenge(&header, &requirements).is_ok());
+    }
+
+    #[test]
+    fn test_price_hook_per_resource() {
+        let config = create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        )
+        .with_price_hook(|resource| if resource == "/premium" { 0.05 } else { 0.01 });
+
+        assert_eq!(config.to_requirements("/premium").unwrap().max_amount_required, "50000");
+        assert_eq!(config.to_requirements("/basic").unwrap().max_amount_required, "10000");
+    }
+
+    #[test]
+    fn test_builder_exact_sub_cent_price() {
+        let builder = PaymentConfigBuilder::new()
+            .pay_to("0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb")
+            .asset("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913")
+            .network("8453")
+            .price("0.001")
+            .facilitator_url("https://facilitator.test");
+
+        let usdc = builder.clone().decimals(6).build().unwrap();
+        assert_eq!(usdc.to_requirements("/api").unwrap().max_amount_required, "1000");
+
+        let eighteen = builder.clone().decimals(18).build().unwrap();
+        assert_eq!(
+            eighteen.to_requirements("/api").unwrap().max_amount_required,
+            "1000000000000000"
+        );
+
+        assert!(matches!(builder.build(), Err(X402Error::MissingField(_))));
+    }
+}
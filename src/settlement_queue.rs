@@ -0,0 +1,679 @@
+//! Durable queue of verified payments awaiting settlement.
+//!
+//! In verify-now-settle-later mode a server answers as soon as a payment is verified
+//! and settles it afterwards. A [`SettlementQueue`] holds those settlements until they
+//! are acknowledged: [`MemoryQueue`] keeps them for the life of the process, while
+//! [`FileQueue`] appends every change to a JSONL file and reloads pending settlements
+//! on startup, so none are lost in a crash. A [`SettlementWorker`] drains the queue
+//! through a [`Facilitator`], setting aside settlements that keep failing as dead
+//! letters.
+
+use crate::errors::{Result, X402Error};
+use crate::facilitator_client::Facilitator;
+use crate::types::SettlementRequest;
+use crate::utils::generate_request_id;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Default delay between polls of an empty queue.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Default delay before retrying a failed settlement.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Default number of attempts before a failing settlement is dead-lettered.
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+
+/// A settlement handed out by [`SettlementQueue::dequeue`].
+#[derive(Debug, Clone)]
+pub struct QueuedSettlement {
+    /// Id to pass to [`SettlementQueue::ack`] or [`SettlementQueue::nack`]
+    pub id: u64,
+
+    /// The settlement to submit
+    pub request: SettlementRequest,
+
+    /// Number of times the settlement was returned with [`SettlementQueue::nack`]
+    pub attempts: u32,
+}
+
+/// Settlements waiting to be submitted.
+///
+/// A dequeued settlement stays in the queue, hidden from other `dequeue` calls, until
+/// it is acknowledged with [`ack`](Self::ack), returned with [`nack`](Self::nack), or
+/// set aside with [`dead_letter`](Self::dead_letter).
+pub trait SettlementQueue: Send + Sync + fmt::Debug {
+    /// Adds a settlement, returning its id.
+    fn enqueue(&self, request: SettlementRequest) -> Result<u64>;
+
+    /// Hands out the settlement pending longest, if any.
+    fn dequeue(&self) -> Result<Option<QueuedSettlement>>;
+
+    /// Removes a settlement once it has been submitted.
+    fn ack(&self, id: u64) -> Result<()>;
+
+    /// Returns a dequeued settlement to the back of the queue so it is retried after
+    /// the others, counting the attempt.
+    fn nack(&self, id: u64) -> Result<()>;
+
+    /// Sets a settlement aside for good, so it is no longer handed out but can still
+    /// be inspected with [`dead_letters`](Self::dead_letters).
+    fn dead_letter(&self, id: u64) -> Result<()>;
+
+    /// Returns the dead-lettered settlements, oldest first.
+    fn dead_letters(&self) -> Vec<QueuedSettlement>;
+
+    /// Returns the number of settlements neither acknowledged nor dead-lettered,
+    /// including dequeued ones.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if every settlement has been acknowledged.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    next_id: u64,
+    entries: BTreeMap<u64, QueuedSettlement>,
+    /// Ids waiting to be handed out, in order
+    ready: VecDeque<u64>,
+    dead: BTreeSet<u64>,
+}
+
+impl QueueState {
+    fn insert(&mut self, id: u64, request: SettlementRequest) {
+        let settlement = QueuedSettlement {
+            id,
+            request,
+            attempts: 0,
+        };
+        self.entries.insert(id, settlement);
+        self.ready.push_back(id);
+        self.next_id = self.next_id.max(id + 1);
+    }
+
+    fn dequeue(&mut self) -> Option<QueuedSettlement> {
+        let id = self.ready.pop_front()?;
+        self.entries.get(&id).cloned()
+    }
+
+    fn ack(&mut self, id: u64) {
+        self.entries.remove(&id);
+        self.ready.retain(|&ready| ready != id);
+        self.dead.remove(&id);
+    }
+
+    fn nack(&mut self, id: u64) {
+        if self.dead.contains(&id) {
+            return;
+        }
+        if let Some(settlement) = self.entries.get_mut(&id) {
+            settlement.attempts += 1;
+            self.ready.retain(|&ready| ready != id);
+            self.ready.push_back(id);
+        }
+    }
+
+    fn dead_letter(&mut self, id: u64) {
+        if self.entries.contains_key(&id) {
+            self.ready.retain(|&ready| ready != id);
+            self.dead.insert(id);
+        }
+    }
+
+    fn dead_letters(&self) -> Vec<QueuedSettlement> {
+        self.dead
+            .iter()
+            .map(|id| self.entries[id].clone())
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len() - self.dead.len()
+    }
+}
+
+/// In-memory [`SettlementQueue`], lost when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryQueue {
+    state: Mutex<QueueState>,
+}
+
+impl MemoryQueue {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SettlementQueue for MemoryQueue {
+    fn enqueue(&self, request: SettlementRequest) -> Result<u64> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.insert(id, request);
+        Ok(id)
+    }
+
+    fn dequeue(&self) -> Result<Option<QueuedSettlement>> {
+        Ok(self.state.lock().unwrap().dequeue())
+    }
+
+    fn ack(&self, id: u64) -> Result<()> {
+        self.state.lock().unwrap().ack(id);
+        Ok(())
+    }
+
+    fn nack(&self, id: u64) -> Result<()> {
+        self.state.lock().unwrap().nack(id);
+        Ok(())
+    }
+
+    fn dead_letter(&self, id: u64) -> Result<()> {
+        self.state.lock().unwrap().dead_letter(id);
+        Ok(())
+    }
+
+    fn dead_letters(&self) -> Vec<QueuedSettlement> {
+        self.state.lock().unwrap().dead_letters()
+    }
+
+    fn len(&self) -> usize {
+        self.state.lock().unwrap().len()
+    }
+}
+
+/// A line of a [`FileQueue`] file.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum QueueRecord {
+    Enqueue { id: u64, request: Box<SettlementRequest> },
+    Ack { id: u64 },
+    Nack { id: u64 },
+    Dead { id: u64 },
+}
+
+/// [`SettlementQueue`] persisted as one JSON record per line.
+///
+/// Every change is appended and flushed before the call returns. Opening the file
+/// replays them, so settlements that were pending or dequeued but not acknowledged
+/// when the process stopped are pending again, with their attempts counted.
+///
+/// # Examples
+///
+/// ```no_run
+/// use x402_rs::settlement_queue::{FileQueue, SettlementQueue};
+///
+/// let queue = FileQueue::open("/var/lib/x402/settlements.jsonl")?;
+/// println!("{} settlements pending", queue.len());
+/// # Ok::<(), x402_rs::X402Error>(())
+/// ```
+#[derive(Debug)]
+pub struct FileQueue {
+    path: PathBuf,
+    file: Mutex<File>,
+    state: Mutex<QueueState>,
+}
+
+impl FileQueue {
+    /// Opens the queue at `path`, creating the file if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::ConfigError` if the file can't be opened or holds a line that
+    /// isn't a queue record.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| {
+                X402Error::ConfigError(format!(
+                    "Cannot open settlement queue {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+        let mut state = QueueState::default();
+        for (i, line) in BufReader::new(&file).lines().enumerate() {
+            let line = line.map_err(|e| {
+                X402Error::ConfigError(format!(
+                    "Cannot read settlement queue {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line).map_err(|e| {
+                X402Error::ConfigError(format!(
+                    "Invalid record on line {} of settlement queue {}: {}",
+                    i + 1,
+                    path.display(),
+                    e
+                ))
+            })?;
+            match record {
+                QueueRecord::Enqueue { id, request } => state.insert(id, *request),
+                QueueRecord::Ack { id } => state.ack(id),
+                QueueRecord::Nack { id } => state.nack(id),
+                QueueRecord::Dead { id } => state.dead_letter(id),
+            }
+        }
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Returns the path of the queue file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn append(&self, record: &QueueRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| {
+                X402Error::Other(format!(
+                    "Cannot write settlement queue {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            })
+    }
+}
+
+impl SettlementQueue for FileQueue {
+    fn enqueue(&self, request: SettlementRequest) -> Result<u64> {
+        // Holding the state lock keeps ids in file order
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        self.append(&QueueRecord::Enqueue {
+            id,
+            request: Box::new(request.clone()),
+        })?;
+        state.insert(id, request);
+        Ok(id)
+    }
+
+    fn dequeue(&self) -> Result<Option<QueuedSettlement>> {
+        Ok(self.state.lock().unwrap().dequeue())
+    }
+
+    fn ack(&self, id: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.entries.contains_key(&id) {
+            self.append(&QueueRecord::Ack { id })?;
+            state.ack(id);
+        }
+        Ok(())
+    }
+
+    fn nack(&self, id: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.entries.contains_key(&id) {
+            self.append(&QueueRecord::Nack { id })?;
+            state.nack(id);
+        }
+        Ok(())
+    }
+
+    fn dead_letter(&self, id: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.entries.contains_key(&id) {
+            self.append(&QueueRecord::Dead { id })?;
+            state.dead_letter(id);
+        }
+        Ok(())
+    }
+
+    fn dead_letters(&self) -> Vec<QueuedSettlement> {
+        self.state.lock().unwrap().dead_letters()
+    }
+
+    fn len(&self) -> usize {
+        self.state.lock().unwrap().len()
+    }
+}
+
+/// Background task settling queued payments through a facilitator.
+///
+/// Each settlement is submitted with [`Facilitator::settle`]. It is acknowledged once
+/// the facilitator answers, whether it settled the payment or refused it as invalid or
+/// with an error, since retrying can't change its answer. If the call itself fails,
+/// the settlement goes to the back of the queue and is retried after a delay, until
+/// `max_attempts` failed calls dead-letter it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use x402_rs::facilitator_client::FacilitatorClient;
+/// use x402_rs::settlement_queue::{FileQueue, SettlementWorker};
+///
+/// # async fn example() -> x402_rs::Result<()> {
+/// let queue = Arc::new(FileQueue::open("/var/lib/x402/settlements.jsonl")?);
+/// let facilitator = Arc::new(FacilitatorClient::new("https://facilitator.example.com")?);
+/// let worker = SettlementWorker::new(queue, facilitator).spawn();
+/// // Dropping the handle stops the worker; unacknowledged settlements stay queued
+/// # drop(worker);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SettlementWorker {
+    queue: Arc<dyn SettlementQueue>,
+    facilitator: Arc<dyn Facilitator>,
+    poll_interval: Duration,
+    retry_delay: Duration,
+    max_attempts: u32,
+}
+
+impl SettlementWorker {
+    /// Creates a worker draining `queue` through `facilitator`.
+    pub fn new(queue: Arc<dyn SettlementQueue>, facilitator: Arc<dyn Facilitator>) -> Self {
+        Self {
+            queue,
+            facilitator,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    /// Sets the delay between polls of an empty queue (defaults to 500 milliseconds).
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets the delay before retrying a failed settlement (defaults to 5 seconds).
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Sets how many failed calls dead-letter a settlement (defaults to 10).
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Submits the next pending settlement, returning `false` if the queue was empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of a failed facilitator call, once the settlement has been
+    /// requeued or dead-lettered, or the queue's error if it can't be read or updated.
+    pub async fn run_once(&self) -> Result<bool> {
+        let Some(settlement) = self.queue.dequeue()? else {
+            return Ok(false);
+        };
+
+        let outcome = self
+            .facilitator
+            .settle_with_amount(
+                &settlement.request.payment_header,
+                &settlement.request.payment_requirements,
+                settlement.request.actual_amount.as_deref(),
+                &generate_request_id(),
+            )
+            .await;
+
+        match outcome {
+            Ok(_response) => {
+                #[cfg(feature = "tracing")]
+                if let Some(reason) = _response.invalid_reason.or(_response.error) {
+                    tracing::warn!("Dropping queued settlement {}: {}", settlement.id, reason);
+                }
+                self.queue.ack(settlement.id)?;
+                Ok(true)
+            }
+            Err(e) => {
+                if settlement.attempts + 1 >= self.max_attempts {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Dead-lettering queued settlement {}: {}", settlement.id, e);
+                    self.queue.dead_letter(settlement.id)?;
+                } else {
+                    self.queue.nack(settlement.id)?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs the worker on the current Tokio runtime until the handle is dropped.
+    pub fn spawn(self) -> SettlementWorkerHandle {
+        SettlementWorkerHandle {
+            task: tokio::spawn(async move {
+                loop {
+                    match self.run_once().await {
+                        Ok(true) => {}
+                        Ok(false) => tokio::time::sleep(self.poll_interval).await,
+                        Err(_e) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("Queued settlement failed, retrying: {}", _e);
+                            tokio::time::sleep(self.retry_delay).await;
+                        }
+                    }
+                }
+            }),
+        }
+    }
+}
+
+/// Handle to a running [`SettlementWorker`]; dropping it stops the worker.
+#[derive(Debug)]
+pub struct SettlementWorkerHandle {
+    task: JoinHandle<()>,
+}
+
+impl SettlementWorkerHandle {
+    /// Stops the worker. A settlement being submitted stays queued and is retried.
+    pub fn stop(self) {}
+}
+
+impl Drop for SettlementWorkerHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        PaymentRequirements, SettlementResponse, SupportedResponse, VerificationResponse,
+    };
+    use crate::utils::current_timestamp;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Facilitator failing its first `failures` settlements, every call for the header
+    /// "down", and refusing the header "unsupported".
+    #[derive(Debug, Default)]
+    struct MockFacilitator {
+        failures: AtomicU32,
+        settled: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Facilitator for MockFacilitator {
+        async fn verify(
+            &self,
+            _payment_header: &str,
+            _requirements: &PaymentRequirements,
+            _request_id: &str,
+        ) -> Result<VerificationResponse> {
+            Err(X402Error::Other("Verification not mocked".to_string()))
+        }
+
+        async fn settle(
+            &self,
+            payment_header: &str,
+            _requirements: &PaymentRequirements,
+            _request_id: &str,
+        ) -> Result<SettlementResponse> {
+            let failing = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing || payment_header == "down" {
+                return Err(X402Error::SettlementError("Facilitator down".to_string()));
+            }
+            if payment_header == "unsupported" {
+                return Ok(SettlementResponse {
+                    tx_hash: String::new(),
+                    block_number: None,
+                    error: Some("Unsupported scheme: upto".to_string()),
+                    invalid_reason: None,
+                    settlement: None,
+                });
+            }
+            self.settled.lock().unwrap().push(payment_header.to_string());
+            Ok(SettlementResponse {
+                tx_hash: "0x01".to_string(),
+                block_number: None,
+                error: None,
+                invalid_reason: None,
+                settlement: None,
+            })
+        }
+
+        async fn supported(&self) -> Result<SupportedResponse> {
+            Err(X402Error::Other("Supported kinds not mocked".to_string()))
+        }
+    }
+
+    fn request(payment_header: &str) -> SettlementRequest {
+        SettlementRequest {
+            payment_header: payment_header.to_string(),
+            payment_requirements: PaymentRequirements {
+                scheme: "exact".to_string(),
+                network: "8453".to_string(),
+                max_amount_required: "10000".to_string(),
+                resource: "/weather".to_string(),
+                min_amount_required: None,
+                description: None,
+                mime_type: None,
+                output_schema: None,
+                pay_to: "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb".to_string(),
+                max_timeout_seconds: 60,
+                asset: "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".to_string(),
+                extra: None,
+            },
+            actual_amount: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_queue_survives_crash() {
+        let path = std::env::temp_dir().join(format!(
+            "x402-settlements-{}-{}.jsonl",
+            std::process::id(),
+            current_timestamp()
+        ));
+
+        let queue = Arc::new(FileQueue::open(&path).unwrap());
+        queue.enqueue(request("settled")).unwrap();
+        queue.enqueue(request("pending")).unwrap();
+
+        // The first settlement goes through; the second fails and the worker crashes
+        // before retrying it
+        let facilitator = Arc::new(MockFacilitator::default());
+        let worker = SettlementWorker::new(queue.clone(), facilitator.clone());
+        assert!(worker.run_once().await.unwrap());
+        facilitator.failures.store(1, Ordering::SeqCst);
+        assert!(worker.run_once().await.is_err());
+        drop(worker);
+        drop(queue);
+
+        let queue = Arc::new(FileQueue::open(&path).unwrap());
+        assert_eq!(queue.len(), 1);
+        let handle = SettlementWorker::new(queue.clone(), facilitator.clone())
+            .with_poll_interval(Duration::from_millis(10))
+            .spawn();
+        for _ in 0..100 {
+            if queue.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        handle.stop();
+
+        assert_eq!(*facilitator.settled.lock().unwrap(), vec!["settled", "pending"]);
+        drop(queue);
+        assert!(FileQueue::open(&path).unwrap().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_dequeued_settlement_is_hidden_until_nacked() {
+        let queue = MemoryQueue::new();
+        let id = queue.enqueue(request("a")).unwrap();
+
+        assert_eq!(queue.dequeue().unwrap().unwrap().id, id);
+        assert!(queue.dequeue().unwrap().is_none());
+        queue.nack(id).unwrap();
+        assert_eq!(queue.dequeue().unwrap().unwrap().id, id);
+        queue.ack(id).unwrap();
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failing_settlement_does_not_block_queue() {
+        let path = std::env::temp_dir().join(format!(
+            "x402-dead-letters-{}-{}.jsonl",
+            std::process::id(),
+            current_timestamp()
+        ));
+        let queue = Arc::new(FileQueue::open(&path).unwrap());
+        let down = queue.enqueue(request("down")).unwrap();
+        queue.enqueue(request("unsupported")).unwrap();
+        queue.enqueue(request("good")).unwrap();
+
+        let facilitator = Arc::new(MockFacilitator::default());
+        let worker = SettlementWorker::new(queue.clone(), facilitator.clone()).with_max_attempts(2);
+
+        // The failed call goes to the back, the refusal is dropped, and the rest settle
+        assert!(worker.run_once().await.is_err());
+        assert!(worker.run_once().await.unwrap());
+        assert!(worker.run_once().await.unwrap());
+        assert_eq!(*facilitator.settled.lock().unwrap(), vec!["good"]);
+
+        // A second failed call dead-letters it
+        assert!(worker.run_once().await.is_err());
+        assert!(!worker.run_once().await.unwrap());
+        assert!(queue.is_empty());
+        drop(worker);
+        drop(queue);
+
+        let queue = FileQueue::open(&path).unwrap();
+        assert!(queue.is_empty());
+        let dead = queue.dead_letters();
+        assert_eq!(dead.len(), 1);
+        assert_eq!((dead[0].id, dead[0].attempts), (down, 1));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+//! OpenTelemetry spans linking a server and its facilitator in one trace.
+//!
+//! With the `otel` feature, [`FacilitatorClient`] runs each `/verify` and `/settle`
+//! call in a client span and sends its context in the W3C `traceparent` header, and
+//! the facilitator's `handle_verify` and `handle_settle` each run in a server span,
+//! covering their chain calls. A facilitator serving HTTP runs its handlers under
+//! [`with_trace_context`] so their spans join the calling server's trace.
+//!
+//! Spans come from the global tracer provider under [`TRACER_NAME`], so nothing is
+//! recorded until the application installs one.
+//!
+//! [`FacilitatorClient`]: crate::facilitator_client::FacilitatorClient
+
+use crate::errors::Result;
+use opentelemetry::context::FutureExt;
+use opentelemetry::trace::{
+    SpanContext, SpanId, SpanKind, Status, TraceContextExt, TraceFlags, TraceId, TraceState,
+    Tracer,
+};
+use opentelemetry::{global, Context};
+use reqwest::header::HeaderMap;
+use std::future::Future;
+
+/// W3C Trace Context header carrying the caller's span.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Instrumentation scope of the spans this crate creates.
+pub const TRACER_NAME: &str = "x402-rs";
+
+/// Formats the span of `cx` as a `traceparent` header value, if it has a valid one.
+///
+/// # Examples
+///
+/// ```
+/// use opentelemetry::Context;
+/// use x402_rs::telemetry::traceparent;
+///
+/// // No span is active
+/// assert_eq!(traceparent(&Context::new()), None);
+/// ```
+pub fn traceparent(cx: &Context) -> Option<String> {
+    let span = cx.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return None;
+    }
+    Some(format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags()
+    ))
+}
+
+/// Parses a `traceparent` header value into the remote span it names.
+///
+/// Returns `None` for malformed values and all-zero ids.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::telemetry::parse_traceparent;
+///
+/// let parent =
+///     parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+/// assert!(parent.is_sampled());
+/// assert!(parse_traceparent("00-not-a-span-01").is_none());
+/// ```
+pub fn parse_traceparent(value: &str) -> Option<SpanContext> {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    let [version, trace_id, span_id, flags] = parts[..] else {
+        return None;
+    };
+    let is_hex = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_hexdigit());
+    if !is_hex(version, 2) || version == "ff" || !is_hex(trace_id, 32) || !is_hex(span_id, 16) {
+        return None;
+    }
+    if !is_hex(flags, 2) {
+        return None;
+    }
+
+    let span_context = SpanContext::new(
+        TraceId::from_hex(trace_id).ok()?,
+        SpanId::from_hex(span_id).ok()?,
+        TraceFlags::new(u8::from_str_radix(flags, 16).ok()?),
+        true,
+        TraceState::default(),
+    );
+    span_context.is_valid().then_some(span_context)
+}
+
+/// Runs `future` as a child of the span named in `headers`' `traceparent`, if any.
+///
+/// Facilitators serving HTTP wrap their handlers with this, so the spans of
+/// `handle_verify` and `handle_settle` continue the calling server's trace.
+pub async fn with_trace_context<F: Future>(headers: &HeaderMap, future: F) -> F::Output {
+    let parent = headers
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_traceparent);
+    let cx = match parent {
+        Some(parent) => Context::current().with_remote_span_context(parent),
+        None => Context::current(),
+    };
+    future.with_context(cx).await
+}
+
+/// Runs `future` in a new span of the current trace, marking it failed on error.
+pub(crate) async fn in_span<T>(
+    name: &'static str,
+    kind: SpanKind,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let tracer = global::tracer(TRACER_NAME);
+    let span = tracer
+        .span_builder(name)
+        .with_kind(kind)
+        .start_with_context(&tracer, &Context::current());
+    let cx = Context::current_with_span(span);
+
+    let result = future.with_context(cx.clone()).await;
+    if let Err(e) = &result {
+        cx.span().set_status(Status::error(e.to_string()));
+    }
+    cx.span().end();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_round_trip() {
+        let value = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let parent = parse_traceparent(value).unwrap();
+        assert!(parent.is_remote());
+
+        let cx = Context::new().with_remote_span_context(parent);
+        assert_eq!(traceparent(&cx).as_deref(), Some(value));
+
+        for malformed in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902bz-01",
+        ] {
+            assert!(parse_traceparent(malformed).is_none(), "{}", malformed);
+        }
+    }
+}
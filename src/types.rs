@@ -3,12 +3,101 @@
 //! This module contains all the data structures used in the x402 protocol,
 //! including payment requirements, payloads, verification, and settlement types.
 
+use crate::errors::X402Error;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 /// Version of the x402 protocol.
 pub const X402_VERSION: u32 = 1;
 
+/// Protocol versions this implementation can process.
+pub const SUPPORTED_X402_VERSIONS: &[u32] = &[X402_VERSION];
+
+/// Basis points the shares of a split payment add up to.
+pub const SPLIT_TOTAL_BPS: u16 = 10_000;
+
+/// Request header carrying the Base64 encoded `PaymentPayload`.
+pub const X_PAYMENT_HEADER: &str = "X-PAYMENT";
+
+/// Response header carrying the Base64 encoded `PaymentResponse` after settlement.
+pub const X_PAYMENT_RESPONSE_HEADER: &str = "X-PAYMENT-RESPONSE";
+
+/// Request header advertising the schemes/networks a client can pay with.
+pub const ACCEPT_PAYMENT_HEADER: &str = "Accept-Payment";
+
+/// Correlation id header sent on server-to-facilitator calls.
+pub const X_REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Request header presenting an access token obtained from a previous payment.
+pub const X_PAYMENT_TOKEN_HEADER: &str = "X-PAYMENT-TOKEN";
+
+/// Request header naming the [`PayloadEncoding`] of the X-PAYMENT header when it isn't
+/// JSON.
+pub const X_PAYMENT_ENCODING_HEADER: &str = "X-PAYMENT-ENCODING";
+
+/// Header naming the API key a server signed a facilitator call with.
+pub const X_FACILITATOR_KEY_ID_HEADER: &str = "X-Facilitator-Key-Id";
+
+/// Header carrying the Unix time at which a facilitator call was signed.
+pub const X_FACILITATOR_TIMESTAMP_HEADER: &str = "X-Facilitator-Timestamp";
+
+/// Header carrying the hex HMAC-SHA256 signature of a facilitator call.
+pub const X_FACILITATOR_SIGNATURE_HEADER: &str = "X-Facilitator-Signature";
+
+/// Start of the reason given for an authorization that expired before it could be
+/// verified, in a facilitator's `invalidReason` and a server's 402 `error`.
+pub const AUTHORIZATION_EXPIRED_REASON: &str = "Authorization expired";
+
+/// Serialization of the `PaymentPayload` inside the Base64 X-PAYMENT header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadEncoding {
+    /// JSON, the protocol default
+    #[default]
+    Json,
+    /// CBOR (RFC 8949), a more compact binary encoding
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl PayloadEncoding {
+    /// Returns the name sent in the `X-PAYMENT-ENCODING` header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadEncoding::Json => "json",
+            #[cfg(feature = "cbor")]
+            PayloadEncoding::Cbor => "cbor",
+        }
+    }
+
+    /// Parses an `X-PAYMENT-ENCODING` header value, treating a missing header as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::InvalidPayload` for encodings this build doesn't support.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::types::PayloadEncoding;
+    ///
+    /// assert_eq!(PayloadEncoding::from_header(None).unwrap(), PayloadEncoding::Json);
+    /// assert_eq!(PayloadEncoding::from_header(Some("JSON")).unwrap(), PayloadEncoding::Json);
+    /// assert!(PayloadEncoding::from_header(Some("xml")).is_err());
+    /// ```
+    pub fn from_header(value: Option<&str>) -> Result<Self, X402Error> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("json") => Ok(PayloadEncoding::Json),
+            #[cfg(feature = "cbor")]
+            Some("cbor") => Ok(PayloadEncoding::Cbor),
+            Some(other) => Err(X402Error::InvalidPayload(format!(
+                "Unsupported payload encoding: {}",
+                other
+            ))),
+        }
+    }
+}
+
 /// Response returned by a server when payment is required (HTTP 402).
 ///
 /// Contains the list of accepted payment requirements that the client can choose from.
@@ -38,6 +127,125 @@ pub struct PaymentRequiredResponse {
     pub error: Option<String>,
 }
 
+impl PaymentRequiredResponse {
+    /// Attaches the reason a payment was refused, such as a failed verification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::types::PaymentRequiredResponse;
+    ///
+    /// let response = PaymentRequiredResponse {
+    ///     x402_version: 1,
+    ///     accepts: vec![],
+    ///     error: None,
+    /// }
+    /// .with_error("Authorization expired at 1735689600");
+    /// assert!(response.is_authorization_expired());
+    /// ```
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+
+    /// Returns `true` if the payment was refused because its authorization expired, so
+    /// paying again with a freshly signed one may succeed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::types::PaymentRequiredResponse;
+    ///
+    /// let response = PaymentRequiredResponse {
+    ///     x402_version: 1,
+    ///     accepts: vec![],
+    ///     error: Some("Authorization expired at 1735689600".to_string()),
+    /// };
+    /// assert!(response.is_authorization_expired());
+    /// ```
+    pub fn is_authorization_expired(&self) -> bool {
+        self.error
+            .as_deref()
+            .is_some_and(|error| error.contains(AUTHORIZATION_EXPIRED_REASON))
+    }
+
+    /// Describes the response for humans, one line per accepted payment option.
+    ///
+    /// Each line names the scheme, network, amount, asset and payee. Amounts are shown
+    /// in token units, and as a decimal too when `extra.decimals` is advertised.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::server::create_simple_config;
+    /// use x402_rs::types::PaymentRequiredResponse;
+    ///
+    /// let requirements = create_simple_config(
+    ///     "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+    ///     0.01,
+    ///     "Weather API access",
+    ///     "https://facilitator.example.com",
+    /// )
+    /// .to_requirements("/weather")
+    /// .unwrap();
+    /// let response = PaymentRequiredResponse {
+    ///     x402_version: 1,
+    ///     accepts: vec![requirements],
+    ///     error: None,
+    /// };
+    ///
+    /// println!("{}", response.summarize());
+    /// ```
+    #[cfg(feature = "evm")]
+    pub fn summarize(&self) -> String {
+        let mut summary = format!(
+            "Payment required (x402 v{}): {} option(s)",
+            self.x402_version,
+            self.accepts.len()
+        );
+        if let Some(error) = &self.error {
+            summary.push_str(&format!(" - {}", error));
+        }
+
+        for (i, requirements) in self.accepts.iter().enumerate() {
+            let extra = |key: &str| requirements.extra.as_ref().and_then(|extra| extra.get(key));
+            let decimals = extra("decimals")
+                .and_then(Value::as_u64)
+                .and_then(|decimals| u8::try_from(decimals).ok());
+            let format_amount = |units: &str| {
+                match decimals.and_then(|d| crate::utils::format_token_amount(units, d).ok()) {
+                    Some(formatted) => format!("{} ({} units)", formatted, units),
+                    None => format!("{} units", units),
+                }
+            };
+            let amount = match &requirements.min_amount_required {
+                Some(min) => format!(
+                    "{} to {}",
+                    format_amount(min),
+                    format_amount(&requirements.max_amount_required)
+                ),
+                None => format_amount(&requirements.max_amount_required),
+            };
+            let asset = match extra("name").and_then(Value::as_str) {
+                Some(name) => format!("{} ({})", name, requirements.asset),
+                None => requirements.asset.clone(),
+            };
+
+            summary.push_str(&format!(
+                "\n{}. {} on network {}: {} of {} to {} for {}",
+                i + 1,
+                requirements.scheme,
+                requirements.network,
+                amount,
+                asset,
+                requirements.pay_to,
+                requirements.resource
+            ));
+        }
+        summary
+    }
+}
+
 /// Describes the payment requirements for a specific resource.
 ///
 /// Each requirement specifies the payment scheme, network, amount, recipient address,
@@ -54,6 +262,11 @@ pub struct PaymentRequirements {
     /// Represented as a string to handle uint256
     #[serde(rename = "maxAmountRequired")]
     pub max_amount_required: String,
+
+    /// Minimum acceptable amount in the smallest unit, for range-priced payments.
+    /// When absent the minimum equals `max_amount_required`.
+    #[serde(rename = "minAmountRequired", default, skip_serializing_if = "Option::is_none")]
+    pub min_amount_required: Option<String>,
     
     /// The resource URL or identifier
     pub resource: String,
@@ -86,6 +299,92 @@ pub struct PaymentRequirements {
     pub extra: Option<Value>,
 }
 
+impl PaymentRequirements {
+    /// Parses `max_amount_required`, which non-standard servers may send as a range.
+    ///
+    /// # Errors
+    ///
+    /// See [`RequiredAmount::parse`].
+    #[cfg(feature = "evm")]
+    pub fn required_amount(&self) -> Result<RequiredAmount, X402Error> {
+        RequiredAmount::parse(&self.max_amount_required)
+    }
+}
+
+/// Amount demanded by a requirement's `maxAmountRequired`.
+#[cfg(feature = "evm")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequiredAmount {
+    /// A single amount, as the protocol specifies
+    Exact(ethers::types::U256),
+    /// A `"min-max"` range, as some non-standard servers send
+    Range {
+        /// Smallest acceptable amount
+        min: ethers::types::U256,
+        /// Largest acceptable amount
+        max: ethers::types::U256,
+    },
+}
+
+#[cfg(feature = "evm")]
+impl RequiredAmount {
+    /// Parses an amount, or a range of two decimal amounts such as `"10000-20000"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::InvalidAmount` if either bound isn't an amount, the range has
+    /// more than two bounds, or its minimum exceeds its maximum.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ethers::types::U256;
+    /// use x402_rs::types::RequiredAmount;
+    ///
+    /// assert_eq!(
+    ///     RequiredAmount::parse("10000").unwrap(),
+    ///     RequiredAmount::Exact(U256::from(10_000))
+    /// );
+    /// assert_eq!(
+    ///     RequiredAmount::parse("10000-20000").unwrap(),
+    ///     RequiredAmount::Range { min: U256::from(10_000), max: U256::from(20_000) }
+    /// );
+    /// assert!(RequiredAmount::parse("20000-10000").is_err());
+    /// ```
+    pub fn parse(s: &str) -> Result<Self, X402Error> {
+        let Some((min, max)) = s.split_once('-') else {
+            return Ok(Self::Exact(crate::utils::string_to_u256(s.trim())?));
+        };
+
+        let bound = |value: &str| {
+            let value = value.trim();
+            match value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                true => Err(X402Error::InvalidAmount(format!(
+                    "Malformed amount range {:?}: bounds must be decimal amounts",
+                    s
+                ))),
+                false => crate::utils::string_to_u256(value),
+            }
+        };
+        let (min, max) = (bound(min)?, bound(max)?);
+        if min > max {
+            return Err(X402Error::InvalidAmount(format!(
+                "Malformed amount range {:?}: minimum exceeds maximum",
+                s
+            )));
+        }
+        Ok(Self::Range { min, max })
+    }
+
+    /// Returns the smallest acceptable amount.
+    pub fn min(&self) -> ethers::types::U256 {
+        match *self {
+            Self::Exact(amount) => amount,
+            Self::Range { min, .. } => min,
+        }
+    }
+}
+
 /// Payment payload sent by the client in the X-PAYMENT header.
 ///
 /// This contains the scheme-specific payment data, encoded as Base64 JSON.
@@ -101,6 +400,8 @@ pub struct PaymentRequirements {
 ///     scheme: "exact".to_string(),
 ///     network: "8453".to_string(),
 ///     payload: json!({"from": "0x...", "to": "0x..."}),
+///     challenge: None,
+///     message_signature: None,
 /// };
 /// ```
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -117,6 +418,112 @@ pub struct PaymentPayload {
     
     /// Scheme-specific payload data
     pub payload: Value,
+
+    /// Server-issued challenge echoed from `PaymentRequirements.extra.challenge`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+
+    /// Payer's EIP-191 signature over [`PaymentPayload::signing_message`], binding the
+    /// scheme payload to this x402 message
+    #[serde(rename = "messageSignature", default, skip_serializing_if = "Option::is_none")]
+    pub message_signature: Option<String>,
+}
+
+/// Typed form of the scheme-specific `payload` of a [`PaymentPayload`].
+///
+/// Schemes declare the shape they expect, e.g. `TransferAuthorization` for `"exact"`,
+/// and extract it with [`PaymentPayload::typed_payload`] instead of re-parsing the raw
+/// JSON value.
+pub trait SchemePayload: Serialize + DeserializeOwned {
+    /// Name of the scheme this payload belongs to.
+    const SCHEME: &'static str;
+
+    /// Parses the raw payload value, without checking the scheme.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::InvalidPayload` naming the scheme if the value has the wrong
+    /// shape.
+    fn from_value(value: &Value) -> Result<Self, X402Error> {
+        serde_json::from_value(value.clone()).map_err(|e| {
+            X402Error::InvalidPayload(format!("Invalid {} payload: {}", Self::SCHEME, e))
+        })
+    }
+}
+
+impl PaymentPayload {
+    /// Extracts the typed scheme payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::UnsupportedScheme` if the payload is for a different scheme
+    /// than `P`, or `X402Error::InvalidPayload` if it doesn't have `P`'s shape.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::types::{PaymentPayload, TransferAuthorization};
+    /// use serde_json::json;
+    ///
+    /// let payload = PaymentPayload {
+    ///     x402_version: 1,
+    ///     scheme: "exact".to_string(),
+    ///     network: "8453".to_string(),
+    ///     payload: json!({ "unexpected": true }),
+    ///     challenge: None,
+    ///     message_signature: None,
+    /// };
+    ///
+    /// assert!(payload.typed_payload::<TransferAuthorization>().is_err());
+    /// ```
+    pub fn typed_payload<P: SchemePayload>(&self) -> Result<P, X402Error> {
+        if self.scheme != P::SCHEME {
+            return Err(X402Error::UnsupportedScheme(format!(
+                "expected {} payload, got {}",
+                P::SCHEME,
+                self.scheme
+            )));
+        }
+        P::from_value(&self.payload)
+    }
+
+    /// Returns the bytes covered by `message_signature`.
+    ///
+    /// This is the payload's JSON with sorted keys, leaving out `messageSignature` and
+    /// the scheme payload's own `signature`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::types::PaymentPayload;
+    /// use serde_json::json;
+    ///
+    /// let mut payload = PaymentPayload {
+    ///     x402_version: 1,
+    ///     scheme: "exact".to_string(),
+    ///     network: "8453".to_string(),
+    ///     payload: json!({ "value": "10000", "signature": "0x01" }),
+    ///     challenge: None,
+    ///     message_signature: None,
+    /// };
+    /// let message = payload.signing_message().unwrap();
+    ///
+    /// payload.message_signature = Some("0x02".to_string());
+    /// assert_eq!(payload.signing_message().unwrap(), message);
+    ///
+    /// payload.network = "84532".to_string();
+    /// assert_ne!(payload.signing_message().unwrap(), message);
+    /// ```
+    pub fn signing_message(&self) -> Result<Vec<u8>, X402Error> {
+        let mut message = serde_json::to_value(self)?;
+        if let Some(fields) = message.as_object_mut() {
+            fields.remove("messageSignature");
+        }
+        if let Some(fields) = message["payload"].as_object_mut() {
+            fields.remove("signature");
+        }
+        Ok(serde_json::to_vec(&message)?)
+    }
 }
 
 /// EIP-3009 transferWithAuthorization parameters for the "exact" scheme on EVM.
@@ -148,6 +555,289 @@ pub struct TransferAuthorization {
     pub signature: String,
 }
 
+impl SchemePayload for TransferAuthorization {
+    const SCHEME: &'static str = "exact";
+}
+
+#[cfg(feature = "evm")]
+impl TransferAuthorization {
+    /// Returns a builder taking typed values.
+    pub fn builder() -> TransferAuthorizationBuilder {
+        TransferAuthorizationBuilder::default()
+    }
+
+    /// Checks every field is well-formed, without checking the signature itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::InvalidAddress` for a malformed `from` or `to`,
+    /// `X402Error::InvalidAmount` for a malformed `value`, `X402Error::InvalidPayload`
+    /// for malformed timestamps, an empty validity window or a nonce that isn't 32
+    /// bytes, and `X402Error::SignatureError` for a signature that isn't 65 bytes or
+    /// 64-byte compact.
+    pub fn validate(&self) -> Result<(), X402Error> {
+        crate::utils::parse_address(&self.from)?;
+        crate::utils::parse_address(&self.to)?;
+        crate::utils::string_to_u256(&self.value)?;
+
+        let timestamp = |name: &str, value: &str| {
+            crate::utils::string_to_u256(value)
+                .map_err(|_| X402Error::InvalidPayload(format!("Invalid {}: {}", name, value)))
+        };
+        let valid_after = timestamp("validAfter", &self.valid_after)?;
+        let valid_before = timestamp("validBefore", &self.valid_before)?;
+        if valid_after >= valid_before {
+            return Err(X402Error::InvalidPayload(format!(
+                "validAfter {} is not before validBefore {}",
+                valid_after, valid_before
+            )));
+        }
+
+        if !is_hex_of_len(&self.nonce, 64) {
+            return Err(X402Error::InvalidPayload(format!(
+                "Nonce must be 32 bytes of 0x-prefixed hex: {}",
+                self.nonce
+            )));
+        }
+        if !is_hex_of_len(&self.signature, 130) && !is_hex_of_len(&self.signature, 128) {
+            return Err(X402Error::SignatureError(format!(
+                "Expected 65-byte or 64-byte compact signature: {}",
+                self.signature
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether `s` is `0x` followed by exactly `len` hex digits.
+#[cfg(feature = "evm")]
+fn is_hex_of_len(s: &str, len: usize) -> bool {
+    s.strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == len && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Builder for [`TransferAuthorization`] from typed values.
+///
+/// Fields are rendered the way payers send them: addresses and the nonce as
+/// `0x`-prefixed hex, amounts and timestamps in decimal, and the signature as 65 bytes
+/// `r || s || v`. `valid_after` defaults to 0.
+///
+/// # Examples
+///
+/// ```
+/// use ethers::types::{Address, Signature, H256, U256};
+/// use x402_rs::types::TransferAuthorization;
+///
+/// let auth = TransferAuthorization::builder()
+///     .from(Address::repeat_byte(0x11))
+///     .to(Address::repeat_byte(0x22))
+///     .value(U256::from(10_000))
+///     .valid_before(U256::from(1_735_689_600u64))
+///     .nonce(H256::repeat_byte(0x01))
+///     .signature(Signature { r: U256::one(), s: U256::one(), v: 27 })
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(auth.value, "10000");
+/// assert_eq!(auth.nonce, format!("0x{}", "01".repeat(32)));
+/// ```
+#[cfg(feature = "evm")]
+#[derive(Debug, Clone, Default)]
+pub struct TransferAuthorizationBuilder {
+    from: Option<ethers::types::Address>,
+    to: Option<ethers::types::Address>,
+    value: Option<ethers::types::U256>,
+    valid_after: ethers::types::U256,
+    valid_before: Option<ethers::types::U256>,
+    nonce: Option<ethers::types::H256>,
+    signature: Option<ethers::types::Signature>,
+}
+
+#[cfg(feature = "evm")]
+impl TransferAuthorizationBuilder {
+    /// Sets the payer.
+    pub fn from(mut self, from: ethers::types::Address) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Sets the payee.
+    pub fn to(mut self, to: ethers::types::Address) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Sets the amount in the token's smallest unit.
+    pub fn value(mut self, value: ethers::types::U256) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// Sets the Unix time after which the authorization is valid.
+    pub fn valid_after(mut self, valid_after: ethers::types::U256) -> Self {
+        self.valid_after = valid_after;
+        self
+    }
+
+    /// Sets the Unix time before which the authorization is valid.
+    pub fn valid_before(mut self, valid_before: ethers::types::U256) -> Self {
+        self.valid_before = Some(valid_before);
+        self
+    }
+
+    /// Sets the authorization nonce.
+    pub fn nonce(mut self, nonce: ethers::types::H256) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Sets the payer's EIP-712 signature.
+    pub fn signature(mut self, signature: ethers::types::Signature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+
+    /// Builds and validates the authorization.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X402Error::MissingField` if a field other than `valid_after` was not
+    /// set, or any error of [`TransferAuthorization::validate`].
+    pub fn build(self) -> Result<TransferAuthorization, X402Error> {
+        let missing = |field: &str| X402Error::MissingField(field.to_string());
+        let auth = TransferAuthorization {
+            from: format!("{:?}", self.from.ok_or_else(|| missing("from"))?),
+            to: format!("{:?}", self.to.ok_or_else(|| missing("to"))?),
+            value: self.value.ok_or_else(|| missing("value"))?.to_string(),
+            valid_after: self.valid_after.to_string(),
+            valid_before: self
+                .valid_before
+                .ok_or_else(|| missing("validBefore"))?
+                .to_string(),
+            nonce: format!("{:?}", self.nonce.ok_or_else(|| missing("nonce"))?),
+            signature: format!(
+                "0x{}",
+                hex::encode(self.signature.ok_or_else(|| missing("signature"))?.to_vec())
+            ),
+        };
+        auth.validate()?;
+        Ok(auth)
+    }
+}
+
+/// Transfer paid from an ERC-20 allowance, for the "exact" scheme on tokens without
+/// EIP-3009.
+///
+/// The payer has `approve`d the facilitator, which settles with
+/// `transferFrom(from, to, value)`. Used when the requirements set
+/// `extra.assetTransferMethod` to `"allowance"`. The signature also covers the asset,
+/// spender and chain, so it can't be redeemed against another approved token.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AllowanceTransfer {
+    /// Address of the payer (token holder)
+    pub from: String,
+
+    /// Address of the payee
+    pub to: String,
+
+    /// Amount to transfer (uint256 as string)
+    pub value: String,
+
+    /// Token contract the transfer is drawn from
+    pub asset: String,
+
+    /// Account the payer approved, which settles the transfer
+    pub spender: String,
+
+    /// Chain the transfer is settled on
+    #[serde(rename = "chainId")]
+    pub chain_id: u64,
+
+    /// Timestamp before which the transfer may be settled
+    #[serde(rename = "validBefore")]
+    pub valid_before: String,
+
+    /// Unique nonce for replay protection (32 bytes as hex string)
+    pub nonce: String,
+
+    /// Payer's EIP-191 signature over the payload's
+    /// [`signing_message`](PaymentPayload::signing_message)
+    pub signature: String,
+}
+
+impl SchemePayload for AllowanceTransfer {
+    const SCHEME: &'static str = "exact";
+}
+
+/// One recipient of a split payment, listed in the requirements' `extra.splits`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SplitRecipient {
+    /// Address receiving this share
+    #[serde(rename = "payTo")]
+    pub pay_to: String,
+
+    /// Share of the amount in basis points; the shares of a split add up to 10000
+    pub bps: u16,
+}
+
+/// Payment split across several recipients, for the "exact" scheme.
+///
+/// Holds one EIP-3009 authorization per recipient of `extra.splits`, in the same
+/// order, all signed by the same payer.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SplitTransfer {
+    /// Authorization paying each recipient its share
+    pub authorizations: Vec<TransferAuthorization>,
+}
+
+impl SchemePayload for SplitTransfer {
+    const SCHEME: &'static str = "exact";
+}
+
+/// EIP-2612 permit capping a payment, for the "upto" scheme on EVM.
+///
+/// The payer lets `spender`, the facilitator's settlement account, move up to `value`
+/// of the asset. The facilitator submits the permit and then transfers the amount the
+/// server actually charges, which may be less.
+///
+/// The permit alone doesn't name a recipient, so the payer also signs `payTo` and
+/// `resource` in `paymentSignature`; the charge can only be paid to that recipient.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UptoAuthorization {
+    /// Address of the payer (token holder)
+    pub owner: String,
+
+    /// Account allowed to move the payer's tokens
+    pub spender: String,
+
+    /// Most the spender may transfer (uint256 as string)
+    pub value: String,
+
+    /// The payer's EIP-2612 permit nonce on the token (uint256 as string)
+    pub nonce: String,
+
+    /// Timestamp after which the permit can no longer be used
+    pub deadline: String,
+
+    /// Payer's EIP-712 signature over the permit
+    pub signature: String,
+
+    /// Recipient the payer agreed to pay
+    #[serde(rename = "payTo")]
+    pub pay_to: String,
+
+    /// Resource the payer agreed to pay for
+    pub resource: String,
+
+    /// Payer's EIP-712 signature binding the permit to `payTo`, the asset and `resource`
+    #[serde(rename = "paymentSignature")]
+    pub payment_signature: String,
+}
+
+impl SchemePayload for UptoAuthorization {
+    const SCHEME: &'static str = "upto";
+}
+
 /// Request to verify a payment without settling it on-chain.
 ///
 /// Sent from the server to a facilitator's `/verify` endpoint.
@@ -160,6 +850,15 @@ pub struct VerificationRequest {
     /// The payment requirements that the server expects
     #[serde(rename = "paymentRequirements")]
     pub payment_requirements: PaymentRequirements,
+
+    /// Amount the server means to charge, for schemes where the payer authorizes a
+    /// maximum (e.g. "upto"); checked against the authorization if present
+    #[serde(
+        rename = "actualAmount",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub actual_amount: Option<String>,
 }
 
 /// Response from the facilitator's `/verify` endpoint.
@@ -186,6 +885,15 @@ pub struct SettlementRequest {
     /// The payment requirements
     #[serde(rename = "paymentRequirements")]
     pub payment_requirements: PaymentRequirements,
+
+    /// Amount to charge, for schemes where the payer authorizes a maximum (e.g.
+    /// "upto"); the full authorized amount if absent
+    #[serde(
+        rename = "actualAmount",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub actual_amount: Option<String>,
 }
 
 /// Response from the facilitator's `/settle` endpoint.
@@ -199,9 +907,46 @@ pub struct SettlementResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub block_number: Option<u64>,
     
-    /// Optional error message if settlement failed
+    /// Optional error message if settlement failed, on-chain or as `invalid_reason`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+
+    /// Why the payment was rejected before settling, as `/verify` would report it;
+    /// `error` carries the same message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_reason: Option<String>,
+
+    /// Details of the settled payment (absent if settlement failed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement: Option<SettlementInfo>,
+}
+
+/// Details of a settled payment, shared by the facilitator, server and client.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SettlementInfo {
+    /// Address that paid
+    pub payer: String,
+
+    /// Address that received the payment
+    #[serde(rename = "payTo")]
+    pub pay_to: String,
+
+    /// Amount transferred, in the token's smallest unit
+    pub amount: String,
+
+    /// Token contract address
+    pub asset: String,
+
+    /// Network identifier
+    pub network: String,
+
+    /// Transaction hash of the settlement
+    #[serde(rename = "txHash")]
+    pub tx_hash: String,
+
+    /// Block number where the transaction was included
+    #[serde(rename = "blockNumber", skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
 }
 
 /// Information returned in the X-PAYMENT-RESPONSE header.
@@ -220,6 +965,18 @@ pub struct PaymentResponse {
     /// Additional metadata
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<Value>,
+
+    /// Details of the settled payment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement: Option<SettlementInfo>,
+
+    /// Token granting further access to the resource without paying again
+    #[serde(
+        rename = "accessToken",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub access_token: Option<String>,
 }
 
 /// Represents a supported payment kind (scheme + network combination).
@@ -236,6 +993,99 @@ pub struct SupportedKind {
     /// Optional list of supported assets on this network
     #[serde(skip_serializing_if = "Option::is_none")]
     pub assets: Option<Vec<String>>,
+
+    /// Whether the facilitator only verifies payments of this kind, leaving settlement
+    /// to the server
+    #[serde(rename = "verifyOnly", default, skip_serializing_if = "std::ops::Not::not")]
+    pub verify_only: bool,
+}
+
+impl SupportedKind {
+    /// Returns `true` if this kind can settle the given requirement.
+    ///
+    /// Scheme and network must match; if `assets` is listed, the requirement's asset
+    /// must be among them (compared case-insensitively).
+    pub fn covers(&self, requirements: &PaymentRequirements) -> bool {
+        self.scheme == requirements.scheme
+            && self.network == requirements.network
+            && self.assets.as_ref().map_or(true, |assets| {
+                assets.iter().any(|a| a.eq_ignore_ascii_case(&requirements.asset))
+            })
+    }
+
+    /// Returns `true` if both kinds accept the same payments: same scheme, network and
+    /// settlement support, and the same assets regardless of order and case.
+    pub fn same_as(&self, other: &SupportedKind) -> bool {
+        let assets = |kind: &SupportedKind| {
+            kind.assets.as_ref().map(|assets| {
+                let mut assets: Vec<_> = assets.iter().map(|a| a.to_lowercase()).collect();
+                assets.sort();
+                assets.dedup();
+                assets
+            })
+        };
+        self.scheme == other.scheme
+            && self.network == other.network
+            && self.verify_only == other.verify_only
+            && assets(self) == assets(other)
+    }
+}
+
+impl<S: Into<String>> From<(S, S)> for SupportedKind {
+    /// Builds a kind from `(scheme, network)`, accepting any asset.
+    fn from((scheme, network): (S, S)) -> Self {
+        Self {
+            scheme: scheme.into(),
+            network: network.into(),
+            assets: None,
+            verify_only: false,
+        }
+    }
+}
+
+impl<S: Into<String>> From<(S, S, S)> for SupportedKind {
+    /// Builds a kind from `(scheme, network, asset)`, limited to that asset.
+    fn from((scheme, network, asset): (S, S, S)) -> Self {
+        Self {
+            scheme: scheme.into(),
+            network: network.into(),
+            assets: Some(vec![asset.into()]),
+            verify_only: false,
+        }
+    }
+}
+
+impl TryFrom<&str> for SupportedKind {
+    type Error = X402Error;
+
+    /// Parses `scheme/network` or `scheme/network/asset`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::types::SupportedKind;
+    ///
+    /// let kind = SupportedKind::try_from("exact/8453").unwrap();
+    /// assert_eq!((kind.scheme.as_str(), kind.network.as_str()), ("exact", "8453"));
+    /// assert!(SupportedKind::try_from("exact").is_err());
+    /// ```
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let parts: Vec<&str> = value.split('/').map(str::trim).collect();
+        match parts.as_slice() {
+            [scheme, network] if !scheme.is_empty() && !network.is_empty() => {
+                Ok((*scheme, *network).into())
+            }
+            [scheme, network, asset]
+                if !scheme.is_empty() && !network.is_empty() && !asset.is_empty() =>
+            {
+                Ok((*scheme, *network, *asset).into())
+            }
+            _ => Err(X402Error::InvalidPayload(format!(
+                "Expected scheme/network[/asset], got {}",
+                value
+            ))),
+        }
+    }
 }
 
 /// Response from the facilitator's `/supported` endpoint.
@@ -245,11 +1095,219 @@ pub struct SupportedResponse {
     pub supported: Vec<SupportedKind>,
 }
 
+impl SupportedResponse {
+    /// Returns `true` if any supported kind covers the given requirement.
+    pub fn covers(&self, requirements: &PaymentRequirements) -> bool {
+        self.supported.iter().any(|kind| kind.covers(requirements))
+    }
+
+    /// Compares two facilitators' supported kinds, e.g. before failing over between
+    /// them.
+    ///
+    /// Returns the kinds only `self` supports and the kinds only `other` supports, as
+    /// judged by [`SupportedKind::same_as`]. Both are empty when the sets match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use x402_rs::types::{SupportedKind, SupportedResponse};
+    ///
+    /// let primary = SupportedResponse {
+    ///     supported: vec![("exact", "8453").into(), ("exact", "137").into()],
+    /// };
+    /// let backup = SupportedResponse {
+    ///     supported: vec![("exact", "8453").into()],
+    /// };
+    /// let (only_primary, only_backup) = primary.difference(&backup);
+    /// assert_eq!(only_primary.len(), 1);
+    /// assert_eq!(only_primary[0].network, "137");
+    /// assert!(only_backup.is_empty());
+    /// ```
+    pub fn difference(
+        &self,
+        other: &SupportedResponse,
+    ) -> (Vec<SupportedKind>, Vec<SupportedKind>) {
+        let missing_from = |kinds: &[SupportedKind], from: &[SupportedKind]| {
+            kinds
+                .iter()
+                .filter(|kind| !from.iter().any(|other| kind.same_as(other)))
+                .cloned()
+                .collect()
+        };
+        (
+            missing_from(&self.supported, &other.supported),
+            missing_from(&other.supported, &self.supported),
+        )
+    }
+}
+
+/// Response from the facilitator's `/nonces/{payer}` audit endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PayerNoncesResponse {
+    /// Address of the payer
+    pub payer: String,
+
+    /// Nonces consumed by the payer, as hex strings
+    pub nonces: Vec<String>,
+}
+
+/// Overall status reported by the facilitator's `/health` endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// Every signer account can pay for settlements
+    Healthy,
+    /// At least one signer account is below its minimum balance
+    Degraded,
+    /// At least one network's balance could not be queried
+    Unhealthy,
+}
+
+/// Signer account health on one network.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkHealth {
+    /// Network identifier
+    pub network: String,
+
+    /// Address of the account paying gas on this network
+    pub signer: String,
+
+    /// Native balance of the signer in wei, if it could be queried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<String>,
+
+    /// Whether the balance is below the configured minimum
+    pub low_balance: bool,
+
+    /// Why the balance could not be queried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response from the facilitator's `/health` endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HealthResponse {
+    /// Overall status
+    pub status: HealthStatus,
+
+    /// Per-network signer health
+    pub networks: Vec<NetworkHealth>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    #[cfg(feature = "evm")]
+    fn authorization_builder() -> TransferAuthorizationBuilder {
+        use ethers::types::{Address, Signature, H256, U256};
+        TransferAuthorization::builder()
+            .from(Address::repeat_byte(0x11))
+            .to(Address::repeat_byte(0x22))
+            .value(U256::from(10_000u64))
+            .valid_after(U256::from(1_735_689_000u64))
+            .valid_before(U256::from(1_735_689_600u64))
+            .nonce(H256::repeat_byte(0xab))
+            .signature(Signature {
+                r: U256::from(1u64),
+                s: U256::from(2u64),
+                v: 28,
+            })
+    }
+
+    #[cfg(feature = "evm")]
+    #[test]
+    fn test_transfer_authorization_builder() {
+        let auth = authorization_builder().build().unwrap();
+        assert_eq!(auth.from, format!("0x{}", "11".repeat(20)));
+        assert_eq!(auth.to, format!("0x{}", "22".repeat(20)));
+        assert_eq!(auth.value, "10000");
+        assert_eq!(auth.valid_after, "1735689000");
+        assert_eq!(auth.valid_before, "1735689600");
+        assert_eq!(auth.nonce, format!("0x{}", "ab".repeat(32)));
+        assert_eq!(auth.signature, format!("0x{:064x}{:064x}1c", 1, 2));
+        auth.validate().unwrap();
+
+        let err = TransferAuthorization::builder().build().unwrap_err();
+        assert!(matches!(err, X402Error::MissingField(field) if field == "from"));
+    }
+
+    #[cfg(feature = "evm")]
+    #[test]
+    fn test_transfer_authorization_validate_catches_malformed_fields() {
+        let valid = authorization_builder().build().unwrap();
+        let with = |edit: fn(&mut TransferAuthorization)| {
+            let mut auth = valid.clone();
+            edit(&mut auth);
+            auth.validate().unwrap_err()
+        };
+
+        assert!(matches!(
+            with(|a| a.from = "0x1234".to_string()),
+            X402Error::InvalidAddress(_)
+        ));
+        assert!(matches!(
+            with(|a| a.to = "not an address".to_string()),
+            X402Error::InvalidAddress(_)
+        ));
+        assert!(matches!(
+            with(|a| a.value = "ten".to_string()),
+            X402Error::InvalidAmount(_)
+        ));
+        assert!(matches!(
+            with(|a| a.valid_after = "soon".to_string()),
+            X402Error::InvalidPayload(_)
+        ));
+        assert!(matches!(
+            with(|a| a.valid_before = "1735689000".to_string()),
+            X402Error::InvalidPayload(_)
+        ));
+        assert!(matches!(
+            with(|a| a.nonce = "0xabcd".to_string()),
+            X402Error::InvalidPayload(_)
+        ));
+        assert!(matches!(
+            with(|a| a.nonce = format!("0x{}", "zz".repeat(32))),
+            X402Error::InvalidPayload(_)
+        ));
+        assert!(matches!(
+            with(|a| a.signature = format!("0x{}", "00".repeat(64))[..100].to_string()),
+            X402Error::SignatureError(_)
+        ));
+    }
+
+    #[test]
+    fn test_typed_payload() {
+        let mut payload = PaymentPayload {
+            x402_version: 1,
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            payload: json!({
+                "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+                "to": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+                "value": "10000",
+                "validAfter": "0",
+                "validBefore": "1",
+                "nonce": "0x00",
+                "signature": "0x00",
+            }),
+            challenge: None,
+            message_signature: None,
+        };
+        let auth: TransferAuthorization = payload.typed_payload().unwrap();
+        assert_eq!(auth.value, "10000");
+
+        payload.payload = json!({ "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266" });
+        let err = payload.typed_payload::<TransferAuthorization>().unwrap_err();
+        assert!(matches!(&err, X402Error::InvalidPayload(m) if m.starts_with("Invalid exact")));
+
+        payload.scheme = "upto".to_string();
+        let err = payload.typed_payload::<TransferAuthorization>().unwrap_err();
+        assert!(matches!(err, X402Error::UnsupportedScheme(_)));
+    }
+
     #[test]
     fn test_payment_required_response_serialization() {
         let response = PaymentRequiredResponse {
@@ -258,6 +1316,7 @@ mod tests {
                 scheme: "exact".to_string(),
                 network: "8453".to_string(),
                 max_amount_required: "10000".to_string(),
+                min_amount_required: None,
                 resource: "/api/weather".to_string(),
                 description: Some("Weather API access".to_string()),
                 mime_type: Some("application/json".to_string()),
@@ -278,6 +1337,45 @@ mod tests {
         assert_eq!(deserialized.accepts[0].scheme, "exact");
     }
 
+    #[cfg(feature = "evm")]
+    #[test]
+    fn test_payment_required_response_summary() {
+        let requirements = |network: &str, extra: Value| -> PaymentRequirements {
+            serde_json::from_value(json!({
+                "scheme": "exact",
+                "network": network,
+                "maxAmountRequired": "10000",
+                "resource": "/weather",
+                "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+                "maxTimeoutSeconds": 300,
+                "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                "extra": extra
+            }))
+            .unwrap()
+        };
+        let response = PaymentRequiredResponse {
+            x402_version: 1,
+            accepts: vec![
+                requirements("8453", json!({ "name": "USD Coin", "decimals": 6 })),
+                requirements("84532", json!({})),
+            ],
+            error: Some("X-PAYMENT header is required".to_string()),
+        };
+
+        let summary = response.summarize();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("2 option(s)"));
+        assert!(lines[0].contains("X-PAYMENT header is required"));
+        assert_eq!(
+            lines[1],
+            "1. exact on network 8453: 0.01 (10000 units) of USD Coin \
+             (0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913) to \
+             0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb for /weather"
+        );
+        assert!(lines[2].starts_with("2. exact on network 84532: 10000 units of 0x833589fC"));
+    }
+
     #[test]
     fn test_payment_payload_serialization() {
         let payload = PaymentPayload {
@@ -289,6 +1387,8 @@ mod tests {
                 "to": "0x456",
                 "value": "10000"
             }),
+            challenge: None,
+            message_signature: None,
         };
 
         let json = serde_json::to_string(&payload).unwrap();
@@ -298,6 +1398,15 @@ mod tests {
         assert_eq!(deserialized.network, "8453");
     }
 
+    #[test]
+    fn test_header_constants() {
+        assert_eq!(X_PAYMENT_HEADER, "X-PAYMENT");
+        assert_eq!(X_PAYMENT_RESPONSE_HEADER, "X-PAYMENT-RESPONSE");
+        assert_eq!(ACCEPT_PAYMENT_HEADER, "Accept-Payment");
+        assert_eq!(X_REQUEST_ID_HEADER, "X-Request-Id");
+        assert_eq!(X_PAYMENT_TOKEN_HEADER, "X-PAYMENT-TOKEN");
+    }
+
     #[test]
     fn test_transfer_authorization() {
         let auth = TransferAuthorization {
@@ -314,5 +1423,117 @@ mod tests {
         assert!(json.contains("validAfter"));
         assert!(json.contains("validBefore"));
     }
-}
 
+    #[test]
+    fn test_settlement_info_round_trip() {
+        let info = SettlementInfo {
+            payer: "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".to_string(),
+            pay_to: "0x742d35cc6634c0532925a3b844bc9e7595f0bebb".to_string(),
+            amount: "10000".to_string(),
+            asset: "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913".to_string(),
+            network: "8453".to_string(),
+            tx_hash: "0xabc".to_string(),
+            block_number: Some(42),
+        };
+        let response = SettlementResponse {
+            tx_hash: info.tx_hash.clone(),
+            block_number: info.block_number,
+            error: None,
+            invalid_reason: None,
+            settlement: Some(info.clone()),
+        };
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["settlement"]["payTo"], info.pay_to);
+        assert_eq!(json["settlement"]["blockNumber"], 42);
+
+        let deserialized: SettlementResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.settlement, Some(info));
+    }
+
+    #[test]
+    fn test_supported_response_covers() {
+        let requirements: PaymentRequiredResponse = serde_json::from_value(json!({
+            "x402Version": 1,
+            "accepts": [{
+                "scheme": "exact",
+                "network": "8453",
+                "maxAmountRequired": "10000",
+                "resource": "/test",
+                "payTo": "0x742d35cc6634c0532925a3b844bc9e7595f0bebb",
+                "maxTimeoutSeconds": 300,
+                "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+            }]
+        }))
+        .unwrap();
+        let requirement = &requirements.accepts[0];
+
+        let usdc_only = SupportedResponse {
+            supported: vec![
+                ("exact", "8453", "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913").into(),
+            ],
+        };
+        assert!(usdc_only.covers(requirement));
+
+        let other_network = SupportedResponse {
+            supported: vec![SupportedKind::try_from("exact/137").unwrap()],
+        };
+        assert!(!other_network.covers(requirement));
+
+        let other_asset = SupportedResponse {
+            supported: vec![("exact", "8453", "0xdeadbeef").into()],
+        };
+        assert!(!other_asset.covers(requirement));
+    }
+
+    #[test]
+    fn test_supported_response_difference() {
+        let primary: SupportedResponse = serde_json::from_value(json!({
+            "supported": [
+                { "scheme": "exact", "network": "8453",
+                  "assets": ["0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913", "0xdeadbeef"] },
+                { "scheme": "exact", "network": "137" },
+                { "scheme": "exact", "network": "1" }
+            ]
+        }))
+        .unwrap();
+        let backup: SupportedResponse = serde_json::from_value(json!({
+            "supported": [
+                { "scheme": "exact", "network": "1" },
+                { "scheme": "exact", "network": "8453",
+                  "assets": ["0xDEADBEEF", "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913"] },
+                { "scheme": "exact", "network": "137", "verifyOnly": true },
+                { "scheme": "exact", "network": "84532" }
+            ]
+        }))
+        .unwrap();
+
+        let (only_primary, only_backup) = primary.difference(&backup);
+        let kinds = |kinds: &[SupportedKind]| {
+            kinds
+                .iter()
+                .map(|k| (k.network.clone(), k.verify_only))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(&only_primary), vec![("137".to_string(), false)]);
+        assert_eq!(
+            kinds(&only_backup),
+            vec![("137".to_string(), true), ("84532".to_string(), false)]
+        );
+        let (only_primary, only_backup) = primary.difference(&primary);
+        assert!(only_primary.is_empty() && only_backup.is_empty());
+
+        let requirement: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10000",
+            "resource": "/test",
+            "payTo": "0x742d35cc6634c0532925a3b844bc9e7595f0bebb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }))
+        .unwrap();
+        assert!(primary.covers(&requirement));
+        assert!(backup.covers(&requirement));
+    }
+}
@@ -4,11 +4,23 @@
 //! and other common operations used throughout the library.
 
 use crate::errors::{Result, X402Error};
-use crate::types::PaymentPayload;
+use crate::types::{
+    PayloadEncoding, PaymentPayload, PaymentRequirements, PaymentResponse, SplitRecipient,
+    SPLIT_TOTAL_BPS, SUPPORTED_X402_VERSIONS,
+};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use ethers::types::{Address, U256};
+#[cfg(feature = "evm")]
+use ethers::core::utils::hash_message;
+#[cfg(feature = "evm")]
+use ethers::signers::LocalWallet;
+#[cfg(feature = "evm")]
+use ethers::types::{Address, Signature, U256};
+#[cfg(feature = "evm")]
 use std::str::FromStr;
 
+/// Largest decoded payment payload servers and facilitators accept, in bytes.
+pub const MAX_PAYMENT_PAYLOAD_BYTES: usize = 16 * 1024;
+
 /// Encodes a PaymentPayload as Base64 JSON for the X-PAYMENT header.
 ///
 /// # Arguments
@@ -27,6 +39,8 @@ use std::str::FromStr;
 ///     scheme: "exact".to_string(),
 ///     network: "8453".to_string(),
 ///     payload: json!({}),
+///     challenge: None,
+///     message_signature: None,
 /// };
 ///
 /// let encoded = encode_payment_header(&payload).unwrap();
@@ -39,6 +53,9 @@ pub fn encode_payment_header(payload: &PaymentPayload) -> Result<String> {
 
 /// Decodes a Base64 JSON PaymentPayload from the X-PAYMENT header.
 ///
+/// Payloads with an unsupported `x402Version` are rejected with
+/// `X402Error::UnsupportedVersion` rather than processed with v1 semantics.
+///
 /// # Arguments
 ///
 /// * `encoded` - The Base64 encoded payment payload
@@ -55,6 +72,8 @@ pub fn encode_payment_header(payload: &PaymentPayload) -> Result<String> {
 ///     scheme: "exact".to_string(),
 ///     network: "8453".to_string(),
 ///     payload: json!({}),
+///     challenge: None,
+///     message_signature: None,
 /// };
 ///
 /// let encoded = encode_payment_header(&payload).unwrap();
@@ -66,9 +85,202 @@ pub fn decode_payment_header(encoded: &str) -> Result<PaymentPayload> {
     let json_str = String::from_utf8(decoded)
         .map_err(|e| X402Error::InvalidPayload(format!("Invalid UTF-8: {}", e)))?;
     let payload: PaymentPayload = serde_json::from_str(&json_str)?;
+    check_x402_version(payload.x402_version)?;
     Ok(payload)
 }
 
+/// Encodes a PaymentPayload for the X-PAYMENT header using `encoding`.
+///
+/// [`PayloadEncoding::Json`] matches [`encode_payment_header`]. Other encodings must be
+/// announced to the server in the `X-PAYMENT-ENCODING` header.
+pub fn encode_payment_header_with(
+    payload: &PaymentPayload,
+    encoding: PayloadEncoding,
+) -> Result<String> {
+    match encoding {
+        PayloadEncoding::Json => encode_payment_header(payload),
+        #[cfg(feature = "cbor")]
+        PayloadEncoding::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(payload, &mut bytes).map_err(|e| {
+                X402Error::InvalidPayload(format!("CBOR encoding failed: {}", e))
+            })?;
+            Ok(BASE64.encode(bytes))
+        }
+    }
+}
+
+/// Decodes a PaymentPayload from an X-PAYMENT header encoded with `encoding`.
+///
+/// # Errors
+///
+/// Returns `X402Error::UnsupportedVersion` like [`decode_payment_header`], and
+/// `X402Error::InvalidPayload` if the header isn't valid for `encoding`.
+pub fn decode_payment_header_with(
+    encoded: &str,
+    encoding: PayloadEncoding,
+) -> Result<PaymentPayload> {
+    match encoding {
+        PayloadEncoding::Json => decode_payment_header(encoded),
+        #[cfg(feature = "cbor")]
+        PayloadEncoding::Cbor => {
+            let decoded = BASE64.decode(encoded.as_bytes())?;
+            let payload: PaymentPayload = ciborium::from_reader(decoded.as_slice())
+                .map_err(|e| X402Error::InvalidPayload(format!("Invalid CBOR: {}", e)))?;
+            check_x402_version(payload.x402_version)?;
+            Ok(payload)
+        }
+    }
+}
+
+/// Re-encodes an X-PAYMENT header sent with `encoding` as Base64 JSON.
+///
+/// Servers accepting other encodings call this with the `X-PAYMENT-ENCODING` header
+/// before verification, since facilitators only read JSON payloads.
+pub fn normalize_payment_header(encoded: &str, encoding: PayloadEncoding) -> Result<String> {
+    if encoding == PayloadEncoding::Json {
+        return Ok(encoded.to_string());
+    }
+    encode_payment_header(&decode_payment_header_with(encoded, encoding)?)
+}
+
+/// Decodes a Base64 JSON PaymentPayload, refusing payloads larger than `max_bytes`.
+///
+/// The header is decoded and parsed as a stream, so oversized headers are rejected
+/// before any JSON is read and no intermediate `String` holds the whole payload.
+/// Servers and facilitators decode untrusted headers with this, bounded by
+/// [`MAX_PAYMENT_PAYLOAD_BYTES`].
+///
+/// # Arguments
+///
+/// * `encoded` - The Base64 encoded payment payload
+/// * `max_bytes` - Largest decoded JSON payload accepted
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::types::PaymentPayload;
+/// use x402_rs::utils::{encode_payment_header, decode_payment_header_bounded};
+/// use serde_json::json;
+///
+/// let payload = PaymentPayload {
+///     x402_version: 1,
+///     scheme: "exact".to_string(),
+///     network: "8453".to_string(),
+///     payload: json!({}),
+///     challenge: None,
+///     message_signature: None,
+/// };
+///
+/// let encoded = encode_payment_header(&payload).unwrap();
+/// assert!(decode_payment_header_bounded(&encoded, 1024).is_ok());
+/// assert!(decode_payment_header_bounded(&encoded, 16).is_err());
+/// ```
+pub fn decode_payment_header_bounded(encoded: &str, max_bytes: usize) -> Result<PaymentPayload> {
+    use std::io::Read;
+
+    let too_large =
+        || X402Error::InvalidPayload(format!("Payment payload exceeds {} bytes", max_bytes));
+
+    // Every 4 Base64 characters decode to 3 bytes, less at most 2 bytes of padding
+    if (encoded.len() / 4).saturating_mul(3).saturating_sub(2) > max_bytes {
+        return Err(too_large());
+    }
+
+    let mut reader =
+        base64::read::DecoderReader::new(encoded.as_bytes(), &BASE64).take(max_bytes as u64);
+    let payload: PaymentPayload = match serde_json::from_reader(&mut reader) {
+        Ok(payload) => payload,
+        Err(_) if reader.limit() == 0 => return Err(too_large()),
+        Err(e) if e.is_io() => {
+            return Err(X402Error::InvalidPayload(format!("Invalid Base64: {}", e)))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if reader.limit() == 0 && reader.into_inner().read(&mut [0u8; 1]).unwrap_or(0) > 0 {
+        return Err(too_large());
+    }
+
+    check_x402_version(payload.x402_version)?;
+    Ok(payload)
+}
+
+/// Encodes a PaymentResponse as Base64 JSON for the X-PAYMENT-RESPONSE header.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::types::PaymentResponse;
+/// use x402_rs::utils::encode_payment_response_header;
+///
+/// let response = PaymentResponse {
+///     tx_hash: "0xabc".to_string(),
+///     settled_at: None,
+///     metadata: None,
+///     settlement: None,
+///     access_token: None,
+/// };
+///
+/// let encoded = encode_payment_response_header(&response).unwrap();
+/// assert!(!encoded.is_empty());
+/// ```
+pub fn encode_payment_response_header(response: &PaymentResponse) -> Result<String> {
+    let json = serde_json::to_string(response)?;
+    Ok(BASE64.encode(json.as_bytes()))
+}
+
+/// Decodes a Base64 JSON PaymentResponse from the X-PAYMENT-RESPONSE header.
+///
+/// # Errors
+///
+/// Returns `X402Error::InvalidPayload` naming the header if it isn't Base64 JSON of a
+/// `PaymentResponse`.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::types::PaymentResponse;
+/// use x402_rs::utils::{decode_payment_response_header, encode_payment_response_header};
+///
+/// let response = PaymentResponse {
+///     tx_hash: "0xabc".to_string(),
+///     settled_at: None,
+///     metadata: None,
+///     settlement: None,
+///     access_token: Some("token".to_string()),
+/// };
+///
+/// let encoded = encode_payment_response_header(&response).unwrap();
+/// let decoded = decode_payment_response_header(&encoded).unwrap();
+/// assert_eq!(decoded.tx_hash, "0xabc");
+/// assert_eq!(decoded.access_token.as_deref(), Some("token"));
+/// ```
+pub fn decode_payment_response_header(encoded: &str) -> Result<PaymentResponse> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        X402Error::InvalidPayload(format!("Invalid X-PAYMENT-RESPONSE header: {}", e))
+    };
+    let decoded = BASE64.decode(encoded.as_bytes()).map_err(|e| invalid(&e))?;
+    serde_json::from_slice(&decoded).map_err(|e| invalid(&e))
+}
+
+/// Checks that an x402 protocol version is supported.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::utils::check_x402_version;
+///
+/// assert!(check_x402_version(1).is_ok());
+/// assert!(check_x402_version(2).is_err());
+/// ```
+pub fn check_x402_version(version: u32) -> Result<()> {
+    if SUPPORTED_X402_VERSIONS.contains(&version) {
+        Ok(())
+    } else {
+        Err(X402Error::UnsupportedVersion(version))
+    }
+}
+
 /// Converts a string representation of a uint256 to ethers U256.
 ///
 /// # Arguments
@@ -86,6 +298,7 @@ pub fn decode_payment_header(encoded: &str) -> Result<PaymentPayload> {
 /// let hex_value = string_to_u256("0x0f4240").unwrap();
 /// assert_eq!(hex_value, 1000000u64.into());
 /// ```
+#[cfg(feature = "evm")]
 pub fn string_to_u256(s: &str) -> Result<U256> {
     // Try decimal first
     if let Ok(value) = U256::from_dec_str(s) {
@@ -102,6 +315,49 @@ pub fn string_to_u256(s: &str) -> Result<U256> {
     Err(X402Error::InvalidAmount(format!("Cannot parse '{}' as U256", s)))
 }
 
+/// Returns the `(min, max)` amount range accepted by the requirements.
+///
+/// The minimum falls back to `max_amount_required` when `min_amount_required` is not
+/// set, so fixed-price requirements yield `min == max`.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::server::create_simple_config;
+/// use x402_rs::utils::amount_range;
+///
+/// let config = create_simple_config(
+///     "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEb",
+///     0.01,
+///     "API access",
+///     "https://facilitator.example.com",
+/// );
+/// let mut requirements = config.to_requirements("/api").unwrap();
+/// let (min, max) = amount_range(&requirements).unwrap();
+/// assert_eq!(min, max);
+///
+/// requirements.min_amount_required = Some("5000".to_string());
+/// let (min, _) = amount_range(&requirements).unwrap();
+/// assert_eq!(min, 5000u64.into());
+/// ```
+#[cfg(feature = "evm")]
+pub fn amount_range(requirements: &PaymentRequirements) -> Result<(U256, U256)> {
+    let max = string_to_u256(&requirements.max_amount_required)?;
+    let min = match &requirements.min_amount_required {
+        Some(min) => string_to_u256(min)?,
+        None => max,
+    };
+
+    if min > max {
+        return Err(X402Error::InvalidAmount(format!(
+            "minAmountRequired {} exceeds maxAmountRequired {}",
+            min, max
+        )));
+    }
+
+    Ok((min, max))
+}
+
 /// Converts a U256 to its string representation.
 ///
 /// # Arguments
@@ -118,6 +374,7 @@ pub fn string_to_u256(s: &str) -> Result<U256> {
 /// let s = u256_to_string(value);
 /// assert_eq!(s, "1000000");
 /// ```
+#[cfg(feature = "evm")]
 pub fn u256_to_string(value: U256) -> String {
     value.to_string()
 }
@@ -137,10 +394,189 @@ pub fn u256_to_string(value: U256) -> String {
 /// // Verify it parsed successfully
 /// assert!(format!("{:?}", addr).len() > 0);
 /// ```
+#[cfg(feature = "evm")]
 pub fn parse_address(addr: &str) -> Result<Address> {
     Address::from_str(addr).map_err(|e| X402Error::InvalidAddress(format!("{}: {}", addr, e)))
 }
 
+/// Returns whether `requirements` ask for an x402 message signature
+/// (`extra.requireMessageSignature`).
+pub fn message_signature_required(requirements: &PaymentRequirements) -> bool {
+    requirements
+        .extra
+        .as_ref()
+        .and_then(|extra| extra.get("requireMessageSignature"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Returns the attestation `requirements` require payers to hold
+/// (`extra.requiredAttestation`), if any.
+pub fn required_attestation(requirements: &PaymentRequirements) -> Option<&str> {
+    requirements
+        .extra
+        .as_ref()
+        .and_then(|extra| extra.get("requiredAttestation"))
+        .and_then(|v| v.as_str())
+}
+
+/// Returns how `requirements` ask the asset to be moved (`extra.assetTransferMethod`),
+/// if they say.
+///
+/// `"allowance"` selects
+/// [`AllowanceEvm`](crate::schemes::allowance_evm::AllowanceEvm); otherwise `exact`
+/// payments use EIP-3009.
+pub fn asset_transfer_method(requirements: &PaymentRequirements) -> Option<&str> {
+    requirements
+        .extra
+        .as_ref()
+        .and_then(|extra| extra.get("assetTransferMethod"))
+        .and_then(|v| v.as_str())
+}
+
+/// Returns `true` if `requirements` split the payment between several recipients
+/// (`extra.splits`).
+pub fn is_split_payment(requirements: &PaymentRequirements) -> bool {
+    requirements
+        .extra
+        .as_ref()
+        .is_some_and(|extra| extra.get("splits").is_some())
+}
+
+/// Returns the recipients `requirements` split the payment between, if any.
+///
+/// # Errors
+///
+/// Returns `X402Error::ConfigError` if `extra.splits` isn't a non-empty list of
+/// recipients whose shares add up to 10000 basis points.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::types::PaymentRequirements;
+/// use x402_rs::utils::split_recipients;
+///
+/// let requirements: PaymentRequirements = serde_json::from_value(serde_json::json!({
+///     "scheme": "exact",
+///     "network": "8453",
+///     "maxAmountRequired": "10000",
+///     "resource": "/weather",
+///     "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+///     "maxTimeoutSeconds": 300,
+///     "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+///     "extra": { "splits": [
+///         { "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb", "bps": 7000 },
+///         { "payTo": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8", "bps": 3000 }
+///     ] }
+/// }))?;
+///
+/// let recipients = split_recipients(&requirements)?.unwrap();
+/// assert_eq!(recipients[1].bps, 3000);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn split_recipients(requirements: &PaymentRequirements) -> Result<Option<Vec<SplitRecipient>>> {
+    let Some(splits) = requirements.extra.as_ref().and_then(|extra| extra.get("splits")) else {
+        return Ok(None);
+    };
+    let recipients: Vec<SplitRecipient> = serde_json::from_value(splits.clone())
+        .map_err(|e| X402Error::ConfigError(format!("Invalid extra.splits: {}", e)))?;
+
+    if recipients.is_empty() {
+        return Err(X402Error::ConfigError("extra.splits lists no recipients".to_string()));
+    }
+    let total: u32 = recipients.iter().map(|r| u32::from(r.bps)).sum();
+    if total != u32::from(SPLIT_TOTAL_BPS) {
+        return Err(X402Error::ConfigError(format!(
+            "extra.splits shares add up to {} basis points, not {}",
+            total, SPLIT_TOTAL_BPS
+        )));
+    }
+    Ok(Some(recipients))
+}
+
+/// Splits `maxAmountRequired` between the recipients of `extra.splits`.
+///
+/// Each recipient gets its share rounded down, and the last one also the remainder,
+/// so the legs add up to exactly the required amount. Returns `None` if the
+/// requirements don't split the payment.
+///
+/// # Errors
+///
+/// See [`split_recipients`]; also returns `X402Error::InvalidAddress` or
+/// `X402Error::InvalidAmount` for a malformed recipient or amount.
+#[cfg(feature = "evm")]
+pub fn split_amounts(requirements: &PaymentRequirements) -> Result<Option<Vec<(Address, U256)>>> {
+    let Some(recipients) = split_recipients(requirements)? else {
+        return Ok(None);
+    };
+    let total = string_to_u256(&requirements.max_amount_required)?;
+
+    let mut remaining = total;
+    let mut legs = Vec::with_capacity(recipients.len());
+    for (i, recipient) in recipients.iter().enumerate() {
+        let amount = match i + 1 == recipients.len() {
+            true => remaining,
+            false => total * U256::from(recipient.bps) / U256::from(SPLIT_TOTAL_BPS),
+        };
+        remaining -= amount;
+        legs.push((parse_address(&recipient.pay_to)?, amount));
+    }
+    Ok(Some(legs))
+}
+
+/// Signs a payload's [`PaymentPayload::signing_message`] with EIP-191 `personal_sign`.
+///
+/// The result belongs in `PaymentPayload::message_signature`, and must be computed after
+/// every other field, including `challenge`, is final.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::types::PaymentPayload;
+/// use x402_rs::utils::{recover_message_signer, sign_payment_message};
+/// use serde_json::json;
+///
+/// let mut payload = PaymentPayload {
+///     x402_version: 1,
+///     scheme: "exact".to_string(),
+///     network: "8453".to_string(),
+///     payload: json!({}),
+///     challenge: None,
+///     message_signature: None,
+/// };
+/// let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+/// payload.message_signature = Some(sign_payment_message(&payload, key).unwrap());
+///
+/// let signer = recover_message_signer(&payload).unwrap();
+/// assert_eq!(format!("{:?}", signer), "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+/// ```
+#[cfg(feature = "evm")]
+pub fn sign_payment_message(payload: &PaymentPayload, private_key: &str) -> Result<String> {
+    let wallet = private_key
+        .parse::<LocalWallet>()
+        .map_err(|e| X402Error::InvalidPayload(format!("Invalid private key: {}", e)))?;
+    let signature = wallet
+        .sign_hash(hash_message(payload.signing_message()?))
+        .map_err(|e| X402Error::SignatureError(e.to_string()))?;
+    Ok(format!("0x{}", hex::encode(signature.to_vec())))
+}
+
+/// Recovers the signer of a payload's `message_signature`.
+///
+/// # Errors
+///
+/// Returns `X402Error::MissingField` if the payload has no message signature, or
+/// `X402Error::SignatureError` if it cannot be recovered.
+#[cfg(feature = "evm")]
+pub fn recover_message_signer(payload: &PaymentPayload) -> Result<Address> {
+    let signature = payload
+        .message_signature
+        .as_deref()
+        .ok_or_else(|| X402Error::MissingField("messageSignature".to_string()))?;
+    let signature = Signature::from_str(signature)?;
+    Ok(signature.recover(payload.signing_message()?)?)
+}
+
 /// Generates a random 32-byte nonce for EIP-3009 authorization.
 ///
 /// # Examples
@@ -151,15 +587,38 @@ pub fn parse_address(addr: &str) -> Result<Address> {
 /// let nonce = generate_nonce();
 /// assert_eq!(nonce.len(), 66); // "0x" + 64 hex chars
 /// ```
+#[cfg(feature = "evm")]
 pub fn generate_nonce() -> String {
     use rand::Rng;
-    let mut rng = rand::thread_rng();
-    let nonce: [u8; 32] = rng.gen();
+    let mut rng = rand::rng();
+    let nonce: [u8; 32] = rng.random();
     format!("0x{}", hex::encode(nonce))
 }
 
+/// Generates a random request id for correlating facilitator calls.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::utils::generate_request_id;
+///
+/// let id = generate_request_id();
+/// assert_eq!(id.len(), 32);
+/// ```
+#[cfg(feature = "evm")]
+pub fn generate_request_id() -> String {
+    use rand::Rng;
+    let bytes: [u8; 16] = rand::rng().random();
+    hex::encode(bytes)
+}
+
 /// Converts a dollar amount to the smallest token unit based on decimals.
 ///
+/// The conversion is done on the decimal representation of the amount, so it stays
+/// exact for tokens with more than 18 decimals. Fractions smaller than one unit are
+/// rounded half-up; an amount that is positive but rounds to zero units (e.g. $0.01
+/// of a 0-decimal token) is rejected rather than silently made free.
+///
 /// # Arguments
 ///
 /// * `dollar_amount` - Amount in dollars (e.g., 0.01 for 1 cent)
@@ -174,23 +633,169 @@ pub fn generate_nonce() -> String {
 /// // $0.01 in USDC (6 decimals, $1 per USDC)
 /// let amount = dollar_to_token_amount(0.01, 6, 1.0).unwrap();
 /// assert_eq!(amount, "10000");
+///
+/// // $0.01 can't be represented by a 0-decimal token
+/// assert!(dollar_to_token_amount(0.01, 0, 1.0).is_err());
 /// ```
+#[cfg(feature = "evm")]
 pub fn dollar_to_token_amount(
     dollar_amount: f64,
     decimals: u8,
     token_usd_price: f64,
 ) -> Result<String> {
-    if token_usd_price <= 0.0 {
+    if token_usd_price <= 0.0 || !token_usd_price.is_finite() {
         return Err(X402Error::InvalidAmount("Token price must be positive".to_string()));
     }
-    
+    if dollar_amount < 0.0 || !dollar_amount.is_finite() {
+        return Err(X402Error::InvalidAmount(format!(
+            "Invalid dollar amount: {}",
+            dollar_amount
+        )));
+    }
+
     let token_amount = dollar_amount / token_usd_price;
-    let multiplier = 10f64.powi(decimals as i32);
-    let smallest_unit = (token_amount * multiplier).round() as u128;
-    
+    let smallest_unit = decimal_to_units(&token_amount.to_string(), decimals)?;
+
+    if smallest_unit.is_zero() && token_amount > 0.0 {
+        return Err(X402Error::InvalidAmount(format!(
+            "${} is below the smallest unit of a {}-decimal token",
+            dollar_amount, decimals
+        )));
+    }
+
     Ok(smallest_unit.to_string())
 }
 
+/// Converts an exact decimal amount (e.g. `"0.001"`) to the smallest token unit.
+///
+/// Unlike [`dollar_to_token_amount`], no floating point is involved: the string is
+/// scaled with integer math. Amounts more precise than the token's decimals are
+/// rejected rather than rounded.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::utils::decimal_to_token_amount;
+///
+/// assert_eq!(decimal_to_token_amount("0.001", 6).unwrap(), "1000");
+/// assert_eq!(decimal_to_token_amount("0.001", 18).unwrap(), "1000000000000000");
+/// assert!(decimal_to_token_amount("0.0000001", 6).is_err());
+/// ```
+#[cfg(feature = "evm")]
+pub fn decimal_to_token_amount(amount: &str, decimals: u8) -> Result<String> {
+    let amount = amount.trim();
+    let fraction = amount.split_once('.').map_or("", |(_, fraction)| fraction);
+    if fraction
+        .get(decimals as usize..)
+        .is_some_and(|dropped| dropped.chars().any(|c| c != '0'))
+    {
+        return Err(X402Error::InvalidAmount(format!(
+            "{} has more precision than a {}-decimal token",
+            amount, decimals
+        )));
+    }
+
+    Ok(decimal_to_units(amount, decimals)?.to_string())
+}
+
+/// Converts an amount in the smallest token unit back to dollars.
+///
+/// This is the inverse of [`dollar_to_token_amount`] and is intended for display;
+/// the result is subject to `f64` precision.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::utils::token_amount_to_dollar;
+///
+/// let dollars = token_amount_to_dollar("10000", 6, 1.0).unwrap();
+/// assert!((dollars - 0.01).abs() < 1e-12);
+/// ```
+#[cfg(feature = "evm")]
+pub fn token_amount_to_dollar(amount: &str, decimals: u8, token_usd_price: f64) -> Result<f64> {
+    if token_usd_price <= 0.0 || !token_usd_price.is_finite() {
+        return Err(X402Error::InvalidAmount("Token price must be positive".to_string()));
+    }
+
+    let units = string_to_u256(amount)?.to_string();
+    let decimals = decimals as usize;
+    let padded = format!("{:0>width$}", units, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+
+    let token_amount: f64 = format!("{}.{}", whole, fraction)
+        .trim_end_matches('.')
+        .parse()
+        .map_err(|e| X402Error::InvalidAmount(format!("Cannot convert '{}': {}", amount, e)))?;
+
+    Ok(token_amount * token_usd_price)
+}
+
+/// Formats an amount in the smallest token unit as an exact decimal string.
+///
+/// Unlike [`token_amount_to_dollar`], no precision is lost, so it suits displaying
+/// amounts of 18-decimal tokens.
+///
+/// # Examples
+///
+/// ```
+/// use x402_rs::utils::format_token_amount;
+///
+/// assert_eq!(format_token_amount("10000", 6).unwrap(), "0.01");
+/// assert_eq!(format_token_amount("1500000000000000000", 18).unwrap(), "1.5");
+/// assert_eq!(format_token_amount("42", 0).unwrap(), "42");
+/// ```
+#[cfg(feature = "evm")]
+pub fn format_token_amount(amount: &str, decimals: u8) -> Result<String> {
+    let digits = string_to_u256(amount)?.to_string();
+    let decimals = decimals as usize;
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+
+    Ok(if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    })
+}
+
+/// Scales a non-negative decimal string (e.g. `"0.01"`) to integer units.
+///
+/// Digits beyond `decimals` are rounded half-up.
+#[cfg(feature = "evm")]
+fn decimal_to_units(value: &str, decimals: u8) -> Result<U256> {
+    let invalid = || X402Error::InvalidAmount(format!("Invalid decimal amount: '{}'", value));
+
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let decimals = decimals as usize;
+    let (kept, dropped) = if fraction.len() > decimals {
+        fraction.split_at(decimals)
+    } else {
+        (fraction, "")
+    };
+
+    let digits = format!("{}{}{}", whole, kept, "0".repeat(decimals - kept.len()));
+    let digits = digits.trim_start_matches('0');
+    let mut units = if digits.is_empty() {
+        U256::zero()
+    } else {
+        U256::from_dec_str(digits).map_err(|_| invalid())?
+    };
+
+    if dropped.starts_with(|c: char| c >= '5') {
+        units = units.checked_add(U256::one()).ok_or_else(invalid)?;
+    }
+
+    Ok(units)
+}
+
 /// Gets the current Unix timestamp in seconds.
 ///
 /// # Examples
@@ -242,6 +847,8 @@ mod tests {
             scheme: "exact".to_string(),
             network: "8453".to_string(),
             payload: json!({"test": "data"}),
+            challenge: None,
+            message_signature: None,
         };
 
         let encoded = encode_payment_header(&payload).unwrap();
@@ -251,6 +858,38 @@ mod tests {
         assert_eq!(decoded.network, payload.network);
     }
 
+    #[test]
+    fn test_encode_decode_payment_response_header() {
+        let response = PaymentResponse {
+            tx_hash: "0xabc".to_string(),
+            settled_at: Some("2025-01-01T00:00:00Z".to_string()),
+            metadata: Some(json!({"order": 42})),
+            settlement: None,
+            access_token: Some("token".to_string()),
+        };
+
+        let encoded = encode_payment_response_header(&response).unwrap();
+        let decoded = decode_payment_response_header(&encoded).unwrap();
+
+        assert_eq!(decoded.tx_hash, response.tx_hash);
+        assert_eq!(decoded.settled_at, response.settled_at);
+        assert_eq!(decoded.metadata, response.metadata);
+        assert_eq!(decoded.access_token, response.access_token);
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_payment_response_header() {
+        let not_json = BASE64.encode(b"{\"txHash\": ");
+        for encoded in ["not base64!", not_json.as_str()] {
+            assert!(matches!(
+                decode_payment_response_header(encoded),
+                Err(X402Error::InvalidPayload(reason))
+                    if reason.starts_with("Invalid X-PAYMENT-RESPONSE header")
+            ));
+        }
+    }
+
+    #[cfg(feature = "evm")]
     #[test]
     fn test_string_to_u256() {
         assert_eq!(string_to_u256("1000000").unwrap(), U256::from(1000000u64));
@@ -258,18 +897,85 @@ mod tests {
         assert_eq!(string_to_u256("0x0f4240").unwrap(), U256::from(1000000u64));
     }
 
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_amount_range() {
+        let mut requirements = crate::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEb",
+            0.02,
+            "Test",
+            "https://facilitator.test",
+        )
+        .to_requirements("/test")
+        .unwrap();
+        requirements.min_amount_required = Some("10000".to_string());
+
+        assert_eq!(
+            amount_range(&requirements).unwrap(),
+            (U256::from(10000u64), U256::from(20000u64))
+        );
+
+        requirements.min_amount_required = None;
+        assert_eq!(
+            amount_range(&requirements).unwrap(),
+            (U256::from(20000u64), U256::from(20000u64))
+        );
+
+        requirements.min_amount_required = Some("30000".to_string());
+        assert!(amount_range(&requirements).is_err());
+    }
+
+    #[cfg(feature = "evm")]
+    #[test]
+    fn test_split_amounts() {
+        let mut requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "8453",
+            "maxAmountRequired": "10001",
+            "resource": "/test",
+            "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "maxTimeoutSeconds": 300,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+            "extra": { "splits": [
+                { "payTo": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb", "bps": 7000 },
+                { "payTo": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8", "bps": 3000 }
+            ] }
+        }))
+        .unwrap();
+        assert!(is_split_payment(&requirements));
+
+        // The rounding remainder goes to the last recipient
+        let legs = split_amounts(&requirements).unwrap().unwrap();
+        assert_eq!(legs[0].1, U256::from(7000u64));
+        assert_eq!(legs[1].1, U256::from(3001u64));
+        assert_eq!(
+            legs[1].0,
+            parse_address("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap()
+        );
+
+        requirements.extra.as_mut().unwrap()["splits"][1]["bps"] = json!(2000);
+        let err = split_amounts(&requirements).unwrap_err();
+        assert!(err.to_string().contains("9000 basis points"), "{}", err);
+
+        requirements.extra = None;
+        assert!(!is_split_payment(&requirements));
+        assert!(split_amounts(&requirements).unwrap().is_none());
+    }
+
+    #[cfg(feature = "evm")]
     #[test]
     fn test_u256_to_string() {
         assert_eq!(u256_to_string(U256::from(1000000u64)), "1000000");
         assert_eq!(u256_to_string(U256::zero()), "0");
     }
 
+    #[cfg(feature = "evm")]
     #[test]
     fn test_parse_address() {
         // Use a properly formatted Ethereum address (40 hex chars)
         let addr = parse_address("0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb").unwrap();
         // Just verify it parsed successfully
-        assert_eq!(format!("{:?}", addr).len() > 0, true);
+        assert!(!format!("{:?}", addr).is_empty());
         
         // Test address without 0x prefix
         let addr2 = parse_address("742d35Cc6634C0532925a3b844Bc9e7595f0bEbb").unwrap();
@@ -280,6 +986,7 @@ mod tests {
         assert!(invalid.is_err());
     }
 
+    #[cfg(feature = "evm")]
     #[test]
     fn test_generate_nonce() {
         let nonce1 = generate_nonce();
@@ -290,6 +997,7 @@ mod tests {
         assert_ne!(nonce1, nonce2); // Should be different
     }
 
+    #[cfg(feature = "evm")]
     #[test]
     fn test_dollar_to_token_amount() {
         // $0.01 in USDC (6 decimals)
@@ -305,6 +1013,47 @@ mod tests {
         assert_eq!(amount, "10000000000000000");
     }
 
+    #[cfg(feature = "evm")]
+    #[test]
+    fn test_dollar_to_token_amount_edge_decimals() {
+        // 0 decimals: whole units round, sub-unit amounts are rejected
+        assert_eq!(dollar_to_token_amount(3.0, 0, 1.0).unwrap(), "3");
+        assert_eq!(dollar_to_token_amount(2.5, 0, 1.0).unwrap(), "3");
+        assert!(dollar_to_token_amount(0.01, 0, 1.0).is_err());
+        assert_eq!(dollar_to_token_amount(0.0, 0, 1.0).unwrap(), "0");
+
+        // 2 decimals: a cent is one unit, a tenth of a cent is not representable
+        assert_eq!(dollar_to_token_amount(0.01, 2, 1.0).unwrap(), "1");
+        assert_eq!(dollar_to_token_amount(0.015, 2, 1.0).unwrap(), "2");
+        assert!(dollar_to_token_amount(0.001, 2, 1.0).is_err());
+
+        // 18 and 24 decimals scale exactly
+        assert_eq!(dollar_to_token_amount(0.01, 18, 1.0).unwrap(), "10000000000000000");
+        assert_eq!(
+            dollar_to_token_amount(0.01, 24, 1.0).unwrap(),
+            "10000000000000000000000"
+        );
+        assert_eq!(
+            dollar_to_token_amount(123.456, 24, 1.0).unwrap(),
+            "123456000000000000000000000"
+        );
+
+        assert!(dollar_to_token_amount(-1.0, 6, 1.0).is_err());
+    }
+
+    #[cfg(feature = "evm")]
+    #[test]
+    fn test_token_amount_to_dollar() {
+        assert_eq!(token_amount_to_dollar("3", 0, 1.0).unwrap(), 3.0);
+        assert_eq!(token_amount_to_dollar("1", 2, 1.0).unwrap(), 0.01);
+        assert_eq!(token_amount_to_dollar("10000000000000000", 18, 1.0).unwrap(), 0.01);
+        assert_eq!(
+            token_amount_to_dollar("10000000000000000000000", 24, 1.0).unwrap(),
+            0.01
+        );
+        assert!(token_amount_to_dollar("abc", 6, 1.0).is_err());
+    }
+
     #[test]
     fn test_timestamp_validation() {
         let now = current_timestamp();
@@ -319,5 +1068,112 @@ mod tests {
         assert!(ts > 1600000000); // After Sept 2020
         assert!(ts < 2000000000); // Before May 2033
     }
-}
 
+    #[test]
+    fn test_decode_rejects_unsupported_version() {
+        let payload = PaymentPayload {
+            x402_version: 2,
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            payload: serde_json::json!({}),
+            challenge: None,
+            message_signature: None,
+        };
+        let encoded = encode_payment_header(&payload).unwrap();
+
+        assert!(matches!(
+            decode_payment_header(&encoded),
+            Err(X402Error::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn test_decode_payment_header_bounded() {
+        let payload = PaymentPayload {
+            x402_version: 1,
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            payload: json!({ "signers": vec!["0x".to_string() + &"ab".repeat(65); 500] }),
+            challenge: None,
+            message_signature: None,
+        };
+        let encoded = encode_payment_header(&payload).unwrap();
+        let size = serde_json::to_vec(&payload).unwrap().len();
+        assert!(size > 64 * 1024);
+
+        let decoded = decode_payment_header_bounded(&encoded, size).unwrap();
+        assert_eq!(decoded.payload, payload.payload);
+
+        for max_bytes in [size - 1, size / 2, 1024] {
+            assert!(matches!(
+                decode_payment_header_bounded(&encoded, max_bytes),
+                Err(X402Error::InvalidPayload(reason)) if reason.contains("exceeds")
+            ));
+        }
+        assert!(decode_payment_header_bounded("not base64!", 1024).is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    fn signed_payload() -> PaymentPayload {
+        PaymentPayload {
+            x402_version: 1,
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            payload: json!({
+                "signature": format!("0x{}", "ab".repeat(65)),
+                "authorization": {
+                    "from": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                    "to": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+                    "value": "10000",
+                    "validAfter": "0",
+                    "validBefore": "1735689600",
+                    "nonce": format!("0x{}", "01".repeat(32)),
+                },
+            }),
+            challenge: Some("c1".to_string()),
+            message_signature: None,
+        }
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_payment_header_round_trip() {
+        let payload = signed_payload();
+        let cbor = encode_payment_header_with(&payload, PayloadEncoding::Cbor).unwrap();
+        let json = encode_payment_header_with(&payload, PayloadEncoding::Json).unwrap();
+        assert!(cbor.len() < json.len());
+
+        let from_cbor = decode_payment_header_with(&cbor, PayloadEncoding::Cbor).unwrap();
+        let from_json = decode_payment_header(&json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&from_cbor).unwrap(),
+            serde_json::to_value(&from_json).unwrap()
+        );
+
+        // Normalized for a facilitator, the CBOR header is the JSON header
+        assert_eq!(normalize_payment_header(&cbor, PayloadEncoding::Cbor).unwrap(), json);
+        assert!(decode_payment_header_with(&json, PayloadEncoding::Cbor).is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_decode_rejects_unsupported_version() {
+        let mut payload = signed_payload();
+        payload.x402_version = 2;
+        let cbor = encode_payment_header_with(&payload, PayloadEncoding::Cbor).unwrap();
+        assert!(matches!(
+            decode_payment_header_with(&cbor, PayloadEncoding::Cbor),
+            Err(X402Error::UnsupportedVersion(2))
+        ));
+    }
+
+    #[cfg(feature = "evm")]
+    #[test]
+    fn test_decimal_to_token_amount_exact() {
+        assert_eq!(decimal_to_token_amount("0.001", 6).unwrap(), "1000");
+        assert_eq!(decimal_to_token_amount("0.001", 18).unwrap(), "1000000000000000");
+        assert_eq!(decimal_to_token_amount("0.0010000", 6).unwrap(), "1000");
+        assert!(decimal_to_token_amount("0.0000001", 6).is_err());
+        assert!(decimal_to_token_amount("-1", 6).is_err());
+    }
+}
@@ -3,13 +3,14 @@
 //! These tests verify the end-to-end functionality of the protocol,
 //! including client, server, and facilitator interactions.
 
-use serde_json::json;
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::sync::Arc;
 use x402_rs::{
-    client::{X402ClientConfig, request_with_payment},
-    facilitator::{FacilitatorConfig, handle_supported, handle_verify},
+    client::X402ClientConfig,
+    facilitator::{FacilitatorConfig, handle_supported},
     server::{PaymentConfig, create_payment_required_response},
-    types::{PaymentRequiredResponse, VerificationRequest},
+    types::PaymentRequiredResponse,
     utils::{encode_payment_header, decode_payment_header, dollar_to_token_amount},
 };
 
@@ -181,6 +182,8 @@ fn test_payment_header_encoding_decoding() {
         scheme: "exact".to_string(),
         network: "8453".to_string(),
         payload: json!({"test": "data"}),
+        challenge: None,
+        message_signature: None,
     };
 
     let encoded = encode_payment_header(&payload).unwrap();
@@ -317,6 +320,7 @@ fn test_type_serialization() {
         scheme: "exact".to_string(),
         network: "8453".to_string(),
         max_amount_required: "10000".to_string(),
+        min_amount_required: None,
         resource: "/test".to_string(),
         description: Some("Test".to_string()),
         mime_type: Some("application/json".to_string()),
@@ -347,3 +351,4202 @@ fn test_type_serialization() {
     assert!(json.contains("validBefore"));
 }
 
+
+/// Starts a local axum app on an ephemeral port and returns its base URL.
+async fn spawn_app(app: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_facilitator_calls_carry_user_agent_and_request_id() {
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use std::sync::Mutex;
+    use x402_rs::server::verify_and_settle_payment;
+    use x402_rs::types::X_REQUEST_ID_HEADER;
+
+    let seen: Arc<Mutex<Vec<(String, String)>>> = Arc::new(Mutex::new(Vec::new()));
+    let record = |seen: Arc<Mutex<Vec<(String, String)>>>, headers: &HeaderMap| {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        seen.lock()
+            .unwrap()
+            .push((header("user-agent"), header(X_REQUEST_ID_HEADER)));
+    };
+
+    let verify_seen = seen.clone();
+    let settle_seen = seen.clone();
+    let app = Router::new()
+        .route(
+            "/verify",
+            post(move |headers: HeaderMap| async move {
+                record(verify_seen, &headers);
+                Json(json!({ "isValid": true }))
+            }),
+        )
+        .route(
+            "/settle",
+            post(move |headers: HeaderMap| async move {
+                record(settle_seen, &headers);
+                Json(json!({ "txHash": "0xabc" }))
+            }),
+        );
+    let facilitator_url = spawn_app(app).await;
+
+    let config = PaymentConfig::new(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEb",
+        "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+        6,
+        "8453",
+        "exact",
+        0.01,
+        "Test",
+        facilitator_url,
+    )
+    .with_user_agent("test-agent/1.0");
+
+    let settlement = verify_and_settle_payment("header", &config, "/test").await.unwrap();
+    assert_eq!(settlement.tx_hash, "0xabc");
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    assert!(seen.iter().all(|(ua, _)| ua == "test-agent/1.0"));
+    assert!(!seen[0].1.is_empty());
+    assert_eq!(seen[0].1, seen[1].1); // verify and settle share the request id
+}
+
+#[tokio::test]
+async fn test_challenge_survives_failed_verification() {
+    use axum::{routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use x402_rs::server::{create_simple_config, verify_and_settle_payment};
+    use x402_rs::types::PaymentPayload;
+    use x402_rs::X402Error;
+
+    // Rejects the first payment, accepts any after it
+    let verifications = Arc::new(AtomicUsize::new(0));
+    let counter = verifications.clone();
+    let app = Router::new()
+        .route(
+            "/verify",
+            post(move || async move {
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => Json(json!({ "isValid": false, "invalid_reason": "Insufficient funds" })),
+                    _ => Json(json!({ "isValid": true })),
+                }
+            }),
+        )
+        .route("/settle", post(|| async { Json(json!({ "txHash": "0xabc" })) }));
+    let facilitator_url = spawn_app(app).await;
+
+    let config = create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    )
+    .with_challenge("server-secret", 60);
+    let configs = HashMap::from([("usdc".to_string(), config.clone())]);
+    let response = create_payment_required_response(&configs, "/test").unwrap();
+    let header = encode_payment_header(&PaymentPayload {
+        x402_version: 1,
+        scheme: "exact".to_string(),
+        network: "8453".to_string(),
+        payload: json!({}),
+        challenge: response.accepts[0].extra.as_ref().unwrap()["challenge"]
+            .as_str()
+            .map(String::from),
+        message_signature: None,
+    })
+    .unwrap();
+
+    let pay = || verify_and_settle_payment(&header, &config, "/test");
+    let err = pay().await.unwrap_err();
+    assert!(matches!(err, X402Error::VerificationFailed(reason) if reason == "Insufficient funds"));
+
+    // The rejected payment didn't spend the challenge, but a settled one does
+    assert_eq!(pay().await.unwrap().tx_hash, "0xabc");
+    let err = pay().await.unwrap_err();
+    assert!(err.to_string().contains("already used"), "{}", err);
+    assert_eq!(verifications.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_verify_and_enqueue_settlement() {
+    use axum::{routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use x402_rs::server::verify_and_enqueue_settlement;
+
+    let settle_calls = Arc::new(AtomicUsize::new(0));
+    let settle_counter = settle_calls.clone();
+    let app = Router::new()
+        .route("/verify", post(|| async { Json(json!({ "isValid": true })) }))
+        .route(
+            "/settle",
+            post(move || async move {
+                settle_counter.fetch_add(1, Ordering::SeqCst);
+                Json(json!({ "txHash": "0xabc" }))
+            }),
+        );
+    let facilitator_url = spawn_app(app).await;
+
+    let config = PaymentConfig::new(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEb",
+        "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+        6,
+        "8453",
+        "exact",
+        0.01,
+        "Test",
+        facilitator_url,
+    );
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    verify_and_enqueue_settlement("header", &config, "/test", None, &tx)
+        .await
+        .unwrap();
+
+    let queued = rx.try_recv().unwrap();
+    assert_eq!(queued.payment_header, "header");
+    assert_eq!(queued.payment_requirements.resource, "/test");
+    assert_eq!(settle_calls.load(Ordering::SeqCst), 0);
+}
+
+type RpcHandler = Arc<dyn Fn(&str, &Value) -> Value + Send + Sync>;
+
+/// Starts a mock JSON-RPC node answering each call with `handler(method, params)`.
+async fn spawn_mock_rpc(handler: RpcHandler) -> String {
+    use axum::{routing::post, Json, Router};
+
+    let app = Router::new().route(
+        "/",
+        post(move |Json(request): Json<Value>| async move {
+            let method = request["method"].as_str().unwrap_or_default();
+            let result = handler(method, &request["params"]);
+            Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+        }),
+    );
+    spawn_app(app).await
+}
+
+/// Answers an `eth_call` to `balanceOf` with a balance covering any payment, and any
+/// other call with zero.
+fn funded_eth_call(params: &Value) -> Value {
+    let call = &params[0];
+    let data = call["data"].as_str().or(call["input"].as_str()).unwrap_or_default();
+    match data.starts_with("0x70a08231") {
+        true => json!(format!("0x{:064x}", u128::MAX)),
+        false => json!(format!("0x{}", "00".repeat(32))),
+    }
+}
+
+/// A mock RPC node that only knows its chain id (Base mainnet) and that every payer is
+/// funded.
+async fn spawn_chain_id_rpc() -> String {
+    spawn_mock_rpc(Arc::new(|method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => funded_eth_call(params),
+        _ => Value::Null,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn test_refresh_expired_payload() {
+    use x402_rs::client::refresh_payload;
+    use x402_rs::types::{PaymentPayload, TransferAuthorization};
+
+    let rpc_url = spawn_chain_id_rpc().await;
+    let config = X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let expired = TransferAuthorization {
+        from: "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".to_string(),
+        to: requirements.pay_to.clone(),
+        value: requirements.max_amount_required.clone(),
+        valid_after: "1000".to_string(),
+        valid_before: "1300".to_string(),
+        nonce: format!("0x{}", "00".repeat(32)),
+        signature: format!("0x{}", "00".repeat(65)),
+    };
+    let old_payload = PaymentPayload {
+        x402_version: 1,
+        scheme: "exact".to_string(),
+        network: "8453".to_string(),
+        payload: json!(expired),
+        challenge: None,
+        message_signature: None,
+    };
+
+    let refreshed = refresh_payload(&old_payload, &requirements, &config).await.unwrap();
+    let fresh: TransferAuthorization = serde_json::from_value(refreshed.payload).unwrap();
+
+    assert!(fresh.valid_before.parse::<u64>().unwrap() > 1300);
+    assert_ne!(fresh.nonce, expired.nonce);
+    assert_eq!(fresh.value, expired.value);
+    assert_eq!(fresh.to.to_lowercase(), expired.to.to_lowercase());
+}
+
+#[tokio::test]
+async fn test_verify_detailed_reports_all_failures() {
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::{PaymentPayload, TransferAuthorization};
+
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x2105"),
+        // authorizationState(...) == false
+        "eth_call" => json!(format!("0x{}", "00".repeat(32))),
+        _ => Value::Null,
+    }))
+    .await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let now = x402_rs::utils::current_timestamp();
+    let auth = TransferAuthorization {
+        from: "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".to_string(),
+        to: "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".to_string(),
+        value: "1".to_string(),
+        valid_after: (now - 10).to_string(),
+        valid_before: (now + 290).to_string(),
+        nonce: format!("0x{}", "11".repeat(32)),
+        signature: format!("0x{}", "00".repeat(65)),
+    };
+    let payload = PaymentPayload {
+        x402_version: 1,
+        scheme: "exact".to_string(),
+        network: "8453".to_string(),
+        payload: json!(auth),
+        challenge: None,
+        message_signature: None,
+    };
+
+    let failures = ExactEvm::new()
+        .verify_detailed(&payload, &requirements, &rpc_url)
+        .await
+        .unwrap();
+
+    assert!(failures.iter().any(|f| f.starts_with("Payee mismatch")));
+    assert!(failures.iter().any(|f| f.starts_with("Amount 1 outside")));
+    assert!(!failures.iter().any(|f| f.starts_with("Nonce")));
+}
+
+#[tokio::test]
+async fn test_verify_rejects_asset_without_code() {
+    use x402_rs::errors::X402Error;
+    use x402_rs::facilitator::handle_verify;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::VerificationRequest;
+
+    // The asset is an account with no code, e.g. a mistyped token address
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_getCode" => json!("0x"),
+        "eth_call" => json!(format!("0x{}", "00".repeat(32))),
+        _ => Value::Null,
+    }))
+    .await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let scheme = ExactEvm::new();
+    let payload = scheme
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+
+    let err = scheme.verify(&payload, &requirements, &rpc_url).await.unwrap_err();
+    assert!(matches!(err, X402Error::ConfigError(reason) if reason.contains("is not a contract")));
+    let failures = scheme
+        .verify_detailed(&payload, &requirements, &rpc_url)
+        .await
+        .unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].contains("is not a contract"));
+
+    let config = FacilitatorConfig::new("0xfacilitator", rpc_url.clone());
+    let request = VerificationRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements,
+        actual_amount: None,
+    };
+    let response = handle_verify(request, &config).await.unwrap();
+    assert!(!response.is_valid);
+    assert!(response.invalid_reason.unwrap().contains("is not a contract"));
+}
+
+/// A Base-like mock RPC node that counts the calls it receives.
+async fn spawn_counting_rpc(
+    chain_id: &'static str,
+) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let url = spawn_mock_rpc(Arc::new(move |method, params| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        match method {
+            "eth_chainId" => json!(chain_id),
+            "eth_call" => funded_eth_call(params),
+            _ => Value::Null,
+        }
+    }))
+    .await;
+    (url, calls)
+}
+
+/// Builds an encoded payment header that passes the static checks of `requirements`.
+fn unsigned_payment_header(requirements: &x402_rs::types::PaymentRequirements) -> String {
+    use x402_rs::types::{PaymentPayload, TransferAuthorization};
+
+    let now = x402_rs::utils::current_timestamp();
+    let auth = TransferAuthorization {
+        from: "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".to_string(),
+        to: requirements.pay_to.clone(),
+        value: requirements.max_amount_required.clone(),
+        valid_after: (now - 10).to_string(),
+        valid_before: (now + 290).to_string(),
+        nonce: format!("0x{}", "22".repeat(32)),
+        signature: format!("0x{}", "00".repeat(65)),
+    };
+    encode_payment_header(&PaymentPayload {
+        x402_version: 1,
+        scheme: requirements.scheme.clone(),
+        network: requirements.network.clone(),
+        payload: json!(auth),
+        challenge: None,
+        message_signature: None,
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_facilitator_selects_rpc_per_network() {
+    use std::sync::atomic::Ordering;
+    use x402_rs::facilitator::{handle_settle, handle_verify};
+    use x402_rs::types::{SettlementRequest, VerificationRequest};
+
+    let (base_rpc, base_calls) = spawn_counting_rpc("0x2105").await;
+    let (sepolia_rpc, sepolia_calls) = spawn_counting_rpc("0x14a34").await;
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        base_rpc,
+    );
+    config.add_supported("exact", "84532");
+    config.set_rpc_url("84532", sepolia_rpc);
+
+    let mut base_requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let mut sepolia_requirements = base_requirements.clone();
+    sepolia_requirements.network = "84532".to_string();
+
+    handle_verify(
+        VerificationRequest {
+            payment_header: unsigned_payment_header(&base_requirements),
+            payment_requirements: base_requirements.clone(),
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert!(base_calls.load(Ordering::SeqCst) > 0);
+    assert_eq!(sepolia_calls.load(Ordering::SeqCst), 0);
+
+    let base_before = base_calls.load(Ordering::SeqCst);
+    handle_settle(
+        SettlementRequest {
+            payment_header: unsigned_payment_header(&sepolia_requirements),
+            payment_requirements: sepolia_requirements,
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert!(sepolia_calls.load(Ordering::SeqCst) > 0);
+    assert_eq!(base_calls.load(Ordering::SeqCst), base_before);
+
+    // A network without its own RPC URL is rejected rather than sent to the default
+    // network's chain.
+    let base_before = base_calls.load(Ordering::SeqCst);
+    config.add_supported("exact", "137");
+    base_requirements.network = "137".to_string();
+    let response = handle_verify(
+        VerificationRequest {
+            payment_header: unsigned_payment_header(&base_requirements),
+            payment_requirements: base_requirements,
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert!(!response.is_valid);
+    assert!(response.invalid_reason.unwrap().contains("No RPC URL"));
+    assert_eq!(base_calls.load(Ordering::SeqCst), base_before);
+}
+
+/// A mock node that accepts legacy settlements far enough to record the sender.
+async fn spawn_settlement_rpc(
+    chain_id: &'static str,
+) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+    let senders = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = senders.clone();
+    let url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!(chain_id),
+        "eth_call" => json!(format!("0x{}", "00".repeat(32))),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => {
+            let from = params[0]["from"].as_str().unwrap_or_default();
+            recorded.lock().unwrap().push(from.to_lowercase());
+            json!("0x30000")
+        }
+        _ => Value::Null,
+    }))
+    .await;
+    (url, senders)
+}
+
+#[tokio::test]
+async fn test_settlement_uses_per_network_signer() {
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::SettlementRequest;
+
+    let client_key = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    let (base_rpc, base_senders) = spawn_settlement_rpc("0x2105").await;
+    let (polygon_rpc, polygon_senders) = spawn_settlement_rpc("0x89").await;
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        "",
+    );
+    config.add_supported("exact", "137");
+    config.set_rpc_url("8453", base_rpc.clone());
+    config.set_rpc_url("137", polygon_rpc.clone());
+    config.set_signer_key(
+        "137",
+        "0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a",
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+    config.set_tx_type("137", TxType::Legacy);
+
+    let base_requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let mut polygon_requirements = base_requirements.clone();
+    polygon_requirements.network = "137".to_string();
+
+    for (requirements, rpc_url) in [
+        (base_requirements, base_rpc),
+        (polygon_requirements, polygon_rpc),
+    ] {
+        let payload = ExactEvm::new()
+            .generate_payload(&requirements, client_key, &rpc_url)
+            .await
+            .unwrap();
+        handle_settle(
+            SettlementRequest {
+                payment_header: encode_payment_header(&payload).unwrap(),
+                payment_requirements: requirements,
+                actual_amount: None,
+            },
+            &config,
+        )
+        .await
+        .unwrap();
+    }
+
+    // Default key (hardhat account #0) on Base, account #2 on Polygon
+    assert_eq!(
+        base_senders.lock().unwrap().as_slice(),
+        ["0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"]
+    );
+    assert_eq!(
+        polygon_senders.lock().unwrap().as_slice(),
+        ["0x3c44cdddb6a900fa2b585dd299e03d12fa4293bc"]
+    );
+}
+
+/// A paid endpoint at `/paid` that demands `requirements` until an X-PAYMENT header is sent.
+async fn spawn_paid_api(requirements: x402_rs::types::PaymentRequirements) -> String {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+
+    let app = Router::new().route(
+        "/paid",
+        get(move |headers: HeaderMap| async move {
+            if headers.contains_key(x402_rs::X_PAYMENT_HEADER) {
+                (StatusCode::OK, Json(json!({ "paid": true }))).into_response()
+            } else {
+                let body = PaymentRequiredResponse {
+                    x402_version: 1,
+                    accepts: vec![requirements],
+                    error: None,
+                };
+                (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+            }
+        }),
+    );
+    spawn_app(app).await
+}
+
+#[tokio::test]
+async fn test_client_struct_reuses_chain_id() {
+    use std::sync::atomic::Ordering;
+    use x402_rs::client::X402Client;
+
+    let (rpc_url, rpc_calls) = spawn_counting_rpc("0x2105").await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/paid")
+    .unwrap();
+    let api_url = spawn_paid_api(requirements).await;
+
+    let client = X402Client::new(X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    ));
+
+    for _ in 0..2 {
+        let response = client.get(&format!("{}/paid", api_url)).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+    // One chain id lookup, and a balance check per payment
+    assert_eq!(rpc_calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_cached_verification_invalidated_when_nonce_used() {
+    use x402_rs::facilitator::handle_verify;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::{TransferAuthorization, VerificationRequest};
+
+    let (rpc_url, _) = spawn_settlement_rpc("0x2105").await;
+    let mut config = FacilitatorConfig::new("0xfacilitator", rpc_url.clone());
+    config.enable_verification_cache();
+
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let payload = ExactEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let auth: TransferAuthorization = serde_json::from_value(payload.payload.clone()).unwrap();
+    let request = VerificationRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements,
+        actual_amount: None,
+    };
+
+    assert!(handle_verify(request.clone(), &config).await.unwrap().is_valid);
+    let cache = config.verification_cache.as_ref().unwrap();
+    assert_eq!(cache.len().await, 1);
+
+    config.mark_nonce_used(&auth.from, &auth.nonce).await;
+    assert!(cache.is_empty().await);
+
+    let response = handle_verify(request, &config).await.unwrap();
+    assert!(!response.is_valid);
+    assert_eq!(response.invalid_reason.as_deref(), Some("Nonce already used"));
+}
+
+#[tokio::test]
+async fn test_access_token_skips_second_payment() {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use base64::Engine;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use x402_rs::client::X402Client;
+    use x402_rs::types::{PaymentResponse, SettlementInfo};
+    use x402_rs::{X_PAYMENT_HEADER, X_PAYMENT_RESPONSE_HEADER, X_PAYMENT_TOKEN_HEADER};
+
+    let (rpc_url, _) = spawn_counting_rpc("0x2105").await;
+    let config = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .with_access_tokens("server-secret", 3600);
+
+    let payments = Arc::new(AtomicUsize::new(0));
+    let paid = payments.clone();
+    let app = Router::new().route(
+        "/paid",
+        get(move |headers: HeaderMap| async move {
+            let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+            if header(X_PAYMENT_TOKEN_HEADER).is_some_and(|t| config.has_access(t, "/paid")) {
+                return (StatusCode::OK, Json(json!({ "via": "token" }))).into_response();
+            }
+            if header(X_PAYMENT_HEADER).is_some() {
+                // Settlement itself is out of scope here
+                paid.fetch_add(1, Ordering::SeqCst);
+                let settlement = SettlementInfo {
+                    payer: "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".to_string(),
+                    pay_to: config.pay_to.clone(),
+                    amount: "10000".to_string(),
+                    asset: config.asset.clone(),
+                    network: config.network.clone(),
+                    tx_hash: "0xabc".to_string(),
+                    block_number: None,
+                };
+                let response = PaymentResponse {
+                    tx_hash: settlement.tx_hash.clone(),
+                    settled_at: None,
+                    metadata: None,
+                    access_token: config.issue_access_token("/paid", &settlement),
+                    settlement: Some(settlement),
+                };
+                let encoded = base64::engine::general_purpose::STANDARD
+                    .encode(serde_json::to_vec(&response).unwrap());
+                return (
+                    StatusCode::OK,
+                    [(X_PAYMENT_RESPONSE_HEADER, encoded)],
+                    Json(json!({ "via": "payment" })),
+                )
+                    .into_response();
+            }
+
+            let body = PaymentRequiredResponse {
+                x402_version: 1,
+                accepts: vec![config.to_requirements("/paid").unwrap()],
+                error: None,
+            };
+            (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+        }),
+    );
+    let url = format!("{}/paid", spawn_app(app).await);
+
+    let client = X402Client::new(X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    ));
+
+    let first: Value = client.get(&url).await.unwrap().json().await.unwrap();
+    assert_eq!(first["via"], "payment");
+    assert!(client.access_token(&url).is_some());
+
+    let second: Value = client.get(&url).await.unwrap().json().await.unwrap();
+    assert_eq!(second["via"], "token");
+    assert_eq!(payments.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_fails_fast_when_facilitator_down() {
+    use axum::{http::StatusCode, routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use x402_rs::server::verify_and_settle_payment;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let app = Router::new().route(
+        "/verify",
+        post(move || async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            StatusCode::SERVICE_UNAVAILABLE
+        }),
+    );
+    let facilitator_url = spawn_app(app).await;
+
+    let config = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    )
+    .with_circuit_breaker(2, Duration::from_secs(60));
+
+    for _ in 0..2 {
+        let err = verify_and_settle_payment("header", &config, "/test").await.unwrap_err();
+        assert!(err.to_string().contains("Facilitator verification failed"));
+    }
+
+    let err = verify_and_settle_payment("header", &config, "/test").await.unwrap_err();
+    assert!(err.to_string().contains("facilitator circuit open"));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(feature = "in-process")]
+#[tokio::test]
+async fn test_client_settles_through_in_process_facilitator() {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use x402_rs::client::X402Client;
+    use x402_rs::facilitator::InProcessFacilitator;
+    use x402_rs::schemes::TxType;
+    use x402_rs::server::verify_and_settle_payment;
+
+    let tx_hash = format!("0x{}", "ab".repeat(32));
+    let receipt_hash = tx_hash.clone();
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => funded_eth_call(params),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => json!("0x30000"),
+        "eth_sendRawTransaction" => json!(receipt_hash),
+        "eth_getTransactionReceipt" => json!({
+            "transactionHash": receipt_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "cd".repeat(32)),
+            "blockNumber": "0x10",
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+            "cumulativeGasUsed": "0x30000",
+            "gasUsed": "0x30000",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+        }),
+        _ => Value::Null,
+    }))
+    .await;
+
+    let mut facilitator_config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url.clone(),
+    );
+    facilitator_config.set_tx_type("8453", TxType::Legacy);
+    let facilitator = InProcessFacilitator::new(facilitator_config);
+
+    let config = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "http://127.0.0.1:1",
+    )
+    .with_facilitator(facilitator.clone());
+
+    let app = Router::new().route(
+        "/paid",
+        get(move |headers: HeaderMap| async move {
+            match headers.get(x402_rs::X_PAYMENT_HEADER) {
+                Some(header) => {
+                    let header = header.to_str().unwrap();
+                    match verify_and_settle_payment(header, &config, "/paid").await {
+                        Ok(info) => (StatusCode::OK, Json(json!(info))).into_response(),
+                        Err(e) => (StatusCode::PAYMENT_REQUIRED, e.to_string()).into_response(),
+                    }
+                }
+                None => {
+                    let body = PaymentRequiredResponse {
+                        x402_version: 1,
+                        accepts: vec![config.to_requirements("/paid").unwrap()],
+                        error: None,
+                    };
+                    (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+                }
+            }
+        }),
+    );
+    let url = format!("{}/paid", spawn_app(app).await);
+
+    let client = X402Client::new(X402ClientConfig::new(
+        "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+        rpc_url,
+    ));
+    let response = client.get(&url).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let settlement: x402_rs::SettlementInfo = response.json().await.unwrap();
+    assert_eq!(settlement.tx_hash, tx_hash);
+    assert_eq!(settlement.block_number, Some(16));
+    assert_eq!(
+        facilitator
+            .config()
+            .used_nonces
+            .list_for_payer(&settlement.payer)
+            .await
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_facilitator_rate_limit_retried_after_delay() {
+    use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use x402_rs::server::verify_and_settle_payment;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let app = Router::new()
+        .route(
+            "/verify",
+            post(move || async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    (StatusCode::TOO_MANY_REQUESTS, [("Retry-After", "0")]).into_response()
+                } else {
+                    Json(json!({ "isValid": true })).into_response()
+                }
+            }),
+        )
+        .route(
+            "/settle",
+            post(|| async { Json(json!({ "txHash": "0xabc", "error": null })) }),
+        );
+    let facilitator_url = spawn_app(app).await;
+
+    let config = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    );
+
+    let err = verify_and_settle_payment("header", &config, "/test").await.unwrap_err();
+    assert!(matches!(
+        err,
+        x402_rs::X402Error::RateLimited {
+            retry_after_seconds: Some(0)
+        }
+    ));
+
+    calls.store(0, Ordering::SeqCst);
+    let config = config.with_rate_limit_retry(1, Duration::from_secs(1));
+    let settlement = verify_and_settle_payment("header", &config, "/test").await.unwrap();
+    assert_eq!(settlement.tx_hash, "0xabc");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_paid_request_follows_redirect() {
+    use axum::{
+        http::{header::LOCATION, HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use x402_rs::client::X402Client;
+
+    let (rpc_url, _) = spawn_counting_rpc("0x2105").await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/old")
+    .unwrap();
+    let demand_payment = move || {
+        let body = PaymentRequiredResponse {
+            x402_version: 1,
+            accepts: vec![requirements.clone()],
+            error: None,
+        };
+        (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+    };
+    let demand_again = demand_payment.clone();
+
+    let app = Router::new()
+        .route(
+            "/old",
+            get(move |headers: HeaderMap| async move {
+                if headers.contains_key(x402_rs::X_PAYMENT_HEADER) {
+                    (StatusCode::TEMPORARY_REDIRECT, [(LOCATION, "/new")]).into_response()
+                } else {
+                    demand_payment()
+                }
+            }),
+        )
+        .route(
+            "/new",
+            get(move |headers: HeaderMap| async move {
+                if headers.contains_key(x402_rs::X_PAYMENT_HEADER) {
+                    Json(json!({ "paid": true })).into_response()
+                } else {
+                    demand_again()
+                }
+            }),
+        );
+    let api_url = spawn_app(app).await;
+
+    let client = X402Client::new(X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url.clone(),
+    ));
+    let response = client.get(&format!("{}/old", api_url)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.url().path(), "/new");
+
+    let client = X402Client::new(
+        X402ClientConfig::new(
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            rpc_url,
+        )
+        .with_max_redirects(0),
+    );
+    let err = client.get(&format!("{}/old", api_url)).await.unwrap_err();
+    assert!(err.to_string().contains("Too many redirects"));
+}
+
+#[tokio::test]
+async fn test_paid_request_resigned_once_after_expiry() {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use std::sync::Mutex;
+    use x402_rs::client::X402Client;
+
+    let (rpc_url, _) = spawn_counting_rpc("0x2105").await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/weather")
+    .unwrap();
+
+    // Refuses the first `expired` paid attempts as expired in transit
+    let spawn_api = |expired: usize| {
+        let requirements = requirements.clone();
+        let payments = Arc::new(Mutex::new(Vec::<String>::new()));
+        let seen = payments.clone();
+        let app = Router::new().route(
+            "/weather",
+            get(move |headers: HeaderMap| async move {
+                let refusal = |error: Option<&str>| {
+                    let body = PaymentRequiredResponse {
+                        x402_version: 1,
+                        accepts: vec![requirements.clone()],
+                        error: error.map(String::from),
+                    };
+                    (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+                };
+                let Some(payment) = headers.get(x402_rs::X_PAYMENT_HEADER) else {
+                    return refusal(None);
+                };
+                let mut payments = seen.lock().unwrap();
+                payments.push(payment.to_str().unwrap().to_string());
+                if payments.len() <= expired {
+                    refusal(Some("Authorization expired at 1700000000"))
+                } else {
+                    Json(json!({ "paid": true })).into_response()
+                }
+            }),
+        );
+        async move { (spawn_app(app).await, payments) }
+    };
+    let client = X402Client::new(X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    ));
+
+    let (api_url, payments) = spawn_api(1).await;
+    let response = client.get(&format!("{}/weather", api_url)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let payments = payments.lock().unwrap().clone();
+    assert_eq!(payments.len(), 2);
+    let nonce = |header: &str| {
+        let payload = decode_payment_header(header).unwrap();
+        payload.typed_payload::<x402_rs::types::TransferAuthorization>().unwrap().nonce
+    };
+    assert_ne!(nonce(&payments[0]), nonce(&payments[1]));
+
+    // Expiring again, the refusal is handed back rather than paid for indefinitely
+    let (api_url, payments) = spawn_api(usize::MAX).await;
+    let response = client.get(&format!("{}/weather", api_url)).await.unwrap();
+    assert_eq!(response.status(), 402);
+    let refusal: PaymentRequiredResponse = response.json().await.unwrap();
+    assert!(refusal.is_authorization_expired());
+    assert_eq!(payments.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_server_refusal_resigned_after_expiry() {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::{get, post},
+        Json, Router,
+    };
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use x402_rs::client::X402Client;
+    use x402_rs::server::{create_payment_required_response, verify_and_settle_payment};
+
+    // Facilitator refusing the first payment as expired
+    let verified = Arc::new(Mutex::new(Vec::<String>::new()));
+    let seen = verified.clone();
+    let facilitator = Router::new()
+        .route(
+            "/verify",
+            post(move |Json(request): Json<Value>| async move {
+                let mut verified = seen.lock().unwrap();
+                verified.push(request["paymentHeader"].as_str().unwrap().to_string());
+                if verified.len() == 1 {
+                    Json(json!({
+                        "isValid": false,
+                        "invalid_reason": "Authorization expired at 1700000000",
+                    }))
+                } else {
+                    Json(json!({ "isValid": true }))
+                }
+            }),
+        )
+        .route(
+            "/settle",
+            post(|| async { Json(json!({ "txHash": "0xabc" })) }),
+        );
+    let facilitator_url = spawn_app(facilitator).await;
+
+    let config = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    );
+    let mut configs = HashMap::new();
+    configs.insert("usdc".to_string(), config.clone());
+
+    // Serves the refusal the way the example server does
+    let app = Router::new().route(
+        "/weather",
+        get(move |headers: HeaderMap| async move {
+            let refusal = |error: Option<String>| {
+                let mut body = create_payment_required_response(&configs, "/weather").unwrap();
+                if let Some(error) = error {
+                    body = body.with_error(error);
+                }
+                (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+            };
+            let Some(header) = headers.get(x402_rs::X_PAYMENT_HEADER) else {
+                return refusal(None);
+            };
+            match verify_and_settle_payment(header.to_str().unwrap(), &config, "/weather").await {
+                Ok(info) => Json(json!(info)).into_response(),
+                Err(e) => refusal(Some(e.to_string())),
+            }
+        }),
+    );
+    let api_url = spawn_app(app).await;
+
+    let (rpc_url, _) = spawn_counting_rpc("0x2105").await;
+    let client = X402Client::new(X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    ));
+    let response = client.get(&format!("{}/weather", api_url)).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let verified = verified.lock().unwrap().clone();
+    assert_eq!(verified.len(), 2);
+    assert_ne!(verified[0], verified[1]);
+}
+
+#[tokio::test]
+async fn test_prevalidation_rejects_bad_asset_before_rpc() {
+    use axum::{http::StatusCode, routing::get, Json, Router};
+    use std::sync::atomic::Ordering;
+    use x402_rs::client::X402Client;
+    use x402_rs::X402Error;
+
+    let (rpc_url, calls) = spawn_counting_rpc("0x2105").await;
+    let mut requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/weather")
+    .unwrap();
+    requirements.asset = "0xnot-a-token".to_string();
+
+    let app = Router::new().route(
+        "/weather",
+        get(move || async move {
+            let body = PaymentRequiredResponse {
+                x402_version: 1,
+                accepts: vec![requirements.clone()],
+                error: None,
+            };
+            (StatusCode::PAYMENT_REQUIRED, Json(body))
+        }),
+    );
+    let api_url = spawn_app(app).await;
+    let client = X402Client::new(X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    ));
+
+    let err = client.get(&format!("{}/weather", api_url)).await.unwrap_err();
+    assert!(matches!(err, X402Error::InvalidAddress(_)), "{}", err);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_payer_balance_checked_before_signing() {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        routing::get,
+        Json, Router,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use x402_rs::client::X402Client;
+    use x402_rs::X402Error;
+
+    // The payer holds 2500 of the 10000 required
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => json!(format!("0x{:064x}", 2500)),
+        _ => Value::Null,
+    }))
+    .await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/weather")
+    .unwrap();
+
+    let payments = Arc::new(AtomicUsize::new(0));
+    let counter = payments.clone();
+    let app = Router::new().route(
+        "/weather",
+        get(move |headers: HeaderMap| async move {
+            if headers.contains_key(x402_rs::X_PAYMENT_HEADER) {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            let body = PaymentRequiredResponse {
+                x402_version: 1,
+                accepts: vec![requirements.clone()],
+                error: None,
+            };
+            (StatusCode::PAYMENT_REQUIRED, Json(body))
+        }),
+    );
+    let api_url = spawn_app(app).await;
+    let config = X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+
+    let err = X402Client::new(config.clone())
+        .get(&format!("{}/weather", api_url))
+        .await
+        .unwrap_err();
+    match err {
+        X402Error::InvalidAmount(reason) => {
+            assert_eq!(reason, "Balance of 2500 is 7500 short of the 10000 required")
+        }
+        other => panic!("expected InvalidAmount, got {}", other),
+    }
+    assert_eq!(payments.load(Ordering::SeqCst), 0);
+
+    // Without the check, the payment is signed and sent anyway
+    let response = X402Client::new(config.with_balance_check(false))
+        .get(&format!("{}/weather", api_url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 402);
+    assert_eq!(payments.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_health_flags_low_signer_balance() {
+    use x402_rs::facilitator::{handle_health, FacilitatorConfigBuilder};
+    use x402_rs::types::HealthStatus;
+
+    let balance_rpc = |balance: &'static str| {
+        spawn_mock_rpc(Arc::new(move |method, _| match method {
+            "eth_getBalance" => json!(balance),
+            _ => Value::Null,
+        }))
+    };
+    // 0.0001 ETH on Base, 1 ETH on Polygon
+    let base_rpc = balance_rpc("0x5af3107a4000").await;
+    let polygon_rpc = balance_rpc("0xde0b6b3a7640000").await;
+
+    let config = FacilitatorConfigBuilder::new()
+        .private_key("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .support("exact", "137")
+        .rpc("8453", base_rpc)
+        .rpc("137", polygon_rpc)
+        .build()
+        .unwrap();
+
+    let health = handle_health(&config).await.unwrap();
+    assert_eq!(health.status, HealthStatus::Degraded);
+    assert_eq!(health.networks.len(), 2);
+
+    let base = &health.networks[0];
+    assert_eq!(base.network, "8453");
+    assert_eq!(base.signer, "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    assert_eq!(base.balance.as_deref(), Some("100000000000000"));
+    assert!(base.low_balance);
+    assert!(!health.networks[1].low_balance);
+}
+
+#[tokio::test]
+async fn test_token_without_version_probed_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use x402_rs::facilitator::handle_verify;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::VerificationRequest;
+
+    // `version()` selector; the token answers without return data
+    let probes = Arc::new(AtomicUsize::new(0));
+    let counter = probes.clone();
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => {
+            let input = params[0]["input"].as_str().or(params[0]["data"].as_str());
+            if input.unwrap_or_default().starts_with("0x54fd4d50") {
+                counter.fetch_add(1, Ordering::SeqCst);
+                json!("0x")
+            } else {
+                json!(format!("0x{}", "00".repeat(32)))
+            }
+        }
+        _ => Value::Null,
+    }))
+    .await;
+
+    let config = FacilitatorConfig::new("0xfacilitator", rpc_url.clone());
+    let mut requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    // Leave the domain version to the token
+    requirements.extra = Some(json!({ "name": "USD Coin" }));
+
+    for _ in 0..3 {
+        let payload = ExactEvm::new()
+            .generate_payload(
+                &requirements,
+                "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+                &rpc_url,
+            )
+            .await
+            .unwrap();
+        let request = VerificationRequest {
+            payment_header: encode_payment_header(&payload).unwrap(),
+            payment_requirements: requirements.clone(),
+            actual_amount: None,
+        };
+        // Falls back to the default domain version the client signed with
+        assert!(handle_verify(request, &config).await.unwrap().is_valid);
+    }
+
+    assert_eq!(probes.load(Ordering::SeqCst), 1);
+    assert_eq!(config.token_versions.len().await, 1);
+}
+
+#[tokio::test]
+async fn test_payment_over_plain_http_refused() {
+    use x402_rs::client::X402Client;
+
+    let (rpc_url, _) = spawn_counting_rpc("0x2105").await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/paid")
+    .unwrap();
+    let api_url = spawn_paid_api(requirements).await;
+    let addr: std::net::SocketAddr = api_url.trim_start_matches("http://").parse().unwrap();
+
+    // A non-loopback host name served by the local API
+    let http_client = reqwest::Client::builder()
+        .resolve("api.example.com", addr)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let config = X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    )
+    .with_client(http_client);
+    let url = format!("http://api.example.com:{}/paid", addr.port());
+
+    let err = X402Client::new(config.clone()).get(&url).await.unwrap_err();
+    assert!(matches!(err, x402_rs::X402Error::ConfigError(_)));
+
+    let client = X402Client::new(config.require_https(false));
+    assert_eq!(client.get(&url).await.unwrap().status(), 200);
+}
+
+/// Prices the native currency at 3000 units of a 6-decimal asset.
+struct FixedPrice;
+
+#[async_trait::async_trait]
+impl x402_rs::facilitator::AssetPriceOracle for FixedPrice {
+    async fn native_to_asset(
+        &self,
+        _network: &str,
+        _asset: &str,
+        wei: ethers::types::U256,
+    ) -> x402_rs::Result<ethers::types::U256> {
+        use ethers::types::U256;
+        Ok(wei * 3000u64 * U256::exp10(6) / U256::exp10(18))
+    }
+}
+
+#[tokio::test]
+async fn test_estimate_settlement_cost() {
+    use ethers::types::U256;
+    use x402_rs::facilitator::estimate_settlement_cost;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::SettlementRequest;
+
+    // 100k gas at 1 gwei
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_estimateGas" => json!("0x186a0"),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        _ => Value::Null,
+    }))
+    .await;
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url.clone(),
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let payload = ExactEvm::new()
+        .with_chain_id(8453)
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let request = SettlementRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements,
+        actual_amount: None,
+    };
+
+    let wei = estimate_settlement_cost(&request, &config, None).await.unwrap();
+    assert_eq!(wei, U256::from(100_000_000_000_000u64));
+
+    let usdc = estimate_settlement_cost(&request, &config, Some(&FixedPrice)).await.unwrap();
+    assert_eq!(usdc, U256::from(300_000u64));
+}
+
+#[tokio::test]
+async fn test_settle_rejects_payment_below_gas_cost() {
+    use ethers::types::U256;
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::SettlementRequest;
+
+    // ~196k gas at 1 gwei, about 0.59 USDC at 3000 USDC/ETH
+    let (rpc_url, senders) = spawn_settlement_rpc("0x2105").await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let settle = |config: FacilitatorConfig| {
+        let (requirements, rpc_url) = (requirements.clone(), rpc_url.clone());
+        async move {
+            let payload = ExactEvm::new()
+                .generate_payload(
+                    &requirements,
+                    "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+                    &rpc_url,
+                )
+                .await
+                .unwrap();
+            let request = SettlementRequest {
+                payment_header: encode_payment_header(&payload).unwrap(),
+                payment_requirements: requirements,
+                actual_amount: None,
+            };
+            handle_settle(request, &config).await.unwrap()
+        }
+    };
+    let new_config = || {
+        let mut config = FacilitatorConfig::new(
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            rpc_url.clone(),
+        );
+        config.set_tx_type("8453", TxType::Legacy);
+        config
+    };
+
+    let mut config = new_config();
+    config.set_price_oracle(FixedPrice);
+    let response = settle(config).await;
+    assert_eq!(
+        response.invalid_reason.as_deref(),
+        Some("Payment of 10000 is below the estimated settlement cost of 589824")
+    );
+    assert_eq!(response.error, response.invalid_reason);
+
+    let mut config = new_config();
+    config.set_min_profitable_amount(U256::from(20_000u64));
+    let response = settle(config).await;
+    assert!(response
+        .invalid_reason
+        .is_some_and(|reason| reason.contains("minimum profitable amount of 20000")));
+
+    // Only the oracle's estimate reached the node; nothing was sent
+    assert_eq!(senders.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_message_signature_rejects_tampered_network() {
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::utils::sign_payment_message;
+
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => json!(format!("0x{}", "00".repeat(32))),
+        _ => Value::Null,
+    }))
+    .await;
+    let mut requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .with_message_signature()
+    .to_requirements("/test")
+    .unwrap();
+
+    let key = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    let scheme = ExactEvm::new();
+    let mut payload = scheme.generate_payload(&requirements, key, &rpc_url).await.unwrap();
+    assert!(!scheme.verify(&payload, &requirements, &rpc_url).await.unwrap());
+
+    payload.message_signature = Some(sign_payment_message(&payload, key).unwrap());
+    assert!(scheme.verify(&payload, &requirements, &rpc_url).await.unwrap());
+
+    // The transfer signature doesn't cover the network label, the message signature does
+    payload.network = "84532".to_string();
+    requirements.network = "84532".to_string();
+    assert!(!scheme.verify(&payload, &requirements, &rpc_url).await.unwrap());
+    let failures = scheme
+        .verify_detailed(&payload, &requirements, &rpc_url)
+        .await
+        .unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].starts_with("Message signature recovers to"));
+}
+
+#[tokio::test]
+async fn test_payer_rate_limit_is_per_payer() {
+    use x402_rs::facilitator::{handle_settle, handle_verify};
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::{SettlementRequest, VerificationRequest};
+    use x402_rs::X402Error;
+
+    let (rpc_url, _) = spawn_settlement_rpc("0x2105").await;
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url.clone(),
+    );
+    config.set_payer_rate_limit(2, 0.01);
+
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let signed_by = |private_key: &'static str| {
+        let requirements = requirements.clone();
+        let rpc_url = rpc_url.clone();
+        async move {
+            let payload = ExactEvm::new()
+                .generate_payload(&requirements, private_key, &rpc_url)
+                .await
+                .unwrap();
+            encode_payment_header(&payload).unwrap()
+        }
+    };
+    let spammer_key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    let other_key = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    let verify = |payment_header: String| {
+        let request = VerificationRequest {
+            payment_header,
+            payment_requirements: requirements.clone(),
+            actual_amount: None,
+        };
+        handle_verify(request, &config)
+    };
+
+    // Headers claiming the other payer without their signature don't touch their bucket
+    for _ in 0..3 {
+        let mut forged = decode_payment_header(&signed_by(spammer_key).await).unwrap();
+        forged.payload["from"] = json!("0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+        let forged = encode_payment_header(&forged).unwrap();
+        assert!(!verify(forged).await.unwrap().is_valid);
+    }
+
+    assert!(verify(signed_by(spammer_key).await).await.unwrap().is_valid);
+    assert!(verify(signed_by(spammer_key).await).await.unwrap().is_valid);
+    assert!(matches!(
+        verify(signed_by(spammer_key).await).await,
+        Err(X402Error::RateLimited {
+            retry_after_seconds: Some(seconds)
+        }) if seconds > 0
+    ));
+    let settle = SettlementRequest {
+        payment_header: signed_by(spammer_key).await,
+        payment_requirements: requirements.clone(),
+        actual_amount: None,
+    };
+    assert!(matches!(
+        handle_settle(settle, &config).await,
+        Err(X402Error::RateLimited { .. })
+    ));
+
+    // Payers are limited independently
+    assert!(verify(signed_by(other_key).await).await.unwrap().is_valid);
+    assert!(verify(signed_by(other_key).await).await.unwrap().is_valid);
+}
+
+#[tokio::test]
+async fn test_verify_accepts_sign_typed_data_v4_signature() {
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::{PaymentPayload, PaymentRequirements, TransferAuthorization};
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/sign_typed_data_v4.json");
+    let vector: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    let (domain, message) = (&vector["request"]["domain"], &vector["request"]["message"]);
+    let field = |key: &str| message[key].as_str().unwrap().to_string();
+
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => json!(format!("0x{}", "00".repeat(32))),
+        _ => Value::Null,
+    }))
+    .await;
+    let requirements: PaymentRequirements = serde_json::from_value(json!({
+        "scheme": "exact",
+        "network": "8453",
+        "maxAmountRequired": field("value"),
+        "resource": "/test",
+        // Wallets checksum addresses; requirements may not
+        "payTo": field("to").to_lowercase(),
+        "maxTimeoutSeconds": 3600,
+        "asset": domain["verifyingContract"],
+        "extra": { "name": domain["name"], "version": domain["version"] }
+    }))
+    .unwrap();
+    let auth = TransferAuthorization {
+        from: field("from"),
+        to: field("to"),
+        value: field("value"),
+        valid_after: field("validAfter"),
+        valid_before: field("validBefore"),
+        nonce: field("nonce"),
+        signature: vector["signature"].as_str().unwrap().to_string(),
+    };
+    let payload = PaymentPayload {
+        x402_version: 1,
+        scheme: "exact".to_string(),
+        network: "8453".to_string(),
+        payload: json!(auth),
+        challenge: None,
+        message_signature: None,
+    };
+
+    // The captured authorization has long expired, but its signature still recovers
+    let failures = ExactEvm::new()
+        .verify_detailed(&payload, &requirements, &rpc_url)
+        .await
+        .unwrap();
+    assert_eq!(failures, vec![format!("Authorization expired at {}", field("validBefore"))]);
+}
+
+#[tokio::test]
+async fn test_verify_only_facilitator_refuses_settlement() {
+    use x402_rs::facilitator::{handle_settle, handle_verify, FacilitatorConfigBuilder};
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::{SettlementRequest, VerificationRequest};
+
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => json!(format!("0x{}", "00".repeat(32))),
+        _ => Value::Null,
+    }))
+    .await;
+    assert!(FacilitatorConfigBuilder::new().rpc_url(&rpc_url).build().is_err());
+    let config = FacilitatorConfigBuilder::new()
+        .rpc_url(&rpc_url)
+        .verify_only()
+        .build()
+        .unwrap();
+    assert!(config.private_key.is_empty());
+
+    let supported = handle_supported(&config).await.unwrap();
+    assert!(supported.supported.iter().all(|kind| kind.verify_only));
+    assert_eq!(serde_json::to_value(&supported).unwrap()["supported"][0]["verifyOnly"], true);
+
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let payload = ExactEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let payment_header = encode_payment_header(&payload).unwrap();
+
+    let verification = handle_verify(
+        VerificationRequest {
+            payment_header: payment_header.clone(),
+            payment_requirements: requirements.clone(),
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert!(verification.is_valid, "{:?}", verification.invalid_reason);
+
+    let settlement = handle_settle(
+        SettlementRequest {
+            payment_header,
+            payment_requirements: requirements,
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert!(settlement.tx_hash.is_empty());
+    assert!(settlement.invalid_reason.is_none());
+    assert!(settlement
+        .error
+        .is_some_and(|error| error.starts_with("Settlement not supported")));
+}
+
+#[tokio::test]
+async fn test_chain_id_override_on_fork() {
+    use x402_rs::facilitator::handle_verify;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::VerificationRequest;
+
+    // A local fork of Base reports the Hardhat chain id
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x7a69"),
+        "eth_call" => json!(format!("0x{}", "00".repeat(32))),
+        _ => Value::Null,
+    }))
+    .await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let scheme = ExactEvm::new().with_chain_id(8453);
+    let payload = scheme
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+
+    assert!(scheme.verify(&payload, &requirements, &rpc_url).await.unwrap());
+    assert!(!ExactEvm::new().verify(&payload, &requirements, &rpc_url).await.unwrap());
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    config.set_chain_id_override("8453", 8453);
+    let request = VerificationRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements,
+        actual_amount: None,
+    };
+    let response = handle_verify(request, &config).await.unwrap();
+    assert!(response.is_valid, "{:?}", response.invalid_reason);
+}
+
+#[tokio::test]
+async fn test_client_journal_survives_restart() {
+    use ethers::types::U256;
+    use x402_rs::client::X402Client;
+    use x402_rs::journal::{AuthorizationJournal, FileJournal};
+
+    let rpc_url = spawn_chain_id_rpc().await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/paid")
+    .unwrap();
+    let asset = requirements.asset.clone();
+    let api_url = spawn_paid_api(requirements).await;
+    let path = std::env::temp_dir()
+        .join(format!("x402-client-journal-{}.jsonl", std::process::id()));
+
+    let client = X402Client::new(
+        X402ClientConfig::new(
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            rpc_url,
+        )
+        .with_journal(FileJournal::open(&path).unwrap()),
+    );
+    for _ in 0..2 {
+        let response = client.get(&format!("{}/paid", api_url)).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+    drop(client);
+
+    let journal = FileJournal::open(&path).unwrap();
+    assert_eq!(journal.entries().len(), 2);
+    assert_eq!(journal.total_spent("8453", &asset), U256::from(20_000u64));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_spend_cap_blocks_payment_past_budget() {
+    use ethers::types::U256;
+    use x402_rs::client::X402Client;
+    use x402_rs::X402Error;
+
+    let rpc_url = spawn_chain_id_rpc().await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/paid")
+    .unwrap();
+    let asset = requirements.asset.clone();
+    let api_url = spawn_paid_api(requirements).await;
+
+    // Room for two 10000-unit payments
+    let client = X402Client::new(
+        X402ClientConfig::new(
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            rpc_url,
+        )
+        .with_spend_cap(&asset, U256::from(25_000u64)),
+    );
+    for _ in 0..2 {
+        let response = client.get(&format!("{}/paid", api_url)).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    let err = client.get(&format!("{}/paid", api_url)).await.unwrap_err();
+    assert!(matches!(
+        err,
+        X402Error::PaymentExceedsBudget { remaining } if remaining == U256::from(5_000u64)
+    ));
+    assert_eq!(client.total_spent("8453", &asset), U256::from(20_000u64));
+}
+
+#[tokio::test]
+async fn test_spend_cap_holds_for_concurrent_payments() {
+    use ethers::types::U256;
+    use x402_rs::client::X402Client;
+    use x402_rs::X402Error;
+
+    let rpc_url = spawn_chain_id_rpc().await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/paid")
+    .unwrap();
+    let asset = requirements.asset.clone();
+    let api_url = spawn_paid_api(requirements).await;
+
+    // Room for one 10000-unit payment, raced by two requests
+    let client = X402Client::new(
+        X402ClientConfig::new(
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            rpc_url,
+        )
+        .with_spend_cap(&asset, U256::from(15_000u64)),
+    );
+    let url = format!("{}/paid", api_url);
+    let (first, second) = tokio::join!(client.get(&url), client.get(&url));
+
+    let results = [first, second];
+    assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Err(X402Error::PaymentExceedsBudget { .. }))));
+    assert_eq!(client.total_spent("8453", &asset), U256::from(10_000u64));
+}
+
+/// A USDC `Transfer` event moving `value` units from `from` to `to`.
+fn usdc_transfer_log(from: &str, to: &str, value: u64) -> Value {
+    use ethers::types::H256;
+    use ethers::utils::keccak256;
+
+    let topic = |address: &str| format!("0x{:0>64}", address.trim_start_matches("0x"));
+    json!({
+        "address": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+        "topics": [
+            format!("{:?}", H256::from(keccak256("Transfer(address,address,uint256)"))),
+            topic(from),
+            topic(to),
+        ],
+        "data": format!("0x{:064x}", value),
+    })
+}
+
+/// Spawns a settlement RPC whose receipt shows the payee credited `credited` units, and
+/// the rest of a 10000-unit payment taken as a fee.
+async fn spawn_transfer_log_rpc(credited: u64) -> String {
+    let tx_hash = format!("0x{}", "ab".repeat(32));
+    let payer = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8";
+    let mut logs = vec![usdc_transfer_log(
+        payer,
+        "0x742d35cc6634c0532925a3b844bc9e7595f0bebb",
+        credited,
+    )];
+    if credited < 10_000 {
+        let fee_collector = "0x3c44cdddb6a900fa2b585dd299e03d12fa4293bc";
+        logs.push(usdc_transfer_log(payer, fee_collector, 10_000 - credited));
+    }
+    spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => funded_eth_call(params),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => json!("0x30000"),
+        "eth_sendRawTransaction" => json!(tx_hash),
+        "eth_getTransactionReceipt" => json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "cd".repeat(32)),
+            "blockNumber": "0x10",
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+            "cumulativeGasUsed": "0x30000",
+            "gasUsed": "0x30000",
+            "contractAddress": null,
+            "logs": logs.clone(),
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+        }),
+        _ => Value::Null,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn test_payee_balance_check_after_settlement() {
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::SettlementRequest;
+
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let settle = |credited: u64| {
+        let requirements = requirements.clone();
+        async move {
+            let rpc_url = spawn_transfer_log_rpc(credited).await;
+            let payload = ExactEvm::new()
+                .generate_payload(
+                    &requirements,
+                    "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+                    &rpc_url,
+                )
+                .await
+                .unwrap();
+            let mut config = FacilitatorConfig::new(
+                "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                rpc_url,
+            );
+            config.set_tx_type("8453", TxType::Legacy);
+            config.enable_payee_balance_check();
+            let request = SettlementRequest {
+                payment_header: encode_payment_header(&payload).unwrap(),
+                payment_requirements: requirements,
+                actual_amount: None,
+            };
+            handle_settle(request, &config).await.unwrap()
+        }
+    };
+
+    let response = settle(10_000).await;
+    assert!(response.error.is_none(), "{:?}", response.error);
+    assert_eq!(response.block_number, Some(16));
+
+    // A fee-on-transfer token delivering 1% less than authorized
+    let response = settle(9_900).await;
+    let error = response.error.unwrap();
+    assert!(error.contains("received 9900 instead of 10000"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_settle_waits_for_valid_after_within_skew() {
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::{SettlementRequest, TransferAuthorization};
+    use x402_rs::utils::current_timestamp;
+
+    let tx_hash = format!("0x{}", "ab".repeat(32));
+    let logs = vec![usdc_transfer_log(
+        "0x70997970c51812dc3a010c7d01b50e0d17dc79c8",
+        "0x742d35cc6634c0532925a3b844bc9e7595f0bebb",
+        10_000,
+    )];
+    let sent_at = Arc::new(std::sync::Mutex::new(None));
+    let recorded = sent_at.clone();
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => funded_eth_call(params),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => json!("0x30000"),
+        "eth_sendRawTransaction" => {
+            *recorded.lock().unwrap() = Some(current_timestamp());
+            json!(tx_hash)
+        }
+        "eth_getTransactionReceipt" => json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "cd".repeat(32)),
+            "blockNumber": "0x10",
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+            "cumulativeGasUsed": "0x30000",
+            "gasUsed": "0x30000",
+            "contractAddress": null,
+            "logs": logs.clone(),
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+        }),
+        _ => Value::Null,
+    }))
+    .await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    // A payer whose clock runs two seconds ahead
+    let ahead = current_timestamp() + 2;
+    let payload = ExactEvm::new()
+        .with_clock(move || ahead)
+        .with_valid_after_offset(std::time::Duration::ZERO)
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let auth: TransferAuthorization = payload.typed_payload().unwrap();
+    let valid_after: u64 = auth.valid_after.parse().unwrap();
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+    config.set_valid_after_skew(std::time::Duration::from_secs(30));
+    let request = SettlementRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements,
+        actual_amount: None,
+    };
+
+    // Accepted within the skew, but only submitted once the token would take it
+    let response = handle_settle(request, &config).await.unwrap();
+    assert!(response.error.is_none(), "{:?}", response.error);
+    assert!(response.invalid_reason.is_none(), "{:?}", response.invalid_reason);
+    assert!(sent_at.lock().unwrap().unwrap() > valid_after);
+}
+
+#[tokio::test]
+async fn test_settle_timeout_bounds_confirmation_wait() {
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::SettlementRequest;
+
+    // A node accepting the settlement transaction but never mining it
+    let broadcast = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let sent = broadcast.clone();
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => {
+            let data = params[0]["data"].as_str().or(params[0]["input"].as_str());
+            if data.unwrap_or_default().starts_with("0x70a08231") {
+                json!(format!("0x{:064x}", 1_000_000))
+            } else {
+                json!(format!("0x{}", "00".repeat(32)))
+            }
+        }
+        "eth_getCode" => json!("0x6080"),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => match sent.load(std::sync::atomic::Ordering::SeqCst) {
+            true => json!("0x1"),
+            false => json!("0x0"),
+        },
+        "eth_estimateGas" => json!("0x30000"),
+        "eth_sendRawTransaction" => {
+            sent.store(true, std::sync::atomic::Ordering::SeqCst);
+            json!(format!("0x{}", "ab".repeat(32)))
+        }
+        _ => Value::Null,
+    }))
+    .await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let payload = ExactEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+
+    // The authorization stays valid for minutes; the facilitator gives up after a second
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+    config.set_settle_timeout(1);
+    let request = SettlementRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements,
+        actual_amount: None,
+    };
+
+    let started = std::time::Instant::now();
+    let err = handle_settle(request, &config).await.unwrap_err();
+    assert!(matches!(err, x402_rs::X402Error::TimeoutExceeded), "{:?}", err);
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+
+    // The transaction went out, so the authorization stays spent
+    assert!(broadcast.load(std::sync::atomic::Ordering::SeqCst));
+    let auth: x402_rs::types::TransferAuthorization = payload.typed_payload().unwrap();
+    assert!(config.used_nonces.contains(&auth.from, &auth.nonce).await);
+}
+
+#[tokio::test]
+async fn test_settle_timeout_before_broadcast_releases_nonce() {
+    use axum::{routing::post, Json, Router};
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::{SettlementRequest, TransferAuthorization};
+    use x402_rs::X402Error;
+
+    // A node stalling on gas estimation, so nothing is ever broadcast
+    let app = Router::new().route(
+        "/",
+        post(|Json(request): Json<Value>| async move {
+            let result = match request["method"].as_str().unwrap_or_default() {
+                "eth_chainId" => json!("0x2105"),
+                "eth_call" => funded_eth_call(&request["params"]),
+                "eth_getCode" => json!("0x6080"),
+                "eth_gasPrice" => json!("0x3b9aca00"),
+                "eth_getTransactionCount" => json!("0x0"),
+                "eth_estimateGas" => {
+                    tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+                    json!("0x30000")
+                }
+                _ => Value::Null,
+            };
+            Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+        }),
+    );
+    let rpc_url = spawn_app(app).await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let payload = ExactEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+    config.set_settle_timeout(1);
+    let request = SettlementRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements,
+        actual_amount: None,
+    };
+
+    let err = handle_settle(request, &config).await.unwrap_err();
+    assert!(matches!(err, X402Error::TimeoutExceeded), "{:?}", err);
+
+    // The authorization never left the facilitator, so it can be settled again
+    let auth: TransferAuthorization = payload.typed_payload().unwrap();
+    assert!(!config.used_nonces.contains(&auth.from, &auth.nonce).await);
+}
+
+#[tokio::test]
+async fn test_settle_rejects_header_differing_from_verified() {
+    use base64::Engine;
+    use x402_rs::facilitator::{handle_settle, handle_verify};
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::{SettlementRequest, TransferAuthorization, VerificationRequest};
+
+    let (rpc_url, _) = spawn_settlement_rpc("0x2105").await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url.clone(),
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+
+    let payload = ExactEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let verified = encode_payment_header(&payload).unwrap();
+    // The same payload, differing only in insignificant whitespace
+    let tweaked = base64::engine::general_purpose::STANDARD
+        .encode(serde_json::to_string_pretty(&payload).unwrap());
+    assert_ne!(verified, tweaked);
+
+    let verification = handle_verify(
+        VerificationRequest {
+            payment_header: verified.clone(),
+            payment_requirements: requirements.clone(),
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert!(verification.is_valid);
+
+    let settle = |payment_header: String| {
+        handle_settle(
+            SettlementRequest {
+                payment_header,
+                payment_requirements: requirements.clone(),
+                actual_amount: None,
+            },
+            &config,
+        )
+    };
+    let response = settle(tweaked).await.unwrap();
+    assert_eq!(
+        response.invalid_reason.as_deref(),
+        Some("Payment header differs from the verified header")
+    );
+    let auth = payload.typed_payload::<TransferAuthorization>().unwrap();
+    assert!(!config.used_nonces.contains(&auth.from, &auth.nonce).await);
+
+    // The verified header gets past the binding; this mock node can't send it
+    let response = settle(verified).await.unwrap();
+    assert!(response.invalid_reason.is_none());
+}
+
+/// Vouches for a fixed set of payers holding `"eas:kyc"`.
+struct KnownHolders(Vec<&'static str>);
+
+#[async_trait::async_trait]
+impl x402_rs::facilitator::AttestationVerifier for KnownHolders {
+    async fn has_attestation(
+        &self,
+        _network: &str,
+        payer: &str,
+        attestation: &str,
+    ) -> x402_rs::Result<bool> {
+        Ok(attestation == "eas:kyc"
+            && self.0.iter().any(|holder| holder.eq_ignore_ascii_case(payer)))
+    }
+}
+
+#[tokio::test]
+async fn test_verify_requires_payer_attestation() {
+    use x402_rs::facilitator::handle_verify;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::VerificationRequest;
+
+    let rpc_url = spawn_mock_rpc(Arc::new(|method, _| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => json!(format!("0x{}", "00".repeat(32))),
+        _ => Value::Null,
+    }))
+    .await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .with_required_attestation("eas:kyc")
+    .to_requirements("/test")
+    .unwrap();
+    assert_eq!(requirements.extra.as_ref().unwrap()["requiredAttestation"], "eas:kyc");
+
+    // Hardhat account #1
+    let payload = ExactEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let verify = |config: FacilitatorConfig| {
+        let request = VerificationRequest {
+            payment_header: encode_payment_header(&payload).unwrap(),
+            payment_requirements: requirements.clone(),
+            actual_amount: None,
+        };
+        async move { handle_verify(request, &config).await.unwrap() }
+    };
+    let new_config = || FacilitatorConfig::new("", rpc_url.clone());
+
+    let response = verify(new_config()).await;
+    assert!(!response.is_valid);
+    assert!(response.invalid_reason.unwrap().contains("no attestation verifier"));
+
+    let mut config = new_config();
+    config.set_attestation_verifier(KnownHolders(vec![
+        "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+    ]));
+    let response = verify(config).await;
+    assert!(response.is_valid, "{:?}", response.invalid_reason);
+
+    let mut config = new_config();
+    config.set_attestation_verifier(KnownHolders(vec![
+        "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC",
+    ]));
+    let response = verify(config).await;
+    assert!(!response.is_valid);
+    assert_eq!(
+        response.invalid_reason.as_deref(),
+        Some("Payer 0x70997970c51812dc3a010c7d01b50e0d17dc79c8 lacks attestation eas:kyc")
+    );
+}
+
+#[tokio::test]
+async fn test_cached_requirements_skip_unpaid_request() {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+    use x402_rs::client::X402Client;
+    use x402_rs::types::TransferAuthorization;
+    use x402_rs::X_PAYMENT_HEADER;
+
+    let (rpc_url, _) = spawn_counting_rpc("0x2105").await;
+    let price = |usd| {
+        x402_rs::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            usd,
+            "Test",
+            "https://facilitator.test",
+        )
+        .to_requirements("/paid")
+        .unwrap()
+    };
+    let (cheap, dear) = (price(0.01), price(0.02));
+
+    let raised = Arc::new(AtomicBool::new(false));
+    let unpaid = Arc::new(AtomicUsize::new(0));
+    let rejected = Arc::new(AtomicUsize::new(0));
+    let app = {
+        let (raised, unpaid, rejected) = (raised.clone(), unpaid.clone(), rejected.clone());
+        Router::new().route(
+            "/paid",
+            get(move |headers: HeaderMap| async move {
+                let current = if raised.load(Ordering::SeqCst) { dear } else { cheap };
+                let payment = headers
+                    .get(X_PAYMENT_HEADER)
+                    .map(|v| decode_payment_header(v.to_str().unwrap()).unwrap());
+                match payment {
+                    Some(payload) => {
+                        let auth = payload.typed_payload::<TransferAuthorization>().unwrap();
+                        if auth.value == current.max_amount_required {
+                            return (StatusCode::OK, Json(json!({ "paid": auth.value })))
+                                .into_response();
+                        }
+                        rejected.fetch_add(1, Ordering::SeqCst);
+                    }
+                    None => {
+                        unpaid.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+                let body = PaymentRequiredResponse {
+                    x402_version: 1,
+                    accepts: vec![current],
+                    error: None,
+                };
+                (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+            }),
+        )
+    };
+    let url = format!("{}/paid", spawn_app(app).await);
+
+    let client = X402Client::new(
+        X402ClientConfig::new(
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            rpc_url,
+        )
+        .with_requirements_cache(Duration::from_secs(60)),
+    );
+    let get = || async { client.get(&url).await.unwrap().json::<Value>().await.unwrap() };
+
+    assert_eq!(get().await["paid"], "10000");
+    assert_eq!(unpaid.load(Ordering::SeqCst), 1);
+
+    // Within the TTL the client pays with its first request
+    assert_eq!(get().await["paid"], "10000");
+    assert_eq!(unpaid.load(Ordering::SeqCst), 1);
+
+    // A 402 answering the cached payment replaces the stale requirements
+    raised.store(true, Ordering::SeqCst);
+    assert_eq!(get().await["paid"], "20000");
+    assert_eq!(get().await["paid"], "20000");
+    assert_eq!(rejected.load(Ordering::SeqCst), 1);
+    assert_eq!(unpaid.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_settle_batch_reports_each_item() {
+    use x402_rs::facilitator::handle_settle_batch;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::SettlementRequest;
+
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let rpc_url = spawn_transfer_log_rpc(10_000).await;
+
+    let mut requests = Vec::new();
+    for _ in 0..3 {
+        let payload = ExactEvm::new()
+            .generate_payload(
+                &requirements,
+                "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+                &rpc_url,
+            )
+            .await
+            .unwrap();
+        requests.push(SettlementRequest {
+            payment_header: encode_payment_header(&payload).unwrap(),
+            payment_requirements: requirements.clone(),
+            actual_amount: None,
+        });
+    }
+
+    // The middle authorization expired before it reached the facilitator
+    let mut expired = decode_payment_header(&requests[1].payment_header).unwrap();
+    let now = x402_rs::utils::current_timestamp();
+    expired.payload["validAfter"] = json!((now - 120).to_string());
+    expired.payload["validBefore"] = json!((now - 60).to_string());
+    requests[1].payment_header = encode_payment_header(&expired).unwrap();
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+
+    let responses = handle_settle_batch(requests, &config).await;
+    assert_eq!(responses.len(), 3);
+    for i in [0, 2] {
+        assert!(responses[i].error.is_none(), "{:?}", responses[i].error);
+        assert!(responses[i].invalid_reason.is_none(), "{:?}", responses[i].invalid_reason);
+        assert_eq!(responses[i].block_number, Some(16));
+    }
+    assert!(responses[1].tx_hash.is_empty());
+    assert!(responses[1].invalid_reason.is_some());
+}
+
+#[tokio::test]
+async fn test_token_version_refreshed_after_invalidation() {
+    use ethers::abi::{encode, Token};
+    use ethers::types::{Address, U256};
+    use x402_rs::facilitator::handle_verify;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::types::VerificationRequest;
+
+    // `version()` answers with whatever the token currently reports
+    let version = Arc::new(std::sync::Mutex::new("2".to_string()));
+    let current = version.clone();
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => {
+            let input = params[0]["input"].as_str().or(params[0]["data"].as_str());
+            if input.unwrap_or_default().starts_with("0x54fd4d50") {
+                let version = current.lock().unwrap().clone();
+                json!(format!("0x{}", hex::encode(encode(&[Token::String(version)]))))
+            } else {
+                json!(format!("0x{}", "00".repeat(32)))
+            }
+        }
+        _ => Value::Null,
+    }))
+    .await;
+
+    let config = FacilitatorConfig::new("0xfacilitator", rpc_url.clone());
+    let mut requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    // Leave the domain version to the token
+    requirements.extra = Some(json!({ "name": "USD Coin" }));
+
+    let verify = |signed_version: &'static str| {
+        let (config, requirements, rpc_url) = (&config, requirements.clone(), rpc_url.clone());
+        async move {
+            let mut signed = requirements.clone();
+            signed.extra = Some(json!({ "name": "USD Coin", "version": signed_version }));
+            let payload = ExactEvm::new()
+                .generate_payload(
+                    &signed,
+                    "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+                    &rpc_url,
+                )
+                .await
+                .unwrap();
+            let request = VerificationRequest {
+                payment_header: encode_payment_header(&payload).unwrap(),
+                payment_requirements: requirements,
+                actual_amount: None,
+            };
+            handle_verify(request, config).await.unwrap().is_valid
+        }
+    };
+
+    assert!(verify("2").await);
+
+    // The token upgrades; the cached domain still says version 2
+    *version.lock().unwrap() = "3".to_string();
+    assert!(!verify("3").await);
+
+    let asset: Address = requirements.asset.parse().unwrap();
+    config.token_versions.invalidate(U256::from(8453), asset).await;
+    assert!(verify("3").await);
+    assert!(!verify("2").await);
+}
+
+#[tokio::test]
+async fn test_paid_retry_replays_body_verbatim() {
+    use axum::{
+        body::Bytes,
+        http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::post,
+        Json, Router,
+    };
+    use x402_rs::client::{RequestBody, X402Client};
+    use x402_rs::X_PAYMENT_HEADER;
+
+    let (rpc_url, _) = spawn_counting_rpc("0x2105").await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/upload")
+    .unwrap();
+
+    let app = Router::new().route(
+        "/upload",
+        post(move |headers: HeaderMap, body: Bytes| async move {
+            if !headers.contains_key(X_PAYMENT_HEADER) {
+                let body = PaymentRequiredResponse {
+                    x402_version: 1,
+                    accepts: vec![requirements],
+                    error: None,
+                };
+                return (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response();
+            }
+            let content_type = headers.get(CONTENT_TYPE).map(|v| v.to_str().unwrap().to_string());
+            Json(json!({
+                "contentType": content_type,
+                "body": String::from_utf8(body.to_vec()).unwrap(),
+            }))
+            .into_response()
+        }),
+    );
+    let url = format!("{}/upload", spawn_app(app).await);
+
+    let client = X402Client::new(X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    ));
+    let text = "line one\nline two, not JSON\n";
+    let response = client
+        .request_with_body(
+            reqwest::Method::POST,
+            &url,
+            Some(RequestBody::new("text/plain; charset=utf-8", text)),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let echoed: Value = response.json().await.unwrap();
+    assert_eq!(echoed["contentType"], "text/plain; charset=utf-8");
+    assert_eq!(echoed["body"], text);
+}
+
+#[tokio::test]
+async fn test_facilitator_rejects_unsigned_calls() {
+    use axum::{
+        body::Bytes,
+        http::{HeaderMap, Method, StatusCode, Uri},
+        response::IntoResponse,
+        routing::post,
+        Json, Router,
+    };
+    use x402_rs::auth::RequestSigner;
+    use x402_rs::facilitator_client::FacilitatorClient;
+
+    let mut config = FacilitatorConfig::new("0xfacilitator", "https://rpc.url");
+    config.add_api_key("server-1", "shared secret");
+    let authenticator = config.authenticator.clone().unwrap();
+
+    let app = Router::new().route(
+        "/verify",
+        post(
+            move |method: Method, uri: Uri, headers: HeaderMap, body: Bytes| async move {
+                match authenticator.verify(method.as_str(), uri.path(), &headers, &body) {
+                    Ok(()) => Json(json!({ "isValid": true })).into_response(),
+                    Err(e) => (StatusCode::UNAUTHORIZED, e.to_string()).into_response(),
+                }
+            },
+        ),
+    );
+    let facilitator_url = spawn_app(app).await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let verify = |signer: Option<RequestSigner>| {
+        let client = FacilitatorClient::new(&facilitator_url).unwrap();
+        let client = match signer {
+            Some(signer) => client.with_request_signer(signer),
+            None => client,
+        };
+        let requirements = requirements.clone();
+        async move { client.verify("header", &requirements, "req-1").await }
+    };
+
+    let signed = verify(Some(RequestSigner::new("server-1", "shared secret"))).await;
+    assert!(signed.unwrap().is_valid);
+
+    assert!(verify(Some(RequestSigner::new("server-1", "wrong secret"))).await.is_err());
+    assert!(verify(Some(RequestSigner::new("server-2", "shared secret"))).await.is_err());
+    assert!(verify(None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_duplicate_payment_headers_rejected() {
+    use axum::{http::HeaderMap, http::StatusCode, response::IntoResponse, routing::get, Router};
+    use x402_rs::server::payment_header;
+    use x402_rs::X_PAYMENT_HEADER;
+
+    let app = Router::new().route(
+        "/weather",
+        get(|headers: HeaderMap| async move {
+            match payment_header(&headers) {
+                Ok(Some(header)) => header.to_string().into_response(),
+                Ok(None) => StatusCode::PAYMENT_REQUIRED.into_response(),
+                Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+            }
+        }),
+    );
+    let url = format!("{}/weather", spawn_app(app).await);
+    let client = reqwest::Client::new();
+
+    let single = client
+        .get(&url)
+        .header(X_PAYMENT_HEADER, "first")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(single.status(), 200);
+    assert_eq!(single.text().await.unwrap(), "first");
+
+    let duplicated = client
+        .get(&url)
+        .header(X_PAYMENT_HEADER, "first")
+        .header(X_PAYMENT_HEADER, "second")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(duplicated.status(), 400);
+    assert!(duplicated.text().await.unwrap().contains("Multiple X-PAYMENT headers"));
+}
+
+#[tokio::test]
+async fn test_facilitator_client_captures_failed_exchange() {
+    use axum::{http::StatusCode, routing::post, Router};
+    use x402_rs::facilitator_client::FacilitatorClient;
+
+    let app = Router::new().route(
+        "/verify",
+        post(|| async { (StatusCode::BAD_GATEWAY, "upstream node unreachable") }),
+    );
+    let facilitator_url = spawn_app(app).await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    // Nothing is retained unless debug capture is enabled
+    let client = FacilitatorClient::new(&facilitator_url).unwrap();
+    assert!(client.verify("header", &requirements, "req-1").await.is_err());
+    assert_eq!(client.last_exchange(), None);
+
+    let client = client.with_debug_capture();
+    assert!(client.verify("header", &requirements, "req-2").await.is_err());
+    let exchange = client.last_exchange().unwrap();
+    assert_eq!(exchange.method, "POST");
+    assert_eq!(exchange.url, format!("{}/verify", facilitator_url));
+    assert!(exchange.request_body.contains(r#""paymentHeader":"header""#));
+    assert_eq!(exchange.status, 502);
+    assert_eq!(exchange.response_body, "upstream node unreachable");
+}
+
+#[tokio::test]
+async fn test_facilitator_client_retries_transient_statuses_only() {
+    use axum::{http::StatusCode, routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use x402_rs::facilitator_client::{FacilitatorClient, RetryConfig};
+
+    let bad_requests = Arc::new(AtomicUsize::new(0));
+    let unavailable = Arc::new(AtomicUsize::new(0));
+    let (bad_counter, unavailable_counter) = (bad_requests.clone(), unavailable.clone());
+    let app = Router::new()
+        .route(
+            "/verify",
+            post(move || async move {
+                bad_counter.fetch_add(1, Ordering::SeqCst);
+                StatusCode::BAD_REQUEST
+            }),
+        )
+        .route(
+            "/settle",
+            post(move || async move {
+                unavailable_counter.fetch_add(1, Ordering::SeqCst);
+                (StatusCode::SERVICE_UNAVAILABLE, Json(json!({})))
+            }),
+        );
+    let facilitator_url = spawn_app(app).await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let client = FacilitatorClient::new(&facilitator_url).unwrap().with_retry(
+        RetryConfig::new()
+            .with_max_attempts(3)
+            .with_base_delay(Duration::from_millis(1)),
+    );
+
+    // A rejected request is final
+    let err = client.verify("header", &requirements, "req-1").await.unwrap_err();
+    assert!(matches!(err, x402_rs::X402Error::VerificationFailed(_)));
+    assert_eq!(bad_requests.load(Ordering::SeqCst), 1);
+
+    // An unavailable facilitator is retried until the attempts run out
+    let err = client.settle("header", &requirements, "req-2").await.unwrap_err();
+    assert!(matches!(err, x402_rs::X402Error::SettlementError(_)));
+    assert_eq!(unavailable.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_facilitator_client_gives_up_on_long_retry_after() {
+    use axum::{http::StatusCode, routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use x402_rs::facilitator_client::{FacilitatorClient, RetryConfig};
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let app = Router::new().route(
+        "/settle",
+        post(move || async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            (StatusCode::SERVICE_UNAVAILABLE, [("Retry-After", "3600")])
+        }),
+    );
+    let facilitator_url = spawn_app(app).await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let retry = RetryConfig::new()
+        .with_max_attempts(3)
+        .with_max_delay(Duration::from_secs(1));
+    let client = FacilitatorClient::new(&facilitator_url)
+        .unwrap()
+        .with_retry(retry);
+    let settle = client.settle("header", &requirements, "req-1");
+    let result = tokio::time::timeout(Duration::from_secs(5), settle).await;
+    assert!(matches!(
+        result,
+        Ok(Err(x402_rs::X402Error::SettlementError(_)))
+    ));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_rpc_retries_transport_failures_only() {
+    use axum::{routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme};
+    use x402_rs::X402Error;
+
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "",
+    )
+    .to_requirements("/test")
+    .unwrap();
+    let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    let scheme = ExactEvm::new().with_rpc_retries(2, Duration::from_millis(1));
+
+    // Nothing listens here, so every attempt is refused
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let refused_url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    match scheme.generate_payload(&requirements, key, &refused_url).await {
+        Err(X402Error::BlockchainError(message)) => {
+            assert!(message.contains("after 3 attempts"), "{}", message)
+        }
+        other => panic!("expected a BlockchainError, got {:?}", other),
+    }
+
+    // An error answered by the node is final
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let app = Router::new().route(
+        "/",
+        post(move |Json(request): Json<Value>| async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Json(json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32000, "message": "method disabled" },
+            }))
+        }),
+    );
+    let rpc_url = spawn_app(app).await;
+    match scheme.generate_payload(&requirements, key, &rpc_url).await {
+        Err(X402Error::BlockchainError(message)) => {
+            assert!(!message.contains("attempts"), "{}", message)
+        }
+        other => panic!("expected a BlockchainError, got {:?}", other),
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "otel")]
+#[tokio::test]
+async fn test_trace_context_propagated_to_facilitator_spans() {
+    use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+    use opentelemetry::trace::SpanKind;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+    use std::sync::Mutex;
+    use x402_rs::facilitator::{handle_settle, handle_verify, FacilitatorConfig};
+    use x402_rs::facilitator_client::FacilitatorClient;
+    use x402_rs::telemetry::{parse_traceparent, with_trace_context, TRACEPARENT_HEADER};
+    use x402_rs::types::{SettlementRequest, VerificationRequest};
+
+    let exporter = InMemorySpanExporter::default();
+    opentelemetry::global::set_tracer_provider(
+        SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build(),
+    );
+
+    type Seen = Arc<Mutex<Vec<String>>>;
+    let seen: Seen = Arc::default();
+    let record = |seen: &Seen, headers: &HeaderMap| {
+        if let Some(value) = headers.get(TRACEPARENT_HEADER) {
+            seen.lock().unwrap().push(value.to_str().unwrap().to_string());
+        }
+    };
+    let config = Arc::new(FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        "http://127.0.0.1:1",
+    ));
+    let app = Router::new()
+        .route(
+            "/verify",
+            post(
+                move |State((seen, config)): State<(Seen, Arc<FacilitatorConfig>)>,
+                      headers: HeaderMap,
+                      Json(request): Json<VerificationRequest>| async move {
+                    record(&seen, &headers);
+                    let verify = handle_verify(request, &config);
+                    Json(with_trace_context(&headers, verify).await.unwrap())
+                },
+            ),
+        )
+        .route(
+            "/settle",
+            post(
+                move |State((seen, config)): State<(Seen, Arc<FacilitatorConfig>)>,
+                      headers: HeaderMap,
+                      Json(request): Json<SettlementRequest>| async move {
+                    record(&seen, &headers);
+                    let settle = handle_settle(request, &config);
+                    Json(with_trace_context(&headers, settle).await.unwrap())
+                },
+            ),
+        )
+        .with_state((seen.clone(), config));
+    let facilitator_url = spawn_app(app).await;
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &facilitator_url,
+    )
+    .to_requirements("/test")
+    .unwrap();
+
+    let client = FacilitatorClient::new(&facilitator_url).unwrap();
+    let verification = client.verify("header", &requirements, "req-1").await.unwrap();
+    assert!(!verification.is_valid);
+    client.settle("header", &requirements, "req-2").await.unwrap();
+
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(seen.len(), 2);
+    let spans = exporter.get_finished_spans().unwrap();
+    for (operation, traceparent) in ["verify", "settle"].into_iter().zip(&seen) {
+        let parent = parse_traceparent(traceparent).unwrap();
+        let in_trace: Vec<_> = spans
+            .iter()
+            .filter(|span| span.span_context.trace_id() == parent.trace_id())
+            .collect();
+
+        // The header names the server's client span...
+        let client_span = in_trace
+            .iter()
+            .find(|span| span.span_context.span_id() == parent.span_id())
+            .unwrap();
+        assert_eq!(client_span.name, format!("x402.facilitator_client.{}", operation));
+        assert_eq!(client_span.span_kind, SpanKind::Client);
+
+        // ...under which the facilitator handler ran in exactly one span
+        let handler_spans: Vec<_> = in_trace
+            .iter()
+            .filter(|span| span.name == format!("x402.facilitator.{}", operation))
+            .collect();
+        assert_eq!(handler_spans.len(), 1);
+        assert_eq!(handler_spans[0].span_kind, SpanKind::Server);
+        assert_eq!(handler_spans[0].parent_span_id, parent.span_id());
+        assert!(handler_spans[0].parent_span_is_remote);
+    }
+}
+
+/// A mock node whose head advances a block per query, serving the settlement receipt
+/// in block 0x10 for the first `included_polls` receipt queries only.
+async fn spawn_reorg_rpc(tx_hash: String, included_polls: usize) -> String {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let receipt_polls = Arc::new(AtomicUsize::new(0));
+    let head = Arc::new(AtomicUsize::new(0x10));
+    spawn_mock_rpc(Arc::new(move |method, _params| match method {
+        "eth_blockNumber" => json!(format!("0x{:x}", head.fetch_add(1, Ordering::SeqCst))),
+        "eth_getTransactionReceipt" => {
+            if receipt_polls.fetch_add(1, Ordering::SeqCst) >= included_polls {
+                return Value::Null;
+            }
+            json!({
+                "transactionHash": tx_hash,
+                "transactionIndex": "0x0",
+                "blockHash": format!("0x{}", "cd".repeat(32)),
+                "blockNumber": "0x10",
+                "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+                "to": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+                "cumulativeGasUsed": "0x30000",
+                "gasUsed": "0x30000",
+                "contractAddress": null,
+                "logs": [],
+                "logsBloom": format!("0x{}", "00".repeat(256)),
+                "status": "0x1",
+            })
+        }
+        _ => Value::Null,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn test_finality_watch_flags_reorged_settlement() {
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use x402_rs::finality::{FinalityEvent, FinalityWatch};
+
+    let tx_hash = ethers::types::H256::repeat_byte(0xab);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    let watch = FinalityWatch::new(5)
+        .with_poll_interval(Duration::from_millis(10))
+        .on_event(move |event| recorded.lock().unwrap().push(event.clone()));
+
+    // Included for two polls, then reorged out before reaching five confirmations
+    let rpc_url = spawn_reorg_rpc(format!("{:?}", tx_hash), 2).await;
+    let dropped = FinalityEvent::Dropped {
+        tx_hash,
+        last_block_number: Some(16),
+    };
+    assert_eq!(watch.watch(&rpc_url, tx_hash).await.unwrap(), dropped);
+    assert_eq!(*events.lock().unwrap(), vec![dropped]);
+
+    // Stays included while the head advances
+    let rpc_url = spawn_reorg_rpc(format!("{:?}", tx_hash), usize::MAX).await;
+    let event = watch.watch(&rpc_url, tx_hash).await.unwrap();
+    assert_eq!(
+        event,
+        FinalityEvent::Finalized {
+            tx_hash,
+            block_number: 16,
+            confirmations: 5,
+        }
+    );
+    assert_eq!(events.lock().unwrap().len(), 2);
+}
+
+/// A mock Base node for a token where the payer holds 1 USDC and approved `allowance`.
+async fn spawn_allowance_rpc(allowance: u64) -> String {
+    let tx_hash = format!("0x{}", "ab".repeat(32));
+    spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => {
+            let data = params[0]["data"].as_str().or(params[0]["input"].as_str());
+            match data.unwrap_or_default().get(..10) {
+                Some("0xdd62ed3e") => json!(format!("0x{:064x}", allowance)),
+                Some("0x70a08231") => json!(format!("0x{:064x}", 1_000_000)),
+                _ => json!(format!("0x{}", "00".repeat(32))),
+            }
+        }
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => json!("0x30000"),
+        "eth_sendRawTransaction" => json!(tx_hash),
+        "eth_getTransactionReceipt" => json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "cd".repeat(32)),
+            "blockNumber": "0x10",
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+            "cumulativeGasUsed": "0x30000",
+            "gasUsed": "0x30000",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+        }),
+        _ => Value::Null,
+    }))
+    .await
+}
+
+/// Requirements paid from an allowance granted to hardhat account #0.
+fn allowance_requirements() -> x402_rs::types::PaymentRequirements {
+    x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .with_allowance_transfer("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266")
+    .to_requirements("/test")
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_allowance_transfer_with_sufficient_allowance() {
+    use x402_rs::facilitator::{handle_settle, handle_verify};
+    use x402_rs::schemes::{allowance_evm::AllowanceEvm, Scheme, TxType};
+    use x402_rs::types::{AllowanceTransfer, SettlementRequest, VerificationRequest};
+    use x402_rs::X402Error;
+
+    let rpc_url = spawn_allowance_rpc(10_000).await;
+    let requirements = allowance_requirements();
+    let payload = AllowanceEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let transfer = payload.typed_payload::<AllowanceTransfer>().unwrap();
+    assert_eq!(transfer.from, "0x70997970c51812dc3a010c7d01b50e0d17dc79c8");
+    assert_eq!(transfer.value, "10000");
+    assert_eq!(
+        transfer.spender,
+        "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+    );
+    assert_eq!(transfer.chain_id, 8453);
+
+    // `extra.assetTransferMethod` routes the payment away from EIP-3009
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+    config.set_min_profitable_amount(ethers::types::U256::from(5_000u64));
+    config.set_price_oracle(FixedPrice);
+    let header = encode_payment_header(&payload).unwrap();
+    let verification = handle_verify(
+        VerificationRequest {
+            payment_header: header.clone(),
+            payment_requirements: requirements.clone(),
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert!(verification.is_valid, "{:?}", verification.invalid_reason);
+
+    // The signature can't be redeemed against another token the payer approved
+    let mut other_token = requirements.clone();
+    other_token.asset = "0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb".to_string();
+    let scheme = AllowanceEvm::new().with_tx_type(TxType::Legacy);
+    let failures = scheme
+        .verify_detailed(&payload, &other_token, &config.rpc_url)
+        .await
+        .unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].starts_with("Asset mismatch"));
+    let settled = scheme
+        .settle(&payload, &other_token, &config.rpc_url, &config.private_key)
+        .await;
+    assert!(matches!(settled, Err(X402Error::VerificationFailed(_))));
+
+    let settlement = handle_settle(
+        SettlementRequest {
+            payment_header: header,
+            payment_requirements: requirements,
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert_eq!(settlement.error, None);
+    let info = settlement.settlement.unwrap();
+    assert_eq!(info.payer, transfer.from);
+    assert_eq!(info.amount, "10000");
+    assert_eq!(settlement.block_number, Some(16));
+}
+
+#[tokio::test]
+async fn test_allowance_transfer_with_insufficient_allowance() {
+    use x402_rs::facilitator::handle_verify;
+    use x402_rs::schemes::{allowance_evm::AllowanceEvm, Scheme};
+    use x402_rs::types::VerificationRequest;
+
+    let rpc_url = spawn_allowance_rpc(9_999).await;
+    let requirements = allowance_requirements();
+    let scheme = AllowanceEvm::new();
+    let payload = scheme
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+
+    let failures = scheme
+        .verify_detailed(&payload, &requirements, &rpc_url)
+        .await
+        .unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].starts_with("Allowance of 9999 for spender"));
+
+    let config = FacilitatorConfig::new("0xfacilitator", rpc_url);
+    let verification = handle_verify(
+        VerificationRequest {
+            payment_header: encode_payment_header(&payload).unwrap(),
+            payment_requirements: requirements,
+            actual_amount: None,
+        },
+        &config,
+    )
+    .await
+    .unwrap();
+    assert!(!verification.is_valid);
+}
+
+#[tokio::test]
+async fn test_payment_required_omits_unsupported_options() {
+    use axum::{routing::get, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use x402_rs::server::create_supported_payment_required_response;
+
+    // The facilitator settles Base only
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let counter = fetches.clone();
+    let app = Router::new().route(
+        "/supported",
+        get(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                Json(json!({
+                    "supported": [
+                        { "scheme": "exact", "network": "8453" },
+                        { "scheme": "exact", "network": "84532", "verifyOnly": true }
+                    ]
+                }))
+            }
+        }),
+    );
+    let facilitator_url = spawn_app(app).await;
+
+    // Clones share the cached `/supported` answer
+    let base = PaymentConfig::new(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+        6,
+        "8453",
+        "exact",
+        0.01,
+        "Test",
+        facilitator_url,
+    )
+    .with_supported_filter(Duration::from_secs(60));
+    let mut polygon = base.clone();
+    polygon.network = "137".to_string();
+    let mut base_sepolia = base.clone();
+    base_sepolia.network = "84532".to_string();
+
+    let mut configs = HashMap::new();
+    configs.insert("base".to_string(), base);
+    configs.insert("polygon".to_string(), polygon);
+    configs.insert("base-sepolia".to_string(), base_sepolia);
+
+    for _ in 0..2 {
+        let response = create_supported_payment_required_response(&configs, "/test")
+            .await
+            .unwrap();
+        assert_eq!(response.accepts.len(), 1);
+        assert_eq!(response.accepts[0].network, "8453");
+    }
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_split_payment_settles_both_legs() {
+    use ethers::abi::{decode, ParamType, Token};
+    use ethers::types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256};
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::schemes::{split_evm::SplitEvm, Scheme, TxType};
+    use x402_rs::types::{SettlementRequest, SplitTransfer};
+
+    let first: Address = "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb".parse().unwrap();
+    let second: Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap();
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .with_split(format!("{:?}", first), 7000)
+    .with_split(format!("{:?}", second), 3000)
+    .to_requirements("/test")
+    .unwrap();
+
+    // Each payee is credited its share in the settlement transaction
+    let tx_hash = format!("0x{}", "ab".repeat(32));
+    let payer = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8";
+    let logs = vec![
+        usdc_transfer_log(payer, &format!("{:?}", first), 7_000),
+        usdc_transfer_log(payer, &format!("{:?}", second), 3_000),
+    ];
+    let sent = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+    let recorded = sent.clone();
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => funded_eth_call(params),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => json!("0x60000"),
+        "eth_sendRawTransaction" => {
+            recorded.lock().unwrap().push(params[0].as_str().unwrap().to_string());
+            json!(tx_hash)
+        }
+        "eth_getTransactionReceipt" => json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "cd".repeat(32)),
+            "blockNumber": "0x10",
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0xca11bde05977b3631167028862be2a173976ca11",
+            "cumulativeGasUsed": "0x60000",
+            "gasUsed": "0x60000",
+            "contractAddress": null,
+            "logs": logs.clone(),
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+        }),
+        _ => Value::Null,
+    }))
+    .await;
+
+    let payload = SplitEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let split: SplitTransfer = payload.typed_payload().unwrap();
+    let values: Vec<_> = split.authorizations.iter().map(|a| a.value.as_str()).collect();
+    assert_eq!(values, ["7000", "3000"]);
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+    config.enable_payee_balance_check();
+    // Worth its total, above the floor; the oracle can't price a split and is skipped
+    config.set_min_profitable_amount(U256::from(8_000u64));
+    config.set_price_oracle(FixedPrice);
+    let request = SettlementRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements,
+        actual_amount: None,
+    };
+    let response = handle_settle(request, &config).await.unwrap();
+    assert!(response.error.is_none(), "{:?}", response.error);
+    assert!(response.invalid_reason.is_none(), "{:?}", response.invalid_reason);
+    assert_eq!(response.settlement.unwrap().amount, "10000");
+
+    // Both legs went out in a single Multicall3 transaction
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    let raw: Bytes = sent[0].parse().unwrap();
+    let (tx, _) = TypedTransaction::decode_signed(&ethers::utils::rlp::Rlp::new(&raw)).unwrap();
+    assert_eq!(
+        tx.to_addr(),
+        Some(&"0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap())
+    );
+
+    let call3 = ParamType::Tuple(vec![ParamType::Address, ParamType::Bool, ParamType::Bytes]);
+    let data = tx.data().unwrap();
+    let calls = decode(&[ParamType::Array(Box::new(call3))], &data[4..]).unwrap();
+    let Token::Array(calls) = &calls[0] else {
+        panic!("aggregate3 takes an array of calls");
+    };
+    let legs: Vec<(Address, U256)> = calls
+        .iter()
+        .map(|call| {
+            let Token::Tuple(fields) = call else {
+                panic!("call is not a tuple");
+            };
+            assert_eq!(fields[1], Token::Bool(false));
+            let call_data = fields[2].clone().into_bytes().unwrap();
+            let args = decode(
+                &[ParamType::Address, ParamType::Address, ParamType::Uint(256)],
+                &call_data[4..],
+            )
+            .unwrap();
+            (
+                args[1].clone().into_address().unwrap(),
+                args[2].clone().into_uint().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        legs,
+        [(first, U256::from(7_000u64)), (second, U256::from(3_000u64))]
+    );
+}
+
+#[tokio::test]
+async fn test_upto_payment_settles_actual_amount() {
+    use ethers::abi::{decode, ParamType};
+    use ethers::types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256};
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::schemes::{upto_evm::UptoEvm, Scheme, TxType};
+    use x402_rs::types::SettlementRequest;
+    use x402_rs::X402Error;
+
+    // The facilitator's account is the spender payers permit
+    let requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .with_upto("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266")
+    .to_requirements("/test")
+    .unwrap();
+
+    let tx_hash = format!("0x{}", "ab".repeat(32));
+    let sent = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+    let recorded = sent.clone();
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => funded_eth_call(params),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => json!("0x30000"),
+        "eth_sendRawTransaction" => {
+            recorded.lock().unwrap().push(params[0].as_str().unwrap().to_string());
+            json!(tx_hash)
+        }
+        "eth_getTransactionReceipt" => json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "cd".repeat(32)),
+            "blockNumber": "0x10",
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+            "cumulativeGasUsed": "0x30000",
+            "gasUsed": "0x30000",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+        }),
+        _ => Value::Null,
+    }))
+    .await;
+
+    let payload = UptoEvm::new()
+        .generate_payload(
+            &requirements,
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    assert_eq!(payload.payload["value"], "10000");
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+    config.add_supported("upto", "8453");
+    config.set_min_profitable_amount(U256::from(3_000u64));
+    config.set_price_oracle(FixedPrice);
+    let request = |actual_amount: &str| SettlementRequest {
+        payment_header: encode_payment_header(&payload).unwrap(),
+        payment_requirements: requirements.clone(),
+        actual_amount: Some(actual_amount.to_string()),
+    };
+
+    // Charging more than the payer permitted is refused without spending the permit
+    let response = handle_settle(request("20000"), &config).await.unwrap();
+    assert_eq!(
+        response.invalid_reason.as_deref(),
+        Some("Actual amount 20000 exceeds maxAmountRequired 10000")
+    );
+    assert!(sent.lock().unwrap().is_empty());
+
+    // Anyone holding the header can't redirect the charge to another recipient
+    let mut redirected = request("4000");
+    redirected.payment_requirements.pay_to =
+        "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".to_string();
+    let response = handle_settle(redirected.clone(), &config).await.unwrap();
+    assert!(response.invalid_reason.is_some());
+    let failures = UptoEvm::new()
+        .verify_detailed(&payload, &redirected.payment_requirements, &config.rpc_url)
+        .await
+        .unwrap();
+    assert!(failures.iter().any(|f| f.starts_with("Recipient mismatch")));
+    let settled = UptoEvm::new()
+        .with_tx_type(TxType::Legacy)
+        .settle(
+            &payload,
+            &redirected.payment_requirements,
+            &config.rpc_url,
+            &config.private_key,
+        )
+        .await;
+    assert!(matches!(settled, Err(X402Error::VerificationFailed(_))));
+    assert!(sent.lock().unwrap().is_empty());
+
+    // Rewriting the payload's recipient too breaks the payer's payment signature
+    let mut tampered = payload.clone();
+    tampered.payload["payTo"] = json!(redirected.payment_requirements.pay_to);
+    redirected.payment_header = encode_payment_header(&tampered).unwrap();
+    let response = handle_settle(redirected.clone(), &config).await.unwrap();
+    assert!(response.invalid_reason.is_some());
+    let failures = UptoEvm::new()
+        .verify_detailed(&tampered, &redirected.payment_requirements, &config.rpc_url)
+        .await
+        .unwrap();
+    assert!(failures
+        .iter()
+        .any(|f| f.starts_with("Payment signature recovers to")));
+    assert!(sent.lock().unwrap().is_empty());
+
+    // Profitability is judged on the amount charged, not the permit
+    let response = handle_settle(request("2000"), &config).await.unwrap();
+    assert_eq!(
+        response.invalid_reason.as_deref(),
+        Some("Payment of 2000 is below the minimum profitable amount of 3000")
+    );
+    assert!(sent.lock().unwrap().is_empty());
+
+    let response = handle_settle(request("4000"), &config).await.unwrap();
+    assert!(response.error.is_none(), "{:?}", response.error);
+    assert!(response.invalid_reason.is_none(), "{:?}", response.invalid_reason);
+    assert_eq!(response.settlement.unwrap().amount, "4000");
+
+    // The permit went out first, for the whole signed value, leaving the facilitator
+    // an allowance of what wasn't charged
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 2);
+    let raw: Bytes = sent[0].parse().unwrap();
+    let (tx, _) = TypedTransaction::decode_signed(&ethers::utils::rlp::Rlp::new(&raw)).unwrap();
+    let data = tx.data().unwrap();
+    assert_eq!(data[..4], [0xd5, 0x05, 0xac, 0xcf]);
+    let args = decode(
+        &[ParamType::Address, ParamType::Address, ParamType::Uint(256)],
+        &data[4..100],
+    )
+    .unwrap();
+    assert_eq!(args[2].clone().into_uint(), Some(U256::from(10_000u64)));
+
+    // Then a transfer of only the actual amount
+    let raw: Bytes = sent[1].parse().unwrap();
+    let (tx, _) = TypedTransaction::decode_signed(&ethers::utils::rlp::Rlp::new(&raw)).unwrap();
+    let data = tx.data().unwrap();
+    assert_eq!(data[..4], [0x23, 0xb8, 0x72, 0xdd]);
+    let args = decode(
+        &[ParamType::Address, ParamType::Address, ParamType::Uint(256)],
+        &data[4..],
+    )
+    .unwrap();
+    let pay_to: Address = "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb".parse().unwrap();
+    assert_eq!(args[1].clone().into_address(), Some(pay_to));
+    assert_eq!(args[2].clone().into_uint(), Some(U256::from(4_000u64)));
+}
+
+#[tokio::test]
+async fn test_server_charges_upto_payment_over_http() {
+    use axum::{extract::State, routing::post, Json, Router};
+    use ethers::types::U256;
+    use x402_rs::facilitator::{handle_settle, handle_verify};
+    use x402_rs::schemes::{upto_evm::UptoEvm, Scheme, TxType};
+    use x402_rs::server::verify_and_charge_payment;
+    use x402_rs::types::{SettlementRequest, VerificationRequest};
+    use x402_rs::X402Error;
+
+    let tx_hash = format!("0x{}", "ab".repeat(32));
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => funded_eth_call(params),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => json!("0x30000"),
+        "eth_sendRawTransaction" => json!(tx_hash),
+        "eth_getTransactionReceipt" => json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "cd".repeat(32)),
+            "blockNumber": "0x10",
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+            "cumulativeGasUsed": "0x30000",
+            "gasUsed": "0x30000",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+        }),
+        _ => Value::Null,
+    }))
+    .await;
+
+    let mut facilitator = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url.clone(),
+    );
+    facilitator.set_tx_type("8453", TxType::Legacy);
+    facilitator.add_supported("upto", "8453");
+    type Charged = Arc<std::sync::Mutex<Vec<Option<String>>>>;
+    type Shared = (Arc<FacilitatorConfig>, Charged, Charged);
+    let verified: Charged = Arc::default();
+    let charged: Charged = Arc::default();
+    let app = Router::new()
+        .route(
+            "/verify",
+            post(
+                |State((config, verified, _)): State<Shared>,
+                 Json(request): Json<VerificationRequest>| async move {
+                    verified.lock().unwrap().push(request.actual_amount.clone());
+                    Json(handle_verify(request, &config).await.unwrap())
+                },
+            ),
+        )
+        .route(
+            "/settle",
+            post(
+                |State((config, _, charged)): State<Shared>,
+                 Json(request): Json<SettlementRequest>| async move {
+                    charged.lock().unwrap().push(request.actual_amount.clone());
+                    Json(handle_settle(request, &config).await.unwrap())
+                },
+            ),
+        )
+        .with_state((Arc::new(facilitator), verified.clone(), charged.clone()));
+
+    let config = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        &spawn_app(app).await,
+    )
+    .with_upto("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let payload = UptoEvm::new()
+        .generate_payload(
+            &config.to_requirements("/test").unwrap(),
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            &rpc_url,
+        )
+        .await
+        .unwrap();
+    let header = encode_payment_header(&payload).unwrap();
+
+    // An over-charge is refused at /verify, before anything is settled
+    let err = verify_and_charge_payment(&header, &config, "/test", U256::from(20_000u64))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, X402Error::VerificationFailed(_)), "{}", err);
+    assert!(err.to_string().contains("exceeds maxAmountRequired"));
+    assert!(charged.lock().unwrap().is_empty());
+
+    let settlement = verify_and_charge_payment(&header, &config, "/test", U256::from(4_000u64))
+        .await
+        .unwrap();
+    assert_eq!(settlement.amount, "4000");
+    assert_eq!(
+        *verified.lock().unwrap(),
+        vec![Some("20000".to_string()), Some("4000".to_string())]
+    );
+    assert_eq!(*charged.lock().unwrap(), vec![Some("4000".to_string())]);
+}
+
+#[tokio::test]
+async fn test_client_pays_upto_offer() {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use ethers::types::U256;
+    use x402_rs::client::X402Client;
+    use x402_rs::types::UptoAuthorization;
+    use x402_rs::X_PAYMENT_HEADER;
+
+    let config = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .with_upto("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let asset = config.asset.clone();
+
+    let app = Router::new().route(
+        "/paid",
+        get(move |headers: HeaderMap| async move {
+            let Some(header) = headers.get(X_PAYMENT_HEADER) else {
+                let body = PaymentRequiredResponse {
+                    x402_version: 1,
+                    accepts: vec![config.to_requirements("/paid").unwrap()],
+                    error: None,
+                };
+                return (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response();
+            };
+            let payload = decode_payment_header(header.to_str().unwrap()).unwrap();
+            let permit: UptoAuthorization = payload.typed_payload().unwrap();
+            (
+                StatusCode::OK,
+                Json(json!({ "scheme": payload.scheme, "value": permit.value })),
+            )
+                .into_response()
+        }),
+    );
+    let url = format!("{}/paid", spawn_app(app).await);
+
+    let client = X402Client::new(
+        X402ClientConfig::new(
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            spawn_chain_id_rpc().await,
+        )
+        .with_scheme("upto"),
+    );
+    let body: Value = client.get(&url).await.unwrap().json().await.unwrap();
+    assert_eq!(body, json!({ "scheme": "upto", "value": "10000" }));
+
+    // The permit is journaled like any other payment
+    assert_eq!(client.total_spent("8453", &asset), U256::from(10_000u64));
+}
+
+#[tokio::test]
+async fn test_probe_reports_affordable_offers() {
+    use axum::{http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+    use ethers::types::U256;
+    use x402_rs::client::probe;
+
+    // The payer holds too little USDC, plenty of the second token, and the third
+    // token's balance can't be read
+    let usdc = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913";
+    let other = "0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb";
+    let unreadable = "0x4200000000000000000000000000000000000006";
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => {
+            let to = params[0]["to"].as_str().unwrap_or_default();
+            if to.eq_ignore_ascii_case(unreadable) {
+                return json!("0x");
+            }
+            let balance = match to.eq_ignore_ascii_case(usdc) {
+                true => 5_000,
+                false => 1_000_000,
+            };
+            json!(format!("0x{:064x}", balance))
+        }
+        _ => Value::Null,
+    }))
+    .await;
+
+    let offer = |asset: &str| {
+        let mut requirement = x402_rs::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            0.01,
+            "Test",
+            "https://facilitator.test",
+        )
+        .to_requirements("/paid")
+        .unwrap();
+        requirement.asset = asset.to_string();
+        requirement
+    };
+    let accepts = vec![offer(usdc), offer(unreadable), offer(other)];
+    let app = Router::new().route(
+        "/paid",
+        get(move || async move {
+            let body = PaymentRequiredResponse {
+                x402_version: 1,
+                accepts,
+                error: None,
+            };
+            (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response()
+        }),
+    );
+    let url = format!("{}/paid", spawn_app(app).await);
+
+    let config = X402ClientConfig::new(
+        "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+        rpc_url,
+    );
+    let offers = probe(&config, &url, true).await.unwrap();
+    let annotated: Vec<_> = offers
+        .iter()
+        .map(|probed| (probed.offer.asset.as_str(), probed.affordable, probed.balance))
+        .collect();
+    assert_eq!(
+        annotated,
+        [
+            (usdc, false, Some(U256::from(5_000u64))),
+            (unreadable, false, None),
+            (other, true, Some(U256::from(1_000_000u64))),
+        ]
+    );
+
+    // Without balance checks nothing is read and every offer is reported affordable
+    let offers = probe(&config, &url, false).await.unwrap();
+    assert!(offers.iter().all(|probed| probed.affordable && probed.balance.is_none()));
+}
+
+#[tokio::test]
+async fn test_settlements_recorded_in_ledger() {
+    use ethers::types::U256;
+    use x402_rs::facilitator::handle_settle;
+    use x402_rs::ledger::MemoryLedger;
+    use x402_rs::schemes::{exact_evm::ExactEvm, Scheme, TxType};
+    use x402_rs::types::SettlementRequest;
+
+    let tx_hash = format!("0x{}", "ab".repeat(32));
+    let receipt_hash = tx_hash.clone();
+    let rpc_url = spawn_mock_rpc(Arc::new(move |method, params| match method {
+        "eth_chainId" => json!("0x2105"),
+        "eth_call" => funded_eth_call(params),
+        "eth_gasPrice" => json!("0x3b9aca00"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_estimateGas" => json!("0x30000"),
+        "eth_sendRawTransaction" => json!(receipt_hash),
+        "eth_getTransactionReceipt" => json!({
+            "transactionHash": receipt_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "cd".repeat(32)),
+            "blockNumber": "0x10",
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+            "cumulativeGasUsed": "0x30000",
+            "gasUsed": "0x30000",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+        }),
+        _ => Value::Null,
+    }))
+    .await;
+
+    let mut config = FacilitatorConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url.clone(),
+    );
+    config.set_tx_type("8453", TxType::Legacy);
+    config.set_ledger(MemoryLedger::new());
+
+    for price in [0.01, 0.02] {
+        let requirements = x402_rs::server::create_simple_config(
+            "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            price,
+            "Test",
+            "https://facilitator.test",
+        )
+        .to_requirements("/test")
+        .unwrap();
+        let payload = ExactEvm::new()
+            .generate_payload(
+                &requirements,
+                "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+                &rpc_url,
+            )
+            .await
+            .unwrap();
+        let request = SettlementRequest {
+            payment_header: encode_payment_header(&payload).unwrap(),
+            payment_requirements: requirements,
+            actual_amount: None,
+        };
+        let response = handle_settle(request, &config).await.unwrap();
+        assert!(response.error.is_none(), "{:?}", response.error);
+        assert!(response.invalid_reason.is_none(), "{:?}", response.invalid_reason);
+    }
+
+    let entries = config.ledger.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].amount, "10000");
+    assert_eq!(entries[1].amount, "20000");
+    assert_eq!(entries[1].tx_hash, tx_hash);
+    assert_eq!(
+        config
+            .ledger
+            .volume("8453", "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913"),
+        U256::from(30_000u64)
+    );
+    assert_eq!(
+        config.ledger.volume("84532", "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913"),
+        U256::zero()
+    );
+}
+
+#[tokio::test]
+async fn test_request_with_payment_ex_returns_settlement() {
+    use axum::{
+        extract::Path,
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use reqwest::Method;
+    use x402_rs::client::request_with_payment_ex;
+    use x402_rs::types::PaymentResponse;
+    use x402_rs::utils::encode_payment_response_header;
+    use x402_rs::{X_PAYMENT_HEADER, X_PAYMENT_RESPONSE_HEADER};
+
+    let (rpc_url, _) = spawn_counting_rpc("0x2105").await;
+    let config = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    );
+
+    let app = Router::new().route(
+        "/{kind}",
+        get(
+            move |Path(kind): Path<String>, headers: HeaderMap| async move {
+                if headers.get(X_PAYMENT_HEADER).is_none() {
+                    let body = PaymentRequiredResponse {
+                        x402_version: 1,
+                        accepts: vec![config.to_requirements(&format!("/{}", kind)).unwrap()],
+                        error: None,
+                    };
+                    return (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response();
+                }
+                let header = match kind.as_str() {
+                    "good" => encode_payment_response_header(&PaymentResponse {
+                        tx_hash: "0xabc".to_string(),
+                        settled_at: Some("2026-01-01T00:00:00Z".to_string()),
+                        metadata: None,
+                        settlement: None,
+                        access_token: None,
+                    })
+                    .unwrap(),
+                    _ => "not base64!".to_string(),
+                };
+                (
+                    StatusCode::OK,
+                    [(X_PAYMENT_RESPONSE_HEADER, header)],
+                    Json(json!({ "paid": true })),
+                )
+                    .into_response()
+            },
+        ),
+    );
+    let base = spawn_app(app).await;
+    let client_config = X402ClientConfig::new(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        rpc_url,
+    );
+
+    let good =
+        request_with_payment_ex(&client_config, Method::GET, &format!("{}/good", base), None)
+            .await
+            .unwrap();
+    let payment = good.payment.unwrap();
+    assert_eq!(payment.tx_hash, "0xabc");
+    assert_eq!(payment.settled_at.as_deref(), Some("2026-01-01T00:00:00Z"));
+    let body: Value = good.response.json().await.unwrap();
+    assert_eq!(body["paid"], true);
+
+    // A malformed header doesn't fail the request
+    let bad = request_with_payment_ex(&client_config, Method::GET, &format!("{}/bad", base), None)
+        .await
+        .unwrap();
+    assert!(bad.payment.is_none());
+    assert_eq!(bad.response.status(), 200);
+}
+
+/// Client-side "stream" scheme whose payload just names the amount, counting the
+/// requirements it prevalidates.
+#[derive(Default)]
+struct StreamScheme {
+    prevalidated: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl x402_rs::schemes::Scheme for StreamScheme {
+    fn name(&self) -> &str {
+        "stream"
+    }
+
+    async fn prevalidate(
+        &self,
+        _requirements: &x402_rs::types::PaymentRequirements,
+    ) -> x402_rs::Result<()> {
+        self.prevalidated
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn generate_payload(
+        &self,
+        requirements: &x402_rs::types::PaymentRequirements,
+        _private_key: &str,
+        _rpc_url: &str,
+    ) -> x402_rs::Result<x402_rs::types::PaymentPayload> {
+        Ok(x402_rs::types::PaymentPayload {
+            x402_version: 1,
+            scheme: self.name().to_string(),
+            network: requirements.network.clone(),
+            payload: json!({ "value": requirements.max_amount_required }),
+            challenge: None,
+            message_signature: None,
+        })
+    }
+
+    async fn verify(
+        &self,
+        _payload: &x402_rs::types::PaymentPayload,
+        _requirements: &x402_rs::types::PaymentRequirements,
+        _rpc_url: &str,
+    ) -> x402_rs::Result<bool> {
+        Err(x402_rs::X402Error::UnsupportedScheme(
+            "stream payments are verified by the server".to_string(),
+        ))
+    }
+
+    async fn settle(
+        &self,
+        _payload: &x402_rs::types::PaymentPayload,
+        _requirements: &x402_rs::types::PaymentRequirements,
+        _rpc_url: &str,
+        _facilitator_key: &str,
+    ) -> x402_rs::Result<x402_rs::types::SettlementInfo> {
+        Err(x402_rs::X402Error::UnsupportedScheme(
+            "stream payments are settled by the server".to_string(),
+        ))
+    }
+}
+
+#[tokio::test]
+async fn test_client_pays_with_registered_scheme() {
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use ethers::types::U256;
+    use x402_rs::client::X402Client;
+    use x402_rs::X_PAYMENT_HEADER;
+
+    let mut requirements = x402_rs::server::create_simple_config(
+        "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+        0.01,
+        "Test",
+        "https://facilitator.test",
+    )
+    .to_requirements("/paid")
+    .unwrap();
+    requirements.scheme = "stream".to_string();
+    let asset = requirements.asset.clone();
+
+    let app = Router::new().route(
+        "/paid",
+        get(move |headers: HeaderMap| async move {
+            let Some(header) = headers.get(X_PAYMENT_HEADER) else {
+                let body = PaymentRequiredResponse {
+                    x402_version: 1,
+                    accepts: vec![requirements.clone()],
+                    error: None,
+                };
+                return (StatusCode::PAYMENT_REQUIRED, Json(body)).into_response();
+            };
+            let payload = decode_payment_header(header.to_str().unwrap()).unwrap();
+            (StatusCode::OK, Json(json!({ "scheme": payload.scheme }))).into_response()
+        }),
+    );
+    let url = format!("{}/paid", spawn_app(app).await);
+
+    // "exact" stays preferred, but only the registered scheme is offered
+    let scheme = StreamScheme::default();
+    let prevalidated = scheme.prevalidated.clone();
+    let client = X402Client::new(
+        X402ClientConfig::new(
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+            spawn_chain_id_rpc().await,
+        )
+        .with_registered_scheme(scheme)
+        .with_strict_preferences(false),
+    );
+    let body: Value = client.get(&url).await.unwrap().json().await.unwrap();
+    assert_eq!(body["scheme"], "stream");
+    assert_eq!(client.total_spent("8453", &asset), U256::from(10_000u64));
+
+    // Each payment is prevalidated once, before and after the chain id is known
+    assert_eq!(prevalidated.load(std::sync::atomic::Ordering::SeqCst), 1);
+    client.get(&url).await.unwrap();
+    assert_eq!(prevalidated.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
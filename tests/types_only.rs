@@ -0,0 +1,84 @@
+//! Tests for the protocol types and header encoding on their own.
+//!
+//! Only items available with the `types` feature are used, so these tests also pass
+//! in a build without the EVM and HTTP dependencies:
+//!
+//! ```text
+//! cargo test --no-default-features --features types --test types_only
+//! ```
+
+use serde_json::json;
+use x402_rs::{
+    types::{PaymentPayload, PaymentRequiredResponse, PaymentRequirements, TransferAuthorization},
+    utils::{decode_payment_header, encode_payment_header},
+    X402Error, X402_VERSION,
+};
+
+fn payload() -> PaymentPayload {
+    PaymentPayload {
+        x402_version: X402_VERSION,
+        scheme: "exact".to_string(),
+        network: "8453".to_string(),
+        payload: json!({
+            "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "to": "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb",
+            "value": "10000",
+            "validAfter": "0",
+            "validBefore": "1735689600",
+            "nonce": format!("0x{}", "01".repeat(32)),
+            "signature": format!("0x{}", "02".repeat(65)),
+        }),
+        challenge: Some("challenge".to_string()),
+        message_signature: None,
+    }
+}
+
+#[test]
+fn test_payment_header_round_trip() {
+    let encoded = encode_payment_header(&payload()).unwrap();
+    let decoded = decode_payment_header(&encoded).unwrap();
+
+    assert_eq!(decoded.scheme, "exact");
+    assert_eq!(decoded.network, "8453");
+    assert_eq!(decoded.challenge.as_deref(), Some("challenge"));
+
+    let auth: TransferAuthorization = decoded.typed_payload().unwrap();
+    assert_eq!(auth.value, "10000");
+    assert_eq!(auth.valid_before, "1735689600");
+}
+
+#[test]
+fn test_decode_rejects_malformed_header() {
+    assert!(matches!(
+        decode_payment_header("not base64!"),
+        Err(X402Error::Base64Error(_))
+    ));
+}
+
+#[test]
+fn test_payment_required_response_round_trip() {
+    let response = PaymentRequiredResponse {
+        x402_version: X402_VERSION,
+        accepts: vec![PaymentRequirements {
+            scheme: "exact".to_string(),
+            network: "8453".to_string(),
+            max_amount_required: "10000".to_string(),
+            min_amount_required: None,
+            resource: "/weather".to_string(),
+            description: Some("Weather data".to_string()),
+            mime_type: Some("application/json".to_string()),
+            output_schema: None,
+            pay_to: "0x742d35Cc6634C0532925a3b844Bc9e7595f0bEbb".to_string(),
+            max_timeout_seconds: 300,
+            asset: "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".to_string(),
+            extra: None,
+        }],
+        error: None,
+    };
+
+    let json = serde_json::to_string(&response).unwrap();
+    let parsed: PaymentRequiredResponse = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.accepts.len(), 1);
+    assert_eq!(parsed.accepts[0].max_amount_required, "10000");
+    assert_eq!(parsed.accepts[0].resource, "/weather");
+}